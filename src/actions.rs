@@ -0,0 +1,258 @@
+//! Handles the actions that can be performed from menus and the command palette
+use crate::renderer::CameraPreset;
+use crate::theme::Theme;
+use egui::{Key, Modifiers};
+
+/// An action that can be performed on the app, or on the focused [StageDefInstance](crate::stagedef::instance::StageDefInstance).
+///
+/// Both the menus and the command palette go through [``MkbViewerApp::dispatch_action``](crate::app::MkbViewerApp::dispatch_action).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    OpenFile,
+    #[cfg(not(target_arch = "wasm32"))]
+    Quit,
+    UseFloatingWindowLayout,
+    UseTabbedLayout,
+    UseTheme(Theme),
+    CloseFocusedInstance,
+    CloseAllInstances,
+    /// Closes every instance except the focused one.
+    CloseOtherInstances,
+    #[cfg(not(target_arch = "wasm32"))]
+    OpenContainingFolder,
+    #[cfg(not(target_arch = "wasm32"))]
+    ReloadFocusedInstance,
+    ViewCameraPreset(CameraPreset),
+    ClearSelection,
+    /// Selects every object in the stagedef.
+    SelectAll,
+    /// Selects every object of the type with this name, as given by
+    /// [``StageDefObject::get_name``](crate::stagedef::common::StageDefObject::get_name).
+    SelectAllOfType(&'static str),
+    DeleteSelection,
+}
+
+impl Action {
+    /// Every action, in the order they should be listed in the command palette.
+    pub const ALL: &'static [Action] = &[
+        Action::OpenFile,
+        #[cfg(not(target_arch = "wasm32"))]
+        Action::Quit,
+        Action::UseFloatingWindowLayout,
+        Action::UseTabbedLayout,
+        Action::UseTheme(Theme::Dark),
+        Action::UseTheme(Theme::Light),
+        Action::UseTheme(Theme::HighContrast),
+        Action::CloseFocusedInstance,
+        Action::CloseAllInstances,
+        Action::CloseOtherInstances,
+        #[cfg(not(target_arch = "wasm32"))]
+        Action::OpenContainingFolder,
+        #[cfg(not(target_arch = "wasm32"))]
+        Action::ReloadFocusedInstance,
+        Action::ViewCameraPreset(CameraPreset::Top),
+        Action::ViewCameraPreset(CameraPreset::Front),
+        Action::ViewCameraPreset(CameraPreset::Side),
+        Action::ViewCameraPreset(CameraPreset::Fit),
+        Action::ClearSelection,
+        Action::SelectAll,
+        Action::SelectAllOfType("Goal"),
+        Action::SelectAllOfType("Bumper"),
+        Action::SelectAllOfType("Jamabar"),
+        Action::SelectAllOfType("Banana"),
+        Action::SelectAllOfType("Cone Collision"),
+        Action::SelectAllOfType("Sphere Collision"),
+        Action::SelectAllOfType("Cylinder Collision"),
+        Action::SelectAllOfType("Fallout Volume"),
+        Action::SelectAllOfType("BG Model"),
+        Action::SelectAllOfType("Level Model Instance"),
+        Action::SelectAllOfType("Reflective Model"),
+        Action::DeleteSelection,
+    ];
+
+    pub fn get_name(&self) -> String {
+        let name = match self {
+            Action::OpenFile => "Open File...",
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Quit => "Quit",
+            Action::UseFloatingWindowLayout => "Layout: Floating Windows",
+            Action::UseTabbedLayout => "Layout: Tabs",
+            Action::UseTheme(theme) => return format!("Theme: {}", theme.get_name()),
+            Action::CloseFocusedInstance => "Close Stagedef",
+            Action::CloseAllInstances => "Close All Stagedefs",
+            Action::CloseOtherInstances => "Close Other Stagedefs",
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::OpenContainingFolder => "Open Containing Folder",
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ReloadFocusedInstance => "Reload Stagedef from Disk",
+            Action::ViewCameraPreset(preset) => return format!("Camera: {}", preset.get_name()),
+            Action::ClearSelection => "Clear Selection",
+            Action::SelectAll => "Select All Objects",
+            Action::SelectAllOfType(type_name) => return format!("Select All {type_name}s"),
+            Action::DeleteSelection => "Delete Selected Objects",
+        };
+        name.to_string()
+    }
+}
+
+/// Returns a score for how well ``query`` matches ``candidate``, or ``None`` if it doesn't match.
+///
+/// Every character of the query must appear in the candidate, in order, ignoring case. Consecutive
+/// matches and matches at the start of words score higher.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<i32> {
+    let candidate_chars: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut candidate_index = 0;
+    let mut last_match_index: Option<usize> = None;
+
+    for query_char in query.chars().flat_map(char::to_lowercase).filter(|c| !c.is_whitespace()) {
+        let found_offset = candidate_chars[candidate_index..].iter().position(|c| *c == query_char)?;
+        let match_index = candidate_index + found_offset;
+
+        score += 1;
+        if last_match_index.map_or(false, |last| last + 1 == match_index) {
+            score += 5;
+        }
+        if match_index == 0 || !candidate_chars[match_index - 1].is_alphanumeric() {
+            score += 3;
+        }
+
+        last_match_index = Some(match_index);
+        candidate_index = match_index + 1;
+    }
+
+    Some(score)
+}
+
+/// The state of the command palette, opened with Ctrl+P.
+#[derive(Default)]
+pub struct CommandPalette {
+    is_open: bool,
+    query: String,
+    selected_index: usize,
+}
+
+impl CommandPalette {
+    /// Returns the actions matching the current query, best match first.
+    fn get_matching_actions(&self) -> Vec<Action> {
+        let mut matches: Vec<(i32, Action)> = Action::ALL
+            .iter()
+            .filter_map(|action| fuzzy_match(&self.query, &action.get_name()).map(|score| (score, *action)))
+            .collect();
+        // Stable sort, so ties stay in the order of Action::ALL
+        matches.sort_by(|a, b| b.0.cmp(&a.0));
+        matches.into_iter().map(|(_, action)| action).collect()
+    }
+
+    /// Displays the palette if it is open, and returns the action chosen by the user, if any.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<Action> {
+        if ctx.input_mut().consume_key(Modifiers::COMMAND, Key::P) {
+            self.is_open = !self.is_open;
+            self.query.clear();
+            self.selected_index = 0;
+        }
+
+        if !self.is_open {
+            return None;
+        }
+
+        if ctx.input_mut().consume_key(Modifiers::NONE, Key::Escape) {
+            self.is_open = false;
+            return None;
+        }
+
+        let matching_actions = self.get_matching_actions();
+        if ctx.input_mut().consume_key(Modifiers::NONE, Key::ArrowDown) {
+            self.selected_index += 1;
+        }
+        if ctx.input_mut().consume_key(Modifiers::NONE, Key::ArrowUp) {
+            self.selected_index = self.selected_index.saturating_sub(1);
+        }
+        self.selected_index = self.selected_index.min(matching_actions.len().saturating_sub(1));
+
+        let mut chosen_action = None;
+
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(&mut self.query);
+                response.request_focus();
+                if response.changed() {
+                    self.selected_index = 0;
+                }
+
+                if ui.input().key_pressed(Key::Enter) {
+                    chosen_action = matching_actions.get(self.selected_index).copied();
+                }
+
+                ui.separator();
+
+                for (index, action) in matching_actions.iter().enumerate() {
+                    if ui.selectable_label(index == self.selected_index, action.get_name()).clicked() {
+                        chosen_action = Some(*action);
+                    }
+                }
+            });
+
+        if chosen_action.is_some() {
+            self.is_open = false;
+        }
+
+        chosen_action
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("", "Open File...").is_some());
+        assert!(fuzzy_match("opn", "Open File...").is_some());
+        assert!(fuzzy_match("OPEN file", "Open File...").is_some());
+        assert!(fuzzy_match("fo", "Open File...").is_none());
+        assert!(fuzzy_match("xyz", "Open File...").is_none());
+
+        // Consecutive and word-start matches should rank higher
+        let consecutive = fuzzy_match("tab", "Layout: Tabs").unwrap();
+        let scattered = fuzzy_match("tab", "Layout: Floating Windows Tab").unwrap();
+        assert!(consecutive > scattered);
+
+        let word_start = fuzzy_match("lt", "Layout: Tabs").unwrap();
+        let mid_word = fuzzy_match("lt", "Clear Selection").unwrap();
+        assert!(word_start > mid_word);
+    }
+
+    #[test]
+    fn test_select_all_of_type_names() {
+        use crate::stagedef::common::StageDefObject;
+        use crate::stagedef::objects::*;
+
+        // Types are matched by name, so every one listed needs to be spelled the same as the object
+        let type_names = [
+            Goal::get_name(),
+            Bumper::get_name(),
+            Jamabar::get_name(),
+            Banana::get_name(),
+            ConeCollision::get_name(),
+            SphereCollision::get_name(),
+            CylinderCollision::get_name(),
+            FalloutVolume::get_name(),
+            BackgroundModel::get_name(),
+            LevelModelInstance::get_name(),
+            ReflectiveModel::get_name(),
+        ];
+        let listed_names: Vec<&str> = Action::ALL
+            .iter()
+            .filter_map(|action| match action {
+                Action::SelectAllOfType(type_name) => Some(*type_name),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(listed_names, type_names);
+        assert_eq!(Action::SelectAllOfType("Goal").get_name(), "Select All Goals");
+    }
+}
@@ -1,38 +1,228 @@
 //! Handles all the UI-related activities
-use crate::renderer::{self, FrameInput};
-use crate::stagedef::instance::StageDefInstance;
+use crate::actions::{Action, CommandPalette};
+use crate::description;
+use crate::logging;
+use crate::renderer::{self, CameraPreset, CameraSettings, ConveyorArrow, FrameInput, StartMarker};
+use crate::stagedef::autosave::AutosaveSettings;
+use crate::stagedef::compression::{self, Compression, LzError};
+use crate::stagedef::diff::StageDiff;
+use crate::stagedef::instance::{InstanceId, ParsedStageDef, StageDefInstance};
+use crate::stagedef::ui_state::PendingSelection;
+use crate::theme::Theme;
 use egui::style::Margin;
 use egui::{collapsing_header, vec2, Button, Frame, Label, Response, Vec2, Window};
-use egui::{CentralPanel, Separator, TopBottomPanel};
+use egui::{CentralPanel, Key, Modifiers, Separator, TopBottomPanel};
 use egui_inspect::EguiInspect;
 use futures::executor::block_on;
 use poll_promise::Promise;
 use rfd::AsyncFileDialog;
 use rfd::FileHandle;
 use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::vec::Vec;
 use tracing::{event, instrument, trace, Level};
 
+/// The name of a file picked from a file dialog, and the result of parsing it.
+type LoadedFile = (String, anyhow::Result<ParsedStageDef>);
+
 /// Our root window.
 #[derive(Default)]
 pub struct MkbViewerApp {
     /// A file pending to load, which we will split off into a new window to handle once the
     /// promise has a result.
-    pending_file_to_load: Option<Promise<Option<FileHandleWrapper>>>,
+    pending_file_to_load: Option<Promise<Option<Vec<LoadedFile>>>>,
     /// Collection of all loaded [StageDefInstance] structs.
     stagedef_viewers: Vec<StageDefInstance>,
     /// The state of the central widget, used to display a message indicating the status.
     state: CentralWidgetState,
+    /// How loaded [StageDefInstance] structs are laid out.
+    layout: InstanceLayout,
+    /// The index of the instance shown when using the [tabbed](InstanceLayout::Tabs) layout.
+    selected_tab: usize,
+    /// The most recently interacted with instance when using the
+    /// [floating window](InstanceLayout::FloatingWindows) layout.
+    focused_instance: Option<InstanceId>,
+    /// Searchable list of every [Action], opened with Ctrl+P.
+    command_palette: CommandPalette,
+    /// The IDs of the two instances currently being compared, if any.
+    comparison: Option<(InstanceId, InstanceId)>,
+    /// The differences between the two instances being compared, along with each instance's ID and
+    /// edit count when they were found, so they're only found again once either side changes.
+    comparison_diff: Option<([(InstanceId, u64); 2], StageDiff)>,
+    autosave_settings: AutosaveSettings,
+    theme: Theme,
+    /// The theme last applied to egui, so it's only set again when it changes.
+    applied_theme: Option<Theme>,
+    /// Why each file that couldn't be opened failed, shown until dismissed.
+    load_errors: Vec<String>,
 }
 
 impl MkbViewerApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
         // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
         // for e.g. egui::PaintCallback.
-        Self::default()
+        #[allow(unused_mut)]
+        let mut app = Self::default();
+
+        // Restore app state using cc.storage (requires the "persistence" feature). Panel sizes are
+        // restored by egui itself.
+        #[cfg(feature = "persistence")]
+        if let Some(persisted_state) = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<PersistedAppState>(storage, eframe::APP_KEY))
+        {
+            app.restore_persisted_state(persisted_state);
+        }
+
+        app
+    }
+
+    /// Returns the parts of the app's state that should be saved between sessions.
+    #[cfg(feature = "persistence")]
+    fn get_persisted_state(&self) -> PersistedAppState {
+        PersistedAppState {
+            layout: self.layout,
+            selected_tab: self.selected_tab,
+            autosave_settings: self.autosave_settings,
+            theme: self.theme,
+            instances: self
+                .stagedef_viewers
+                .iter()
+                .map(|viewer| PersistedInstance {
+                    file_name: viewer.get_filename(),
+                    file_path: viewer.get_file_path(),
+                    camera_settings: viewer.camera_settings,
+                    custom_title: viewer.custom_title.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restores state saved from a previous session.
+    ///
+    /// On native, files are reopened from their paths. On the web, files can't be reopened, so only
+    /// the UI state is restored.
+    #[cfg(feature = "persistence")]
+    fn restore_persisted_state(&mut self, persisted_state: PersistedAppState) {
+        self.layout = persisted_state.layout;
+        self.selected_tab = persisted_state.selected_tab;
+        self.autosave_settings = persisted_state.autosave_settings;
+        self.theme = persisted_state.theme;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for persisted_instance in persisted_state.instances {
+            let Some(file_path) = persisted_instance.file_path else {
+                continue;
+            };
+
+            let filehandle = match FileHandleWrapper::from_path(&file_path, MkbFileType::StagedefType) {
+                Ok(f) => f,
+                Err(e) => {
+                    event!(Level::WARN, "Could not reopen {}: {e}", persisted_instance.file_name);
+                    continue;
+                }
+            };
+
+            match StageDefInstance::new(filehandle) {
+                Ok(mut instance) => {
+                    instance.camera_settings = persisted_instance.camera_settings;
+                    instance.custom_title = persisted_instance.custom_title;
+                    self.stagedef_viewers.push(instance);
+                }
+                Err(e) => event!(Level::WARN, "Could not reload {}: {e}", persisted_instance.file_name),
+            }
+        }
+
+        self.state = self.get_non_loading_state();
+    }
+
+    /// Performs the given [Action]. Menus and the command palette both go through here.
+    pub fn dispatch_action(&mut self, action: Action, frame: &mut eframe::Frame) {
+        event!(Level::DEBUG, "Dispatching action {action:?}");
+        match action {
+            Action::OpenFile => {
+                event!(Level::INFO, "Opening file");
+                self.open_file_dialog(MkbFileType::StagedefType);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Quit => {
+                event!(Level::INFO, "Quitting...");
+                frame.close();
+            }
+            Action::UseFloatingWindowLayout => self.layout = InstanceLayout::FloatingWindows,
+            Action::UseTabbedLayout => self.layout = InstanceLayout::Tabs,
+            Action::UseTheme(theme) => self.theme = theme,
+            Action::CloseFocusedInstance => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.is_active = false;
+                }
+            }
+            Action::CloseAllInstances => {
+                for viewer in &mut self.stagedef_viewers {
+                    viewer.is_active = false;
+                }
+            }
+            Action::CloseOtherInstances => {
+                // With nothing focused there's nothing to keep, so this does nothing rather than
+                // closing everything
+                if let Some(focused_id) = self.get_focused_instance_mut().map(|viewer| viewer.id) {
+                    for viewer in &mut self.stagedef_viewers {
+                        viewer.is_active = viewer.id == focused_id;
+                    }
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::OpenContainingFolder => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.open_containing_folder();
+                }
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::ReloadFocusedInstance => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.request_reload();
+                }
+            }
+            Action::ViewCameraPreset(preset) => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.view_camera_preset(preset);
+                }
+            }
+            Action::ClearSelection => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.ui_state.selected_tree_items.clear();
+                }
+            }
+            Action::SelectAll => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.ui_state.pending_selection = Some(PendingSelection::All);
+                }
+            }
+            Action::SelectAllOfType(type_name) => {
+                if let Some(viewer) = self.get_focused_instance_mut() {
+                    viewer.ui_state.pending_selection = Some(PendingSelection::Type(type_name));
+                }
+            }
+            Action::DeleteSelection => {
+                if let Some(viewer) = self.get_focused_instance_mut().filter(|viewer| !viewer.is_reloading()) {
+                    viewer.ui_state.request_delete_selection();
+                }
+            }
+        }
+    }
+
+    /// Returns the instance that actions should apply to - the selected tab when using tabs, or the
+    /// most recently interacted with window otherwise.
+    fn get_focused_instance_mut(&mut self) -> Option<&mut StageDefInstance> {
+        match self.layout {
+            InstanceLayout::Tabs => self.stagedef_viewers.get_mut(self.selected_tab),
+            InstanceLayout::FloatingWindows => {
+                let focused_id = self.focused_instance?;
+                self.stagedef_viewers.iter_mut().find(|viewer| viewer.id == focused_id)
+            }
+        }
     }
 
     /// Open a file dialog with the given restriction on file type.
@@ -41,9 +231,10 @@ impl MkbViewerApp {
         self.pending_file_to_load = Some(MkbViewerApp::get_promise_from_file_dialog(file_type));
     }
 
-    /// Poll [`pending_file_to_load`](MkbViewerApp::pending_file_to_load) for a file to load, handle it based on the assigned type.
+    /// Poll [`pending_file_to_load`](MkbViewerApp::pending_file_to_load) for files that have finished loading, and add
+    /// them as new instances.
     ///
-    /// This is run every frame.
+    /// This is run every frame. The files are parsed before the promise completes, so this doesn't block the UI.
     fn poll_pending_file(&mut self) {
         let pending_file_to_load = self.pending_file_to_load.take();
 
@@ -56,7 +247,7 @@ impl MkbViewerApp {
         self.state = CentralWidgetState::Loading;
 
         // If we do, checks if that promise has completed yet
-        let filehandle_opt = match promise.try_take() {
+        let loaded_files_opt = match promise.try_take() {
             Ok(o) => {
                 trace!("Promise completed");
                 o
@@ -69,57 +260,160 @@ impl MkbViewerApp {
         };
 
         // If it has completed, check to see if it returned anything
-        let Some(filehandle) = filehandle_opt else {
+        let Some(loaded_files) = loaded_files_opt else {
             event!(Level::INFO, "No file was selected");
             self.state = self.get_non_loading_state();
             self.pending_file_to_load = None;
             return;
         };
 
-        // Construct the new StageDefInstance since we've loaded the file
-        event!(Level::INFO, "Loading pending file: {}...", filehandle.file_name);
-
-        // TODO: Handle error results instead of unwrapping
-        let new_instance = StageDefInstance::new(filehandle).unwrap();
+        self.add_loaded_files(loaded_files);
+        self.pending_file_to_load = None;
+    }
 
-        self.stagedef_viewers.push(new_instance);
+    /// Constructs new [StageDefInstance]s from already parsed files, and records why the rest failed.
+    fn add_loaded_files(&mut self, loaded_files: Vec<LoadedFile>) {
+        for (file_name, parse_result) in loaded_files {
+            match parse_result {
+                Ok(parsed) => {
+                    event!(Level::INFO, "Loaded file: {file_name}");
+                    self.stagedef_viewers.push(StageDefInstance::from_parsed(parsed));
+                }
+                Err(e) => {
+                    event!(Level::WARN, "Could not load {file_name}: {e}");
+                    self.load_errors.push(format!("Could not load {file_name}: {e}"));
+                }
+            }
+        }
 
         self.state = self.get_non_loading_state();
-        self.pending_file_to_load = None;
+    }
+
+    /// Reads and opens each of the given files, such as those passed on the command line.
+    ///
+    /// This blocks until every file has been parsed. Files that can't be read or parsed are shown as
+    /// load errors.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_paths(&mut self, paths: &[PathBuf]) {
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            match FileHandleWrapper::from_path(path, MkbFileType::StagedefType) {
+                Ok(file) => files.push(file),
+                Err(e) => {
+                    event!(Level::WARN, "Could not read {}: {e}", path.display());
+                    self.load_errors.push(format!("Could not read {}: {e}", path.display()));
+                }
+            }
+        }
+
+        self.add_loaded_files(MkbViewerApp::parse_files(files));
+    }
+
+    /// Fetches a stagedef from the given URL and opens it once it's arrived, like a file picked from
+    /// the file dialog.
+    ///
+    /// The server has to allow cross-origin requests from wherever the viewer is hosted. If it
+    /// doesn't, or the fetch fails for any other reason, the error is shown instead.
+    #[cfg(target_arch = "wasm32")]
+    pub fn open_url(&mut self, url: String) {
+        event!(Level::INFO, "Fetching {url}");
+        let file_name = get_file_name_from_url(&url);
+        let (sender, promise) = Promise::new();
+        ehttp::fetch(ehttp::Request::get(&url), move |result| {
+            let loaded_file = match result {
+                Ok(response) if response.ok => {
+                    let file = FileHandleWrapper::from_buffer(response.bytes, file_name, MkbFileType::StagedefType);
+                    MkbViewerApp::parse_files(vec![file])
+                }
+                Ok(response) => {
+                    let error = anyhow::anyhow!("{} returned {} {}", url, response.status, response.status_text);
+                    vec![(file_name, Err(error))]
+                }
+                // Browsers don't say why a fetch failed, which is usually CORS
+                Err(e) => {
+                    let error = anyhow::anyhow!("Could not fetch {url}: {e}. The server may not allow cross-origin requests");
+                    vec![(file_name, Err(error))]
+                }
+            };
+            sender.send(Some(loaded_file));
+        });
+        self.pending_file_to_load = Some(promise);
+    }
+
+    /// Lists the files that couldn't be opened, until the user dismisses them.
+    fn show_load_errors(&mut self, ctx: &egui::Context) {
+        if self.load_errors.is_empty() {
+            return;
+        }
+
+        let mut is_dismissed = false;
+        Window::new("Could not open file")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                for error in &self.load_errors {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                is_dismissed = ui.button("OK").clicked();
+            });
+
+        if is_dismissed {
+            self.load_errors.clear();
+        }
+    }
+
+    /// Parses each of the given files, in parallel on native.
+    fn parse_files(files: Vec<FileHandleWrapper>) -> Vec<LoadedFile> {
+        let parse = |file: FileHandleWrapper| (file.file_name.clone(), ParsedStageDef::parse_catching_panics(file));
+
+        #[cfg(target_arch = "wasm32")]
+        let loaded_files = files.into_iter().map(parse).collect();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let loaded_files = std::thread::scope(|scope| {
+            let handles: Vec<_> = files.into_iter().map(|file| scope.spawn(move || parse(file))).collect();
+            // A parser panic only fails the file it happened on, so the threads themselves don't panic
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Stagedef parsing thread panicked"))
+                .collect()
+        });
+
+        loaded_files
     }
 
     /// Creates a promise for loading of files from a file picker.
     ///
     /// Spawns a new thread on native, otherwise handles asyncronously on Wasm32.
-    fn get_promise_from_file_dialog(filter_type: MkbFileType) -> Promise<Option<FileHandleWrapper>> {
-        let filter = MkbFileType::get_rfd_extension_filter(&filter_type);
-
+    fn get_promise_from_file_dialog(filter_type: MkbFileType) -> Promise<Option<Vec<LoadedFile>>> {
+        // Parsing is done here too, before the promise resolves
         #[cfg(target_arch = "wasm32")]
-        let promise = Promise::spawn_async(async {
-            let file_dialog = AsyncFileDialog::new().add_filter(filter.0, filter.1).pick_file().await;
-            if let Some(f) = file_dialog {
-                Some(FileHandleWrapper::new(f, filter_type).await)
-            } else {
-                None
-            }
+        let promise = Promise::spawn_async(async move {
+            MkbViewerApp::read_files_from_dialog(filter_type)
+                .await
+                .map(MkbViewerApp::parse_files)
         });
 
         #[cfg(not(target_arch = "wasm32"))]
-        let promise = Promise::spawn_thread("get_file_from_dialog_native", || {
-            let file_dialog_future = async {
-                let file_dialog = AsyncFileDialog::new().add_filter(filter.0, filter.1).pick_file().await;
-                if let Some(f) = file_dialog {
-                    Some(FileHandleWrapper::new(f, filter_type).await)
-                } else {
-                    None
-                }
-            };
-            block_on(file_dialog_future)
+        let promise = Promise::spawn_thread("get_file_from_dialog_native", move || {
+            block_on(MkbViewerApp::read_files_from_dialog(filter_type)).map(MkbViewerApp::parse_files)
         });
 
         promise
     }
 
+    /// Opens a file picker, and reads every file picked. Returns ``None`` if nothing was picked.
+    async fn read_files_from_dialog(filter_type: MkbFileType) -> Option<Vec<FileHandleWrapper>> {
+        let filter = MkbFileType::get_rfd_extension_filter(&filter_type);
+        let file_dialog = AsyncFileDialog::new().add_filter(filter.0, filter.1).pick_files().await?;
+
+        let mut files = Vec::with_capacity(file_dialog.len());
+        for f in file_dialog {
+            files.push(FileHandleWrapper::new(f, filter_type).await);
+        }
+        Some(files)
+    }
+
     /// Handle the central widget's panel, which will display something depending on whether or not
     /// a stagedef is loaded.
     // TODO: On 'Loading' state, we need to display a button that allows users to cancel loading.
@@ -152,6 +446,496 @@ impl MkbViewerApp {
             CentralWidgetState::NoStagedefLoaded
         }
     }
+
+    /// Displays the contents of a [StageDefInstance], which includes the menu bar, the tree/inspector
+    /// side panel, and the 3D viewport.
+    ///
+    /// This is shared between the floating window and tabbed layouts.
+    ///
+    /// ``other_instances`` lists the IDs and names of every other open instance. If the user picks
+    /// one of them to compare against, its ID is returned.
+    fn show_instance_contents(
+        viewer: &mut StageDefInstance,
+        other_instances: &[(InstanceId, String)],
+        ui: &mut egui::Ui,
+    ) -> Option<InstanceId> {
+        let mut compare_with = None;
+        let instance_rect = ui.max_rect();
+
+        // TODO: Actual menu options
+        egui::TopBottomPanel::top("stagedef_instance_menu_bar").show_inside(ui, |ui| {
+            egui::menu::bar(ui, |ui| {
+                viewer.show_thumbnail_icon(ui);
+                ui.menu_button("Viewport", |ui| {
+                    viewer.camera_settings.show_ui(ui);
+                    if ui.button("Move Camera to Start").clicked() {
+                        viewer.move_camera_to_start_position();
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    viewer.ui_state.collision_render_settings.show_ui(ui);
+                    ui.separator();
+                    viewer.ui_state.layer_settings.show_ui(ui);
+                    ui.separator();
+                    viewer.ui_state.lod_settings.show_ui(ui);
+                    ui.separator();
+                    viewer.ui_state.gizmo_settings.show_ui(ui);
+                });
+                for (index, preset) in CameraPreset::ALL.into_iter().enumerate() {
+                    let hover_text = format!("Frame the whole stage from this view ({})", index + 1);
+                    if ui.button(preset.get_name()).on_hover_text(hover_text).clicked() {
+                        viewer.view_camera_preset(preset);
+                    }
+                }
+
+                ui.separator();
+                viewer.ui_state.placement_palette.show_ui(ui);
+                ui.separator();
+                ui.menu_button("Units", |ui| viewer.ui_state.unit_settings.show_ui(ui));
+                ui.menu_button("Rename View", |ui| viewer.show_rename_ui(ui));
+                #[cfg(not(target_arch = "wasm32"))]
+                if ui.button("Reload").on_hover_text("Read the file from disk again (F5)").clicked() {
+                    viewer.request_reload();
+                }
+                viewer.show_export_selection_button(ui);
+                #[cfg(not(target_arch = "wasm32"))]
+                ui.menu_button("Export", |ui| {
+                    if ui.button("STL").on_hover_text("Save the collision as a binary STL").clicked() {
+                        viewer.save_collision_stl();
+                        ui.close_menu();
+                    }
+                    if ui.button("CSV").on_hover_text("Save a CSV of each kind of object to a folder").clicked() {
+                        viewer.save_csv();
+                        ui.close_menu();
+                    }
+                });
+
+                ui.checkbox(&mut viewer.ui_state.show_hex_viewer, "Hex Viewer");
+                ui.checkbox(&mut viewer.ui_state.show_raw_header, "Raw Header");
+                ui.checkbox(&mut viewer.ui_state.show_minimap, "Minimap");
+                let issue_count = viewer.get_validation_issues().len();
+                ui.checkbox(&mut viewer.ui_state.show_validation, format!("Validation ({issue_count})"));
+
+                let non_finite_count = viewer.geometry.non_finite_component_count;
+                if non_finite_count > 0 {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {non_finite_count} NaN/infinite values"))
+                        .on_hover_text("Some positions in this stagedef aren't finite numbers. They're drawn at 0 instead.");
+                }
+
+                ui.menu_button("Debug", |ui| {
+                    ui.checkbox(&mut viewer.ui_state.show_debug_overlay, "Show Debug Overlay");
+                });
+
+                ui.weak(viewer.get_file_size_text());
+                let file_usage = viewer.stagedef.file_usage;
+                match file_usage.get_warning() {
+                    Some(warning) => {
+                        ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {file_usage}"))
+                            .on_hover_text(warning);
+                    }
+                    None => {
+                        ui.weak(file_usage.to_string());
+                    }
+                }
+
+                ui.menu_button("Compare with...", |ui| {
+                    if other_instances.is_empty() {
+                        ui.label("No other stagedefs are open");
+                    }
+                    for (id, name) in other_instances {
+                        if ui.button(name).clicked() {
+                            compare_with = Some(*id);
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+        });
+
+        viewer.show_autosave_prompt(ui.ctx());
+        viewer.show_deletion_confirmation(ui.ctx());
+        #[cfg(not(target_arch = "wasm32"))]
+        viewer.show_reload_confirmation(ui.ctx());
+        #[cfg(not(target_arch = "wasm32"))]
+        viewer.show_reload_error(ui.ctx());
+        #[cfg(not(target_arch = "wasm32"))]
+        viewer.poll_pending_reload();
+        // Nothing can be edited while a reload is in flight, as it'd be thrown away when it lands
+        let is_reloading = viewer.is_reloading();
+        if is_reloading {
+            ui.ctx().request_repaint();
+        }
+        viewer.apply_pending_deletion();
+        viewer.apply_pending_move();
+
+        // Copied out now, as the tree borrows the stagedef until the inspector is drawn
+        let mut picked_triangle = viewer.get_picked_triangle();
+        let mut is_picked_triangle_edited = false;
+        let mut pasted_json = None;
+        let mut is_pointer_over_inspector = false;
+
+        // Side panel containing tree/inspector
+        egui::SidePanel::left("stagedef_instance_side_panel")
+            .resizable(true)
+            .show_inside(ui, |ui| {
+                ui.set_enabled(!is_reloading);
+                let mut open_inspector_items = Vec::new();
+                // Stagedef tree view
+                egui::TopBottomPanel::top("stagedef_instance_side_panel_container_u")
+                    .exact_height(ui.available_height() * 0.75)
+                    .show_inside(ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            ui.allocate_space(vec2(ui.available_width(), 0.0));
+                            viewer.ui_state.display_tree_and_inspector(
+                                &mut viewer.stagedef,
+                                viewer.game,
+                                &mut open_inspector_items,
+                                ui,
+                            );
+                        });
+
+                        // Objects copied as JSON can be pasted with Ctrl+V while the pointer is over the tree, as
+                        // long as nothing else with keyboard focus would take the paste
+                        let can_paste = !is_reloading && ui.memory().focus().is_none();
+                        if can_paste && ui.rect_contains_pointer(ui.max_rect()) {
+                            pasted_json = ui.input().events.iter().find_map(|event| match event {
+                                egui::Event::Paste(text) => Some(text.clone()),
+                                _ => None,
+                            });
+                        }
+                        viewer.ui_state.show_paste_error(ui);
+
+                        // Unselect if we click outside of the tree
+                        if ui.allocate_response(ui.available_size(), egui::Sense::click()).clicked() {
+                            viewer.ui_state.selected_tree_items.clear();
+                        }
+                    });
+
+                // Inspector for selected
+                viewer.ui_state.unit_settings.store(ui.ctx());
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.allocate_space(vec2(ui.available_width(), 0.0));
+                    ui.horizontal(|ui| {
+                        ui.strong("Inspector");
+                        ui.checkbox(&mut viewer.ui_state.show_relative_positions, "Relative to start")
+                            .on_hover_text("Also show object positions relative to the start position");
+                        ui.checkbox(&mut viewer.ui_state.show_raw_bytes, "Raw bytes")
+                            .on_hover_text("Also show the bytes each object was read from");
+                    });
+                    let show_relative_positions = viewer.ui_state.show_relative_positions;
+                    let show_raw_bytes = viewer.ui_state.show_raw_bytes;
+                    let mut inspectable_count = open_inspector_items.len();
+
+                    for inspectable in open_inspector_items {
+                        inspectable_count -= 1;
+                        inspectable.field.inspect_mut(&inspectable.label, ui);
+                        if show_relative_positions {
+                            inspectable.show_relative_position(ui);
+                        }
+                        if show_raw_bytes {
+                            inspectable.show_raw_bytes(ui, &viewer.file.buffer);
+                        }
+                        description::show_description(ui, &inspectable.label, inspectable.description);
+                        inspectable.show_references(ui);
+                        if inspectable_count > 0 {
+                            ui.separator();
+                        }
+                    }
+
+                    if let Some(picked_triangle) = &mut picked_triangle {
+                        ui.separator();
+                        is_picked_triangle_edited = picked_triangle.show_ui(ui);
+                    }
+                    is_pointer_over_inspector = ui.ui_contains_pointer();
+                });
+            });
+
+        viewer.check_inspector_edits(ui.ctx(), is_pointer_over_inspector);
+        if let Some(pasted_json) = pasted_json {
+            viewer.paste_json(&pasted_json);
+        }
+
+        if is_picked_triangle_edited {
+            if let Some(picked_triangle) = &picked_triangle {
+                viewer.set_picked_triangle(picked_triangle);
+            }
+        }
+
+        // Raw bytes of the file, below the viewport
+        if viewer.ui_state.show_hex_viewer {
+            egui::TopBottomPanel::bottom("stagedef_instance_hex_viewer")
+                .resizable(true)
+                .show_inside(ui, |ui| viewer.show_hex_viewer(ui));
+        }
+
+        // Annotated file header, for files in formats we don't recognise
+        if viewer.ui_state.show_raw_header {
+            egui::TopBottomPanel::bottom("stagedef_instance_raw_header")
+                .resizable(true)
+                .show_inside(ui, |ui| viewer.show_raw_header(ui));
+        }
+
+        // Problems found in the stagedef, below the viewport
+        if viewer.ui_state.show_validation {
+            egui::TopBottomPanel::bottom("stagedef_instance_validation")
+                .resizable(true)
+                .show_inside(ui, |ui| viewer.show_validation_panel(ui));
+        }
+
+        // Animation preview timeline, directly below the viewport
+        if viewer.get_animation_duration() > 0.0 {
+            egui::TopBottomPanel::bottom("stagedef_instance_animation_timeline")
+                .show_inside(ui, |ui| viewer.show_animation_timeline(ui));
+        }
+
+        // Top-down overview, to the right of the viewport
+        if viewer.ui_state.show_minimap {
+            egui::SidePanel::right("stagedef_instance_minimap")
+                .resizable(true)
+                .default_width(250.0)
+                .show_inside(ui, |ui| viewer.show_minimap(ui));
+        }
+
+        // 3D renderer
+        // TODO: Once we have collision triangle stuff imported, pass the stagedef into the
+        // renderer (or maybe just the triangles?? somehow idk) and render collision
+        let instance_id = viewer.id;
+        let camera_settings = viewer.camera_settings;
+        let conveyor_arrows = ConveyorArrow::from_collision_headers(&viewer.stagedef.collision_headers);
+        let start_marker = viewer
+            .ui_state
+            .layer_settings
+            .start_position
+            .then(|| StartMarker::from_start_position(&viewer.stagedef.start_position));
+        let collision_render_settings = viewer.ui_state.collision_render_settings;
+        let lod_settings = viewer.ui_state.lod_settings;
+        let gizmo_settings = viewer.ui_state.gizmo_settings;
+        let camera_move = viewer.pending_camera_move.take();
+        let camera_preset = viewer.pending_camera_preset.take();
+        let animation_rotations = viewer.get_animation_preview_rotations();
+        let render_stats = viewer.render_stats.clone();
+        let view_projection = viewer.viewport_view_projection.clone();
+        let renderer_error = viewer.renderer_error.clone();
+        // Clear to the same color as other canvases so the viewport follows the theme
+        let clear_color = ui.visuals().extreme_bg_color;
+
+        // Animate over real time rather than per frame, so camera moves take the same time at any frame rate
+        let dt = ui.ctx().input().stable_dt;
+        if viewer.camera_animation_time_left > 0.0 {
+            viewer.camera_animation_time_left -= dt;
+            ui.ctx().request_repaint();
+        }
+
+        egui::Frame::canvas(ui.style())
+            .outer_margin(Margin::symmetric(5.0, 5.0))
+            .show(ui, |ui| {
+                let (rect, response) = ui.allocate_at_least(ui.max_rect().size(), egui::Sense::click_and_drag());
+
+                // Everything else works without the renderer, so show why it's missing in its place
+                let error = renderer_error.lock().unwrap().clone();
+                if let Some(error) = error {
+                    let mut should_retry = false;
+                    ui.allocate_ui_at_rect(rect.shrink(8.0), |ui| {
+                        ui.vertical_centered(|ui| {
+                            ui.heading("3D preview unavailable");
+                            ui.label(error);
+                            should_retry = ui.button("Retry").clicked();
+                        });
+                    });
+                    if should_retry {
+                        *renderer_error.lock().unwrap() = None;
+                    }
+                    return;
+                }
+
+                // Turns a point on screen into normalized device coordinates, from -1 to 1 with +Y up
+                let to_viewport_point = |point: egui::Pos2| {
+                    let x = (point.x - rect.left()) / rect.width() * 2.0 - 1.0;
+                    let y = 1.0 - (point.y - rect.top()) / rect.height() * 2.0;
+                    [x, y]
+                };
+
+                // Dragging an arrow of the translate gizmo moves the selected object along its axis
+                match (response.dragged() && !is_reloading, response.interact_pointer_pos()) {
+                    (true, Some(pointer_pos)) if response.drag_started() => {
+                        viewer.start_translate_drag(to_viewport_point(pointer_pos))
+                    }
+                    (true, Some(pointer_pos)) => viewer.drag_translate_gizmo(to_viewport_point(pointer_pos)),
+                    _ => viewer.ui_state.translate_drag = None,
+                }
+                let translate_gizmo = (!is_reloading)
+                    .then(|| viewer.get_translate_gizmo(response.hover_pos().map(to_viewport_point)))
+                    .flatten();
+                let geometry = viewer.geometry.clone();
+
+                let callback = egui::PaintCallback {
+                    rect,
+                    callback: Arc::new(egui_glow::CallbackFn::new(move |info, painter| {
+                        let result = renderer::with_three_d(painter.gl(), instance_id, |renderer| {
+                            renderer.set_camera_settings(camera_settings);
+                            renderer.set_clear_color(clear_color);
+                            if let Some(camera_move) = camera_move {
+                                renderer.animate_camera(camera_move);
+                            }
+                            renderer.update_camera(dt);
+                            renderer.set_conveyor_arrows(&conveyor_arrows);
+                            renderer.set_start_marker(start_marker);
+                            renderer.set_translate_gizmo(translate_gizmo);
+                            renderer.load_stagedef(&geometry);
+                            if let Some(camera_preset) = camera_preset {
+                                renderer.view_preset(camera_preset);
+                            }
+                            renderer.set_collision_header_rotations(&animation_rotations);
+                            renderer.set_lod_settings(lod_settings);
+                            renderer.set_gizmo_settings(gizmo_settings);
+                            renderer.render(
+                                FrameInput::new(&renderer.context, &info, painter),
+                                collision_render_settings,
+                            );
+                            *render_stats.lock().unwrap() = renderer.get_render_stats();
+                            *view_projection.lock().unwrap() = Some(renderer.get_view_projection());
+                        });
+                        if let Err(e) = result {
+                            event!(Level::WARN, "3D preview unavailable: {e}");
+                            *renderer_error.lock().unwrap() = Some(e.to_string());
+                        }
+                    })),
+                };
+
+                ui.painter().add(callback);
+
+                // Clicking places an object of the kind picked in the palette, or otherwise picks the collision
+                // triangle under the pointer, unless the stagedef is about to be replaced or the click was on the
+                // translate gizmo
+                let clicked = response.clicked() && !is_reloading;
+                let is_gizmo_clicked = translate_gizmo.map_or(false, |gizmo| gizmo.active_axis.is_some());
+                if let (true, false, Some(pointer_pos)) = (clicked, is_gizmo_clicked, response.interact_pointer_pos()) {
+                    let point = to_viewport_point(pointer_pos);
+                    if viewer.ui_state.placement_palette.selected.is_some() {
+                        viewer.place_object_at(point);
+                    } else {
+                        viewer.pick_collision_triangle(point);
+                    }
+                }
+                if response.hovered() && ui.input_mut().consume_key(Modifiers::NONE, Key::Escape) {
+                    viewer.ui_state.placement_palette.selected = None;
+                }
+
+                if viewer.ui_state.show_debug_overlay {
+                    ui.allocate_ui_at_rect(rect.shrink(8.0), |ui| {
+                        egui::Frame::popup(ui.style()).show(ui, |ui| viewer.show_debug_overlay(ui));
+                    });
+                }
+            });
+
+        if is_reloading {
+            let dim_color = ui.visuals().extreme_bg_color.linear_multiply(0.6);
+            ui.painter_at(instance_rect).rect_filled(instance_rect, 0.0, dim_color);
+            let spinner_rect = egui::Rect::from_center_size(instance_rect.center(), vec2(32.0, 32.0));
+            ui.put(spinner_rect, egui::Spinner::new().size(32.0));
+        }
+
+        compare_with
+    }
+
+    /// Returns the ID and name of every loaded instance except the given one.
+    fn get_other_instances(&self, id: InstanceId) -> Vec<(InstanceId, String)> {
+        self.stagedef_viewers
+            .iter()
+            .filter(|viewer| viewer.id != id)
+            .map(|viewer| (viewer.id, viewer.get_title()))
+            .collect()
+    }
+
+    /// Displays a window listing the differences between the two instances being compared, if any.
+    fn show_comparison_window(&mut self, ctx: &egui::Context) {
+        let Some((before_id, after_id)) = self.comparison else {
+            return;
+        };
+
+        let before = self.stagedef_viewers.iter().find(|viewer| viewer.id == before_id);
+        let after = self.stagedef_viewers.iter().find(|viewer| viewer.id == after_id);
+        let (Some(before), Some(after)) = (before, after) else {
+            // One of the instances was closed
+            self.comparison = None;
+            self.comparison_diff = None;
+            return;
+        };
+
+        let key = [(before_id, before.get_edit_count()), (after_id, after.get_edit_count())];
+        let diff = match &mut self.comparison_diff {
+            Some((diff_key, diff)) if *diff_key == key => diff,
+            comparison_diff => &comparison_diff.insert((key, before.stagedef.diff(&after.stagedef))).1,
+        };
+
+        let mut is_open = true;
+        egui::Window::new(format!("{} vs. {}", before.get_title(), after.get_title()))
+            .id(egui::Id::new("mkbviewer_comparison_window"))
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    diff.show_ui(ui);
+                });
+            });
+
+        if !is_open {
+            self.comparison = None;
+            self.comparison_diff = None;
+        }
+    }
+
+    /// Displays a tab bar with one tab per loaded [StageDefInstance], and shows the selected
+    /// instance in the central panel.
+    fn show_instance_tabs(&mut self, ctx: &egui::Context) {
+        self.selected_tab = self.selected_tab.min(self.stagedef_viewers.len() - 1);
+
+        TopBottomPanel::top("mkbviewer_instance_tabs").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (index, viewer) in self.stagedef_viewers.iter_mut().enumerate() {
+                    let response = ui.selectable_label(self.selected_tab == index, viewer.get_title());
+                    if response.on_hover_ui(|ui| viewer.show_thumbnail(ui)).clicked() {
+                        self.selected_tab = index;
+                    }
+                    if ui.small_button("x").clicked() {
+                        viewer.is_active = false;
+                    }
+                    ui.separator();
+                }
+            });
+        });
+
+        let other_instances = self.get_other_instances(self.stagedef_viewers[self.selected_tab].id);
+        let viewer = &mut self.stagedef_viewers[self.selected_tab];
+        let viewer_id = viewer.id;
+        CentralPanel::default().show(ctx, |ui| {
+            if let Some(other_id) = MkbViewerApp::show_instance_contents(viewer, &other_instances, ui) {
+                self.comparison = Some((viewer_id, other_id));
+            }
+        });
+    }
+}
+
+/// The parts of [MkbViewerApp] that are saved between sessions.
+#[cfg(feature = "persistence")]
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+#[serde(default)]
+struct PersistedAppState {
+    layout: InstanceLayout,
+    selected_tab: usize,
+    autosave_settings: AutosaveSettings,
+    theme: Theme,
+    instances: Vec<PersistedInstance>,
+}
+
+/// A reference to a previously opened [StageDefInstance], along with its view settings.
+#[cfg(feature = "persistence")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PersistedInstance {
+    file_name: String,
+    file_path: Option<PathBuf>,
+    camera_settings: CameraSettings,
+    #[serde(default)]
+    custom_title: Option<String>,
 }
 
 /// The state of the central widget, used to display a message indicating the status.
@@ -168,30 +952,126 @@ impl Default for CentralWidgetState {
     }
 }
 
+/// How loaded [StageDefInstance] structs are laid out.
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum InstanceLayout {
+    /// Each instance is displayed in its own floating window.
+    #[default]
+    FloatingWindows,
+    /// One instance is displayed at a time in the central panel, with a tab bar to switch between them.
+    Tabs,
+}
+
 impl eframe::App for MkbViewerApp {
+    #[cfg(feature = "persistence")]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.get_persisted_state());
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.applied_theme != Some(self.theme) {
+            ctx.set_visuals(self.theme.get_visuals());
+            self.applied_theme = Some(self.theme);
+        }
+
         self.poll_pending_file();
+        self.show_load_errors(ctx);
+
+        // Actions chosen from the menus or command palette this frame
+        let mut pending_action = self.command_palette.show(ctx);
+
+        // Global shortcuts. These are ignored while a file dialog is open, or while a text field has
+        // focus so typing isn't interrupted.
+        let can_use_shortcuts = self.pending_file_to_load.is_none() && ctx.memory().focus().is_none();
+        // Ctrl+O, or Cmd+O on mac
+        if can_use_shortcuts && ctx.input_mut().consume_key(Modifiers::COMMAND, Key::O) {
+            pending_action = Some(Action::OpenFile);
+        }
+        if can_use_shortcuts && ctx.input_mut().consume_key(Modifiers::NONE, Key::Delete) {
+            pending_action = Some(Action::DeleteSelection);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if can_use_shortcuts && ctx.input_mut().consume_key(Modifiers::NONE, Key::F5) {
+            pending_action = Some(Action::ReloadFocusedInstance);
+        }
+        // 1-4 for the camera presets, in the order they're shown in the viewport toolbar
+        for (key, preset) in [Key::Num1, Key::Num2, Key::Num3, Key::Num4].into_iter().zip(CameraPreset::ALL) {
+            if can_use_shortcuts && ctx.input_mut().consume_key(Modifiers::NONE, key) {
+                pending_action = Some(Action::ViewCameraPreset(preset));
+            }
+        }
 
         // Menubar
         TopBottomPanel::top("mkbviewer_menubar").show(ctx, |ui| {
-            ui.menu_button("File", |ui| {
-                if ui.button(" Open...").clicked() {
-                    event!(Level::INFO, "Opening file");
-                    self.open_file_dialog(MkbFileType::StagedefType);
-                }
+            egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button(" Open...").clicked() {
+                        pending_action = Some(Action::OpenFile);
+                    }
 
-                // Can't quit on web...
-                #[cfg(not(target_arch = "wasm32"))]
-                ui.add(Separator::default().spacing(0.0));
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button(" Open Containing Folder").clicked() {
+                        pending_action = Some(Action::OpenContainingFolder);
+                    }
 
-                #[cfg(not(target_arch = "wasm32"))]
-                if ui.button(" Quit").clicked() {
-                    event!(Level::INFO, "Quitting...");
-                    frame.close();
-                }
+                    ui.menu_button(" Autosave", |ui| self.autosave_settings.show_ui(ui));
+
+                    // Can't quit on web...
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.add(Separator::default().spacing(0.0));
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if ui.button(" Quit").clicked() {
+                        pending_action = Some(Action::Quit);
+                    }
+                });
+
+                ui.menu_button("View", |ui| {
+                    if ui
+                        .radio(self.layout == InstanceLayout::FloatingWindows, "Floating windows")
+                        .clicked()
+                    {
+                        pending_action = Some(Action::UseFloatingWindowLayout);
+                    }
+                    if ui.radio(self.layout == InstanceLayout::Tabs, "Tabs").clicked() {
+                        pending_action = Some(Action::UseTabbedLayout);
+                    }
+                    ui.separator();
+                    for theme in Theme::ALL {
+                        if ui.radio(self.theme == theme, theme.get_name()).clicked() {
+                            pending_action = Some(Action::UseTheme(theme));
+                        }
+                    }
+                });
+
+                ui.menu_button("Window", |ui| {
+                    let has_instances = !self.stagedef_viewers.is_empty();
+                    if ui.add_enabled(has_instances, Button::new("Close")).clicked() {
+                        pending_action = Some(Action::CloseFocusedInstance);
+                        ui.close_menu();
+                    }
+                    if ui.add_enabled(has_instances, Button::new("Close All")).clicked() {
+                        pending_action = Some(Action::CloseAllInstances);
+                        ui.close_menu();
+                    }
+                    let has_others = self.stagedef_viewers.len() > 1;
+                    if ui.add_enabled(has_others, Button::new("Close Others")).clicked() {
+                        pending_action = Some(Action::CloseOtherInstances);
+                        ui.close_menu();
+                    }
+                });
+
+                ui.menu_button("Debug", |ui| {
+                    ui.menu_button("Log Level", logging::show_log_level_ui);
+                });
             });
         });
 
+        if let Some(action) = pending_action {
+            self.dispatch_action(action, frame);
+        }
+
         // Toolbar
         TopBottomPanel::top("mkbviewer_toolbar").min_height(32.0).show(ctx, |ui| {
             ui.horizontal_centered(|ui| {
@@ -199,90 +1079,79 @@ impl eframe::App for MkbViewerApp {
             });
         });
 
-        // Central panel
-        MkbViewerApp::get_central_widget_frame(self, ctx);
-
-        // Get rid of inactive instances
+        // Get rid of inactive instances, along with their renderers' GPU resources
+        let instance_count = self.stagedef_viewers.len();
         self.stagedef_viewers.retain(|v| v.is_active);
+        if self.stagedef_viewers.len() < instance_count {
+            let open_instances: Vec<InstanceId> = self.stagedef_viewers.iter().map(|viewer| viewer.id).collect();
+            let dropped_count = renderer::drop_closed_renderers(&open_instances);
+            event!(Level::DEBUG, "Dropped {dropped_count} renderers of closed instances");
+        }
 
-        // Iterate over stagedef instances and display their respective windows
-        for viewer in self.stagedef_viewers.iter_mut() {
-            // Handle whether or not the window is closed. We do this to avoid borrowing the entire
-            // struct just to mutate this, we'll check if this is modified later on
-            let mut is_open = viewer.is_active;
+        match self.layout {
+            InstanceLayout::FloatingWindows => {
+                // Central panel
+                MkbViewerApp::get_central_widget_frame(self, ctx);
 
-            let window = egui::Window::new(viewer.get_filename()).constrain(true).open(&mut is_open);
+                // Iterate over stagedef instances and display their respective windows
+                let all_instances: Vec<(InstanceId, String)> = self
+                    .stagedef_viewers
+                    .iter()
+                    .map(|viewer| (viewer.id, viewer.get_title()))
+                    .collect();
 
-            window.show(ctx, |ui| {
-                // TODO: Actual menu options
-                egui::TopBottomPanel::top("stagedef_instance_menu_bar").show_inside(ui, |ui| {
-                    ui.label("Menu bar");
-                });
+                for viewer in self.stagedef_viewers.iter_mut() {
+                    // Handle whether or not the window is closed. We do this to avoid borrowing the entire
+                    // struct just to mutate this, we'll check if this is modified later on
+                    let mut is_open = viewer.is_active;
 
-                // Side panel containing tree/inspector
-                egui::SidePanel::left("stagedef_instance_side_panel")
-                    .resizable(true)
-                    .show_inside(ui, |ui| {
-                        let mut open_inspector_items = Vec::new();
-                        // Stagedef tree view
-                        egui::TopBottomPanel::top("stagedef_instance_side_panel_container_u")
-                            .exact_height(ui.available_height() * 0.75)
-                            .show_inside(ui, |ui| {
-                                egui::ScrollArea::vertical().show(ui, |ui| {
-                                    ui.allocate_space(vec2(ui.available_width(), 0.0));
-                                    viewer.ui_state.display_tree_and_inspector(
-                                        &mut viewer.stagedef,
-                                        &mut open_inspector_items,
-                                        ui,
-                                    );
-                                });
-
-                                // Unselect if we click outside of the tree
-                                if ui.allocate_response(ui.available_size(), egui::Sense::click()).clicked() {
-                                    viewer.ui_state.selected_tree_items.clear();
-                                }
-                            });
+                    // Identified by instance rather than title, so views with the same name don't share
+                    // window state
+                    let window = egui::Window::new(viewer.get_title())
+                        .id(egui::Id::new(("stagedef_instance_window", viewer.id)))
+                        .constrain(true)
+                        .open(&mut is_open);
 
-                        // Inspector for selected
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            ui.allocate_space(vec2(ui.available_width(), 0.0));
-                            ui.strong("Inspector");
-                            let mut inspectable_count = open_inspector_items.len();
-
-                            for inspectable in open_inspector_items {
-                                inspectable_count -= 1;
-                                let (field, label, description) = inspectable;
-                                field.inspect_mut(&label, ui);
-                                ui.label(description);
-                                if inspectable_count > 0 {
-                                    ui.separator();
-                                }
-                            }
-                        });
+                    let other_instances: Vec<(InstanceId, String)> =
+                        all_instances.iter().filter(|(id, _)| *id != viewer.id).cloned().collect();
+                    let mut compare_with = None;
+
+                    let window_response = window.show(ctx, |ui| {
+                        compare_with = MkbViewerApp::show_instance_contents(viewer, &other_instances, ui);
                     });
 
-                // 3D renderer
-                // TODO: Once we have collision triangle stuff imported, pass the stagedef into the
-                // renderer (or maybe just the triangles?? somehow idk) and render collision
-                egui::Frame::canvas(ui.style())
-                    .outer_margin(Margin::symmetric(5.0, 5.0))
-                    .show(ui, |ui| {
-                        let (rect, response) = ui.allocate_at_least(ui.max_rect().size(), egui::Sense::drag());
-
-                        let callback = egui::PaintCallback {
-                            rect,
-                            callback: Arc::new(egui_glow::CallbackFn::new(move |info, painter| {
-                                renderer::with_three_d(painter.gl(), |renderer| {
-                                    renderer.render(FrameInput::new(&renderer.context, &info, painter));
-                                })
-                            })),
-                        };
-
-                        ui.painter().add(callback);
-                    })
-            });
+                    if let Some(other_id) = compare_with {
+                        self.comparison = Some((viewer.id, other_id));
+                    }
+
+                    // Track which window was last clicked, so actions know which instance to apply to
+                    if let Some(window_response) = window_response {
+                        if window_response.response.hovered() && ctx.input().pointer.any_pressed() {
+                            self.focused_instance = Some(viewer.id);
+                        }
+                    }
+
+                    viewer.is_active = is_open;
+                }
+            }
+            InstanceLayout::Tabs => {
+                if self.stagedef_viewers.is_empty() {
+                    MkbViewerApp::get_central_widget_frame(self, ctx);
+                } else {
+                    self.show_instance_tabs(ctx);
+                }
+            }
+        }
+
+        self.show_comparison_window(ctx);
 
-            viewer.is_active = is_open;
+        let mut storage = frame.storage_mut();
+        for viewer in &mut self.stagedef_viewers {
+            viewer.update_autosave(&self.autosave_settings, storage.as_deref_mut());
+        }
+        // Wake up to autosave even if nothing else happens
+        if self.autosave_settings.enabled && !self.stagedef_viewers.is_empty() {
+            ctx.request_repaint_after(std::time::Duration::from_secs_f32(self.autosave_settings.interval_seconds));
         }
     }
 }
@@ -293,7 +1162,12 @@ impl eframe::App for MkbViewerApp {
 #[derive(Debug, Default)]
 pub struct FileHandleWrapper {
     pub buffer: Vec<u8>,
+    /// The size of the file on disk, if it was compressed and [``decompress``](Self::decompress)
+    /// has been run.
+    pub compressed_size: Option<usize>,
     pub file_name: String,
+    /// The location of the file on disk. Only available on native.
+    pub file_path: Option<PathBuf>,
     pub file_type: MkbFileType,
 }
 
@@ -305,8 +1179,55 @@ impl FileHandleWrapper {
 
         Self {
             buffer,
+            compressed_size: None,
             // TODO: Verify that this works with non-UTF8 filenames
             file_name: fh.file_name(),
+            #[cfg(not(target_arch = "wasm32"))]
+            file_path: Some(fh.path().to_path_buf()),
+            #[cfg(target_arch = "wasm32")]
+            file_path: None,
+            file_type,
+        }
+    }
+
+    /// Reads a file directly from the given path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_path(path: &Path, file_type: MkbFileType) -> std::io::Result<Self> {
+        let buffer = std::fs::read(path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(Self {
+            buffer,
+            compressed_size: None,
+            file_name,
+            file_path: Some(path.to_path_buf()),
+            file_type,
+        })
+    }
+
+    /// Replaces the buffer with its decompressed contents, if it's compressed.
+    ///
+    /// This can take a while for large files, so it's left to whoever parses the file rather than
+    /// being done as soon as it's read.
+    pub fn decompress(mut self) -> Result<Self, LzError> {
+        let decompressed = compression::decompress(std::mem::take(&mut self.buffer))?;
+        if decompressed.compression != Compression::None {
+            self.compressed_size = Some(decompressed.compressed_size);
+        }
+        self.buffer = decompressed.buffer;
+        Ok(self)
+    }
+
+    /// Wraps a file that was read some other way, like fetched from a URL.
+    pub fn from_buffer(buffer: Vec<u8>, file_name: String, file_type: MkbFileType) -> Self {
+        Self {
+            buffer,
+            compressed_size: None,
+            file_name,
+            file_path: None,
             file_type,
         }
     }
@@ -321,10 +1242,20 @@ impl FileHandleWrapper {
     }
 }
 
+/// Returns the last part of a URL's path, without any query or fragment, to use as a file name.
+#[cfg(target_arch = "wasm32")]
+fn get_file_name_from_url(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match path.trim_end_matches('/').rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => url.to_string(),
+    }
+}
+
 /// Represents which type of file we are expecting from a file picker.
 ///
 /// By default, this will be a [``StagedefType``](MkbFileType::StagedefType).
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum MkbFileType {
     StagedefType,
     WsModConfigType,
@@ -0,0 +1,122 @@
+//! Displays the descriptions of stagedef objects and fields in the inspector.
+//!
+//! Descriptions are plain strings with a small subset of markdown, so longer explanations stay
+//! readable: each line is shown on its own, blank lines add space, lines starting with ``- `` are
+//! bullet points, and text can be made ``**bold**`` or `` `code` ``.
+use egui::{RichText, Ui};
+use std::hash::Hash;
+
+/// A run of text with a single style.
+#[derive(Debug, PartialEq)]
+enum Span<'a> {
+    Text(&'a str),
+    Bold(&'a str),
+    Code(&'a str),
+}
+
+/// A single line of a description.
+#[derive(Debug, PartialEq)]
+enum Line<'a> {
+    Blank,
+    Paragraph(Vec<Span<'a>>),
+    Bullet(Vec<Span<'a>>),
+}
+
+fn parse_line(line: &str) -> Line<'_> {
+    let line = line.trim();
+    if line.is_empty() {
+        Line::Blank
+    } else if let Some(bullet) = line.strip_prefix("- ") {
+        Line::Bullet(parse_spans(bullet))
+    } else {
+        Line::Paragraph(parse_spans(line))
+    }
+}
+
+/// Splits text into styled spans. Markers without a matching closing marker are left as text.
+fn parse_spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let next_marker = [("**", Span::Bold as fn(&str) -> Span), ("`", Span::Code)]
+            .into_iter()
+            .filter_map(|(marker, make_span)| {
+                let start = rest.find(marker)?;
+                let end = rest[start + marker.len()..].find(marker)? + start + marker.len();
+                Some((start, end, marker.len(), make_span))
+            })
+            .min_by_key(|(start, ..)| *start);
+
+        let Some((start, end, marker_length, make_span)) = next_marker else {
+            spans.push(Span::Text(rest));
+            break;
+        };
+
+        if start > 0 {
+            spans.push(Span::Text(&rest[..start]));
+        }
+        spans.push(make_span(&rest[start + marker_length..end]));
+        rest = &rest[end + marker_length..];
+    }
+
+    spans
+}
+
+fn show_spans(ui: &mut Ui, spans: &[Span]) {
+    ui.horizontal_wrapped(|ui| {
+        ui.spacing_mut().item_spacing.x = 0.0;
+        for span in spans {
+            match span {
+                Span::Text(text) => ui.label(*text),
+                Span::Bold(text) => ui.label(RichText::new(*text).strong()),
+                Span::Code(text) => ui.label(RichText::new(*text).code()),
+            };
+        }
+    });
+}
+
+/// Shows a description under a collapsible header, open by default.
+pub fn show_description(ui: &mut Ui, id_source: impl Hash, description: &str) {
+    egui::CollapsingHeader::new("Description")
+        .id_source(id_source)
+        .default_open(true)
+        .show(ui, |ui| {
+            for line in description.lines().map(parse_line) {
+                match line {
+                    Line::Blank => ui.add_space(ui.spacing().item_spacing.y * 2.0),
+                    Line::Paragraph(spans) => show_spans(ui, &spans),
+                    Line::Bullet(spans) => {
+                        ui.horizontal(|ui| {
+                            ui.label("•");
+                            show_spans(ui, &spans);
+                        });
+                    }
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        // Plain, one line descriptions are a single paragraph of text
+        assert_eq!(parse_line("A bumper."), Line::Paragraph(vec![Span::Text("A bumper.")]));
+        assert_eq!(parse_line("  "), Line::Blank);
+
+        assert_eq!(
+            parse_line("- **Seesaw**: tilts with the `ball`"),
+            Line::Bullet(vec![
+                Span::Bold("Seesaw"),
+                Span::Text(": tilts with the "),
+                Span::Code("ball"),
+            ])
+        );
+
+        // Unclosed markers are kept as text
+        assert_eq!(parse_spans("2 ** 3 `x"), vec![Span::Text("2 ** 3 `x")]);
+    }
+}
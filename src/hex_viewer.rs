@@ -0,0 +1,232 @@
+//! A read-only hex dump of a file's raw bytes, for comparing parsed objects to the data behind them
+use egui::text::LayoutJob;
+use egui::{Color32, FontId, TextFormat, TextStyle, Ui};
+use std::ops::Range;
+
+/// How many bytes are shown on each row.
+const BYTES_PER_ROW: usize = 16;
+
+/// Displays a hex dump of a buffer, highlighting the bytes of whatever is selected.
+#[derive(Default)]
+pub struct HexViewer {
+    /// The highlighted range we last scrolled to, so we only scroll when the selection changes.
+    scrolled_to: Option<Range<usize>>,
+}
+
+impl HexViewer {
+    /// Shows the hex dump of ``buffer``, with the bytes in ``highlight`` highlighted.
+    ///
+    /// When the highlighted range changes, the view is scrolled so it's visible.
+    pub fn show(&mut self, ui: &mut Ui, buffer: &[u8], highlight: Option<Range<usize>>) {
+        let font_id = TextStyle::Monospace.resolve(ui.style());
+        let row_height = ui.text_style_height(&TextStyle::Monospace);
+        let row_count = (buffer.len() + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+
+        let mut scroll_area = egui::ScrollArea::vertical()
+            .id_source("hex_viewer")
+            .auto_shrink([false, false]);
+
+        if highlight != self.scrolled_to {
+            if let Some(range) = &highlight {
+                let row = range.start / BYTES_PER_ROW;
+                scroll_area = scroll_area.vertical_scroll_offset(row as f32 * (row_height + ui.spacing().item_spacing.y));
+            }
+            self.scrolled_to = highlight.clone();
+        }
+
+        scroll_area.show_rows(ui, row_height, row_count, |ui, rows| {
+            for row in rows {
+                let mut job = LayoutJob::default();
+                for (text, highlighted) in get_row_sections(buffer, row, highlight.as_ref()) {
+                    job.append(&text, 0.0, get_text_format(ui, &font_id, highlighted));
+                }
+                ui.label(job);
+            }
+        });
+    }
+}
+
+/// Shows a hex dump of ``buffer`` with the name of each field next to the row it starts in, and
+/// the bytes of every other field highlighted so neighbouring fields can be told apart.
+///
+/// Meant for short buffers such as file headers, so every row is shown at once.
+pub fn show_annotated(ui: &mut Ui, buffer: &[u8], fields: &[(&str, Range<usize>)]) {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let row_count = (buffer.len() + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+
+    egui::ScrollArea::vertical()
+        .id_source("annotated_hex_viewer")
+        .auto_shrink([false, false])
+        .show(ui, |ui| {
+            for row in 0..row_count {
+                let mut job = LayoutJob::default();
+                for (text, highlighted) in get_annotated_row_sections(buffer, row, fields) {
+                    job.append(&text, 0.0, get_text_format(ui, &font_id, highlighted));
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label(job);
+                    ui.weak(get_row_annotation(row, fields));
+                });
+            }
+        });
+}
+
+/// Shows a short hex dump of ``bytes`` without scrolling, with each row's offset counted from the
+/// first byte, so they line up with the offsets objects' fields are documented at.
+pub fn show_inline(ui: &mut Ui, bytes: &[u8]) {
+    let font_id = TextStyle::Monospace.resolve(ui.style());
+    let row_count = (bytes.len() + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+
+    for row in 0..row_count {
+        let mut job = LayoutJob::default();
+        for (text, highlighted) in get_row_sections(bytes, row, None) {
+            job.append(&text, 0.0, get_text_format(ui, &font_id, highlighted));
+        }
+        ui.label(job);
+    }
+}
+
+fn get_text_format(ui: &Ui, font_id: &FontId, highlighted: bool) -> TextFormat {
+    let visuals = &ui.style().visuals;
+    TextFormat {
+        font_id: font_id.clone(),
+        color: if highlighted {
+            visuals.strong_text_color()
+        } else {
+            visuals.text_color()
+        },
+        background: if highlighted {
+            visuals.selection.bg_fill
+        } else {
+            Color32::TRANSPARENT
+        },
+        ..Default::default()
+    }
+}
+
+/// Splits a row of the hex dump into pieces of text, each marked with whether it should be
+/// highlighted.
+///
+/// A row is laid out as the offset of its first byte, then the bytes in hex, then the bytes as
+/// ASCII, with anything unprintable shown as a ``.``.
+fn get_row_sections(buffer: &[u8], row: usize, highlight: Option<&Range<usize>>) -> Vec<(String, bool)> {
+    let row_start = row * BYTES_PER_ROW;
+    let row_end = (row_start + BYTES_PER_ROW).min(buffer.len());
+    let is_highlighted = |offset: usize| highlight.map_or(false, |range| range.contains(&offset));
+
+    let mut sections = vec![(format!("{row_start:08X}  "), false)];
+
+    for offset in row_start..row_start + BYTES_PER_ROW {
+        let text = match buffer.get(offset) {
+            Some(byte) if offset < row_end => format!("{byte:02X}"),
+            _ => "  ".to_string(),
+        };
+        sections.push((text, is_highlighted(offset) && offset < row_end));
+        // Keep the gaps between highlighted bytes highlighted too, so ranges read as one block
+        sections.push((" ".to_string(), is_highlighted(offset) && is_highlighted(offset + 1) && offset + 1 < row_end));
+    }
+
+    sections.push((" ".to_string(), false));
+
+    for (offset, byte) in buffer.iter().enumerate().take(row_end).skip(row_start) {
+        let character = if byte.is_ascii_graphic() || *byte == b' ' {
+            char::from(*byte)
+        } else {
+            '.'
+        };
+        sections.push((character.to_string(), is_highlighted(offset)));
+    }
+
+    sections
+}
+
+/// Splits a row of the hex dump into pieces of text like [``get_row_sections``], highlighting the
+/// bytes of every other field.
+fn get_annotated_row_sections(buffer: &[u8], row: usize, fields: &[(&str, Range<usize>)]) -> Vec<(String, bool)> {
+    let row_range = row * BYTES_PER_ROW..(row + 1) * BYTES_PER_ROW;
+    let highlighted_fields = fields
+        .iter()
+        .step_by(2)
+        .map(|(_, range)| range)
+        .filter(|range| range.start < row_range.end && range.end > row_range.start);
+
+    // Each highlighted field is done as its own pass, then merged
+    let mut sections = get_row_sections(buffer, row, None);
+    for range in highlighted_fields {
+        for (section, (_, highlighted)) in get_row_sections(buffer, row, Some(range)).into_iter().zip(&mut sections) {
+            *highlighted |= section.1;
+        }
+    }
+    sections
+}
+
+/// Returns the names of the fields that start in the given row, separated by commas.
+fn get_row_annotation(row: usize, fields: &[(&str, Range<usize>)]) -> String {
+    let row_range = row * BYTES_PER_ROW..(row + 1) * BYTES_PER_ROW;
+    fields
+        .iter()
+        .filter(|(_, range)| row_range.contains(&range.start))
+        .map(|(name, range)| format!("{:X}: {name}", range.start))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn to_text(sections: &[(String, bool)]) -> String {
+        sections.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    fn highlighted_text(sections: &[(String, bool)]) -> String {
+        sections
+            .iter()
+            .filter(|(_, highlighted)| *highlighted)
+            .map(|(text, _)| text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_row_sections() {
+        let buffer: Vec<u8> = (0x40..0x54).collect();
+
+        let first_row = get_row_sections(&buffer, 0, Some(&(0x2..0x4)));
+        assert_eq!(
+            to_text(&first_row),
+            "00000000  40 41 42 43 44 45 46 47 48 49 4A 4B 4C 4D 4E 4F  @ABCDEFGHIJKLMNO"
+        );
+        assert_eq!(highlighted_text(&first_row), "42 43BC");
+
+        // The last row is padded so the ASCII column lines up
+        let last_row = get_row_sections(&buffer, 1, None);
+        assert_eq!(
+            to_text(&last_row),
+            "00000010  50 51 52 53                                      PQRS"
+        );
+        assert_eq!(highlighted_text(&last_row), "");
+    }
+
+    #[test]
+    fn test_row_sections_unprintable() {
+        let buffer = [0x00, 0x0A, 0x7F, 0x41];
+        let row = get_row_sections(&buffer, 0, None);
+        assert!(to_text(&row).ends_with("...A"));
+    }
+
+    #[test]
+    fn test_annotated_row() {
+        let buffer = [0; 0x20];
+        let fields = [("first", 0x0..0x4), ("second", 0x4..0xC), ("third", 0xC..0x14)];
+
+        assert_eq!(get_row_annotation(0, &fields), "0: first, 4: second, C: third");
+        assert_eq!(get_row_annotation(1, &fields), "");
+
+        // Every other field is highlighted, including where it carries over into the next row
+        let first_row = get_annotated_row_sections(&buffer, 0, &fields);
+        assert_eq!(highlighted_text(&first_row), "00 00 00 0000 00 00 00........");
+        let second_row = get_annotated_row_sections(&buffer, 1, &fields);
+        assert_eq!(highlighted_text(&second_row), "00 00 00 00....");
+    }
+}
@@ -0,0 +1,30 @@
+//! A viewer and editor for Monkey Ball stage files written in Rust that runs on native platforms
+//! as well as on the web.
+//!
+//! The viewer is behind the default ``gui`` feature. Building with ``--no-default-features``
+//! leaves just [``stagedef``]'s parser and object definitions, without egui or three-d.
+#![allow(dead_code)]
+#![allow(unused_imports)]
+
+#[macro_use]
+extern crate num_derive;
+
+#[cfg(feature = "gui")]
+pub mod actions;
+#[cfg(feature = "gui")]
+pub mod app;
+#[cfg(feature = "gui")]
+pub mod description;
+#[cfg(feature = "gui")]
+pub mod hex_viewer;
+#[cfg(feature = "gui")]
+pub mod logging;
+#[cfg(feature = "gui")]
+pub mod minimap;
+#[cfg(feature = "gui")]
+pub mod renderer;
+pub mod stagedef;
+#[cfg(feature = "gui")]
+pub mod theme;
+#[cfg(feature = "wasm-api")]
+mod wasm_api;
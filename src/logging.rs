@@ -0,0 +1,61 @@
+//! Sets up logging, and lets the log level be changed while the app is running.
+use std::sync::Mutex;
+use tracing::{warn, Level};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+/// The log levels that can be picked from the menu, from most to least verbose.
+pub const LOG_LEVELS: [Level; 5] = [Level::TRACE, Level::DEBUG, Level::INFO, Level::WARN, Level::ERROR];
+
+/// Lets us swap out the level filter of the global subscriber after it's been installed.
+static RELOAD_HANDLE: Mutex<Option<reload::Handle<LevelFilter, Registry>>> = Mutex::new(None);
+
+/// Installs the global subscriber, logging to stdout at the given level.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init(level: Level) {
+    let (filter, handle) = reload::Layer::new(LevelFilter::from_level(level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+    *RELOAD_HANDLE.lock().unwrap() = Some(handle);
+}
+
+/// Installs the global subscriber, logging to the browser console at the given level.
+#[cfg(target_arch = "wasm32")]
+pub fn init(level: Level) {
+    // The WASM layer lets everything through, so the reloadable filter alone decides what's logged
+    let (filter, handle) = reload::Layer::new(LevelFilter::from_level(level));
+    let wasm_layer = tracing_wasm::WASMLayer::new(tracing_wasm::WASMLayerConfigBuilder::new().build());
+    tracing_subscriber::registry().with(filter).with(wasm_layer).init();
+    *RELOAD_HANDLE.lock().unwrap() = Some(handle);
+}
+
+/// Returns the level currently being logged at, or ``None`` if logging hasn't been set up.
+pub fn get_log_level() -> Option<Level> {
+    let handle = RELOAD_HANDLE.lock().unwrap();
+    handle.as_ref()?.clone_current()?.into_level()
+}
+
+/// Changes the level being logged at. Does nothing if logging hasn't been set up.
+pub fn set_log_level(level: Level) {
+    if let Some(handle) = RELOAD_HANDLE.lock().unwrap().as_ref() {
+        if let Err(err) = handle.reload(LevelFilter::from_level(level)) {
+            // The previous level is still in place, so this is logged at that level
+            warn!("Failed to change log level: {err}");
+        }
+    }
+}
+
+/// Displays a radio button for each log level, and changes the level when one is picked.
+pub fn show_log_level_ui(ui: &mut egui::Ui) {
+    let current_level = get_log_level();
+    for level in LOG_LEVELS {
+        if ui.radio(current_level == Some(level), level.as_str()).clicked() {
+            set_log_level(level);
+            tracing::info!("Log level set to {level}");
+            ui.close_menu();
+        }
+    }
+}
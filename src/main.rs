@@ -1,32 +1,30 @@
-//! A viewer and editor for Monkey Ball stage files written in Rust that runs on native platforms
-//! as well as on the web.
-#![allow(dead_code)]
-#![allow(unused_imports)]
-
-#[macro_use]
-extern crate num_derive;
-
-mod app;
-mod renderer;
-mod stagedef;
-
+//! Starts the viewer, natively or in the browser. Everything else lives in the library.
+use mkbviewer::{app, logging};
 use tracing::Level;
-/// Verbosity of console logs.
+/// Verbosity of console logs at startup. Can be changed from the Debug menu.
 const LOG_LEVEL: Level = Level::DEBUG;
 
 // Not web
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
 #[cfg(not(target_arch = "wasm32"))]
 fn main() {
-    // Log to stdout (if you run with `RUST_LOG=debug`).
-    //let log_config = tracing_subscriber::fmt::format().
-    //
-    tracing_subscriber::fmt().with_max_level(LOG_LEVEL).init();
+    // Log to stdout
+    logging::init(LOG_LEVEL);
+
+    // Every argument is a file to open, e.g. when launched through a file association
+    let paths: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
 
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "MKBViewer",
         native_options,
-        Box::new(|cc| Box::new(app::MkbViewerApp::new(cc))),
+        Box::new(move |cc| {
+            let mut app = app::MkbViewerApp::new(cc);
+            app.open_paths(&paths);
+            Box::new(app)
+        }),
     );
 }
 
@@ -40,9 +38,7 @@ fn main() {
 
     console_error_panic_hook::set_once();
 
-    let log_config = tracing_wasm::WASMLayerConfigBuilder::new().set_max_level(LOG_LEVEL).build();
-
-    tracing_wasm::set_as_global_default_with_config(log_config);
+    logging::init(LOG_LEVEL);
 
     let web_options = eframe::WebOptions::default();
 
@@ -50,7 +46,14 @@ fn main() {
         eframe::start_web(
             "mkbviewer_canvas",
             web_options,
-            Box::new(|cc| Box::new(app::MkbViewerApp::new(cc))),
+            Box::new(|cc| {
+                let mut app = app::MkbViewerApp::new(cc);
+                // Open the stagedef at ?url=, if there is one
+                if let Some(url) = cc.integration_info.web_info.location.query_map.get("url") {
+                    app.open_url(url.clone());
+                }
+                Box::new(app)
+            }),
         )
         .await
         .expect("failed to start eframe");
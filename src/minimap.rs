@@ -0,0 +1,296 @@
+//! A top-down view of a stage, drawn with egui's painter rather than the GPU
+use crate::renderer::{ObjectMarker, StageGeometry};
+use egui::{Color32, ColorImage, Pos2, Rect, Sense, Shape, Stroke, Ui, Vec2};
+use three_d::Vec3;
+
+/// The range the minimap can be zoomed in or out by, relative to fitting the whole stage.
+const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.1..=50.0;
+/// How much of the minimap the stage takes up when it's fit to the view.
+const FIT_MARGIN: f32 = 0.9;
+
+/// The background and collision colors of thumbnails. Thumbnails are made once and shown in any
+/// theme, so these don't follow the visuals.
+const THUMBNAIL_BACKGROUND_COLOR: Color32 = Color32::from_rgb(27, 27, 27);
+const THUMBNAIL_COLLISION_COLOR: Color32 = Color32::from_rgb(140, 140, 140);
+
+/// A 2D projection of a stage's collision and objects onto the XZ plane, seen from above.
+///
+/// +X points right and +Z points down, so the stage faces the same way as it does from the default
+/// camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minimap {
+    /// How far zoomed in we are, where 1.0 fits the whole stage.
+    zoom: f32,
+    /// How far the view has been dragged, in points.
+    pan: Vec2,
+}
+
+impl Default for Minimap {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
+impl Minimap {
+    /// Shows the minimap of ``geometry``, filling the available space.
+    ///
+    /// Dragging pans, scrolling zooms around the cursor, and clicking returns the clicked point on
+    /// the XZ plane.
+    pub fn show(&mut self, ui: &mut Ui, geometry: &StageGeometry) -> Option<[f32; 2]> {
+        ui.horizontal(|ui| {
+            ui.label(format!("Zoom: {:.1}x", self.zoom));
+            if ui.button("Reset").clicked() {
+                *self = Self::default();
+            }
+        });
+
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let rect = response.rect;
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        let Some(bounds) = get_xz_bounds(geometry) else {
+            painter.text(
+                rect.center(),
+                egui::Align2::CENTER_CENTER,
+                "Nothing to show",
+                egui::FontId::default(),
+                ui.visuals().weak_text_color(),
+            );
+            return None;
+        };
+
+        if response.dragged() {
+            self.pan += response.drag_delta();
+        }
+
+        if let Some(hover_pos) = response.hover_pos() {
+            let scroll = ui.ctx().input().scroll_delta.y;
+            if scroll != 0.0 {
+                // Keep the point under the cursor where it is
+                let projection = self.get_projection(rect, bounds);
+                let hovered_point = projection.to_world(hover_pos);
+                self.zoom = (self.zoom * (scroll * 0.005).exp()).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+                self.pan += hover_pos - self.get_projection(rect, bounds).to_screen(hovered_point);
+            }
+        }
+
+        let projection = self.get_projection(rect, bounds);
+        let painter = painter.with_clip_rect(rect);
+
+        let collision_stroke = Stroke::new(1.0, ui.visuals().weak_text_color());
+        // Non-finite positions would make egui's tessellator produce garbage, so they're left out
+        for triangle in geometry.iter_collision_triangles().filter(|t| t.iter().all(is_finite_xz)) {
+            let points = triangle.iter().map(|v| projection.to_screen([v.x, v.z])).collect();
+            painter.add(Shape::closed_line(points, collision_stroke));
+        }
+
+        for (markers, radius) in [(&geometry.goals, 4.0), (&geometry.bananas, 2.0)] {
+            for marker in markers.iter().filter(|m| is_finite_xz(&m.position)) {
+                let center = projection.to_screen([marker.position.x, marker.position.z]);
+                painter.circle_filled(center, radius, get_marker_color(marker));
+            }
+        }
+
+        if response.clicked() {
+            return response.interact_pointer_pos().map(|pos| projection.to_world(pos));
+        }
+
+        None
+    }
+
+    fn get_projection(&self, rect: Rect, bounds: Rect) -> MinimapProjection {
+        MinimapProjection::fit(rect, bounds, self.zoom, self.pan)
+    }
+}
+
+/// Draws the whole stage from above into a square image ``size`` pixels wide, like a minimap that
+/// can't be moved.
+///
+/// This is done on the CPU so it can be made once when a stagedef loads, rather than drawn every
+/// frame.
+pub fn render_thumbnail(geometry: &StageGeometry, size: usize) -> ColorImage {
+    let mut image = ColorImage::new([size, size], THUMBNAIL_BACKGROUND_COLOR);
+    let Some(bounds) = get_xz_bounds(geometry) else {
+        return image;
+    };
+
+    let rect = Rect::from_min_size(Pos2::ZERO, Vec2::splat(size as f32));
+    let projection = MinimapProjection::fit(rect, bounds, 1.0, Vec2::ZERO);
+
+    for triangle in geometry.iter_collision_triangles().filter(|t| t.iter().all(is_finite_xz)) {
+        let points = triangle.map(|v| projection.to_screen([v.x, v.z]));
+        for (index, from) in points.iter().enumerate() {
+            draw_thumbnail_line(&mut image, *from, points[(index + 1) % 3], THUMBNAIL_COLLISION_COLOR);
+        }
+    }
+
+    for (markers, radius) in [(&geometry.goals, 1), (&geometry.bananas, 0)] {
+        for marker in markers.iter().filter(|m| is_finite_xz(&m.position)) {
+            let center = projection.to_screen([marker.position.x, marker.position.z]);
+            for x in -radius..=radius {
+                for y in -radius..=radius {
+                    set_thumbnail_pixel(&mut image, center + Vec2::new(x as f32, y as f32), get_marker_color(marker));
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/// Draws a one pixel wide line between two points in the image.
+fn draw_thumbnail_line(image: &mut ColorImage, from: Pos2, to: Pos2, color: Color32) {
+    let delta = to - from;
+    let steps = delta.x.abs().max(delta.y.abs()).ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        set_thumbnail_pixel(image, from + delta * (step as f32 / steps as f32), color);
+    }
+}
+
+/// Colors the pixel the given point is in, if it's inside the image.
+fn set_thumbnail_pixel(image: &mut ColorImage, point: Pos2, color: Color32) {
+    let [width, height] = image.size;
+    if point.x < 0.0 || point.y < 0.0 {
+        return;
+    }
+    let (x, y) = (point.x as usize, point.y as usize);
+    if x < width && y < height {
+        image.pixels[y * width + x] = color;
+    }
+}
+
+/// Maps between positions on the XZ plane and positions on screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MinimapProjection {
+    /// The point on the XZ plane at the center of the screen rect.
+    world_center: Pos2,
+    screen_center: Pos2,
+    /// Points per game unit.
+    scale: f32,
+}
+
+impl MinimapProjection {
+    /// Returns a projection that fits ``bounds`` (on the XZ plane) into ``rect``, then applies the
+    /// given zoom and pan.
+    fn fit(rect: Rect, bounds: Rect, zoom: f32, pan: Vec2) -> Self {
+        // Avoid dividing by 0 for stages that are a single point or line
+        let size = bounds.size().max(Vec2::splat(1.0));
+        let fit_scale = (rect.width() / size.x).min(rect.height() / size.y) * FIT_MARGIN;
+
+        Self {
+            world_center: bounds.center(),
+            screen_center: rect.center() + pan,
+            scale: fit_scale * zoom,
+        }
+    }
+
+    fn to_screen(self, [x, z]: [f32; 2]) -> Pos2 {
+        self.screen_center + (Pos2::new(x, z) - self.world_center) * self.scale
+    }
+
+    fn to_world(self, screen_pos: Pos2) -> [f32; 2] {
+        let world = self.world_center + (screen_pos - self.screen_center) / self.scale;
+        [world.x, world.y]
+    }
+}
+
+/// Returns the bounds of everything in the geometry on the XZ plane, with Z as the rect's Y, or
+/// ``None`` if there's nothing in it.
+fn get_xz_bounds(geometry: &StageGeometry) -> Option<Rect> {
+    let triangle_points = geometry.iter_collision_triangles().flatten();
+    let marker_points = geometry.goals.iter().chain(&geometry.bananas).map(|marker| &marker.position);
+
+    triangle_points
+        .chain(marker_points)
+        .filter(|v| is_finite_xz(v))
+        .map(|v| Pos2::new(v.x, v.z))
+        .fold(None, |bounds: Option<Rect>, point| match bounds {
+            Some(bounds) => Some(bounds.union(Rect::from_min_max(point, point))),
+            None => Some(Rect::from_min_max(point, point)),
+        })
+}
+
+fn is_finite_xz(v: &Vec3) -> bool {
+    v.x.is_finite() && v.z.is_finite()
+}
+
+fn get_marker_color(marker: &ObjectMarker) -> Color32 {
+    let color = marker.color;
+    Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_projection_round_trip() {
+        let rect = Rect::from_min_size(Pos2::new(10.0, 20.0), Vec2::new(200.0, 100.0));
+        let bounds = Rect::from_min_max(Pos2::new(-50.0, -200.0), Pos2::new(50.0, 0.0));
+        let projection = MinimapProjection::fit(rect, bounds, 2.5, Vec2::new(30.0, -15.0));
+
+        for point in [[0.0, 0.0], [-50.0, -200.0], [12.5, -73.25]] {
+            let [x, z] = projection.to_world(projection.to_screen(point));
+            assert!((x - point[0]).abs() < 1e-3 && (z - point[1]).abs() < 1e-3, "{point:?}");
+        }
+    }
+
+    #[test]
+    fn test_projection_fits_bounds() {
+        let rect = Rect::from_min_size(Pos2::ZERO, Vec2::new(200.0, 100.0));
+        let bounds = Rect::from_min_max(Pos2::new(-50.0, -200.0), Pos2::new(50.0, 0.0));
+        let projection = MinimapProjection::fit(rect, bounds, 1.0, Vec2::ZERO);
+
+        // The stage is taller than it is wide, so its height limits the scale
+        assert_eq!(projection.to_screen([0.0, -100.0]), rect.center());
+        let top = projection.to_screen([0.0, -200.0]);
+        let bottom = projection.to_screen([0.0, 0.0]);
+        assert!((bottom.y - top.y - 100.0 * FIT_MARGIN).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_xz_bounds() {
+        use crate::renderer::CollisionHeaderGeometry;
+        use three_d::vec3;
+
+        assert_eq!(get_xz_bounds(&StageGeometry::default()), None);
+
+        let geometry = StageGeometry {
+            collision_headers: vec![CollisionHeaderGeometry {
+                triangles: vec![[vec3(-1.0, 5.0, 2.0), vec3(3.0, -5.0, -4.0), vec3(f32::NAN, 0.0, 100.0)]],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(
+            get_xz_bounds(&geometry),
+            Some(Rect::from_min_max(Pos2::new(-1.0, -4.0), Pos2::new(3.0, 2.0)))
+        );
+    }
+
+    #[test]
+    fn test_render_thumbnail() {
+        use crate::renderer::CollisionHeaderGeometry;
+        use three_d::vec3;
+
+        let empty = render_thumbnail(&StageGeometry::default(), 16);
+        assert_eq!(empty.size, [16, 16]);
+        assert!(empty.pixels.iter().all(|p| *p == THUMBNAIL_BACKGROUND_COLOR));
+
+        let geometry = StageGeometry {
+            collision_headers: vec![CollisionHeaderGeometry {
+                triangles: vec![[vec3(-10.0, 0.0, -10.0), vec3(10.0, 0.0, -10.0), vec3(0.0, 0.0, 10.0)]],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let thumbnail = render_thumbnail(&geometry, 16);
+        assert!(thumbnail.pixels.contains(&THUMBNAIL_COLLISION_COLOR));
+        // The triangle is only outlined, and the margin around the stage is left empty
+        assert_eq!(thumbnail.pixels[8 * 16 + 8], THUMBNAIL_BACKGROUND_COLOR);
+        assert_eq!(thumbnail.pixels[15 * 16], THUMBNAIL_BACKGROUND_COLOR);
+    }
+}
@@ -1,27 +1,97 @@
+use crate::stagedef::common::{intersect_ray_triangle, StageDef, Vector3};
+use crate::stagedef::instance::InstanceId;
+use crate::stagedef::objects::{BananaType, CollisionHeader, StartPosition};
 use eframe::egui_glow;
 use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
 use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
 use three_d::renderer::geometry::CpuMesh;
-use three_d::{degrees, vec3, Camera, ClearState, Color, ColorMaterial, Context, Gm, Mesh, Viewport};
+use tracing::warn;
+use three_d::{
+    degrees, rotation_matrix_from_dir_to_dir, vec3, Blend, Camera, ClearState, Color, ColorMaterial, Context, Cull,
+    DepthTest, Gm, InnerSpace, InstancedMesh, Instances, Mat4, Mesh, Object, Positions, RenderStates, SquareMatrix, Vec3,
+    Viewport, WriteMask,
+};
 
-/// Gives us a [Renderer] object to do render-y stuff with
-/// src: https://github.com/emilk/egui/blob/master/examples/custom_3d_three-d/src/main.rs
-pub fn with_three_d<R>(gl: &std::sync::Arc<glow::Context>, f: impl FnOnce(&mut Renderer) -> R) -> R {
-    thread_local! {
-        pub static THREE_D: RefCell<Option<Renderer>> = RefCell::new(None);
-    }
+/// Why a [Renderer] couldn't be created.
+#[derive(Debug, Error)]
+pub enum RendererError {
+    /// three-d couldn't use the GL context, e.g. because the browser or driver doesn't support a
+    /// feature it needs.
+    #[error("Could not create a 3D context: {0}")]
+    Context(#[from] three_d::CoreError),
+}
+
+thread_local! {
+    /// Every instance's [Renderer], kept on the thread egui paints on, as that's where the GL
+    /// context is.
+    static THREE_D: RefCell<HashMap<InstanceId, Renderer>> = RefCell::new(HashMap::new());
+}
 
+/// Gives us the [Renderer] object belonging to the given instance to do render-y stuff with
+///
+/// Each instance gets its own [Renderer], so cameras and loaded geometry are independent between
+/// windows. If the renderer can't be created, ``f`` isn't called, and the error is returned
+/// instead. Nothing is kept for the instance in that case, so the next call tries again.
+/// src: https://github.com/emilk/egui/blob/master/examples/custom_3d_three-d/src/main.rs
+pub fn with_three_d<R>(
+    gl: &std::sync::Arc<glow::Context>,
+    instance_id: InstanceId,
+    f: impl FnOnce(&mut Renderer) -> R,
+) -> Result<R, RendererError> {
     THREE_D.with(|three_d| {
         let mut three_d = three_d.borrow_mut();
-        let three_d = three_d.get_or_insert_with(|| Renderer::new(gl.clone()));
-        f(three_d)
+        let three_d = try_get_or_insert_keyed(&mut three_d, instance_id, || Renderer::new(gl.clone()))?;
+        Ok(f(three_d))
     })
 }
 
+/// Drops the renderer of every instance that isn't in ``open_instances``, freeing the meshes it
+/// uploaded to the GPU. Returns how many were dropped.
+///
+/// This must be called on the thread egui paints on. eframe runs ``update`` there too, with the GL
+/// context current, so calling it from there is fine.
+pub fn drop_closed_renderers(open_instances: &[InstanceId]) -> usize {
+    THREE_D.with(|three_d| retain_keys(&mut three_d.borrow_mut(), open_instances))
+}
+
+/// Removes every entry whose key isn't in ``keys``, returning how many were removed.
+fn retain_keys<K: Eq + Hash, V>(map: &mut HashMap<K, V>, keys: &[K]) -> usize {
+    let original_len = map.len();
+    map.retain(|key, _| keys.contains(key));
+    original_len - map.len()
+}
+
+/// Returns the value for the given key, constructing it with ``init`` if it does not exist yet.
+/// If ``init`` fails, nothing is inserted and its error is returned.
+fn try_get_or_insert_keyed<K: Eq + Hash, V, E>(
+    map: &mut HashMap<K, V>,
+    key: K,
+    init: impl FnOnce() -> Result<V, E>,
+) -> Result<&mut V, E> {
+    match map.entry(key) {
+        Entry::Occupied(entry) => Ok(entry.into_mut()),
+        Entry::Vacant(entry) => Ok(entry.insert(init()?)),
+    }
+}
+
 ///
 /// Translates from egui input to three-d input
 /// src: https://github.com/emilk/egui/blob/master/examples/custom_3d_three-d/src/main.rs
 ///
+/// All sizes given to three-d are in physical pixels. egui lays things out in points, so anything
+/// taken from [egui::PaintCallbackInfo] must go through ``pixels_per_point`` first - the
+/// ``*_in_pixels`` helpers do this for us. Both the screen and the intermediate framebuffer cover
+/// the whole window, so the render target is always sized to the full screen in pixels, and the
+/// viewport/scissor box pick out the part we paint to.
+///
+/// Manual test matrix: native and web, at 100%, 150% and 200% display scaling, with the viewport
+/// both fully visible and partially clipped by the window edge. The rendered image should line up
+/// with the canvas frame in every case.
 pub struct FrameInput<'a> {
     screen: three_d::RenderTarget<'a>,
     viewport: three_d::Viewport,
@@ -40,29 +110,26 @@ impl FrameInput<'_> {
             context.disable(glow::FRAMEBUFFER_SRGB);
         }
 
-        // Constructs a screen render target to render the final image to
+        // Constructs a screen render target to render the final image to. This has to be the size
+        // of the whole screen in pixels, not the size of our viewport in points
+        let [screen_width, screen_height] = info.screen_size_px;
         let screen = painter.intermediate_fbo().map_or_else(
-            || RenderTarget::screen(context, info.viewport.width() as u32, info.viewport.height() as u32),
-            |fbo| RenderTarget::from_framebuffer(context, info.viewport.width() as u32, info.viewport.height() as u32, fbo),
+            || RenderTarget::screen(context, screen_width, screen_height),
+            |fbo| RenderTarget::from_framebuffer(context, screen_width, screen_height, fbo),
         );
 
         // Set where to paint
         let viewport = info.viewport_in_pixels();
-        let viewport = Viewport {
-            x: viewport.left_px.round() as _,
-            y: viewport.from_bottom_px.round() as _,
-            width: viewport.width_px.round() as _,
-            height: viewport.height_px.round() as _,
-        };
+        let (x, y, width, height) =
+            round_pixel_rect(viewport.left_px, viewport.from_bottom_px, viewport.width_px, viewport.height_px);
+        let viewport = Viewport { x, y, width, height };
 
         // Respect the egui clip region (e.g. if we are inside an `egui::ScrollArea`).
         let clip_rect = info.clip_rect_in_pixels();
-        let scissor_box = ScissorBox {
-            x: clip_rect.left_px.round() as _,
-            y: clip_rect.from_bottom_px.round() as _,
-            width: clip_rect.width_px.round() as _,
-            height: clip_rect.height_px.round() as _,
-        };
+        let (x, y, width, height) =
+            round_pixel_rect(clip_rect.left_px, clip_rect.from_bottom_px, clip_rect.width_px, clip_rect.height_px);
+        let scissor_box = ScissorBox { x, y, width, height };
+
         Self {
             screen,
             scissor_box,
@@ -71,15 +138,1140 @@ impl FrameInput<'_> {
     }
 }
 
+/// Rounds a rectangle in fractional pixels to whole pixels.
+///
+/// Each edge is rounded on its own rather than rounding the size, so that neighbouring rectangles
+/// don't end up overlapping or leaving a gap at fractional scale factors.
+fn round_pixel_rect(left: f32, bottom: f32, width: f32, height: f32) -> (i32, i32, u32, u32) {
+    let x = left.round();
+    let y = bottom.round();
+    let width = ((left + width).round() - x).max(0.0);
+    let height = ((bottom + height).round() - y).max(0.0);
+
+    (x as i32, y as i32, width as u32, height as u32)
+}
+
+/// User-configurable projection settings for a [Renderer]'s camera.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct CameraSettings {
+    /// Vertical field of view, in degrees.
+    pub fov_degrees: f32,
+    /// Distance to the near clipping plane.
+    pub near: f32,
+    /// Distance to the far clipping plane.
+    pub far: f32,
+    /// How long smooth camera moves take, in seconds.
+    pub animation_duration: f32,
+}
+
+impl CameraSettings {
+    pub const FOV_RANGE: std::ops::RangeInclusive<f32> = 10.0..=150.0;
+    pub const NEAR_RANGE: std::ops::RangeInclusive<f32> = 0.01..=100.0;
+    pub const FAR_RANGE: std::ops::RangeInclusive<f32> = 100.0..=1_000_000.0;
+    pub const ANIMATION_DURATION_RANGE: std::ops::RangeInclusive<f32> = 0.0..=5.0;
+
+    /// Returns settings with a far plane suitable for a stage whose bounding box has the given
+    /// diagonal length.
+    pub fn with_far_from_diagonal(mut self, diagonal: f32) -> Self {
+        // The camera can be anywhere within (or a bit outside) the stage, so leave some headroom
+        self.far = diagonal * 2.0;
+        self.clamped()
+    }
+
+    /// Returns a copy of these settings with every value restricted to a sensible range.
+    pub fn clamped(mut self) -> Self {
+        self.fov_degrees = self.fov_degrees.clamp(*Self::FOV_RANGE.start(), *Self::FOV_RANGE.end());
+        self.far = self.far.clamp(*Self::FAR_RANGE.start(), *Self::FAR_RANGE.end());
+        self.near = self.near.clamp(*Self::NEAR_RANGE.start(), *Self::NEAR_RANGE.end());
+        self.animation_duration = self
+            .animation_duration
+            .clamp(*Self::ANIMATION_DURATION_RANGE.start(), *Self::ANIMATION_DURATION_RANGE.end());
+        self
+    }
+
+    /// Displays editable fields for each setting.
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.fov_degrees, Self::FOV_RANGE).text("FOV"));
+        ui.add(
+            egui::DragValue::new(&mut self.near)
+                .clamp_range(Self::NEAR_RANGE)
+                .speed(0.01)
+                .prefix("Near: "),
+        );
+        ui.add(
+            egui::DragValue::new(&mut self.far)
+                .clamp_range(Self::FAR_RANGE)
+                .speed(10.0)
+                .prefix("Far: "),
+        );
+        ui.add(
+            egui::Slider::new(&mut self.animation_duration, Self::ANIMATION_DURATION_RANGE)
+                .text("Camera move duration")
+                .suffix("s"),
+        );
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            fov_degrees: 90.0,
+            near: 0.1,
+            far: 20000.0,
+            animation_duration: 0.5,
+        }
+    }
+}
+
+/// An arrow showing the direction a collision header's conveyor pushes the ball.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConveyorArrow {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl ConveyorArrow {
+    /// Length of a drawn conveyor arrow, in game units.
+    const LENGTH: f32 = 4.0;
+
+    /// Returns an arrow for every collision header with a non-zero conveyor vector, placed at the
+    /// header's center of rotation.
+    pub fn from_collision_headers(collision_headers: &[CollisionHeader]) -> Vec<Self> {
+        collision_headers
+            .iter()
+            .filter(|header| {
+                let conveyor = &header.conveyor_vector;
+                conveyor.x != 0.0 || conveyor.y != 0.0 || conveyor.z != 0.0
+            })
+            .map(|header| {
+                let origin = &header.center_of_rotation_position;
+                let conveyor = &header.conveyor_vector;
+                Self {
+                    origin: [origin.x, origin.y, origin.z],
+                    direction: [conveyor.x, conveyor.y, conveyor.z],
+                }
+            })
+            .collect()
+    }
+
+    fn get_transformation(&self) -> Mat4 {
+        let origin = Vec3::from(self.origin);
+        let direction = Vec3::from(self.direction).normalize();
+
+        // Arrow meshes point down the positive X axis by default
+        Mat4::from_translation(origin)
+            * rotation_matrix_from_dir_to_dir(vec3(1.0, 0.0, 0.0), direction)
+            * Mat4::from_scale(Self::LENGTH)
+    }
+}
+
+/// A ball and an arrow showing where the ball starts, and which way it faces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StartMarker {
+    pub position: [f32; 3],
+    /// In degrees.
+    pub rotation: [f32; 3],
+}
+
+impl StartMarker {
+    const BALL_RADIUS: f32 = 0.5;
+    const ARROW_LENGTH: f32 = 2.0;
+    const COLOR: Color = Color {
+        r: 230,
+        g: 60,
+        b: 230,
+        a: 255,
+    };
+
+    pub fn from_start_position(start_position: &StartPosition) -> Self {
+        let position = &start_position.position;
+        let rotation = Vector3::from(start_position.rotation);
+        Self {
+            position: [position.x, position.y, position.z],
+            rotation: [rotation.x, rotation.y, rotation.z],
+        }
+    }
+
+    /// Returns the direction the ball faces at the start, which is down the negative Z axis before
+    /// it's rotated.
+    fn get_forward(&self) -> Vec3 {
+        let [x, y, _] = self.rotation;
+        let rotation = Mat4::from_angle_y(degrees(y)) * Mat4::from_angle_x(degrees(x));
+        (rotation * vec3(0.0, 0.0, -1.0).extend(0.0)).truncate()
+    }
+
+    fn get_ball_transformation(&self, gizmo_scale: f32) -> Mat4 {
+        Mat4::from_translation(Vec3::from(self.position)) * Mat4::from_scale(Self::BALL_RADIUS * gizmo_scale)
+    }
+
+    fn get_arrow_transformation(&self, gizmo_scale: f32) -> Mat4 {
+        // Arrow meshes point down the positive X axis by default
+        Mat4::from_translation(Vec3::from(self.position))
+            * rotation_matrix_from_dir_to_dir(vec3(1.0, 0.0, 0.0), self.get_forward())
+            * Mat4::from_scale(Self::ARROW_LENGTH * gizmo_scale)
+    }
+}
+
+/// One of the axes the translate gizmo moves objects along.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn get_direction(&self) -> Vec3 {
+        match self {
+            GizmoAxis::X => vec3(1.0, 0.0, 0.0),
+            GizmoAxis::Y => vec3(0.0, 1.0, 0.0),
+            GizmoAxis::Z => vec3(0.0, 0.0, 1.0),
+        }
+    }
+
+    /// Returns the component of ``vector`` along this axis.
+    pub fn get_component_mut<'a>(&self, vector: &'a mut Vector3) -> &'a mut f32 {
+        match self {
+            GizmoAxis::X => &mut vector.x,
+            GizmoAxis::Y => &mut vector.y,
+            GizmoAxis::Z => &mut vector.z,
+        }
+    }
+
+    fn get_color(&self) -> Color {
+        match self {
+            GizmoAxis::X => Color::new(230, 50, 50, 255),
+            GizmoAxis::Y => Color::new(50, 200, 50, 255),
+            GizmoAxis::Z => Color::new(50, 100, 240, 255),
+        }
+    }
+}
+
+/// Three arrows along the X, Y and Z axes, drawn at the selected object so it can be moved along
+/// one of them by dragging it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TranslateGizmo {
+    pub position: [f32; 3],
+    /// How long each arrow is.
+    pub length: f32,
+    /// The arrow being dragged or hovered, which is highlighted.
+    pub active_axis: Option<GizmoAxis>,
+}
+
+impl TranslateGizmo {
+    /// How long each arrow is at a gizmo scale of 1.
+    const ARROW_LENGTH: f32 = 4.0;
+    /// How far from an arrow, relative to its length, the pointer can be and still grab it. This
+    /// is wider than the arrow is drawn, so it's easy to hit.
+    const PICK_RADIUS: f32 = 0.1;
+    const ACTIVE_COLOR: Color = Color {
+        r: 255,
+        g: 220,
+        b: 0,
+        a: 255,
+    };
+
+    pub fn new(position: [f32; 3], gizmo_scale: f32) -> Self {
+        Self {
+            position,
+            length: Self::ARROW_LENGTH * gizmo_scale,
+            active_axis: None,
+        }
+    }
+
+    /// Returns the axis of the arrow hit by the given ray, preferring the one it passes closest to
+    /// if it hits more than one.
+    pub fn pick_axis(&self, origin: Vec3, direction: Vec3) -> Option<GizmoAxis> {
+        let position = Vec3::from(self.position);
+        let pick_radius = Self::PICK_RADIUS * self.length;
+        let mut nearest: Option<(f32, GizmoAxis)> = None;
+        for axis in GizmoAxis::ALL {
+            let axis_direction = axis.get_direction();
+            let Some(parameter) = get_closest_line_parameter(position, axis_direction, origin, direction) else {
+                continue;
+            };
+            let arrow_point = position + axis_direction * parameter.clamp(0.0, self.length);
+            // The point on the ray closest to the arrow, which can't be behind the ray's start
+            let ray_point = origin + direction * (arrow_point - origin).dot(direction).max(0.0);
+            let distance = (arrow_point - ray_point).magnitude();
+            if distance <= pick_radius && nearest.map_or(true, |(nearest_distance, _)| distance < nearest_distance) {
+                nearest = Some((distance, axis));
+            }
+        }
+        nearest.map(|(_, axis)| axis)
+    }
+
+    fn get_arrow_transformation(&self, axis: GizmoAxis) -> Mat4 {
+        // Arrow meshes point down the positive X axis by default
+        Mat4::from_translation(Vec3::from(self.position))
+            * rotation_matrix_from_dir_to_dir(vec3(1.0, 0.0, 0.0), axis.get_direction())
+            * Mat4::from_scale(self.length)
+    }
+}
+
+/// Returns how far along the line through ``point`` in ``direction`` the point closest to the given
+/// ray is, or ``None`` if the line and ray are parallel. Both directions must be normalized.
+pub fn get_closest_line_parameter(point: Vec3, direction: Vec3, ray_origin: Vec3, ray_direction: Vec3) -> Option<f32> {
+    let to_point = point - ray_origin;
+    let cos_angle = direction.dot(ray_direction);
+    let denominator = 1.0 - cos_angle * cos_angle;
+    // Close to parallel, the closest point races off towards infinity
+    if denominator < 1e-4 {
+        return None;
+    }
+    Some((cos_angle * ray_direction.dot(to_point) - direction.dot(to_point)) / denominator)
+}
+
+/// Which optional layers are drawn in the viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerSettings {
+    pub start_position: bool,
+}
+
+impl LayerSettings {
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Layers");
+        ui.checkbox(&mut self.start_position, "Start position");
+    }
+}
+
+impl Default for LayerSettings {
+    fn default() -> Self {
+        Self { start_position: true }
+    }
+}
+
+/// Controls how big object gizmos are drawn, for stages much smaller or bigger than usual, and how
+/// the translate gizmo moves objects.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GizmoSettings {
+    /// Multiplies the size of every goal, banana and start position gizmo, so they keep their
+    /// sizes relative to each other.
+    pub scale: f32,
+    /// Rounds the position of objects moved with the translate gizmo to a multiple of
+    /// [``grid_size``](Self::grid_size), along the axis they're moved along.
+    pub snap_to_grid: bool,
+    pub grid_size: f32,
+}
+
+impl GizmoSettings {
+    const SCALE_RANGE: std::ops::RangeInclusive<f32> = 0.1..=10.0;
+
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.add(
+            egui::Slider::new(&mut self.scale, Self::SCALE_RANGE)
+                .logarithmic(true)
+                .text("Gizmo scale"),
+        )
+        .on_hover_text("Resize goals, bananas and the start position to suit the stage's size");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.snap_to_grid, "Snap to grid")
+                .on_hover_text("Round the positions of objects moved with the translate gizmo");
+            ui.add_enabled(
+                self.snap_to_grid,
+                egui::DragValue::new(&mut self.grid_size).clamp_range(0.01..=100.0).speed(0.1),
+            );
+        });
+    }
+
+    /// Rounds ``value`` to the grid, if snapping to it is on.
+    pub fn snap(&self, value: f32) -> f32 {
+        if self.snap_to_grid && self.grid_size > 0.0 {
+            (value / self.grid_size).round() * self.grid_size
+        } else {
+            value
+        }
+    }
+}
+
+impl Default for GizmoSettings {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            snap_to_grid: false,
+            grid_size: 1.0,
+        }
+    }
+}
+
+/// Geometry extracted from a [StageDef] for rendering.
+///
+/// This is built once when a stagedef is loaded, and shared with the renderer through an [Arc] so
+/// the renderer can tell when it needs to rebuild its meshes.
+#[derive(Default)]
+pub struct StageGeometry {
+    /// The collision triangles of each collision header, in the same order as the stagedef.
+    pub collision_headers: Vec<CollisionHeaderGeometry>,
+    pub bananas: Vec<ObjectMarker>,
+    pub goals: Vec<ObjectMarker>,
+    /// How many NaN or infinite components there are in the positions above. These are drawn at
+    /// 0 instead.
+    pub non_finite_component_count: usize,
+}
+
+impl StageGeometry {
+    pub fn from_stagedef(stagedef: &StageDef) -> Self {
+        let collision_headers = stagedef
+            .collision_headers
+            .iter()
+            .map(|header| {
+                let center = &header.center_of_rotation_position;
+                let initial_rotation = Vector3::from(header.initial_rotation);
+                CollisionHeaderGeometry {
+                    triangles: header
+                        .collision_triangles
+                        .iter()
+                        .map(|triangle| triangle.get_vertices().map(|v| vec3(v.x, v.y, v.z)))
+                        .collect(),
+                    center_of_rotation: vec3(center.x, center.y, center.z),
+                    initial_rotation: [initial_rotation.x, initial_rotation.y, initial_rotation.z],
+                }
+            })
+            .collect();
+
+        let bananas = stagedef
+            .bananas
+            .iter()
+            .map(|banana| {
+                let banana = banana.object.lock().unwrap();
+                let [r, g, b, a] = banana.banana_type.get_color().to_array();
+                let marker = ObjectMarker::new(&banana.position, Color::new(r, g, b, a));
+                match banana.banana_type {
+                    BananaType::Single => marker,
+                    BananaType::Bunch => marker.with_scale(ObjectMarker::BANANA_BUNCH_SCALE),
+                }
+            })
+            .collect();
+
+        let goals = stagedef
+            .goals
+            .iter()
+            .map(|goal| {
+                let goal = goal.object.lock().unwrap();
+                let [r, g, b, a] = goal.goal_type.get_color().to_array();
+                ObjectMarker::new(&goal.position, Color::new(r, g, b, a))
+            })
+            .collect();
+
+        let mut geometry = Self {
+            collision_headers,
+            bananas,
+            goals,
+            non_finite_component_count: 0,
+        };
+        geometry.non_finite_component_count = geometry.get_sanitized().1;
+        geometry
+    }
+
+    /// Returns the nearest collision triangle hit by the given ray, if any.
+    ///
+    /// This uses the triangles as they are in the stagedef, so animation previews are ignored.
+    pub fn pick_collision_triangle(&self, origin: Vec3, direction: Vec3) -> Option<CollisionHit> {
+        let mut nearest: Option<(f32, CollisionHit)> = None;
+        for (header_index, header) in self.collision_headers.iter().enumerate() {
+            let transformation = header.get_transformation(header.initial_rotation);
+            for (triangle_index, triangle) in header.triangles.iter().enumerate() {
+                let triangle = triangle.map(|vertex| {
+                    let vertex = (transformation * vertex.extend(1.0)).truncate();
+                    Vector3 {
+                        x: vertex.x,
+                        y: vertex.y,
+                        z: vertex.z,
+                    }
+                });
+                let Some(distance) = intersect_ray_triangle(origin.into(), direction.into(), &triangle) else {
+                    continue;
+                };
+                if nearest.map_or(true, |(nearest_distance, _)| distance < nearest_distance) {
+                    let hit = CollisionHit {
+                        header_index,
+                        triangle_index,
+                        point: origin + direction * distance,
+                    };
+                    nearest = Some((distance, hit));
+                }
+            }
+        }
+        nearest.map(|(_, hit)| hit)
+    }
+
+    /// Returns every collision triangle, from every collision header.
+    pub fn iter_collision_triangles(&self) -> impl Iterator<Item = &[Vec3; 3]> {
+        self.collision_headers.iter().flat_map(|header| header.triangles.iter())
+    }
+
+    /// Returns the minimum and maximum corners of the box containing every triangle and marker,
+    /// ignoring non-finite positions, or ``None`` if there's nothing in the geometry.
+    pub fn get_bounds(&self) -> Option<(Vec3, Vec3)> {
+        let triangle_points = self.iter_collision_triangles().flatten();
+        let marker_points = self.goals.iter().chain(&self.bananas).map(|marker| &marker.position);
+
+        triangle_points
+            .chain(marker_points)
+            .filter(|v| v.x.is_finite() && v.y.is_finite() && v.z.is_finite())
+            .fold(None, |bounds, point| match bounds {
+                Some((min, max)) => Some((
+                    vec3(point.x.min(min.x), point.y.min(min.y), point.z.min(min.z)),
+                    vec3(point.x.max(max.x), point.y.max(max.y), point.z.max(max.z)),
+                )),
+                None => Some((*point, *point)),
+            })
+    }
+
+    /// Returns a copy of the geometry with every non-finite position component replaced with 0,
+    /// along with how many components were replaced.
+    ///
+    /// Non-finite positions produce garbage on screen at best, and can crash the GPU driver at worst.
+    pub fn get_sanitized(&self) -> (Self, usize) {
+        let mut fixed_count = 0;
+
+        let collision_headers = self
+            .collision_headers
+            .iter()
+            .map(|header| CollisionHeaderGeometry {
+                triangles: header
+                    .triangles
+                    .iter()
+                    .map(|triangle| triangle.map(|vertex| sanitize_vec3(vertex, &mut fixed_count)))
+                    .collect(),
+                center_of_rotation: sanitize_vec3(header.center_of_rotation, &mut fixed_count),
+                initial_rotation: header.initial_rotation,
+            })
+            .collect();
+        let mut sanitize_markers = |markers: &[ObjectMarker]| {
+            markers
+                .iter()
+                .map(|marker| ObjectMarker {
+                    position: sanitize_vec3(marker.position, &mut fixed_count),
+                    ..*marker
+                })
+                .collect()
+        };
+        let bananas = sanitize_markers(&self.bananas);
+        let goals = sanitize_markers(&self.goals);
+
+        let sanitized = Self {
+            collision_headers,
+            bananas,
+            goals,
+            non_finite_component_count: 0,
+        };
+        (sanitized, fixed_count)
+    }
+}
+
+/// A collision triangle picked in the viewport, identified by its position in the stagedef.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionHit {
+    pub header_index: usize,
+    pub triangle_index: usize,
+    /// Where on the triangle was hit.
+    pub point: Vec3,
+}
+
+/// Returns the origin and direction of the ray going through the given point of the viewport, for
+/// a camera with the given view-projection matrix.
+///
+/// The point is in normalized device coordinates, from -1 to 1 with +Y up. The ray starts on the
+/// near plane.
+pub fn get_pick_ray(view_projection: Mat4, [x, y]: [f32; 2]) -> Option<(Vec3, Vec3)> {
+    let inverse = view_projection.invert()?;
+    let unproject = |z: f32| {
+        let point = inverse * vec3(x, y, z).extend(1.0);
+        point.truncate() / point.w
+    };
+    let near = unproject(-1.0);
+    let far = unproject(1.0);
+    Some((near, (far - near).normalize()))
+}
+
+/// The collision of a single collision header.
+#[derive(Default)]
+pub struct CollisionHeaderGeometry {
+    /// The vertices of every collision triangle in the header.
+    pub triangles: Vec<[Vec3; 3]>,
+    /// The point the header's animation rotates around.
+    pub center_of_rotation: Vec3,
+    /// The header's rotation when it isn't animated, in degrees.
+    pub initial_rotation: [f32; 3],
+}
+
+impl CollisionHeaderGeometry {
+    /// Returns the transformation that rotates the header's triangles by ``rotation``, in degrees,
+    /// around its center of rotation.
+    ///
+    /// The triangles are stored unrotated, and the center of rotation is a point in that same
+    /// space, so it stays put while everything else turns around it. An animated rotation replaces
+    /// the initial rotation rather than adding to it. Like collision triangles, the rotation is
+    /// applied around Z, then X, then Y.
+    pub fn get_transformation(&self, rotation: [f32; 3]) -> Mat4 {
+        rotation_around(self.center_of_rotation, rotation)
+    }
+}
+
+/// Returns a rotation by ``rotation``, in degrees, around ``center``, applied around Z, then X,
+/// then Y.
+fn rotation_around(center: Vec3, rotation: [f32; 3]) -> Mat4 {
+    let [x, y, z] = rotation.map(degrees);
+    Mat4::from_translation(center)
+        * Mat4::from_angle_y(y)
+        * Mat4::from_angle_x(x)
+        * Mat4::from_angle_z(z)
+        * Mat4::from_translation(-center)
+}
+
+/// Replaces any non-finite components of the given vector with 0, adding how many were replaced to
+/// ``fixed_count``.
+fn sanitize_vec3(vector: Vec3, fixed_count: &mut usize) -> Vec3 {
+    let mut sanitize = |component: f32| {
+        if component.is_finite() {
+            component
+        } else {
+            *fixed_count += 1;
+            0.0
+        }
+    };
+    vec3(sanitize(vector.x), sanitize(vector.y), sanitize(vector.z))
+}
+
+/// The position, color and size of a single object to draw as a gizmo.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ObjectMarker {
+    pub position: Vec3,
+    pub color: Color,
+    /// How big the gizmo is relative to the others of its kind.
+    pub scale: f32,
+}
+
+impl ObjectMarker {
+    /// How much bigger banana bunches are drawn than single bananas.
+    const BANANA_BUNCH_SCALE: f32 = 1.6;
+
+    fn new(position: &Vector3, color: Color) -> Self {
+        Self {
+            position: vec3(position.x, position.y, position.z),
+            color,
+            scale: 1.0,
+        }
+    }
+
+    fn with_scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+}
+
+/// Controls when object gizmos are drawn in full detail.
+///
+/// Stages with more objects than [``max_full_detail_objects``](Self::max_full_detail_objects) only
+/// draw full gizmos for objects near the camera, and draw everything else as a tiny low-poly marker.
+///
+/// Every gizmo is drawn through instancing, so a kind of object costs at most two draw calls no
+/// matter how many there are. Drawing 50k bananas as individual models would need 50k draw calls,
+/// and 50k full-detail spheres are over 25 million triangles, while their low detail markers are
+/// 600k.
+///
+/// These are counts worked out from the meshes, not measurements. No frame times have been taken
+/// with 50k objects yet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSettings {
+    /// Stages with at most this many objects of a kind draw all of them in full detail.
+    pub max_full_detail_objects: usize,
+    /// How close to the camera an object needs to be to be drawn in full detail, when the stage
+    /// has too many objects to draw them all in full detail.
+    pub full_detail_distance: f32,
+}
+
+impl LodSettings {
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Level of Detail");
+        ui.add(
+            egui::DragValue::new(&mut self.max_full_detail_objects)
+                .clamp_range(0..=100_000)
+                .prefix("Full detail object limit: "),
+        );
+        ui.add(
+            egui::DragValue::new(&mut self.full_detail_distance)
+                .clamp_range(0.0..=10_000.0)
+                .speed(1.0)
+                .prefix("Full detail distance: "),
+        );
+    }
+
+    /// Splits the given markers into those that should be drawn in full detail, and those that
+    /// should be drawn as low detail markers, based on their distance from the camera.
+    ///
+    /// At most [``max_full_detail_objects``](Self::max_full_detail_objects) markers are drawn in
+    /// full detail, preferring the closest ones.
+    pub fn partition(&self, markers: &[ObjectMarker], camera_position: Vec3) -> (Vec<ObjectMarker>, Vec<ObjectMarker>) {
+        if markers.len() <= self.max_full_detail_objects {
+            return (markers.to_vec(), Vec::new());
+        }
+
+        let max_distance2 = self.full_detail_distance * self.full_detail_distance;
+        let (mut near, mut far): (Vec<ObjectMarker>, Vec<ObjectMarker>) = markers
+            .iter()
+            .copied()
+            .partition(|marker| (marker.position - camera_position).magnitude2() <= max_distance2);
+
+        if near.len() > self.max_full_detail_objects {
+            let distance2 = |marker: &ObjectMarker| (marker.position - camera_position).magnitude2();
+            near.sort_by(|a, b| distance2(a).total_cmp(&distance2(b)));
+            far.extend(near.drain(self.max_full_detail_objects..));
+        }
+
+        (near, far)
+    }
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            max_full_detail_objects: 2000,
+            full_detail_distance: 150.0,
+        }
+    }
+}
+
+/// Instanced models for one kind of object, split into full and low detail by [LodSettings].
+struct LodObjectModels {
+    context: Context,
+    markers: Vec<ObjectMarker>,
+    full_detail_mesh: CpuMesh,
+    low_detail_mesh: CpuMesh,
+    /// ``None`` when there are no objects to draw in full detail.
+    full_detail_model: Option<Gm<InstancedMesh, ColorMaterial>>,
+    /// ``None`` when there are no objects to draw in low detail.
+    low_detail_model: Option<Gm<InstancedMesh, ColorMaterial>>,
+    /// How many objects are currently drawn in full and low detail.
+    full_detail_count: usize,
+    low_detail_count: usize,
+}
+
+impl LodObjectModels {
+    /// How far the camera has to move, relative to the full detail distance, before we re-sort
+    /// objects into full and low detail.
+    const REPARTITION_FRACTION: f32 = 0.1;
+
+    fn new(context: &Context, markers: &[ObjectMarker], full_detail_mesh: CpuMesh, scale: f32) -> Self {
+        let mut full_detail_mesh = full_detail_mesh;
+        full_detail_mesh.transform(&Mat4::from_scale(scale)).unwrap();
+
+        let mut low_detail_mesh = CpuMesh::cube();
+        low_detail_mesh.transform(&Mat4::from_scale(scale * 0.5)).unwrap();
+
+        Self {
+            context: context.clone(),
+            markers: markers.to_vec(),
+            full_detail_mesh,
+            low_detail_mesh,
+            full_detail_model: None,
+            low_detail_model: None,
+            full_detail_count: 0,
+            low_detail_count: 0,
+        }
+    }
+
+    /// Replaces the instances of the given model with the given markers, scaled by ``gizmo_scale``,
+    /// creating the model if needed.
+    fn set_instances(
+        context: &Context,
+        model: &mut Option<Gm<InstancedMesh, ColorMaterial>>,
+        mesh: &CpuMesh,
+        markers: &[ObjectMarker],
+        gizmo_scale: f32,
+    ) {
+        if markers.is_empty() {
+            *model = None;
+            return;
+        }
+
+        let instances = Instances {
+            transformations: markers
+                .iter()
+                .map(|marker| Mat4::from_translation(marker.position) * Mat4::from_scale(marker.scale * gizmo_scale))
+                .collect(),
+            colors: Some(markers.iter().map(|marker| marker.color).collect()),
+            ..Default::default()
+        };
+
+        match model {
+            Some(model) => model.geometry.set_instances(&instances),
+            None => *model = Some(Gm::new(InstancedMesh::new(context, &instances, mesh), ColorMaterial::default())),
+        }
+    }
+
+    fn update(&mut self, lod_settings: &LodSettings, camera_position: Vec3, gizmo_scale: f32) {
+        let (full_detail, low_detail) = lod_settings.partition(&self.markers, camera_position);
+        let context = &self.context;
+        Self::set_instances(context, &mut self.full_detail_model, &self.full_detail_mesh, &full_detail, gizmo_scale);
+        Self::set_instances(context, &mut self.low_detail_model, &self.low_detail_mesh, &low_detail, gizmo_scale);
+        self.full_detail_count = full_detail.len();
+        self.low_detail_count = low_detail.len();
+    }
+
+    fn get_triangle_count(&self) -> usize {
+        self.full_detail_count * self.full_detail_mesh.triangle_count()
+            + self.low_detail_count * self.low_detail_mesh.triangle_count()
+    }
+
+    fn get_objects(&self) -> impl Iterator<Item = &dyn Object> {
+        [&self.full_detail_model, &self.low_detail_model]
+            .into_iter()
+            .flatten()
+            .map(|model| model as &dyn Object)
+    }
+}
+
+/// Which representations of the collision mesh should be drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CollisionRenderSettings {
+    pub solid: bool,
+    pub wireframe: bool,
+    /// Hides the back of collision triangles, like the game does. Off by default since surfaces
+    /// disappearing when viewed from behind is confusing, but useful for checking winding order.
+    pub backface_culling: bool,
+    /// How opaque the solid collision is, from 0.0 to 1.0. Lowering it shows objects inside
+    /// platforms, like bananas and goals.
+    pub opacity: f32,
+}
+
+impl CollisionRenderSettings {
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.label("Collision");
+        ui.checkbox(&mut self.solid, "Solid");
+        ui.checkbox(&mut self.wireframe, "Wireframe");
+        ui.checkbox(&mut self.backface_culling, "Backface culling")
+            .on_hover_text("Hide the back of collision triangles, to check their winding order");
+        ui.add_enabled(self.solid, egui::Slider::new(&mut self.opacity, 0.0..=1.0).text("Opacity"))
+            .on_hover_text("See through the solid collision, to find objects inside it");
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
+    }
+
+    /// Returns the render states for the solid collision. Transparent collision is blended over
+    /// whatever is behind it, and doesn't write depth so objects inside it are still drawn.
+    fn get_solid_render_states(&self) -> RenderStates {
+        if self.is_transparent() {
+            RenderStates {
+                cull: self.get_cull(),
+                write_mask: WriteMask::COLOR,
+                blend: Blend::TRANSPARENCY,
+                ..Default::default()
+            }
+        } else {
+            RenderStates {
+                cull: self.get_cull(),
+                ..Default::default()
+            }
+        }
+    }
+
+    fn get_cull(&self) -> Cull {
+        if self.backface_culling {
+            Cull::Back
+        } else {
+            Cull::None
+        }
+    }
+}
+
+impl Default for CollisionRenderSettings {
+    fn default() -> Self {
+        Self {
+            solid: true,
+            wireframe: false,
+            backface_culling: false,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// Returns the clear state that fills the viewport with the given color and resets its depth.
+///
+/// egui's colors are sRGB, and sRGB conversion is turned off while drawing with three-d, so the
+/// color is cleared to as it is.
+fn get_clear_state(color: egui::Color32) -> ClearState {
+    let [red, green, blue, _] = color.to_array();
+    let to_float = |channel: u8| f32::from(channel) / 255.0;
+    ClearState::color_and_depth(to_float(red), to_float(green), to_float(blue), 1.0, 1.0)
+}
+
+/// Smoothstep-like ease-in-out curve, mapping ``t`` in ``[0, 1]`` to ``[0, 1]``.
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        2.0 * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+    }
+}
+
+/// Returns how far along an animation of the given duration is after ``elapsed`` seconds, eased.
+///
+/// Since this works in real time rather than per-frame fractions, an animation looks the same
+/// regardless of frame rate.
+pub fn eased_progress(elapsed: f32, duration: f32) -> f32 {
+    if duration <= 0.0 {
+        1.0
+    } else {
+        ease_in_out(elapsed / duration)
+    }
+}
+
+/// The solid and wireframe models for a single collision header's collision.
+struct CollisionHeaderModels {
+    solid: Gm<Mesh, ColorMaterial>,
+    wireframe: Gm<InstancedMesh, ColorMaterial>,
+    solid_triangle_count: usize,
+    wireframe_triangle_count: usize,
+    center_of_rotation: Vec3,
+    /// The rotation used when the header's animation isn't being previewed, in degrees.
+    initial_rotation: [f32; 3],
+    /// The rotation the models are currently transformed by, in degrees.
+    rotation: [f32; 3],
+}
+
+impl CollisionHeaderModels {
+    /// Builds the models for the given collision header, or returns ``None`` if it has no triangles.
+    fn new(context: &Context, geometry: &CollisionHeaderGeometry) -> Option<Self> {
+        if geometry.triangles.is_empty() {
+            return None;
+        }
+
+        // Solid mesh, shaded by how much each triangle faces a fixed light direction since we
+        // don't have any lights
+        let light_direction = vec3(0.3, 1.0, 0.5).normalize();
+        let mut positions = Vec::with_capacity(geometry.triangles.len() * 3);
+        let mut colors = Vec::with_capacity(geometry.triangles.len() * 3);
+        for [v1, v2, v3] in geometry.triangles.iter() {
+            let normal = (v2 - v1).cross(v3 - v1);
+            let normal = if normal.magnitude2() > 0.0 { normal.normalize() } else { normal };
+            let shade = (0.4 + 0.6 * normal.dot(light_direction).abs()) * 255.0;
+            let color = Color::new(shade as u8, shade as u8, shade as u8, 255);
+
+            positions.extend([*v1, *v2, *v3]);
+            colors.extend([color, color, color]);
+        }
+
+        let solid_mesh = CpuMesh {
+            positions: Positions::F32(positions),
+            colors: Some(colors),
+            ..Default::default()
+        };
+        // Double-sided by default, see CollisionRenderSettings::backface_culling
+        let solid_material = ColorMaterial {
+            render_states: RenderStates {
+                cull: Cull::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let solid = Gm::new(Mesh::new(context, &solid_mesh), solid_material);
+
+        // Wireframe mesh, made of a thin cylinder along each triangle edge
+        let mut edge_mesh = CpuMesh::cylinder(6);
+        edge_mesh.transform(&Mat4::from_nonuniform_scale(1.0, 0.03, 0.03)).unwrap();
+
+        let transformations = geometry
+            .triangles
+            .iter()
+            .flat_map(|[v1, v2, v3]| [(*v1, *v2), (*v2, *v3), (*v3, *v1)])
+            .filter(|(start, end)| (end - start).magnitude2() > 0.0)
+            .map(|(start, end)| {
+                let edge = end - start;
+                Mat4::from_translation(start)
+                    * rotation_matrix_from_dir_to_dir(vec3(1.0, 0.0, 0.0), edge.normalize())
+                    * Mat4::from_nonuniform_scale(edge.magnitude(), 1.0, 1.0)
+            })
+            .collect();
+
+        let wireframe_triangle_count = transformations.len() * edge_mesh.triangle_count();
+        let instances = Instances {
+            transformations,
+            ..Default::default()
+        };
+        let wireframe = Gm::new(
+            InstancedMesh::new(context, &instances, &edge_mesh),
+            ColorMaterial {
+                color: Color::new(40, 220, 255, 255),
+                ..Default::default()
+            },
+        );
+
+        let mut models = Self {
+            solid,
+            wireframe,
+            solid_triangle_count: geometry.triangles.len(),
+            wireframe_triangle_count,
+            center_of_rotation: geometry.center_of_rotation,
+            initial_rotation: geometry.initial_rotation,
+            rotation: [0.0; 3],
+        };
+        models.set_rotation(geometry.initial_rotation);
+        Some(models)
+    }
+
+    /// Updates the solid model's material to match the given settings.
+    fn apply_render_settings(&mut self, settings: CollisionRenderSettings) {
+        let alpha = (settings.opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let material = &mut self.solid.material;
+        material.render_states = settings.get_solid_render_states();
+        material.is_transparent = settings.is_transparent();
+        material.color = Color::new(255, 255, 255, alpha);
+    }
+
+    /// Rotates the models around the header's center of rotation, in degrees.
+    fn set_rotation(&mut self, rotation: [f32; 3]) {
+        if rotation == self.rotation {
+            return;
+        }
+
+        let transformation = rotation_around(self.center_of_rotation, rotation);
+        self.solid.set_transformation(transformation);
+        self.wireframe.set_transformation(transformation);
+        self.rotation = rotation;
+    }
+}
+
+/// How much work a single frame took to draw, for the debug overlay.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// Instanced models count as a single draw call, however many instances they have.
+    pub draw_calls: usize,
+    pub triangles: usize,
+    /// How long the CPU spent preparing and submitting the frame. The GPU may still be working on
+    /// it after this.
+    pub render_time: Duration,
+}
+
+/// A request to move the camera to look at ``target`` from ``position``.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraMove {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+}
+
+/// A direction to look at the whole stage from, chosen from the viewport toolbar or with the number
+/// keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraPreset {
+    /// Looking straight down.
+    Top,
+    /// Looking towards -Z.
+    Front,
+    /// Looking towards -X.
+    Side,
+    /// Looking the same way as the camera already is.
+    Fit,
+}
+
+impl CameraPreset {
+    pub const ALL: [CameraPreset; 4] = [CameraPreset::Top, CameraPreset::Front, CameraPreset::Side, CameraPreset::Fit];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            CameraPreset::Top => "Top",
+            CameraPreset::Front => "Front",
+            CameraPreset::Side => "Side",
+            CameraPreset::Fit => "Fit",
+        }
+    }
+
+    /// Returns the yaw and pitch this preset looks from, in degrees, or ``None`` if it keeps the
+    /// camera's current direction.
+    ///
+    /// The top view is tilted just short of straight down, as the camera's up vector is always +Y.
+    fn get_yaw_pitch(&self) -> Option<(f32, f32)> {
+        match self {
+            CameraPreset::Top => Some((0.0, 89.0)),
+            CameraPreset::Front => Some((0.0, 0.0)),
+            CameraPreset::Side => Some((90.0, 0.0)),
+            CameraPreset::Fit => None,
+        }
+    }
+}
+
+/// Returns where the camera should be to look at ``center`` from ``distance`` away, at ``yaw``
+/// degrees around the Y axis and ``pitch`` degrees above the horizon. A yaw and pitch of 0 looks
+/// towards -Z.
+fn get_orbit_position(center: Vec3, yaw: f32, pitch: f32, distance: f32) -> Vec3 {
+    let (sin_yaw, cos_yaw) = yaw.to_radians().sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.to_radians().sin_cos();
+    center + vec3(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch) * distance
+}
+
+/// Returns how far a camera with the given vertical field of view needs to be from the center of
+/// a sphere to fit all of it on screen.
+fn get_fit_distance(radius: f32, fov_degrees: f32) -> f32 {
+    radius / (fov_degrees / 2.0).to_radians().sin()
+}
+
+/// A camera move in progress.
+struct CameraAnimation {
+    start_position: Vec3,
+    start_target: Vec3,
+    end_position: Vec3,
+    end_target: Vec3,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl CameraAnimation {
+    /// Advances the animation by ``dt`` seconds, and returns the camera's new position and target.
+    fn advance(&mut self, dt: f32) -> (Vec3, Vec3) {
+        self.elapsed += dt;
+        let progress = eased_progress(self.elapsed, self.duration);
+
+        (
+            self.start_position + (self.end_position - self.start_position) * progress,
+            self.start_target + (self.end_target - self.start_target) * progress,
+        )
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
 pub struct Renderer {
     pub context: Context,
     camera: Camera,
+    camera_settings: CameraSettings,
     test_model: Gm<Mesh, ColorMaterial>,
+    conveyor_arrows: Vec<ConveyorArrow>,
+    conveyor_arrow_models: Vec<Gm<Mesh, ColorMaterial>>,
+    conveyor_arrow_triangle_count: usize,
+    start_marker: Option<StartMarker>,
+    start_marker_models: Vec<Gm<Mesh, ColorMaterial>>,
+    start_marker_triangle_count: usize,
+    translate_gizmo: Option<TranslateGizmo>,
+    /// The X, Y and Z arrows of the translate gizmo, drawn over everything else.
+    translate_gizmo_models: Vec<Gm<Mesh, ColorMaterial>>,
+    translate_gizmo_triangle_count: usize,
+    loaded_geometry: Option<Arc<StageGeometry>>,
+    /// The models for each collision header, or ``None`` for headers without collision.
+    collision_models: Vec<Option<CollisionHeaderModels>>,
+    camera_animation: Option<CameraAnimation>,
+    lod_settings: LodSettings,
+    banana_models: Option<LodObjectModels>,
+    goal_models: Option<LodObjectModels>,
+    /// Where the camera was when objects were last sorted into full and low detail.
+    lod_camera_position: Option<Vec3>,
+    last_render_stats: RenderStats,
+    /// What the viewport is cleared to before drawing, which follows the UI theme.
+    clear_color: egui::Color32,
+    gizmo_settings: GizmoSettings,
 }
 
 impl Renderer {
-    fn new(ctx: Arc<glow::Context>) -> Self {
-        let three_d_ctx = three_d::Context::from_gl_context(ctx).unwrap();
+    fn new(ctx: Arc<glow::Context>) -> Result<Self, RendererError> {
+        let three_d_ctx = three_d::Context::from_gl_context(ctx)?;
+        let camera_settings = CameraSettings::default();
         let camera = Camera::new_perspective(
             Viewport {
                 x: 0,
@@ -90,9 +1282,9 @@ impl Renderer {
             vec3(0.0, 0.0, 1.0),
             vec3(0.0, 0.0, 0.0),
             vec3(0.0, 1.0, 0.0),
-            degrees(90.0),
-            0.1,
-            20000.0,
+            degrees(camera_settings.fov_degrees),
+            camera_settings.near,
+            camera_settings.far,
         );
 
         let pos = vec![vec3(0.5, -0.5, 0.0), vec3(-0.5, -0.5, 0.0), vec3(0.0, 0.5, 0.0)];
@@ -111,22 +1303,775 @@ impl Renderer {
 
         let model = Gm::new(Mesh::new(&three_d_ctx, &trimesh), ColorMaterial::default());
 
-        Self {
+        Ok(Self {
             context: three_d_ctx,
             camera,
+            camera_settings,
             test_model: model,
+            conveyor_arrows: Vec::new(),
+            conveyor_arrow_models: Vec::new(),
+            conveyor_arrow_triangle_count: 0,
+            start_marker: None,
+            start_marker_models: Vec::new(),
+            start_marker_triangle_count: 0,
+            translate_gizmo: None,
+            translate_gizmo_models: Vec::new(),
+            translate_gizmo_triangle_count: 0,
+            loaded_geometry: None,
+            collision_models: Vec::new(),
+            camera_animation: None,
+            lod_settings: LodSettings::default(),
+            banana_models: None,
+            goal_models: None,
+            lod_camera_position: None,
+            last_render_stats: RenderStats::default(),
+            clear_color: egui::Color32::BLACK,
+            gizmo_settings: GizmoSettings::default(),
+        })
+    }
+
+    /// Starts smoothly moving the camera, using the animation duration from the camera settings.
+    pub fn animate_camera(&mut self, camera_move: CameraMove) {
+        self.camera_animation = Some(CameraAnimation {
+            start_position: *self.camera.position(),
+            start_target: *self.camera.target(),
+            end_position: Vec3::from(camera_move.position),
+            end_target: Vec3::from(camera_move.target),
+            elapsed: 0.0,
+            duration: self.camera_settings.animation_duration,
+        });
+    }
+
+    /// Smoothly moves the camera to look at ``center`` from ``distance`` away, at ``yaw`` degrees
+    /// around the Y axis and ``pitch`` degrees above the horizon.
+    pub fn orbit_to(&mut self, center: Vec3, yaw: f32, pitch: f32, distance: f32) {
+        let position = get_orbit_position(center, yaw, pitch, distance);
+        self.animate_camera(CameraMove {
+            position: position.into(),
+            target: center.into(),
+        });
+    }
+
+    /// Smoothly moves the camera to the given preset, framing everything in the loaded stage. Does
+    /// nothing if no stage is loaded, or it's empty.
+    pub fn view_preset(&mut self, preset: CameraPreset) {
+        let Some((min, max)) = self.loaded_geometry.as_ref().and_then(|geometry| geometry.get_bounds()) else {
+            return;
+        };
+
+        let center = (min + max) / 2.0;
+        // Stages with a single object still need some room around them
+        let radius = ((max - min).magnitude() / 2.0).max(1.0);
+        let distance = get_fit_distance(radius, self.camera_settings.fov_degrees);
+
+        let (yaw, pitch) = preset.get_yaw_pitch().unwrap_or_else(|| {
+            let offset = *self.camera.position() - *self.camera.target();
+            let pitch = (offset.y / offset.magnitude()).asin().to_degrees();
+            (offset.x.atan2(offset.z).to_degrees(), pitch)
+        });
+        self.orbit_to(center, yaw, pitch, distance);
+    }
+
+    /// Advances any camera move in progress by ``dt`` seconds of real time.
+    pub fn update_camera(&mut self, dt: f32) {
+        let Some(animation) = &mut self.camera_animation else {
+            return;
+        };
+
+        let (position, target) = animation.advance(dt);
+        self.camera.set_view(position, target, vec3(0.0, 1.0, 0.0));
+
+        if animation.is_finished() {
+            self.camera_animation = None;
+        }
+    }
+
+    /// Builds the collision meshes for the given stage geometry, unless it is already loaded.
+    pub fn load_stagedef(&mut self, geometry: &Arc<StageGeometry>) {
+        if let Some(loaded_geometry) = &self.loaded_geometry {
+            if Arc::ptr_eq(loaded_geometry, geometry) {
+                return;
+            }
+        }
+
+        self.loaded_geometry = Some(geometry.clone());
+
+        // The stagedef itself is left alone, so the bad values can still be seen in the inspector
+        let (geometry, fixed_count) = geometry.get_sanitized();
+        if fixed_count > 0 {
+            warn!("Replaced {fixed_count} non-finite position components with 0 for rendering");
+        }
+
+        self.banana_models = (!geometry.bananas.is_empty())
+            .then(|| LodObjectModels::new(&self.context, &geometry.bananas, CpuMesh::sphere(16), 0.5));
+        self.goal_models = (!geometry.goals.is_empty())
+            .then(|| LodObjectModels::new(&self.context, &geometry.goals, CpuMesh::cylinder(24), 2.0));
+        self.lod_camera_position = None;
+
+        self.collision_models = geometry
+            .collision_headers
+            .iter()
+            .map(|header| CollisionHeaderModels::new(&self.context, header))
+            .collect();
+    }
+
+    /// Rotates each collision header's collision by the matching rotation, in degrees, to preview
+    /// its animation. Headers without a rotation are drawn at their initial rotation.
+    pub fn set_collision_header_rotations(&mut self, rotations: &[Option<[f32; 3]>]) {
+        for (index, models) in self.collision_models.iter_mut().enumerate() {
+            if let Some(models) = models {
+                let rotation = rotations.get(index).copied().flatten().unwrap_or(models.initial_rotation);
+                models.set_rotation(rotation);
+            }
+        }
+    }
+
+    /// Rebuilds the conveyor arrow overlay if the given arrows differ from the current ones.
+    pub fn set_conveyor_arrows(&mut self, conveyor_arrows: &[ConveyorArrow]) {
+        if conveyor_arrows == self.conveyor_arrows.as_slice() {
+            return;
+        }
+
+        let arrow_mesh = CpuMesh::arrow(0.7, 0.1, 16);
+        let arrow_material = ColorMaterial {
+            color: Color::new(255, 200, 0, 255),
+            ..Default::default()
+        };
+
+        self.conveyor_arrow_models = conveyor_arrows
+            .iter()
+            .map(|arrow| {
+                let mut model = Gm::new(Mesh::new(&self.context, &arrow_mesh), arrow_material.clone());
+                model.set_transformation(arrow.get_transformation());
+                model
+            })
+            .collect();
+        self.conveyor_arrow_triangle_count = conveyor_arrows.len() * arrow_mesh.triangle_count();
+        self.conveyor_arrows = conveyor_arrows.to_vec();
+    }
+
+    /// Rebuilds the start position marker if it differs from the current one. ``None`` hides it.
+    pub fn set_start_marker(&mut self, start_marker: Option<StartMarker>) {
+        if start_marker == self.start_marker {
+            return;
+        }
+
+        self.start_marker = start_marker;
+        self.start_marker_models.clear();
+        self.start_marker_triangle_count = 0;
+        let Some(start_marker) = start_marker else {
+            return;
+        };
+
+        let material = ColorMaterial {
+            color: StartMarker::COLOR,
+            ..Default::default()
+        };
+        for mesh in [CpuMesh::sphere(16), CpuMesh::arrow(0.7, 0.1, 16)] {
+            self.start_marker_models.push(Gm::new(Mesh::new(&self.context, &mesh), material.clone()));
+            self.start_marker_triangle_count += mesh.triangle_count();
+        }
+        self.update_start_marker_transformations();
+    }
+
+    /// Moves the start marker's ball and arrow into place, at the current gizmo scale.
+    fn update_start_marker_transformations(&mut self) {
+        let Some(start_marker) = self.start_marker else {
+            return;
+        };
+
+        let gizmo_scale = self.gizmo_settings.scale;
+        let transformations = [
+            start_marker.get_ball_transformation(gizmo_scale),
+            start_marker.get_arrow_transformation(gizmo_scale),
+        ];
+        for (model, transformation) in self.start_marker_models.iter_mut().zip(transformations) {
+            model.set_transformation(transformation);
+        }
+    }
+
+    /// Rebuilds the translate gizmo if it differs from the current one. ``None`` hides it.
+    pub fn set_translate_gizmo(&mut self, translate_gizmo: Option<TranslateGizmo>) {
+        if translate_gizmo == self.translate_gizmo {
+            return;
+        }
+
+        self.translate_gizmo = translate_gizmo;
+        self.translate_gizmo_models.clear();
+        self.translate_gizmo_triangle_count = 0;
+        let Some(translate_gizmo) = translate_gizmo else {
+            return;
+        };
+
+        let arrow_mesh = CpuMesh::arrow(0.8, 0.04, 16);
+        for axis in GizmoAxis::ALL {
+            let color = if translate_gizmo.active_axis == Some(axis) {
+                TranslateGizmo::ACTIVE_COLOR
+            } else {
+                axis.get_color()
+            };
+            // Always drawn, even when it's inside or behind collision, so it can be grabbed
+            let material = ColorMaterial {
+                color,
+                render_states: RenderStates {
+                    depth_test: DepthTest::Always,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut model = Gm::new(Mesh::new(&self.context, &arrow_mesh), material);
+            model.set_transformation(translate_gizmo.get_arrow_transformation(axis));
+            self.translate_gizmo_models.push(model);
+            self.translate_gizmo_triangle_count += arrow_mesh.triangle_count();
+        }
+    }
+
+    /// Changes the level of detail settings, re-sorting objects into full and low detail if they
+    /// differ from the current ones.
+    pub fn set_lod_settings(&mut self, lod_settings: LodSettings) {
+        if lod_settings != self.lod_settings {
+            self.lod_settings = lod_settings;
+            self.lod_camera_position = None;
+        }
+    }
+
+    /// Changes how big object gizmos are drawn, resizing them if the settings differ from the
+    /// current ones.
+    pub fn set_gizmo_settings(&mut self, gizmo_settings: GizmoSettings) {
+        if gizmo_settings != self.gizmo_settings {
+            self.gizmo_settings = gizmo_settings;
+            // Object instances are rebuilt with the new scale the next time they're sorted by detail
+            self.lod_camera_position = None;
+            self.update_start_marker_transformations();
+        }
+    }
+
+    /// Sets the color the viewport is cleared to before drawing.
+    pub fn set_clear_color(&mut self, clear_color: egui::Color32) {
+        self.clear_color = clear_color;
+    }
+
+    /// Sorts objects into full and low detail, if the camera has moved far enough since we last did.
+    fn update_lod(&mut self) {
+        let camera_position = *self.camera.position();
+        let repartition_distance = self.lod_settings.full_detail_distance * LodObjectModels::REPARTITION_FRACTION;
+        if let Some(last_position) = self.lod_camera_position {
+            if (camera_position - last_position).magnitude() < repartition_distance {
+                return;
+            }
+        }
+
+        for models in [&mut self.banana_models, &mut self.goal_models].into_iter().flatten() {
+            models.update(&self.lod_settings, camera_position, self.gizmo_settings.scale);
+        }
+        self.lod_camera_position = Some(camera_position);
+    }
+
+    /// Returns the camera's view and projection combined, for turning clicks in the viewport into
+    /// rays with [``get_pick_ray``].
+    pub fn get_view_projection(&self) -> Mat4 {
+        *self.camera.projection() * *self.camera.view()
+    }
+
+    /// Returns how much work the last call to [``Renderer::render``] did.
+    pub fn get_render_stats(&self) -> RenderStats {
+        self.last_render_stats
+    }
+
+    /// Reconfigures the camera's projection if the given settings differ from the current ones.
+    pub fn set_camera_settings(&mut self, camera_settings: CameraSettings) {
+        let camera_settings = camera_settings.clamped();
+        if camera_settings == self.camera_settings {
+            return;
         }
+
+        self.camera
+            .set_perspective_projection(degrees(camera_settings.fov_degrees), camera_settings.near, camera_settings.far);
+        self.camera_settings = camera_settings;
     }
 
-    pub fn render(&mut self, frame_input: FrameInput<'_>) -> Option<glow::Framebuffer> {
+    pub fn render(
+        &mut self,
+        frame_input: FrameInput<'_>,
+        collision_render_settings: CollisionRenderSettings,
+    ) -> Option<glow::Framebuffer> {
+        let start_time = instant::Instant::now();
         self.camera.set_viewport(frame_input.viewport);
+        self.update_lod();
+
+        for models in self.collision_models.iter_mut().flatten() {
+            models.apply_render_settings(collision_render_settings);
+        }
+
+        // The test model is a single triangle
+        let mut objects: Vec<&dyn Object> = vec![&self.test_model];
+        let mut triangles = 1 + self.conveyor_arrow_triangle_count + self.start_marker_triangle_count;
+        objects.extend(self.conveyor_arrow_models.iter().map(|model| model as &dyn Object));
+        objects.extend(self.start_marker_models.iter().map(|model| model as &dyn Object));
+        for models in [&self.banana_models, &self.goal_models].into_iter().flatten() {
+            objects.extend(models.get_objects());
+            triangles += models.get_triangle_count();
+        }
+
+        for models in self.collision_models.iter().flatten() {
+            if collision_render_settings.wireframe {
+                objects.push(&models.wireframe);
+                triangles += models.wireframe_triangle_count;
+            }
+        }
+        // The solid collision goes after every opaque object, so when it's transparent it's blended
+        // over everything inside and behind it
+        for models in self.collision_models.iter().flatten() {
+            if collision_render_settings.solid {
+                objects.push(&models.solid);
+                triangles += models.solid_triangle_count;
+            }
+        }
+        // The translate gizmo is drawn afterwards, so it ends up on top of everything else
+        let gizmo_objects: Vec<&dyn Object> = self.translate_gizmo_models.iter().map(|model| model as &dyn Object).collect();
+        triangles += self.translate_gizmo_triangle_count;
+        let draw_calls = objects.len() + gizmo_objects.len();
 
         frame_input
             .screen
-            .clear_partially(frame_input.scissor_box, ClearState::depth(1.0));
+            .clear_partially(frame_input.scissor_box, get_clear_state(self.clear_color));
         frame_input
             .screen
-            .render_partially(frame_input.scissor_box, &self.camera, [&self.test_model], &[]);
+            .render_partially(frame_input.scissor_box, &self.camera, objects, &[])
+            .render_partially(frame_input.scissor_box, &self.camera, gizmo_objects, &[]);
+
+        self.last_render_stats = RenderStats {
+            draw_calls,
+            triangles,
+            render_time: start_time.elapsed(),
+        };
         frame_input.screen.into_framebuffer()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_geometry_bounds() {
+        assert_eq!(StageGeometry::default().get_bounds(), None);
+
+        let geometry = StageGeometry {
+            collision_headers: vec![CollisionHeaderGeometry {
+                triangles: vec![[vec3(-10.0, 0.0, 0.0), vec3(10.0, 0.0, 0.0), vec3(0.0, f32::NAN, 5.0)]],
+                center_of_rotation: vec3(0.0, 0.0, 0.0),
+                initial_rotation: [0.0; 3],
+            }],
+            goals: vec![ObjectMarker::new(&Vector3 { x: 0.0, y: 3.0, z: -20.0 }, Color::WHITE)],
+            ..Default::default()
+        };
+        assert_eq!(geometry.get_bounds(), Some((vec3(-10.0, 0.0, -20.0), vec3(10.0, 3.0, 0.0))));
+    }
+
+    #[test]
+    fn test_translate_gizmo_pick_axis() {
+        let gizmo = TranslateGizmo::new([10.0, 0.0, 0.0], 1.0);
+        let down = vec3(0.0, -1.0, 0.0);
+
+        // Straight down onto the middle of the X and Z arrows
+        assert_eq!(gizmo.pick_axis(vec3(12.0, 10.0, 0.0), down), Some(GizmoAxis::X));
+        assert_eq!(gizmo.pick_axis(vec3(10.0, 10.0, 2.0), down), Some(GizmoAxis::Z));
+        // Sideways through the Y arrow, which is parallel to neither ray
+        assert_eq!(
+            gizmo.pick_axis(vec3(10.0, 2.0, 10.0), vec3(0.0, 0.0, -1.0)),
+            Some(GizmoAxis::Y)
+        );
+        // Past the tip of the X arrow, and behind the start of the ray
+        assert_eq!(gizmo.pick_axis(vec3(16.0, 10.0, 0.0), down), None);
+        assert_eq!(gizmo.pick_axis(vec3(12.0, 10.0, 0.0), -down), None);
+    }
+
+    #[test]
+    fn test_closest_line_parameter() {
+        // A ray crossing above the X axis at x = 3 is closest to the point 3 along it
+        let parameter = get_closest_line_parameter(
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(3.0, 5.0, -5.0),
+            vec3(0.0, 0.0, 1.0),
+        );
+        assert!((parameter.unwrap() - 3.0).abs() < 1e-5, "{parameter:?}");
+
+        // Looking straight down the axis, there's no one closest point
+        let parameter = get_closest_line_parameter(
+            vec3(0.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(-5.0, 0.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+        );
+        assert_eq!(parameter, None);
+    }
+
+    #[test]
+    fn test_gizmo_snap() {
+        let mut settings = GizmoSettings::default();
+        assert_eq!(settings.snap(1.3), 1.3);
+        settings.snap_to_grid = true;
+        settings.grid_size = 0.5;
+        assert_eq!(settings.snap(1.3), 1.5);
+        assert_eq!(settings.snap(-1.2), -1.0);
+    }
+
+    #[test]
+    fn test_camera_preset_positions() {
+        let assert_close = |a: Vec3, b: Vec3| assert!((a - b).magnitude() < 1e-3, "{a:?} != {b:?}");
+        let center = vec3(1.0, 2.0, 3.0);
+
+        let (yaw, pitch) = CameraPreset::Front.get_yaw_pitch().unwrap();
+        assert_close(get_orbit_position(center, yaw, pitch, 10.0), vec3(1.0, 2.0, 13.0));
+        let (yaw, pitch) = CameraPreset::Side.get_yaw_pitch().unwrap();
+        assert_close(get_orbit_position(center, yaw, pitch, 10.0), vec3(11.0, 2.0, 3.0));
+
+        // The top view is almost, but not quite, straight down
+        let (yaw, pitch) = CameraPreset::Top.get_yaw_pitch().unwrap();
+        let top = get_orbit_position(center, yaw, pitch, 10.0);
+        assert!(top.y > 11.9 && top.z > 3.0, "{top:?}");
+        assert_eq!(CameraPreset::Fit.get_yaw_pitch(), None);
+
+        // At 90 degrees, a sphere of radius 1 just fits from sqrt(2) away
+        assert!((get_fit_distance(1.0, 90.0) - std::f32::consts::SQRT_2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_sanitize_geometry() {
+        use crate::stagedef::common::GlobalStagedefObject;
+        use crate::stagedef::objects::Banana;
+
+        let mut stagedef = StageDef::default();
+        for position in [
+            Vector3 { x: 1.0, y: 2.0, z: 3.0 },
+            Vector3 {
+                x: f32::NAN,
+                y: 2.0,
+                z: f32::INFINITY,
+            },
+        ] {
+            stagedef.bananas.push(GlobalStagedefObject::new(
+                Banana {
+                    position: position.into(),
+                    banana_type: BananaType::Single,
+                },
+                0,
+            ));
+        }
+
+        let geometry = StageGeometry::from_stagedef(&stagedef);
+        assert_eq!(geometry.non_finite_component_count, 2);
+
+        let (sanitized, fixed_count) = geometry.get_sanitized();
+        assert_eq!(fixed_count, 2);
+        assert_eq!(sanitized.bananas[0].position, vec3(1.0, 2.0, 3.0));
+        assert_eq!(sanitized.bananas[1].position, vec3(0.0, 2.0, 0.0));
+
+        // The stagedef keeps its original values
+        assert!(stagedef.bananas[1].object.lock().unwrap().position.x.is_nan());
+    }
+
+    #[test]
+    fn test_banana_markers() {
+        use crate::stagedef::common::GlobalStagedefObject;
+        use crate::stagedef::objects::Banana;
+
+        let mut stagedef = StageDef::default();
+        for banana_type in [BananaType::Single, BananaType::Bunch] {
+            stagedef.bananas.push(GlobalStagedefObject::new(
+                Banana {
+                    position: Default::default(),
+                    banana_type,
+                },
+                0,
+            ));
+        }
+
+        let geometry = StageGeometry::from_stagedef(&stagedef);
+        let [single, bunch] = [geometry.bananas[0], geometry.bananas[1]];
+        assert_ne!(single.color, bunch.color);
+        assert_eq!(single.scale, 1.0);
+        assert!(bunch.scale > single.scale);
+    }
+
+    #[test]
+    fn test_pick_collision_triangle() {
+        let floor = |y: f32| [vec3(-10.0, y, -10.0), vec3(10.0, y, -10.0), vec3(0.0, y, 10.0)];
+        let geometry = StageGeometry {
+            collision_headers: vec![
+                CollisionHeaderGeometry {
+                    triangles: vec![floor(0.0)],
+                    ..Default::default()
+                },
+                CollisionHeaderGeometry {
+                    triangles: vec![floor(-50.0), floor(5.0)],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        // Looking down, the highest floor is hit first
+        let hit = geometry
+            .pick_collision_triangle(vec3(0.0, 20.0, 0.0), vec3(0.0, -1.0, 0.0))
+            .unwrap();
+        assert_eq!((hit.header_index, hit.triangle_index), (1, 1));
+        assert!((hit.point - vec3(0.0, 5.0, 0.0)).magnitude() < 1e-4);
+
+        // Looking up from between the floors hits the one above, from underneath
+        let hit = geometry
+            .pick_collision_triangle(vec3(0.0, -10.0, 0.0), vec3(0.0, 1.0, 0.0))
+            .unwrap();
+        assert_eq!((hit.header_index, hit.triangle_index), (0, 0));
+
+        // Missing to the side, or looking away, hits nothing
+        assert_eq!(geometry.pick_collision_triangle(vec3(50.0, 20.0, 0.0), vec3(0.0, -1.0, 0.0)), None);
+        assert_eq!(geometry.pick_collision_triangle(vec3(0.0, 20.0, 0.0), vec3(0.0, 1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_collision_header_initial_rotation() {
+        let header = CollisionHeaderGeometry {
+            triangles: vec![[vec3(-10.0, 0.0, -10.0), vec3(10.0, 0.0, -10.0), vec3(0.0, 0.0, 10.0)]],
+            center_of_rotation: vec3(10.0, 0.0, 0.0),
+            initial_rotation: [0.0, 0.0, 90.0],
+        };
+
+        // The center of rotation stays put, and everything else turns around it
+        let transformation = header.get_transformation(header.initial_rotation);
+        let center = (transformation * vec3(10.0, 0.0, 0.0).extend(1.0)).truncate();
+        let rotated = (transformation * vec3(11.0, 0.0, 0.0).extend(1.0)).truncate();
+        assert!((center - vec3(10.0, 0.0, 0.0)).magnitude() < 1e-4);
+        assert!((rotated - vec3(10.0, 1.0, 0.0)).magnitude() < 1e-4);
+
+        // The floor is now a wall facing along X, so picking sideways hits it and picking from above
+        // doesn't
+        let geometry = StageGeometry {
+            collision_headers: vec![header],
+            ..Default::default()
+        };
+        let hit = geometry
+            .pick_collision_triangle(vec3(30.0, -10.0, 0.0), vec3(-1.0, 0.0, 0.0))
+            .unwrap();
+        assert!((hit.point.x - 10.0).abs() < 1e-4);
+        assert_eq!(geometry.pick_collision_triangle(vec3(0.0, 20.0, 0.0), vec3(0.0, -1.0, 0.0)), None);
+    }
+
+    #[test]
+    fn test_pick_ray() {
+        // A camera at the eye looking down at the origin, which is 45 degrees below the horizon
+        let eye = vec3(0.0, 10.0, 10.0);
+        let view = Mat4::from_angle_x(degrees(45.0)) * Mat4::from_translation(-eye);
+        // Flip Z so the space in front of the camera is between the near and far planes
+        let view_projection = Mat4::from_nonuniform_scale(1.0, 1.0, -1.0) * view;
+
+        let (origin, direction) = get_pick_ray(view_projection, [0.0, 0.0]).unwrap();
+        let forward = vec3(0.0, -1.0, -1.0).normalize();
+        assert!((direction - forward).magnitude() < 1e-5);
+        // The ray passes through the eye
+        let to_eye = eye - origin;
+        assert!(to_eye.cross(direction).magnitude() < 1e-4);
+
+        assert_eq!(get_pick_ray(Mat4::from_scale(0.0), [0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn test_lod_partition() {
+        let markers: Vec<ObjectMarker> = (0..10)
+            .map(|i| ObjectMarker {
+                position: vec3(i as f32 * 10.0, 0.0, 0.0),
+                color: Color::WHITE,
+                scale: 1.0,
+            })
+            .collect();
+        let camera_position = vec3(0.0, 0.0, 0.0);
+
+        // Under the limit, everything is full detail
+        let settings = LodSettings {
+            max_full_detail_objects: 10,
+            full_detail_distance: 0.0,
+        };
+        let (full_detail, low_detail) = settings.partition(&markers, camera_position);
+        assert_eq!(full_detail.len(), 10);
+        assert!(low_detail.is_empty());
+
+        // Over the limit, only nearby objects are full detail
+        let settings = LodSettings {
+            max_full_detail_objects: 5,
+            full_detail_distance: 25.0,
+        };
+        let (full_detail, low_detail) = settings.partition(&markers, camera_position);
+        assert_eq!(full_detail, markers[0..3]);
+        assert_eq!(low_detail, markers[3..]);
+
+        // Nearby objects are still capped to the limit, keeping the closest ones
+        let settings = LodSettings {
+            max_full_detail_objects: 2,
+            full_detail_distance: 1000.0,
+        };
+        let (full_detail, low_detail) = settings.partition(&markers, vec3(95.0, 0.0, 0.0));
+        assert_eq!(full_detail, vec![markers[9], markers[8]]);
+        assert_eq!(low_detail.len(), 8);
+    }
+
+    #[test]
+    fn test_ease_in_out() {
+        assert_eq!(ease_in_out(0.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(1.0), 1.0);
+        assert_eq!(ease_in_out(-1.0), 0.0);
+        assert_eq!(ease_in_out(2.0), 1.0);
+
+        // Slow at the ends, fast in the middle, and symmetric
+        assert!(ease_in_out(0.1) < 0.1);
+        assert!(ease_in_out(0.9) > 0.9);
+        assert!((ease_in_out(0.25) + ease_in_out(0.75) - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_eased_progress_frame_rate_independent() {
+        let duration = 0.5;
+
+        // Step through the same amount of time at 30 and 144 fps
+        let at_30_fps = (0..15).fold(0.0, |elapsed, _| elapsed + 1.0 / 30.0);
+        let at_144_fps = (0..72).fold(0.0, |elapsed, _| elapsed + 1.0 / 144.0);
+
+        assert!((eased_progress(at_30_fps, duration) - eased_progress(at_144_fps, duration)).abs() < 0.0001);
+        assert_eq!(eased_progress(1.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_round_pixel_rect() {
+        assert_eq!(round_pixel_rect(10.0, 20.0, 100.0, 50.0), (10, 20, 100, 50));
+        // 150% scaling: the right edge is rounded, not the width
+        assert_eq!(round_pixel_rect(10.5, 20.4, 100.5, 50.4), (11, 20, 100, 51));
+        assert_eq!(round_pixel_rect(5.0, 5.0, -3.0, -3.0), (5, 5, 0, 0));
+    }
+
+    #[test]
+    fn test_camera_settings_clamped() {
+        let settings = CameraSettings {
+            fov_degrees: 500.0,
+            near: -1.0,
+            far: 0.0,
+            animation_duration: 10.0,
+        }
+        .clamped();
+
+        assert_eq!(settings.fov_degrees, 150.0);
+        assert_eq!(settings.near, 0.01);
+        assert_eq!(settings.far, 100.0);
+        assert_eq!(settings.animation_duration, 5.0);
+
+        let settings = CameraSettings::default().with_far_from_diagonal(50000.0);
+        assert_eq!(settings.far, 100000.0);
+    }
+
+    #[test]
+    fn test_start_marker_forward() {
+        let assert_close = |a: Vec3, b: Vec3| assert!((a - b).magnitude() < 1e-5, "{a:?} != {b:?}");
+        let marker = |rotation| StartMarker {
+            position: [0.0; 3],
+            rotation,
+        };
+
+        assert_close(marker([0.0; 3]).get_forward(), vec3(0.0, 0.0, -1.0));
+        assert_close(marker([0.0, 90.0, 0.0]).get_forward(), vec3(-1.0, 0.0, 0.0));
+        assert_close(marker([0.0, 180.0, 0.0]).get_forward(), vec3(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_start_marker_gizmo_scale() {
+        let marker = StartMarker {
+            position: [1.0, 2.0, 3.0],
+            rotation: [0.0; 3],
+        };
+
+        // Scaling keeps the marker in place, and its ball and arrow the same size relative to each other
+        let ball = marker.get_ball_transformation(2.0);
+        let arrow = marker.get_arrow_transformation(2.0);
+        assert_eq!(ball.w.truncate(), vec3(1.0, 2.0, 3.0));
+        assert_eq!(ball.x.magnitude(), StartMarker::BALL_RADIUS * 2.0);
+        assert!((arrow.x.truncate().magnitude() - StartMarker::ARROW_LENGTH * 2.0).abs() < 1e-5);
+        assert_eq!(marker.get_ball_transformation(1.0).x.magnitude() * 2.0, ball.x.magnitude());
+    }
+
+    #[test]
+    fn test_collision_opacity_render_states() {
+        let mut settings = CollisionRenderSettings::default();
+        let opaque = settings.get_solid_render_states();
+        assert!(!settings.is_transparent());
+        assert!(opaque.write_mask.depth);
+        assert!(matches!(opaque.blend, Blend::Disabled));
+
+        // Transparent collision mustn't hide the objects inside it from the depth test
+        settings.opacity = 0.3;
+        settings.backface_culling = true;
+        let transparent = settings.get_solid_render_states();
+        assert!(settings.is_transparent());
+        assert!(!transparent.write_mask.depth);
+        assert!(matches!(transparent.blend, Blend::Enabled { .. }));
+        assert!(matches!(transparent.cull, Cull::Back));
+    }
+
+    #[test]
+    fn test_clear_state_from_theme_color() {
+        let clear_state = get_clear_state(egui::Color32::from_rgb(255, 0, 51));
+        assert_eq!(clear_state.red, Some(1.0));
+        assert_eq!(clear_state.green, Some(0.0));
+        assert_eq!(clear_state.blue, Some(0.2));
+        assert_eq!(clear_state.alpha, Some(1.0));
+        assert_eq!(clear_state.depth, Some(1.0));
+    }
+
+    #[test]
+    fn test_renderer_keyed_by_instance() {
+        let mut renderers: HashMap<InstanceId, u32> = HashMap::new();
+        let first_id = InstanceId::next();
+        let second_id = InstanceId::next();
+
+        let init = || Ok::<u32, ()>(0);
+
+        *try_get_or_insert_keyed(&mut renderers, first_id, init).unwrap() += 5;
+        *try_get_or_insert_keyed(&mut renderers, second_id, init).unwrap() += 1;
+
+        // Existing entries are reused, and are not affected by other instances
+        assert_eq!(*try_get_or_insert_keyed(&mut renderers, first_id, init).unwrap(), 5);
+        assert_eq!(*try_get_or_insert_keyed(&mut renderers, second_id, init).unwrap(), 1);
+        assert_eq!(renderers.len(), 2);
+    }
+
+    #[test]
+    fn test_closed_renderers_dropped() {
+        let mut renderers: HashMap<InstanceId, u32> = HashMap::new();
+        let ids = [InstanceId::next(), InstanceId::next(), InstanceId::next()];
+        for id in ids {
+            try_get_or_insert_keyed(&mut renderers, id, || Ok::<u32, ()>(0)).unwrap();
+        }
+
+        // Closing the middle instance drops only its renderer
+        assert_eq!(retain_keys(&mut renderers, &[ids[0], ids[2]]), 1);
+        assert_eq!(renderers.len(), 2);
+        assert!(!renderers.contains_key(&ids[1]));
+
+        // Nothing else is dropped while the rest stay open, and everything goes once they're closed
+        assert_eq!(retain_keys(&mut renderers, &[ids[0], ids[2]]), 0);
+        assert_eq!(retain_keys(&mut renderers, &[]), 2);
+        assert!(renderers.is_empty());
+    }
+
+    #[test]
+    fn test_renderer_creation_failure() {
+        let mut renderers: HashMap<InstanceId, u32> = HashMap::new();
+        let id = InstanceId::next();
+
+        // A renderer that couldn't be created isn't kept, so it can be tried again later
+        assert_eq!(try_get_or_insert_keyed(&mut renderers, id, || Err("no GL context")), Err("no GL context"));
+        assert!(renderers.is_empty());
+
+        assert_eq!(try_get_or_insert_keyed(&mut renderers, id, || Ok::<_, &str>(3)), Ok(&mut 3));
+        assert_eq!(renderers.len(), 1);
+    }
+}
@@ -1,63 +0,0 @@
-//! Handles representation of the Monkey Ball stagedef format
-use std::cell::RefCell;
-use std::collections::HashSet;
-use std::fmt::Display;
-use std::fs::File;
-use std::hash::Hash;
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
-use std::rc::Rc;
-use std::sync::{Arc, Mutex};
-use std::{fs, io::Cursor};
-
-use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
-use num_traits::FromPrimitive;
-
-use crate::app::FileHandleWrapper;
-use crate::parser::StageDefReader;
-
-use egui::{Id, Response, SelectableLabel, Ui};
-use egui_inspect::EguiInspect;
-
-use anyhow::Result;
-
-
-
-
-// Common structures/enums
-
-
-pub enum AnimationState {
-    Play,
-    Pause,
-    Reverse,
-    FastForward,
-    FastReverse,
-}
-
-pub enum AnimationType {
-    LoopingAnimation,
-    PlayOnceAnimation,
-    Seesaw,
-}
-
-
-pub struct CollisionTriangle {
-    pub position: Vector3,
-    pub normal: Vector3,
-    pub rotation: ShortVector3,
-    pub delta_x2_x1: f32,
-    pub delta_y2_y1: f32,
-    pub delta_x3_x1: f32,
-    pub delta_y3_y1: f32,
-    pub x_tangent: f32,
-    pub y_tangent: f32,
-    pub x_bitangent: f32,
-    pub y_bitangent: f32,
-}
-
-pub struct Animation {}
-
-
-
-
@@ -0,0 +1,254 @@
+//! Periodically saving edits somewhere safe, so they aren't lost if the viewer closes unexpectedly.
+//!
+//! Stagedefs can't be written back to their own format yet, so the parts that can be edited in the
+//! viewer are saved as JSON instead, and put back on top of the original file when restored.
+use super::common::*;
+use super::objects::*;
+use super::snippet::StageSnippet;
+use std::path::PathBuf;
+
+/// The editable parts of a [``StageDef``], as saved in an autosave.
+#[derive(Default, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct StageAutosave {
+    pub start_position: StartPosition,
+    pub fallout_level: f32,
+    /// Every object in each global list, in order.
+    pub objects: StageSnippet,
+}
+
+impl StageAutosave {
+    pub fn from_stagedef(stagedef: &StageDef) -> Self {
+        fn copy<T: StageDefObjectList + Clone>(snippet: &mut StageSnippet, list: &[GlobalStagedefObject<T>]) {
+            for object in list {
+                snippet.add(&*object.object.lock().unwrap());
+            }
+        }
+
+        let mut objects = StageSnippet::default();
+        copy(&mut objects, &stagedef.goals);
+        copy(&mut objects, &stagedef.bumpers);
+        copy(&mut objects, &stagedef.jamabars);
+        copy(&mut objects, &stagedef.bananas);
+        copy(&mut objects, &stagedef.cone_collisions);
+        copy(&mut objects, &stagedef.sphere_collisions);
+        copy(&mut objects, &stagedef.cylinder_collisions);
+        copy(&mut objects, &stagedef.fallout_volumes);
+        copy(&mut objects, &stagedef.background_models);
+        copy(&mut objects, &stagedef.level_model_instances);
+        copy(&mut objects, &stagedef.reflective_models);
+
+        Self {
+            start_position: stagedef.start_position.clone(),
+            fallout_level: stagedef.fallout_level,
+            objects,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+impl StageDef {
+    /// Puts the objects from an autosave back into this stagedef, which should be the file the
+    /// autosave was made from.
+    ///
+    /// Objects are matched up by their index in each global list and edited in place, so collision
+    /// headers keep referring to them. Objects past the end of the autosave's lists are removed,
+    /// and extra objects in the autosave are added.
+    pub fn restore_autosave(&mut self, autosave: StageAutosave) {
+        fn restore<T: StageDefObjectList>(stagedef: &mut StageDef, objects: Vec<T>) {
+            let removed: Vec<_> = T::get_global_list(stagedef)
+                .iter()
+                .skip(objects.len())
+                .map(|global_object| global_object.object.clone())
+                .collect();
+            for object in &removed {
+                stagedef.remove_object(object);
+            }
+
+            let mut objects = objects.into_iter();
+            for (global_object, object) in T::get_global_list(stagedef).iter().zip(&mut objects) {
+                *global_object.object.lock().unwrap() = object;
+            }
+            for object in objects {
+                StageDef::push_global_object(T::get_global_list(stagedef), object);
+            }
+        }
+
+        self.start_position = autosave.start_position;
+        self.fallout_level = autosave.fallout_level;
+
+        let objects = autosave.objects;
+        restore(self, objects.goals);
+        restore(self, objects.bumpers);
+        restore(self, objects.jamabars);
+        restore(self, objects.bananas);
+        restore(self, objects.cone_collisions);
+        restore(self, objects.sphere_collisions);
+        restore(self, objects.cylinder_collisions);
+        restore(self, objects.fallout_volumes);
+        restore(self, objects.background_models);
+        restore(self, objects.level_model_instances);
+        restore(self, objects.reflective_models);
+    }
+}
+
+/// Where an autosave is kept.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AutosaveLocation {
+    /// A sidecar file next to the stagedef, on native.
+    File(PathBuf),
+    /// An entry with the given key in eframe's storage, which is the browser's local storage on
+    /// the web.
+    Storage(String),
+}
+
+impl AutosaveLocation {
+    /// Returns where to keep the autosave for the given file: next to it if we know where it is, or
+    /// in storage keyed by its name otherwise.
+    pub fn for_file(file_name: &str, file_path: Option<&PathBuf>) -> Self {
+        let sidecar_path = file_path.and_then(|path| {
+            let mut sidecar_name = path.file_name()?.to_os_string();
+            sidecar_name.push(".autosave.json");
+            Some(path.with_file_name(sidecar_name))
+        });
+        match sidecar_path {
+            Some(path) => AutosaveLocation::File(path),
+            None => AutosaveLocation::Storage(format!("autosave_{file_name}")),
+        }
+    }
+
+    /// Returns the saved JSON, if there is any.
+    #[cfg(feature = "gui")]
+    pub fn read(&self, storage: Option<&dyn eframe::Storage>) -> Option<String> {
+        let json = match self {
+            AutosaveLocation::File(path) => std::fs::read_to_string(path).ok(),
+            AutosaveLocation::Storage(key) => storage?.get_string(key),
+        };
+        // Cleared storage entries are left empty, as they can't be removed
+        json.filter(|json| !json.is_empty())
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn write(&self, json: String, storage: Option<&mut (dyn eframe::Storage + 'static)>) -> std::io::Result<()> {
+        match self {
+            AutosaveLocation::File(path) => std::fs::write(path, json),
+            AutosaveLocation::Storage(key) => {
+                let Some(storage) = storage else {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "No storage available"));
+                };
+                storage.set_string(key, json);
+                storage.flush();
+                Ok(())
+            }
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn clear(&self, storage: Option<&mut (dyn eframe::Storage + 'static)>) -> std::io::Result<()> {
+        match self {
+            AutosaveLocation::File(path) if !path.exists() => Ok(()),
+            AutosaveLocation::File(path) => std::fs::remove_file(path),
+            AutosaveLocation::Storage(_) => self.write(String::new(), storage),
+        }
+    }
+}
+
+/// How often edits are autosaved.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    pub interval_seconds: f32,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: 60.0,
+        }
+    }
+}
+
+impl AutosaveSettings {
+    pub const INTERVAL_RANGE: std::ops::RangeInclusive<f32> = 5.0..=3600.0;
+
+    #[cfg(feature = "gui")]
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Autosave edits");
+        ui.add_enabled(
+            self.enabled,
+            egui::DragValue::new(&mut self.interval_seconds)
+                .clamp_range(Self::INTERVAL_RANGE)
+                .prefix("Every ")
+                .suffix(" s"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_original() -> StageDef {
+        let mut stagedef = StageDef::new()
+            .with_goal(Goal::at_z(0.0))
+            .with_goal(Goal::at_z(1.0))
+            .with_goal(Goal::at_z(2.0));
+
+        // The collision header refers to the last goal
+        stagedef.add_collision_header_referencing::<Goal>(&[2]);
+        stagedef
+    }
+
+    #[test]
+    fn test_restore_autosave() {
+        let mut edited = get_original();
+        edited.start_position.position.y = 10.0;
+        edited.goals[0].object.lock().unwrap().position.z = -50.0;
+        edited = edited.with_banana(Banana {
+            position: Position::default(),
+            banana_type: BananaType::Bunch,
+        });
+
+        let json = StageAutosave::from_stagedef(&edited).to_json().unwrap();
+        let mut restored = get_original();
+        restored.restore_autosave(StageAutosave::from_json(&json).unwrap());
+        assert!(restored.diff(&edited).is_empty(), "{:?}", restored.diff(&edited));
+
+        // Edited objects are still the ones collision headers refer to
+        assert!(restored.goals[2].shares_object_with(&restored.collision_headers[0].goals[0]));
+    }
+
+    #[test]
+    fn test_restore_autosave_with_removed_objects() {
+        let mut edited = get_original();
+        let last_goal = edited.goals[2].object.clone();
+        edited.remove_object(&last_goal);
+
+        let mut restored = get_original();
+        restored.restore_autosave(StageAutosave::from_stagedef(&edited));
+        assert_eq!(restored.goals.len(), 2);
+        assert!(restored.collision_headers[0].goals.is_empty());
+    }
+
+    #[test]
+    fn test_autosave_location() {
+        let path = PathBuf::from("stages").join("STAGE001.lz");
+        assert_eq!(
+            AutosaveLocation::for_file("STAGE001.lz", Some(&path)),
+            AutosaveLocation::File(PathBuf::from("stages").join("STAGE001.lz.autosave.json"))
+        );
+        assert_eq!(
+            AutosaveLocation::for_file("STAGE001.lz", None),
+            AutosaveLocation::Storage("autosave_STAGE001.lz".to_string())
+        );
+    }
+}
@@ -1,24 +1,55 @@
 pub use std::fmt::Display;
-use std::{sync::{Arc, Mutex}, collections::HashSet};
+use std::{sync::{Arc, Mutex}, collections::{HashMap, HashSet}};
 
 pub use super::parser::ReadBytesExtSmb;
-pub use anyhow::Result;
+pub use super::error::{ParseError, ParseResult};
+use super::snippet::StageSnippet;
+#[cfg(feature = "gui")]
+use super::units::UnitSettings;
 pub use byteorder::ByteOrder;
+#[cfg(feature = "gui")]
 pub use egui_inspect::EguiInspect;
 pub use num_traits::FromPrimitive;
 
 use super::objects::*;
 
+/// Calls the given macro with every object type that's kept in a global list of a [``StageDef``],
+/// as ``Type => list``. The same name is used for its local list in each [``CollisionHeader``] and
+/// its list in a [``StageSnippet``], so anything that goes through every list can be written once
+/// and stays in sync when a list is added.
+macro_rules! stagedef_object_lists {
+    ($callback:ident) => {
+        $callback! {
+            $crate::stagedef::objects::Goal => goals,
+            $crate::stagedef::objects::Bumper => bumpers,
+            $crate::stagedef::objects::Jamabar => jamabars,
+            $crate::stagedef::objects::Banana => bananas,
+            $crate::stagedef::objects::ConeCollision => cone_collisions,
+            $crate::stagedef::objects::SphereCollision => sphere_collisions,
+            $crate::stagedef::objects::CylinderCollision => cylinder_collisions,
+            $crate::stagedef::objects::FalloutVolume => fallout_volumes,
+            $crate::stagedef::objects::BackgroundModel => background_models,
+            $crate::stagedef::objects::LevelModelInstance => level_model_instances,
+            $crate::stagedef::objects::ReflectiveModel => reflective_models,
+        }
+    };
+}
+pub(crate) use stagedef_object_lists;
+
 #[derive(Default)]
 pub struct StageDef {
     pub magic_number_1: f32,
     pub magic_number_2: f32,
 
-    pub start_position: Vector3,
-    pub start_rotation: ShortVector3,
+    pub start_position: StartPosition,
 
     pub fallout_level: f32,
 
+    /// Only present in stages that have fog.
+    pub fog: Option<Fog>,
+    /// Believed to be related to fog or other effects. See [``Mystery3``].
+    pub mystery_3: Option<Mystery3>,
+
     pub collision_headers: Vec<CollisionHeader>,
 
     pub goals: Vec<GlobalStagedefObject<Goal>>,
@@ -31,15 +62,469 @@ pub struct StageDef {
     pub fallout_volumes: Vec<GlobalStagedefObject<FalloutVolume>>,
 
     pub background_models: Vec<GlobalStagedefObject<BackgroundModel>>,
+    pub level_model_instances: Vec<GlobalStagedefObject<LevelModelInstance>>,
+    pub reflective_models: Vec<GlobalStagedefObject<ReflectiveModel>>,
 
     // Makes the assumption that stagedefs must have unique model names
     pub model_names: HashSet<String>,
+
+    /// Any data found after the last known structure, such as the footer some Deluxe PC stagedefs
+    /// have. Kept so it can be written back out unchanged.
+    pub trailing_bytes: Vec<u8>,
+
+    /// How much of the file this was read from is referred to by its structures.
+    pub file_usage: FileUsage,
+}
+
+/// How far into a file its structures reach, compared to how big the file actually is.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileUsage {
+    /// The end of the furthest structure referred to by the file, whether or not it could be read.
+    pub referenced_end: u64,
+    pub file_size: u64,
+}
+
+impl FileUsage {
+    /// How many bytes past the last referenced structure are worth pointing out.
+    pub const LARGE_UNUSED_SIZE: u64 = 0x1000;
+
+    /// Returns whether structures refer past the end of the file, which usually means it was cut
+    /// short.
+    pub fn is_truncated(&self) -> bool {
+        self.referenced_end > self.file_size
+    }
+
+    /// Returns how many bytes come after the last referenced structure.
+    pub fn get_unused_size(&self) -> u64 {
+        self.file_size.saturating_sub(self.referenced_end)
+    }
+
+    /// Returns a warning if the file looks truncated, or has a lot of data nothing refers to.
+    pub fn get_warning(&self) -> Option<String> {
+        if self.is_truncated() {
+            Some(format!(
+                "Structures refer to {} bytes past the end of the file, which may be truncated",
+                self.referenced_end - self.file_size
+            ))
+        } else if self.get_unused_size() >= Self::LARGE_UNUSED_SIZE {
+            Some(format!("{} bytes at the end of the file aren't referred to by anything", self.get_unused_size()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for FileUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Uses {} of {} bytes", self.referenced_end.min(self.file_size), self.file_size)
+    }
+}
+
+/// Builder-style helpers for making a [``StageDef``] in code, e.g. for test fixtures or procedural
+/// generation.
+///
+/// Objects are given the next index in their global list.
+///
+/// ```
+/// use mkbviewer::stagedef::{common::*, objects::*};
+///
+/// let mut stagedef = StageDef::new()
+///     .with_goal(Goal {
+///         position: Position(Vector3 { x: 0.0, y: 0.0, z: -115.0 }),
+///         goal_type: GoalType::Blue,
+///         ..Default::default()
+///     })
+///     .with_banana(Banana {
+///         position: Position(Vector3 { x: 0.0, y: 1.0, z: -50.0 }),
+///         banana_type: BananaType::Single,
+///     });
+/// stagedef.set_start(StartPosition {
+///     position: Position(Vector3 { x: 0.0, y: 2.75, z: 14.0 }),
+///     ..Default::default()
+/// });
+/// stagedef.add_collision_header(CollisionHeader::default());
+///
+/// assert_eq!(stagedef.goals.len(), 1);
+/// assert_eq!(stagedef.goals[0].index, 0);
+/// assert_eq!(stagedef.bananas[0].index, 0);
+/// ```
+impl StageDef {
+    /// Returns an empty stagedef, with the magic numbers every stagedef has.
+    pub fn new() -> Self {
+        let (magic_number_1, magic_number_2) = Self::EXPECTED_MAGIC_NUMBERS;
+        Self {
+            magic_number_1,
+            magic_number_2,
+            ..Default::default()
+        }
+    }
+
+    pub(super) fn push_global_object<T>(list: &mut Vec<GlobalStagedefObject<T>>, object: T) {
+        let index = u32::try_from(list.len()).expect("Too many objects in list");
+        list.push(GlobalStagedefObject::new(object, index));
+    }
+
+    pub fn with_goal(mut self, goal: Goal) -> Self {
+        Self::push_global_object(&mut self.goals, goal);
+        self
+    }
+
+    pub fn with_bumper(mut self, bumper: Bumper) -> Self {
+        Self::push_global_object(&mut self.bumpers, bumper);
+        self
+    }
+
+    pub fn with_jamabar(mut self, jamabar: Jamabar) -> Self {
+        Self::push_global_object(&mut self.jamabars, jamabar);
+        self
+    }
+
+    pub fn with_banana(mut self, banana: Banana) -> Self {
+        Self::push_global_object(&mut self.bananas, banana);
+        self
+    }
+
+    pub fn add_collision_header(&mut self, collision_header: CollisionHeader) {
+        self.collision_headers.push(collision_header);
+    }
+
+    /// Adds an otherwise empty collision header whose local list of ``T`` shares the objects at the
+    /// given indices of the global list, in that order.
+    ///
+    /// Panics if an index is past the end of the global list.
+    pub fn add_collision_header_referencing<T: StageDefObjectList>(&mut self, global_indices: &[usize]) {
+        let global_list = T::get_global_list(self);
+        let local_list = global_indices
+            .iter()
+            .enumerate()
+            .map(|(local_index, &global_index)| {
+                let mut local_object = global_list[global_index].clone();
+                local_object.index = u32::try_from(local_index).expect("Too many objects in list");
+                local_object.global_index = Some(global_list[global_index].index);
+                local_object
+            })
+            .collect();
+
+        let mut collision_header = CollisionHeader::default();
+        *T::get_local_list(&mut collision_header) = local_list;
+        self.add_collision_header(collision_header);
+    }
+
+    pub fn set_start(&mut self, start_position: StartPosition) {
+        self.start_position = start_position;
+    }
+}
+
+impl StageDef {
+    /// Removes the given object from its global list, along with every reference to it in the
+    /// collision headers' local lists. Returns whether the object was found in the global list.
+    ///
+    /// Collision headers share objects with the global lists, so removing it from the global list
+    /// alone would leave them holding on to an object that's no longer in the stagedef. Indices are
+    /// updated to match the remaining objects.
+    pub fn remove_object<T: StageDefObjectList>(&mut self, object: &Arc<Mutex<T>>) -> bool {
+        let global_list = T::get_global_list(self);
+        let global_count = global_list.len();
+        global_list.retain(|o| !Arc::ptr_eq(&o.object, object));
+        let was_removed = global_list.len() != global_count;
+        reindex_list(global_list);
+
+        for collision_header in &mut self.collision_headers {
+            let local_list = T::get_local_list(collision_header);
+            local_list.retain(|o| !Arc::ptr_eq(&o.object, object));
+            reindex_list(local_list);
+        }
+        self.update_global_indices::<T>();
+
+        was_removed
+    }
+
+    /// Moves the object at ``from`` in its global list to ``to``, shifting the objects in between.
+    /// Returns whether both positions were in the list.
+    ///
+    /// The order of some objects matters to the game, e.g. which goal takes priority. Collision
+    /// headers keep their own order, but their references to the global list are updated to match.
+    pub fn move_object<T: StageDefObjectList>(&mut self, from: usize, to: usize) -> bool {
+        if !move_within_list(T::get_global_list(self), from, to) {
+            return false;
+        }
+        self.update_global_indices::<T>();
+        true
+    }
+
+    /// Moves the object at ``from`` in the given collision header's local list to ``to``, shifting
+    /// the objects in between. Returns whether the collision header and both positions exist.
+    pub fn move_local_object<T: StageDefObjectList>(
+        &mut self,
+        collision_header_index: usize,
+        from: usize,
+        to: usize,
+    ) -> bool {
+        match self.collision_headers.get_mut(collision_header_index) {
+            Some(collision_header) => move_within_list(T::get_local_list(collision_header), from, to),
+            None => false,
+        }
+    }
+
+    /// Points every collision header's references to a global list at where those objects now are
+    /// in it, after the global list has changed.
+    fn update_global_indices<T: StageDefObjectList>(&mut self) {
+        let global_objects: Vec<_> = T::get_global_list(self).iter().map(|o| o.object.clone()).collect();
+        for collision_header in &mut self.collision_headers {
+            for local_object in T::get_local_list(collision_header) {
+                if local_object.global_index.is_some() {
+                    local_object.global_index = global_objects
+                        .iter()
+                        .position(|o| Arc::ptr_eq(o, &local_object.object))
+                        .and_then(|i| u32::try_from(i).ok());
+                }
+            }
+        }
+    }
+
+    /// Returns the given position relative to the start position, i.e. how far it is from where the
+    /// ball starts.
+    ///
+    /// Only the first start position is read, so stages with more than one are measured from that.
+    pub fn get_position_relative_to_start(&self, position: Vector3) -> Vector3 {
+        position.relative_to(self.start_position.position.0)
+    }
+
+    /// Finds which collision headers refer to each object in the stagedef.
+    ///
+    /// One global object can be in any number of collision headers' local lists, each with its own
+    /// index, so this is the only way to see everywhere it's used.
+    pub fn get_collision_header_references(&self) -> CollisionHeaderReferences {
+        fn add<T>(references: &mut CollisionHeaderReferences, header_index: usize, list: &[GlobalStagedefObject<T>]) {
+            for local_object in list {
+                let headers = references.0.entry(Arc::as_ptr(&local_object.object) as usize).or_default();
+                // An object listed twice in one header is still only one reference from it
+                if headers.last() != Some(&header_index) {
+                    headers.push(header_index);
+                }
+            }
+        }
+
+        let mut references = CollisionHeaderReferences::default();
+        for (header_index, header) in self.collision_headers.iter().enumerate() {
+            macro_rules! add_lists {
+                ($($object:ty => $list:ident),* $(,)?) => {
+                    $(add(&mut references, header_index, &header.$list);)*
+                };
+            }
+            stagedef_object_lists!(add_lists);
+        }
+        references
+    }
+}
+
+/// Sets each object's index to its position in the list.
+fn reindex_list<T>(list: &mut [GlobalStagedefObject<T>]) {
+    for (index, object) in list.iter_mut().enumerate() {
+        object.index = u32::try_from(index).expect("Too many objects in list");
+    }
+}
+
+/// Moves the object at ``from`` to ``to`` and reindexes the list, or returns ``false`` if either is
+/// out of range.
+fn move_within_list<T>(list: &mut Vec<GlobalStagedefObject<T>>, from: usize, to: usize) -> bool {
+    if from >= list.len() || to >= list.len() {
+        return false;
+    }
+    let object = list.remove(from);
+    list.insert(to, object);
+    reindex_list(list);
+    true
+}
+
+/// Which collision headers refer to each object in a [``StageDef``], from
+/// [``StageDef::get_collision_header_references``]. Objects are told apart by their shared
+/// allocation, so clones made with [``GlobalStagedefObject::deep_clone``] aren't the same object.
+#[derive(Default, Debug)]
+pub struct CollisionHeaderReferences(HashMap<usize, Vec<usize>>);
+
+impl CollisionHeaderReferences {
+    /// Returns the index of every collision header that refers to the given object, in order.
+    pub fn get<T>(&self, object: &Arc<Mutex<T>>) -> &[usize] {
+        self.0.get(&(Arc::as_ptr(object) as usize)).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Returns the name and position of every object in the list, unless it's decoration.
+fn iter_list_positions<T: StageDefObject>(
+    list: &[GlobalStagedefObject<T>],
+) -> impl Iterator<Item = (&'static str, Vector3)> + '_ {
+    list.iter()
+        .filter(|_| !T::is_decoration())
+        .filter_map(|o| Some((T::get_name(), o.object.lock().unwrap().get_position()?)))
+}
+
+/// Calls ``f`` with the name and position of every object in the list, unless it's decoration.
+fn for_each_list_position_mut<T: StageDefObject>(
+    list: &[GlobalStagedefObject<T>],
+    f: &mut impl FnMut(&'static str, &mut Vector3),
+) {
+    if T::is_decoration() {
+        return;
+    }
+    for o in list {
+        if let Some(position) = o.object.lock().unwrap().get_position_mut() {
+            f(T::get_name(), position);
+        }
+    }
+}
+
+/// Implements the methods for visiting the position of every object in a [``StageDef``], for the
+/// lists given by ``stagedef_object_lists``.
+macro_rules! impl_stagedef_position_visitors {
+    ($($object:ty => $list:ident),* $(,)?) => {
+        impl StageDef {
+            /// Returns the name and position of the start position, then every object in the global
+            /// lists that has one.
+            ///
+            /// Background models are left out, as they're decoration far outside the playable area.
+            pub fn iter_positions(&self) -> impl Iterator<Item = (&'static str, Vector3)> + '_ {
+                let start_position = std::iter::once((StartPosition::get_name(), self.start_position.position.0));
+                start_position$(.chain(iter_list_positions(&self.$list)))*
+            }
+
+            /// Calls ``f`` with the name and a mutable reference to the position of the start
+            /// position, then every object in the global lists, in the same order as
+            /// [``StageDef::iter_positions``].
+            ///
+            /// Objects are shared with collision headers behind a lock, so they can't be handed out
+            /// by a mutable iterator.
+            pub fn for_each_position_mut(&mut self, mut f: impl FnMut(&'static str, &mut Vector3)) {
+                f(StartPosition::get_name(), &mut self.start_position.position.0);
+                $(for_each_list_position_mut(&self.$list, &mut f);)*
+            }
+        }
+    };
+}
+
+stagedef_object_lists!(impl_stagedef_position_visitors);
+
+impl StageDef {
+    /// The magic numbers found in every known stagedef.
+    pub const EXPECTED_MAGIC_NUMBERS: (f32, f32) = (0.0, 1000.0);
+
+    /// Returns whether the given magic numbers are (close to) the ones found in every known
+    /// stagedef. If they aren't, the file was probably read with the wrong endianness or game.
+    pub fn are_magic_numbers_expected(magic_number_1: f32, magic_number_2: f32) -> bool {
+        let (expected_1, expected_2) = Self::EXPECTED_MAGIC_NUMBERS;
+        (magic_number_1 - expected_1).abs() < 0.001 && (magic_number_2 - expected_2).abs() < 0.001
+    }
+
+    /// Returns a warning describing the problem if this stagedef's magic numbers are unusual.
+    pub fn get_magic_number_warning(&self) -> Option<String> {
+        if Self::are_magic_numbers_expected(self.magic_number_1, self.magic_number_2) {
+            return None;
+        }
+
+        let (expected_1, expected_2) = Self::EXPECTED_MAGIC_NUMBERS;
+        Some(format!(
+            "Magic numbers are {} and {}, expected {expected_1:.1} and {expected_2:.1}. This file may have been misparsed, \
+             or be from an unexpected game or endianness.",
+            self.magic_number_1, self.magic_number_2
+        ))
+    }
+
+    /// Returns the length of the diagonal of the axis-aligned box containing every positioned
+    /// object in the stagedef, or ``None`` if the stagedef has no positioned objects.
+    pub fn get_bounding_box_diagonal(&self) -> Option<f32> {
+        let points = self.iter_positions().map(|(_, p)| [p.x, p.y, p.z]);
+
+        let mut min = [f32::MAX; 3];
+        let mut max = [f32::MIN; 3];
+        for point in points.filter(|p| p.iter().all(|c| c.is_finite())) {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+
+        if min[0] > max[0] {
+            return None;
+        }
+
+        let diagonal = (0..3).map(|axis| (max[axis] - min[axis]).powi(2)).sum::<f32>().sqrt();
+        Some(diagonal)
+    }
+
+    /// Returns roughly how many bytes this stagedef takes up in memory, for the debug overlay.
+    ///
+    /// Counts the stagedef itself, its lists and the objects in them, but not allocator overhead
+    /// or strings held by individual objects.
+    pub fn get_approximate_memory_usage(&self) -> usize {
+        use std::mem::{size_of, size_of_val};
+
+        // Each object in a global list has its own allocation too, holding the Arc's reference
+        // counts and the object itself
+        fn global_list_size<T>(list: &[GlobalStagedefObject<T>]) -> usize {
+            size_of_val(list) + list.len() * (2 * size_of::<usize>() + size_of::<Mutex<T>>())
+        }
+
+        macro_rules! global_lists_size {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                [$(global_list_size(&self.$list)),*].into_iter().sum::<usize>()
+            };
+        }
+        let global_lists = stagedef_object_lists!(global_lists_size);
+
+        // Objects in local lists are shared with the global lists, so only the list entries count
+        let collision_headers: usize = self
+            .collision_headers
+            .iter()
+            .map(|header| {
+                macro_rules! local_lists_size {
+                    ($($object:ty => $list:ident),* $(,)?) => {
+                        [$(size_of_val(header.$list.as_slice())),*].into_iter().sum::<usize>()
+                    };
+                }
+
+                let keyframes = match &header.animation {
+                    CollisionHeaderAnimation::Keyframed(animation) => [
+                        &animation.rotation_x,
+                        &animation.rotation_y,
+                        &animation.rotation_z,
+                        &animation.position_x,
+                        &animation.position_y,
+                        &animation.position_z,
+                    ]
+                    .iter()
+                    .map(|keyframes| size_of_val(keyframes.as_slice()))
+                    .sum(),
+                    _ => 0,
+                };
+
+                size_of::<CollisionHeader>()
+                    + size_of_val(header.collision_triangles.as_slice())
+                    + header
+                        .collision_grid_cells
+                        .iter()
+                        .map(|cell| size_of::<Vec<u16>>() + size_of_val(cell.as_slice()))
+                        .sum::<usize>()
+                    + keyframes
+                    + stagedef_object_lists!(local_lists_size)
+                    + header.level_model_names.iter().map(|name| size_of::<String>() + name.len()).sum::<usize>()
+            })
+            .sum();
+
+        let model_names: usize = self.model_names.iter().map(|name| size_of::<String>() + name.len()).sum();
+
+        size_of::<Self>() + global_lists + collision_headers + model_names + self.trailing_bytes.len()
+    }
 }
 
 #[derive(Debug)]
 pub struct GlobalStagedefObject<T> {
     pub object: Arc<Mutex<T>>,
     pub index: u32,
+    /// The object's index in its global list, if [``index``](Self::index) is its index within a
+    /// collision header's local list instead.
+    pub global_index: Option<u32>,
+    /// Where the object starts in the file it was read from, if it was read from one.
+    pub file_offset: Option<u64>,
 }
 
 impl<T> GlobalStagedefObject<T> {
@@ -47,19 +532,57 @@ impl<T> GlobalStagedefObject<T> {
         Self {
             object: Arc::new(Mutex::new(object)),
             index,
+            global_index: None,
+            file_offset: None,
         }
     }
+
+    pub fn with_file_offset(mut self, file_offset: u64) -> Self {
+        self.file_offset = Some(file_offset);
+        self
+    }
+
+    /// Returns whether ``other`` refers to the very same object, rather than just an equal one like
+    /// [``PartialEq``] checks for.
+    pub fn shares_object_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.object, &other.object)
+    }
+}
+
+impl<T: Clone> GlobalStagedefObject<T> {
+    /// Returns a copy with its own object, so edits to the copy don't show up in the original.
+    pub fn deep_clone(&self) -> Self {
+        let object = self.object.lock().unwrap().clone();
+        Self {
+            object: Arc::new(Mutex::new(object)),
+            ..self.clone()
+        }
+    }
+}
+
+impl<T: StageDefObject> GlobalStagedefObject<T> {
+    /// Returns the range of bytes the object was read from, if it was read from a file of the given
+    /// game.
+    pub fn get_file_range(&self, game: Game) -> Option<std::ops::Range<usize>> {
+        let start = usize::try_from(self.file_offset?).ok()?;
+        Some(start..start + T::get_size_for_game(game) as usize)
+    }
 }
 
+/// Clones share the object with the original, which is how collision headers refer to objects in
+/// the global lists. Use [``GlobalStagedefObject::deep_clone``] for an independent copy.
 impl<T> Clone for GlobalStagedefObject<T> {
     fn clone(&self) -> Self {
         Self {
             object: self.object.clone(),
             index: self.index,
+            global_index: self.global_index,
+            file_offset: self.file_offset,
         }
     }
 }
 
+#[cfg(feature = "gui")]
 impl<T: EguiInspect> EguiInspect for GlobalStagedefObject<T> {
     fn inspect(&self, label: &str, ui: &mut egui::Ui) {
         let guard = self.object.lock().unwrap();
@@ -86,32 +609,283 @@ impl<T: PartialEq> PartialEq for GlobalStagedefObject<T> {
     }
 }
 
+/// Provides access to the lists an object type is kept in, both in a [``StageDef``] and in each of
+/// its [``CollisionHeader``]s, as well as in a [``StageSnippet``].
+pub trait StageDefObjectList: Sized {
+    fn get_global_list(stagedef: &mut StageDef) -> &mut Vec<GlobalStagedefObject<Self>>;
+    fn get_local_list(collision_header: &mut CollisionHeader) -> &mut Vec<GlobalStagedefObject<Self>>;
+    fn get_snippet_list(snippet: &mut StageSnippet) -> &mut Vec<Self>;
+}
+
+macro_rules! impl_stagedef_object_list {
+    ($($object:ty => $list:ident),* $(,)?) => {
+        $(
+            impl StageDefObjectList for $object {
+                fn get_global_list(stagedef: &mut StageDef) -> &mut Vec<GlobalStagedefObject<Self>> {
+                    &mut stagedef.$list
+                }
+                fn get_local_list(collision_header: &mut CollisionHeader) -> &mut Vec<GlobalStagedefObject<Self>> {
+                    &mut collision_header.$list
+                }
+                fn get_snippet_list(snippet: &mut StageSnippet) -> &mut Vec<Self> {
+                    &mut snippet.$list
+                }
+            }
+        )*
+    };
+}
+
+stagedef_object_lists!(impl_stagedef_object_list);
+
 /// Provides a method for returning the file size of an object in a [``StageDef``].
 pub trait StageDefObject {
     fn get_name() -> &'static str;
+    /// Returns an explanation of this object for the inspector. Longer descriptions can use the
+    /// markup described in [``crate::description``].
     fn get_description() -> &'static str;
     fn get_size() -> u32;
+
+    /// Returns the size of this object in the given game's stagedefs. Most objects are the same
+    /// size in every game.
+    fn get_size_for_game(_game: Game) -> u32 {
+        Self::get_size()
+    }
+
+    /// Returns where this object is, for objects that have a position.
+    fn get_position(&self) -> Option<Vector3> {
+        None
+    }
+
+    /// Returns where this object is, to move it, for objects that have a position.
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        None
+    }
+
+    /// Returns whether objects of this type are only decoration, far outside the playable area, so
+    /// are left out of [``StageDef::iter_positions``].
+    fn is_decoration() -> bool {
+        false
+    }
+
+    /// Returns the color used for this object's label in the stagedef tree, if it should differ
+    /// from the default.
+    #[cfg(feature = "gui")]
+    fn get_tree_color(&self) -> Option<egui::Color32> {
+        None
+    }
+
+    /// Returns whether this object type shows a small editor next to its label in the stagedef
+    /// tree.
+    #[cfg(feature = "gui")]
+    fn has_tree_inline_editor() -> bool {
+        false
+    }
+
+    /// Displays a small editor next to this object's label in the stagedef tree. Only called if
+    /// [``StageDefObject::has_tree_inline_editor``] returns ``true``.
+    #[cfg(feature = "gui")]
+    fn show_tree_inline_editor(&mut self, _ui: &mut egui::Ui) {}
 }
 
 pub trait StageDefParsable: StageDefObject {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
         R: ReadBytesExtSmb;
+
+    /// Reads the object in the layout used by the given game.
+    ///
+    /// Objects whose layout doesn't differ between games can rely on the default, which just
+    /// calls [``StageDefParsable::try_from_reader``].
+    fn try_from_reader_for_game<R, B>(reader: &mut R, _game: Game) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        Self::try_from_reader::<R, B>(reader)
+    }
 }
 
 /// 32-bit floating point 3 dimensional vector.
-#[derive(Default, Debug, PartialEq, EguiInspect)]
+#[derive(Default, Debug, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub struct Vector3 {
-    #[inspect(slider = false)]
     pub x: f32,
-    #[inspect(slider = false)]
     pub y: f32,
-    #[inspect(slider = false)]
     pub z: f32,
 }
 
+/// Vectors that aren't positions, like scales, colors and normals, are shown exactly as they're
+/// stored. Positions are shown in the user's unit and up axis instead; see [``Position``].
+#[cfg(feature = "gui")]
+impl EguiInspect for Vector3 {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for component in [self.x, self.y, self.z] {
+                ui.label(format!("{component:.3}"));
+            }
+        });
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for component in [&mut self.x, &mut self.y, &mut self.z] {
+                ui.add(egui::DragValue::new(component));
+            }
+        });
+    }
+}
+
+/// A point in the stage, or the size of something in it, in game units with Y up.
+///
+/// Unlike a plain [``Vector3``], this is always a point or distance in the stage, so it's shown in
+/// the unit and up axis chosen in the instance's [``UnitSettings``].
+#[derive(Default, Debug, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Position(pub Vector3);
+
+impl std::ops::Deref for Position {
+    type Target = Vector3;
+
+    fn deref(&self) -> &Vector3 {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for Position {
+    fn deref_mut(&mut self) -> &mut Vector3 {
+        &mut self.0
+    }
+}
+
+impl From<Vector3> for Position {
+    fn from(value: Vector3) -> Self {
+        Self(value)
+    }
+}
+
+impl PartialEq<Vector3> for Position {
+    fn eq(&self, other: &Vector3) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "gui")]
+impl EguiInspect for Position {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        let units = UnitSettings::load(ui.ctx());
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for component in units.up_axis.from_game([self.x, self.y, self.z]) {
+                ui.label(format!("{:.3}{}", units.get_display_value(component), units.get_suffix()));
+            }
+        });
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        let units = UnitSettings::load(ui.ctx());
+        let mut components = units.up_axis.from_game([self.x, self.y, self.z]);
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for component in &mut components {
+                let mut displayed = units.get_display_value(*component);
+                if ui.add(egui::DragValue::new(&mut displayed).suffix(units.get_suffix())).changed() {
+                    *component = units.get_game_units(displayed, *component);
+                }
+            }
+        });
+        let [x, y, z] = units.up_axis.to_game(components);
+        self.0 = Vector3 { x, y, z };
+    }
+}
+
+impl Vector3 {
+    /// Returns the components separated by spaces, at full precision, for pasting into other tools.
+    pub fn get_clipboard_text(&self) -> String {
+        format!("{} {} {}", self.x, self.y, self.z)
+    }
+
+    /// Returns this position as seen from ``origin``.
+    pub fn relative_to(self, origin: Vector3) -> Vector3 {
+        Vector3 {
+            x: self.x - origin.x,
+            y: self.y - origin.y,
+            z: self.z - origin.z,
+        }
+    }
+}
+
+/// Returns how far along the ray it hits the given triangle, if it does.
+///
+/// This is the Möller-Trumbore intersection. Triangles are hit from either side.
+pub fn intersect_ray_triangle(origin: [f32; 3], direction: [f32; 3], [v1, v2, v3]: &[Vector3; 3]) -> Option<f32> {
+    let sub = |a: &Vector3, b: &Vector3| [a.x - b.x, a.y - b.y, a.z - b.z];
+    let cross = |a: [f32; 3], b: [f32; 3]| {
+        [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ]
+    };
+    let dot = |a: [f32; 3], b: [f32; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+    let edge1 = sub(v2, v1);
+    let edge2 = sub(v3, v1);
+    let p = cross(direction, edge2);
+    let determinant = dot(edge1, p);
+    // The ray runs along the triangle's plane (or the triangle is degenerate)
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let [x, y, z] = origin;
+    let to_origin = sub(&Vector3 { x, y, z }, v1);
+    let u = dot(to_origin, p) / determinant;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = cross(to_origin, edge1);
+    let v = dot(direction, q) / determinant;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = dot(edge2, q) / determinant;
+    (distance >= 0.0).then_some(distance)
+}
+
+/// Returns a single object as pretty-printed JSON, for copying out of the viewer.
+pub fn get_object_json<T: serde::Serialize>(object: &T) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(object)
+}
+
+/// Returns a single object as pretty-printed JSON, with a ``type`` field naming what kind of object
+/// it is, so it can be pasted back in with [``StageSnippet::from_clipboard_json``].
+pub fn get_tagged_object_json<T: StageDefObject + serde::Serialize>(object: &T) -> serde_json::Result<String> {
+    #[derive(serde::Serialize)]
+    struct TaggedObject<'a, T> {
+        #[serde(rename = "type")]
+        type_name: &'static str,
+        #[serde(flatten)]
+        object: &'a T,
+    }
+
+    serde_json::to_string_pretty(&TaggedObject {
+        type_name: T::get_name(),
+        object,
+    })
+}
+
 impl Display for Vector3 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "({:.1}, {:.1}, {:.1})", self.x, self.y, self.z)
@@ -129,13 +903,14 @@ impl From<ShortVector3> for Vector3 {
 }
 
 /// 16-bit 'short' 3 dimensional vector. Used to represent rotations in Monkey Ball stagedefs.
-#[derive(Default, Debug, PartialEq, EguiInspect, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct ShortVector3 {
-    #[inspect(slider, min = 0.0, max = 65535.0)]
+    #[cfg_attr(feature = "gui", inspect(slider, min = 0.0, max = 65535.0))]
     pub x: u16,
-    #[inspect(slider, min = 0.0, max = 65535.0)]
+    #[cfg_attr(feature = "gui", inspect(slider, min = 0.0, max = 65535.0))]
     pub y: u16,
-    #[inspect(slider, min = 0.0, max = 65535.0)]
+    #[cfg_attr(feature = "gui", inspect(slider, min = 0.0, max = 65535.0))]
     pub z: u16,
 }
 
@@ -159,9 +934,316 @@ impl Default for Game {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Endianness {
     #[default]
     BigEndian,
     LittleEndian,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_file_range_uses_size_for_game() {
+        let model = GlobalStagedefObject::new(ReflectiveModel::default(), 0).with_file_offset(0x100);
+        assert_eq!(model.get_file_range(Game::SMB1), Some(0x100..0x108));
+        assert_eq!(model.get_file_range(Game::SMB2), Some(0x100..0x10C));
+    }
+
+    #[test]
+    fn test_intersect_ray_triangle() {
+        let triangle = [
+            Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 2.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 0.0, y: 0.0, z: 2.0 },
+        ];
+
+        assert_eq!(
+            intersect_ray_triangle([0.5, 3.0, 0.5], [0.0, -1.0, 0.0], &triangle),
+            Some(3.0)
+        );
+        // From below, as triangles are hit from either side
+        assert_eq!(
+            intersect_ray_triangle([0.5, -1.0, 0.5], [0.0, 1.0, 0.0], &triangle),
+            Some(1.0)
+        );
+        // Beside the triangle, away from it, and along its plane
+        assert_eq!(intersect_ray_triangle([1.5, 3.0, 1.5], [0.0, -1.0, 0.0], &triangle), None);
+        assert_eq!(intersect_ray_triangle([0.5, 3.0, 0.5], [0.0, 1.0, 0.0], &triangle), None);
+        assert_eq!(intersect_ray_triangle([-1.0, 0.0, 0.5], [1.0, 0.0, 0.0], &triangle), None);
+    }
+
+    #[test]
+    fn test_stagedef_builder() {
+        let mut stagedef = StageDef::new()
+            .with_goal(Goal {
+                goal_type: GoalType::Green,
+                ..Default::default()
+            })
+            .with_banana(Banana {
+                position: Position::default(),
+                banana_type: BananaType::Single,
+            })
+            .with_banana(Banana {
+                position: Position::default(),
+                banana_type: BananaType::Bunch,
+            });
+        stagedef.set_start(StartPosition {
+            position: Position(Vector3 { x: 0.0, y: 2.75, z: 14.0 }),
+            ..Default::default()
+        });
+        stagedef.add_collision_header(CollisionHeader::default());
+
+        assert_eq!(stagedef.get_magic_number_warning(), None);
+        assert_eq!(stagedef.goals.len(), 1);
+        assert_eq!(stagedef.goals[0].object.lock().unwrap().goal_type, GoalType::Green);
+        assert_eq!(stagedef.bananas.iter().map(|b| b.index).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(stagedef.bananas[1].object.lock().unwrap().banana_type, BananaType::Bunch);
+        assert_eq!(stagedef.start_position.position, Vector3 { x: 0.0, y: 2.75, z: 14.0 });
+        assert_eq!(stagedef.collision_headers.len(), 1);
+    }
+
+    #[test]
+    fn test_approximate_memory_usage() {
+        let empty = StageDef::new();
+        assert_eq!(empty.get_approximate_memory_usage(), std::mem::size_of::<StageDef>());
+
+        let banana = || Banana {
+            position: Position::default(),
+            banana_type: BananaType::Single,
+        };
+        let mut stagedef = StageDef::new().with_banana(banana()).with_banana(banana());
+        stagedef.trailing_bytes = vec![0; 100];
+        let with_bananas = stagedef.get_approximate_memory_usage();
+        assert!(with_bananas > empty.get_approximate_memory_usage() + 100 + 2 * std::mem::size_of::<Banana>());
+
+        stagedef.add_collision_header(CollisionHeader {
+            collision_triangles: (0..10).map(|_| CollisionTriangle::default()).collect(),
+            ..Default::default()
+        });
+        assert!(
+            stagedef.get_approximate_memory_usage()
+                >= with_bananas + std::mem::size_of::<CollisionHeader>() + 10 * std::mem::size_of::<CollisionTriangle>()
+        );
+    }
+
+    #[test]
+    fn test_copy_object_text() {
+        let goal = Goal {
+            position: Position(Vector3 {
+                x: 1.5,
+                y: -2.0,
+                z: -115.25,
+            }),
+            rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+            goal_type: GoalType::Green,
+        };
+
+        assert_eq!(goal.get_position().unwrap().get_clipboard_text(), "1.5 -2 -115.25");
+
+        let json = get_object_json(&goal).unwrap();
+        assert_eq!(
+            json,
+            r#"{
+  "position": {
+    "x": 1.5,
+    "y": -2.0,
+    "z": -115.25
+  },
+  "rotation": {
+    "x": 0,
+    "y": 32768,
+    "z": 0
+  },
+  "goal_type": "Green"
+}"#
+        );
+    }
+
+    #[test]
+    fn test_iter_positions() {
+        let mut stagedef = StageDef::new()
+            .with_goal(Goal {
+                position: Position(Vector3 { x: 0.0, y: 0.0, z: -115.0 }),
+                ..Default::default()
+            })
+            .with_banana(Banana {
+                position: Position(Vector3 { x: 1.0, y: 2.0, z: 3.0 }),
+                banana_type: BananaType::Single,
+            });
+        stagedef.set_start(StartPosition {
+            position: Position(Vector3 { x: 0.0, y: 2.75, z: 14.0 }),
+            ..Default::default()
+        });
+
+        let positions: Vec<_> = stagedef.iter_positions().collect();
+        assert_eq!(
+            positions,
+            vec![
+                (StartPosition::get_name(), Vector3 { x: 0.0, y: 2.75, z: 14.0 }),
+                (Goal::get_name(), Vector3 { x: 0.0, y: 0.0, z: -115.0 }),
+                (Banana::get_name(), Vector3 { x: 1.0, y: 2.0, z: 3.0 }),
+            ]
+        );
+
+        stagedef.for_each_position_mut(|_, position| position.y += 10.0);
+        let heights: Vec<_> = stagedef.iter_positions().map(|(_, p)| p.y).collect();
+        assert_eq!(heights, vec![12.75, 10.0, 12.0]);
+        let diagonal = stagedef.get_bounding_box_diagonal().unwrap();
+        assert!((diagonal - (1.0_f32 + 2.75 * 2.75 + 129.0 * 129.0).sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_remove_object_from_collision_headers() {
+        let mut stagedef = StageDef::new()
+            .with_goal(Goal {
+                goal_type: GoalType::Blue,
+                ..Default::default()
+            })
+            .with_goal(Goal {
+                goal_type: GoalType::Red,
+                ..Default::default()
+            });
+
+        // The header refers to both goals, with the red goal first in its local list
+        stagedef.add_collision_header_referencing::<Goal>(&[1, 0]);
+
+        let blue_goal = stagedef.goals[0].object.clone();
+        assert!(stagedef.remove_object(&blue_goal));
+
+        assert_eq!(stagedef.goals.len(), 1);
+        assert_eq!(stagedef.goals[0].index, 0);
+
+        let local_goals = &stagedef.collision_headers[0].goals;
+        assert_eq!(local_goals.len(), 1);
+        assert!(!local_goals.iter().any(|g| Arc::ptr_eq(&g.object, &blue_goal)));
+        assert_eq!(local_goals[0].object.lock().unwrap().goal_type, GoalType::Red);
+        assert_eq!(local_goals[0].index, 0);
+        assert_eq!(local_goals[0].global_index, Some(0));
+
+        // Removing it again finds nothing
+        assert!(!stagedef.remove_object(&blue_goal));
+    }
+
+    #[test]
+    fn test_shared_and_deep_clones() {
+        let original = GlobalStagedefObject::new(Goal::default(), 0);
+        let shared = original.clone();
+        let deep = original.deep_clone();
+
+        // Both are equal to the original, but only the shared clone is the same object
+        assert!(shared == original && deep == original);
+        assert!(shared.shares_object_with(&original));
+        assert!(!deep.shares_object_with(&original));
+
+        // So only edits through the shared clone reach the original
+        shared.object.lock().unwrap().goal_type = GoalType::Red;
+        deep.object.lock().unwrap().goal_type = GoalType::Green;
+        assert_eq!(original.object.lock().unwrap().goal_type, GoalType::Red);
+        assert!(deep != original);
+    }
+
+    #[test]
+    fn test_move_object() {
+        let mut stagedef = StageDef::new();
+        for goal_type in [GoalType::Blue, GoalType::Green, GoalType::Red] {
+            stagedef = stagedef.with_goal(Goal {
+                goal_type,
+                ..Default::default()
+            });
+        }
+
+        // The header lists the red goal before the blue one
+        stagedef.add_collision_header_referencing::<Goal>(&[2, 0]);
+
+        // Moving the red goal to the front shifts the others back
+        assert!(stagedef.move_object::<Goal>(2, 0));
+        let goal_types: Vec<_> = stagedef.goals.iter().map(|g| g.object.lock().unwrap().goal_type.clone()).collect();
+        assert_eq!(goal_types, [GoalType::Red, GoalType::Blue, GoalType::Green]);
+        let indices: Vec<_> = stagedef.goals.iter().map(|g| g.index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+
+        // The header's order stays the same, but its references follow the goals
+        let local_goals = &stagedef.collision_headers[0].goals;
+        assert!(local_goals[0].shares_object_with(&stagedef.goals[0]));
+        assert_eq!(local_goals.iter().map(|g| g.global_index).collect::<Vec<_>>(), [Some(0), Some(1)]);
+
+        // Local lists are reordered on their own
+        assert!(stagedef.move_local_object::<Goal>(0, 0, 1));
+        let local_goals = &stagedef.collision_headers[0].goals;
+        let local_indices: Vec<_> = local_goals.iter().map(|g| (g.index, g.global_index)).collect();
+        assert_eq!(local_indices, [(0, Some(1)), (1, Some(0))]);
+        assert!(local_goals[0].shares_object_with(&stagedef.goals[1]));
+
+        assert!(!stagedef.move_object::<Goal>(0, 3));
+        assert!(!stagedef.move_local_object::<Goal>(1, 0, 1));
+    }
+
+    #[test]
+    fn test_position_relative_to_start() {
+        let mut stagedef = StageDef::new();
+        stagedef.set_start(StartPosition {
+            position: Position(Vector3 { x: 0.0, y: 2.75, z: 14.0 }),
+            ..Default::default()
+        });
+
+        let goal_position = Vector3 { x: 0.0, y: 0.0, z: -115.0 };
+        assert_eq!(
+            stagedef.get_position_relative_to_start(goal_position),
+            Vector3 { x: 0.0, y: -2.75, z: -129.0 }
+        );
+        assert_eq!(stagedef.get_position_relative_to_start(stagedef.start_position.position.0), Vector3::default());
+    }
+
+    #[test]
+    fn test_collision_header_references() {
+        let mut stagedef = StageDef::new()
+            .with_goal(Goal::default())
+            .with_goal(Goal::default())
+            .with_goal(Goal::default());
+
+        // The first goal is shared by both headers, the second is only in the second header, and
+        // the third isn't in either
+        stagedef.add_collision_header_referencing::<Goal>(&[0]);
+        stagedef.add_collision_header_referencing::<Goal>(&[0, 1]);
+
+        let references = stagedef.get_collision_header_references();
+        assert_eq!(references.get(&stagedef.goals[0].object), [0, 1]);
+        assert_eq!(references.get(&stagedef.goals[1].object), [1]);
+        assert!(references.get(&stagedef.goals[2].object).is_empty());
+
+        // A copy of a goal isn't referred to by anything
+        let copy = stagedef.goals[0].deep_clone();
+        assert!(references.get(&copy.object).is_empty());
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn test_inspect_mut_keeps_scale_under_z_up() {
+        use crate::stagedef::units::{DistanceUnit, UnitSettings, UpAxis};
+
+        let scale = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let mut inspected_scale = scale;
+        let mut inspected_position = Position(scale);
+
+        let ctx = egui::Context::default();
+        let _ = ctx.run(egui::RawInput::default(), |ctx| {
+            UnitSettings {
+                unit: DistanceUnit::Meters,
+                meters_per_game_unit: 0.3,
+                up_axis: UpAxis::Z,
+            }
+            .store(ctx);
+            egui::CentralPanel::default().show(ctx, |ui| {
+                inspected_scale.inspect_mut("Scale", ui);
+                inspected_position.inspect_mut("Position", ui);
+            });
+        });
+
+        // Scales aren't rotated into Z-up, and positions come back as they were if they weren't edited
+        assert_eq!(inspected_scale, scale);
+        assert_eq!(inspected_position, scale);
+    }
+}
@@ -0,0 +1,266 @@
+//! Handles detecting and undoing compression applied to stage files.
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::io::Read;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+/// The size of the header at the start of Monkey Ball LZ files: the compressed size (including the
+/// header) and decompressed size, both little endian.
+const LZ_HEADER_SIZE: usize = 8;
+/// The size of the LZSS sliding window.
+const LZ_RING_BUFFER_SIZE: usize = 4096;
+/// The longest run a single LZSS back-reference can copy.
+const LZ_MAX_MATCH_LENGTH: usize = 18;
+/// Runs this short or shorter are stored as literals, so back-reference lengths are stored minus
+/// this plus one.
+const LZ_MATCH_THRESHOLD: usize = 2;
+/// The most LZSS data can grow by when decompressed: a flag byte followed by 8 two-byte
+/// back-references of the longest length turns 17 bytes into 144.
+const LZ_MAX_EXPANSION: usize = 9;
+
+/// A kind of compression a stage file can be wrapped in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Used by the games themselves, with a header giving the compressed and decompressed sizes.
+    MonkeyBallLz,
+    /// Used by some community tools when distributing stagedefs.
+    Gzip,
+    /// Used by some community tools when distributing stagedefs.
+    Zlib,
+    /// No compression we know how to undo.
+    None,
+}
+
+impl Compression {
+    /// Guesses which kind of compression the given buffer uses from its first few bytes.
+    ///
+    /// Monkey Ball LZ files are only recognised by their header, as the compressed size in it
+    /// matches the size of the file. LZ data without a header has to be decompressed with
+    /// [``decompress_lz``] directly.
+    pub fn detect(buffer: &[u8]) -> Self {
+        if read_lz_header(buffer).is_some() {
+            return Compression::MonkeyBallLz;
+        }
+
+        match buffer {
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            // Compression method 8 (deflate) with a window of at most 32K, and a header checksum
+            [cmf, flg, ..] if cmf & 0x0f == 8 && cmf >> 4 <= 7 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 => {
+                Compression::Zlib
+            }
+            _ => Compression::None,
+        }
+    }
+}
+
+/// A failure while decompressing a Monkey Ball LZ file.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LzError {
+    /// The data ended partway through a back-reference.
+    #[error("LZ data ended partway through a back-reference")]
+    Truncated,
+    /// The decompressed data isn't the size the LZ header says it should be.
+    #[error("Decompressed LZ data is {actual:#X} bytes, but its header says {expected:#X}")]
+    SizeMismatch { expected: usize, actual: usize },
+    /// The decompressed data grew past the size the LZ header says it should be, so decompressing
+    /// was stopped.
+    #[error("Decompressed LZ data is larger than the {expected:#X} bytes its header says")]
+    TooLarge { expected: usize },
+}
+
+/// A buffer after [``decompress``] has been run on it.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DecompressedBuffer {
+    pub buffer: Vec<u8>,
+    pub compression: Compression,
+    /// The size of the buffer before it was decompressed.
+    pub compressed_size: usize,
+}
+
+/// Returns the decompressed size from the LZ header at the start of the given buffer, if it has
+/// one.
+fn read_lz_header(buffer: &[u8]) -> Option<usize> {
+    let header = buffer.get(..LZ_HEADER_SIZE)?;
+    let compressed_size = u32::from_le_bytes(header[0..4].try_into().unwrap());
+    let decompressed_size = u32::from_le_bytes(header[4..8].try_into().unwrap());
+
+    (usize::try_from(compressed_size).ok()? == buffer.len()).then_some(decompressed_size as usize)
+}
+
+/// The output of an LZSS decoder, along with the sliding window back-references copy from.
+struct LzWindow {
+    output: Vec<u8>,
+    ring_buffer: [u8; LZ_RING_BUFFER_SIZE],
+    ring_position: usize,
+}
+
+impl LzWindow {
+    fn new(capacity: usize) -> Self {
+        Self {
+            output: Vec::with_capacity(capacity),
+            ring_buffer: [0; LZ_RING_BUFFER_SIZE],
+            ring_position: LZ_RING_BUFFER_SIZE - LZ_MAX_MATCH_LENGTH,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.output.push(byte);
+        self.ring_buffer[self.ring_position] = byte;
+        self.ring_position = (self.ring_position + 1) % LZ_RING_BUFFER_SIZE;
+    }
+}
+
+/// Decompresses Monkey Ball LZ (LZSS) data, without the header.
+///
+/// If ``expected_size`` is given, as it is when the data had a header, it's an error for the
+/// decompressed data to be any other size. It comes from the file, so it's only trusted as far as
+/// the data could actually decompress to.
+pub fn decompress_lz(data: &[u8], expected_size: Option<usize>) -> Result<Vec<u8>, LzError> {
+    let max_size = data.len().saturating_mul(LZ_MAX_EXPANSION);
+    let mut window = LzWindow::new(expected_size.unwrap_or(data.len() * 2).min(max_size));
+    let mut input = data.iter().copied();
+
+    // Each flag byte says whether the next 8 items are literals (1) or back-references (0), lowest
+    // bit first. The last flag byte can have more bits than there are items left.
+    'flags: while let Some(flags) = input.next() {
+        for bit in 0..8 {
+            if let Some(expected) = expected_size.filter(|&expected| window.output.len() > expected) {
+                return Err(LzError::TooLarge { expected });
+            }
+            let Some(first) = input.next() else {
+                break 'flags;
+            };
+
+            if flags >> bit & 1 == 1 {
+                window.push(first);
+                continue;
+            }
+
+            let second = input.next().ok_or(LzError::Truncated)?;
+            let offset = usize::from(first) | usize::from(second & 0xF0) << 4;
+            let length = usize::from(second & 0x0F) + LZ_MATCH_THRESHOLD + 1;
+            for i in 0..length {
+                // Copies can overlap with what they're writing, so this has to be done a byte at a time
+                window.push(window.ring_buffer[(offset + i) % LZ_RING_BUFFER_SIZE]);
+            }
+        }
+    }
+
+    let output = window.output;
+    match expected_size {
+        Some(expected) if output.len() > expected => Err(LzError::TooLarge { expected }),
+        Some(expected) if expected != output.len() => Err(LzError::SizeMismatch {
+            expected,
+            actual: output.len(),
+        }),
+        _ => Ok(output),
+    }
+}
+
+/// Returns the decompressed contents of the given buffer, or the buffer itself if it isn't
+/// compressed.
+///
+/// Compression is checked for in the order Monkey Ball LZ, gzip/zlib, then raw. If decompressing
+/// gzip or zlib fails, the buffer is assumed to be raw. LZ files have a header we can be sure of,
+/// so failing to decompress them is an error.
+pub fn decompress(buffer: Vec<u8>) -> Result<DecompressedBuffer, LzError> {
+    let compression = Compression::detect(&buffer);
+    let compressed_size = buffer.len();
+    let mut decompressed = Vec::new();
+
+    let result = match compression {
+        Compression::MonkeyBallLz => {
+            let expected_size = read_lz_header(&buffer);
+            decompressed = decompress_lz(&buffer[LZ_HEADER_SIZE..], expected_size)?;
+            Ok(decompressed.len())
+        }
+        Compression::Gzip => GzDecoder::new(buffer.as_slice()).read_to_end(&mut decompressed),
+        Compression::Zlib => ZlibDecoder::new(buffer.as_slice()).read_to_end(&mut decompressed),
+        Compression::None => {
+            return Ok(DecompressedBuffer {
+                buffer,
+                compression,
+                compressed_size,
+            })
+        }
+    };
+
+    match result {
+        Ok(_) => {
+            debug!(
+                "Decompressed {compression:?} buffer from {} to {} bytes",
+                buffer.len(),
+                decompressed.len()
+            );
+            Ok(DecompressedBuffer {
+                buffer: decompressed,
+                compression,
+                compressed_size,
+            })
+        }
+        Err(err) => {
+            warn!("Failed to decompress buffer as {compression:?}, treating it as raw: {err}");
+            Ok(DecompressedBuffer {
+                buffer,
+                compression: Compression::None,
+                compressed_size,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// "ABC" as literals, then a back-reference copying 6 bytes from the start of it, which
+    /// overlaps with what it writes.
+    const LZ_PAYLOAD: [u8; 6] = [0b0000_0111, b'A', b'B', b'C', 0xEE, 0xF3];
+
+    fn with_lz_header(decompressed_size: u32) -> Vec<u8> {
+        let compressed_size = (LZ_HEADER_SIZE + LZ_PAYLOAD.len()) as u32;
+        let mut buffer = compressed_size.to_le_bytes().to_vec();
+        buffer.extend(decompressed_size.to_le_bytes());
+        buffer.extend(LZ_PAYLOAD);
+        buffer
+    }
+
+    #[test]
+    fn test_lz_with_header() {
+        let buffer = with_lz_header(9);
+        assert_eq!(Compression::detect(&buffer), Compression::MonkeyBallLz);
+
+        let decompressed = decompress(buffer).unwrap();
+        assert_eq!(decompressed.buffer, b"ABCABCABC");
+        assert_eq!(decompressed.compression, Compression::MonkeyBallLz);
+        assert_eq!(decompressed.compressed_size, 14);
+    }
+
+    #[test]
+    fn test_lz_size_mismatch() {
+        assert_eq!(
+            decompress(with_lz_header(10)),
+            Err(LzError::SizeMismatch { expected: 10, actual: 9 })
+        );
+    }
+
+    #[test]
+    fn test_lz_oversized_header() {
+        // Only as much as the data could decompress to is allocated, rather than the 4 GiB claimed
+        assert_eq!(
+            decompress(with_lz_header(u32::MAX)),
+            Err(LzError::SizeMismatch {
+                expected: u32::MAX as usize,
+                actual: 9
+            })
+        );
+        assert_eq!(decompress(with_lz_header(3)), Err(LzError::TooLarge { expected: 3 }));
+    }
+
+    #[test]
+    fn test_lz_without_header() {
+        assert_eq!(Compression::detect(&LZ_PAYLOAD), Compression::None);
+        assert_eq!(decompress_lz(&LZ_PAYLOAD, None).unwrap(), b"ABCABCABC");
+        assert_eq!(decompress_lz(&LZ_PAYLOAD[..5], None), Err(LzError::Truncated));
+    }
+}
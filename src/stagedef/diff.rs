@@ -0,0 +1,348 @@
+//! Handles structural comparison between two [``StageDef``]s
+use super::common::*;
+use super::objects::*;
+
+/// A single difference between two [``StageDef``]s.
+#[derive(Debug, PartialEq)]
+pub enum StageChange {
+    /// An object exists in the other stagedef, but not in this one.
+    Added { category: String, index: usize, after: String },
+    /// An object exists in this stagedef, but not in the other one.
+    Removed { category: String, index: usize, before: String },
+    /// An object or field exists in both stagedefs, but differs.
+    Changed {
+        category: String,
+        index: Option<usize>,
+        before: String,
+        after: String,
+    },
+}
+
+impl Display for StageChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StageChange::Added { category, index, after } => write!(f, "+ {category} {}: {after}", index + 1),
+            StageChange::Removed { category, index, before } => write!(f, "- {category} {}: {before}", index + 1),
+            StageChange::Changed {
+                category,
+                index: Some(index),
+                before,
+                after,
+            } => write!(f, "~ {category} {}: {before} -> {after}", index + 1),
+            StageChange::Changed {
+                category,
+                index: None,
+                before,
+                after,
+            } => write!(f, "~ {category}: {before} -> {after}"),
+        }
+    }
+}
+
+/// The differences between two [``StageDef``]s, as returned by [``StageDef::diff``].
+#[derive(Debug, Default, PartialEq)]
+pub struct StageDiff {
+    pub changes: Vec<StageChange>,
+}
+
+impl StageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn get_added(&self) -> impl Iterator<Item = &StageChange> {
+        self.changes.iter().filter(|c| matches!(c, StageChange::Added { .. }))
+    }
+
+    pub fn get_removed(&self) -> impl Iterator<Item = &StageChange> {
+        self.changes.iter().filter(|c| matches!(c, StageChange::Removed { .. }))
+    }
+
+    pub fn get_changed(&self) -> impl Iterator<Item = &StageChange> {
+        self.changes.iter().filter(|c| matches!(c, StageChange::Changed { .. }))
+    }
+
+    fn diff_field<T: PartialEq + Display>(&mut self, category: &str, before: &T, after: &T) {
+        if before != after {
+            self.changes.push(StageChange::Changed {
+                category: category.to_string(),
+                index: None,
+                before: before.to_string(),
+                after: after.to_string(),
+            });
+        }
+    }
+
+    /// Compares a field that only some stagedefs have, showing a missing one as "None".
+    fn diff_optional_field<T: PartialEq + Display>(&mut self, category: &str, before: &Option<T>, after: &Option<T>) {
+        fn to_string<T: Display>(value: &Option<T>) -> String {
+            value.as_ref().map_or_else(|| String::from("None"), T::to_string)
+        }
+
+        if before != after {
+            self.changes.push(StageChange::Changed {
+                category: category.to_string(),
+                index: None,
+                before: to_string(before),
+                after: to_string(after),
+            });
+        }
+    }
+
+    /// Compares two object lists index by index.
+    fn diff_list<T: StageDefObject + PartialEq + Display>(
+        &mut self,
+        category_prefix: &str,
+        before: &[GlobalStagedefObject<T>],
+        after: &[GlobalStagedefObject<T>],
+    ) {
+        self.diff_items(&format!("{category_prefix}{}", T::get_name()), before, after);
+    }
+
+    /// Compares two lists of anything index by index.
+    fn diff_items<T: PartialEq + Display>(&mut self, category: &str, before: &[T], after: &[T]) {
+        for index in 0..before.len().max(after.len()) {
+            match (before.get(index), after.get(index)) {
+                (Some(b), Some(a)) => {
+                    if b != a {
+                        self.changes.push(StageChange::Changed {
+                            category: category.to_string(),
+                            index: Some(index),
+                            before: b.to_string(),
+                            after: a.to_string(),
+                        });
+                    }
+                }
+                (Some(b), None) => self.changes.push(StageChange::Removed {
+                    category: category.to_string(),
+                    index,
+                    before: b.to_string(),
+                }),
+                (None, Some(a)) => self.changes.push(StageChange::Added {
+                    category: category.to_string(),
+                    index,
+                    after: a.to_string(),
+                }),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+
+    /// Displays every change, grouped by kind.
+    #[cfg(feature = "gui")]
+    pub fn show_ui(&self, ui: &mut egui::Ui) {
+        if self.is_empty() {
+            ui.label("No differences");
+            return;
+        }
+
+        let groups: [(&str, Vec<&StageChange>); 3] = [
+            ("Added", self.get_added().collect()),
+            ("Removed", self.get_removed().collect()),
+            ("Changed", self.get_changed().collect()),
+        ];
+
+        for (title, changes) in groups {
+            if changes.is_empty() {
+                continue;
+            }
+            egui::CollapsingHeader::new(format!("{title} ({})", changes.len()))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for change in changes {
+                        ui.label(change.to_string());
+                    }
+                });
+        }
+    }
+}
+
+impl StageDef {
+    /// Returns every difference between this stagedef and ``other``.
+    ///
+    /// Object lists are compared index by index, so an inserted object will show up as a change to
+    /// every object after it.
+    pub fn diff(&self, other: &StageDef) -> StageDiff {
+        let mut diff = StageDiff::default();
+
+        diff.diff_field("Magic Number 1", &self.magic_number_1, &other.magic_number_1);
+        diff.diff_field("Magic Number 2", &self.magic_number_2, &other.magic_number_2);
+        diff.diff_field("Start Position", &self.start_position, &other.start_position);
+        diff.diff_field("Fallout Level", &self.fallout_level, &other.fallout_level);
+
+        diff.diff_optional_field("Fog", &self.fog, &other.fog);
+
+        macro_rules! diff_global_lists {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                $(diff.diff_list("", &self.$list, &other.$list);)*
+            };
+        }
+        stagedef_object_lists!(diff_global_lists);
+
+        diff.diff_field(
+            "Collision Header Count",
+            &self.collision_headers.len(),
+            &other.collision_headers.len(),
+        );
+
+        for (index, (before, after)) in self.collision_headers.iter().zip(other.collision_headers.iter()).enumerate() {
+            let prefix = format!("Collision Header {} ", index + 1);
+            diff.diff_field(
+                &format!("{prefix}Center of Rotation"),
+                &before.center_of_rotation_position,
+                &after.center_of_rotation_position,
+            );
+            diff.diff_field(
+                &format!("{prefix}Initial Rotation"),
+                &before.initial_rotation,
+                &after.initial_rotation,
+            );
+            diff.diff_field(
+                &format!("{prefix}Conveyor Vector"),
+                &before.conveyor_vector,
+                &after.conveyor_vector,
+            );
+            diff.diff_items(
+                &format!("{prefix}{}", CollisionTriangle::get_name()),
+                &before.collision_triangles,
+                &after.collision_triangles,
+            );
+            diff.diff_field(
+                &format!("{prefix}Animation Type"),
+                &before.animation_type,
+                &after.animation_type,
+            );
+            diff.diff_field(&format!("{prefix}Animation"), &before.animation, &after.animation);
+            diff.diff_field(
+                &format!("{prefix}Animation Loop Point"),
+                &before.animation_loop_point,
+                &after.animation_loop_point,
+            );
+            diff.diff_optional_field(
+                &format!("{prefix}Texture Scroll"),
+                &before.texture_scroll,
+                &after.texture_scroll,
+            );
+
+            macro_rules! diff_local_lists {
+                ($($object:ty => $list:ident),* $(,)?) => {
+                    $(diff.diff_list(&prefix, &before.$list, &after.$list);)*
+                };
+            }
+            stagedef_object_lists!(diff_local_lists);
+        }
+
+        diff
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_stagedef() -> StageDef {
+        let mut stagedef = StageDef {
+            fallout_level: -20.0,
+            ..Default::default()
+        };
+
+        for (index, x) in [0.0, 10.0].into_iter().enumerate() {
+            let banana = Banana {
+                position: Position(Vector3 { x, y: 1.0, z: 0.0 }),
+                banana_type: BananaType::Single,
+            };
+            stagedef.bananas.push(GlobalStagedefObject::new(banana, index as u32));
+        }
+
+        stagedef.goals.push(GlobalStagedefObject::new(Goal::default(), 0));
+        stagedef
+    }
+
+    #[test]
+    fn test_diff_identical() {
+        assert!(test_stagedef().diff(&test_stagedef()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_modified_copy() {
+        let original = test_stagedef();
+        let mut modified = test_stagedef();
+
+        modified.fallout_level = -50.0;
+        modified.bananas[1].object.lock().unwrap().banana_type = BananaType::Bunch;
+        modified.bananas.push(GlobalStagedefObject::new(
+            Banana {
+                position: Position::default(),
+                banana_type: BananaType::Single,
+            },
+            2,
+        ));
+        modified.goals.clear();
+
+        let diff = original.diff(&modified);
+
+        assert_eq!(diff.get_added().count(), 1);
+        assert_eq!(diff.get_removed().count(), 1);
+        assert_eq!(diff.get_changed().count(), 2);
+
+        assert!(diff.changes.contains(&StageChange::Changed {
+            category: String::from("Fallout Level"),
+            index: None,
+            before: String::from("-20"),
+            after: String::from("-50"),
+        }));
+        assert!(matches!(
+            diff.get_removed().next(),
+            Some(StageChange::Removed { category, index: 0, .. }) if category == "Goal"
+        ));
+        assert!(matches!(
+            diff.get_added().next(),
+            Some(StageChange::Added { category, index: 2, .. }) if category == "Banana"
+        ));
+    }
+
+    #[test]
+    fn test_diff_fog_and_collision_header_contents() {
+        let with_header = |triangle_count: usize, animation_type: AnimationType| {
+            let mut stagedef = test_stagedef();
+            let mut header = CollisionHeader {
+                animation_type,
+                ..Default::default()
+            };
+            header.collision_triangles.extend((0..triangle_count).map(|_| CollisionTriangle::floor()));
+            stagedef.add_collision_header(header);
+            stagedef
+        };
+        let original = with_header(1, AnimationType::LoopingAnimation);
+        let mut modified = with_header(2, AnimationType::Seesaw);
+        modified.fog = Some(Fog::default());
+        modified.collision_headers[0]
+            .background_models
+            .push(GlobalStagedefObject::new(BackgroundModel::default(), 0));
+
+        let diff = original.diff(&modified);
+
+        let categories: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| match change {
+                StageChange::Added { category, .. }
+                | StageChange::Removed { category, .. }
+                | StageChange::Changed { category, .. } => category.as_str(),
+            })
+            .collect();
+        assert_eq!(
+            categories,
+            [
+                "Fog",
+                "Collision Header 1 Collision Triangle",
+                "Collision Header 1 Animation Type",
+                "Collision Header 1 BG Model",
+            ]
+        );
+        assert!(matches!(
+            &diff.changes[0],
+            StageChange::Changed { before, .. } if before == "None"
+        ));
+    }
+}
@@ -0,0 +1,58 @@
+//! Errors that can occur while parsing a stagedef.
+use std::io;
+use thiserror::Error;
+
+/// A failure while parsing a stagedef.
+///
+/// These can be matched on to find out what went wrong, and convert into [``anyhow::Error``] when
+/// that isn't needed.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    /// The file ended partway through something we were reading.
+    #[error("Unexpected end of file")]
+    UnexpectedEof,
+    /// An offset pointed somewhere it can't, or wasn't there at all.
+    #[error("Invalid offset: {0}")]
+    InvalidOffset(&'static str),
+    /// A value didn't correspond to any variant of the enum it's meant to be.
+    #[error("Unknown {type_name} value {value:#X}")]
+    UnknownEnumValue { type_name: &'static str, value: u32 },
+    /// An object took up a different amount of space in the file than it should have.
+    #[error(
+        "Unexpected size for {object_name} at {start_offset:#X}: expected to end at {expected_end:#X}, ended at {actual_end:#X}"
+    )]
+    SizeMismatch {
+        object_name: String,
+        start_offset: u64,
+        expected_end: u64,
+        actual_end: u64,
+    },
+    /// The file is too small to even hold a file header, so it can't be a stagedef.
+    #[error("File too small to be a valid stagedef: {size:#X} bytes, expected at least {minimum_size:#X}")]
+    FileTooSmall { size: u64, minimum_size: u64 },
+    /// A collision header's list of objects couldn't be found in the stagedef's global list of
+    /// them, so it was read as a list of its own.
+    #[error("Failed global object retrieval for type {object_name}: {reason}")]
+    UnmatchedLocalList { object_name: &'static str, reason: String },
+    /// The file is for a game whose stagedef layout we can't read yet.
+    #[error("Unsupported game: {0} stagedefs can't be read yet")]
+    UnsupportedGame(&'static str),
+    /// Something that's only a warning normally, but fails parsing in
+    /// [strict mode](crate::stagedef::parser::StageDefReader::with_strict).
+    #[error("Strict parsing failed: {0}")]
+    Strict(Box<ParseError>),
+    /// Any other I/O error from the underlying reader.
+    #[error(transparent)]
+    Io(io::Error),
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => ParseError::UnexpectedEof,
+            _ => ParseError::Io(err),
+        }
+    }
+}
+
+pub type ParseResult<T> = std::result::Result<T, ParseError>;
@@ -0,0 +1,267 @@
+//! Writes a [``StageDef``]'s collision and objects out in formats other tools can open.
+use super::common::*;
+use super::objects::CollisionTriangle;
+use super::units::UpAxis;
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::collections::HashMap;
+
+/// The size of a binary STL's header, which is free text that readers ignore.
+const STL_HEADER_SIZE: usize = 80;
+/// The size of each triangle in a binary STL: its normal, its three vertices, and an unused
+/// attribute byte count.
+const STL_TRIANGLE_SIZE: usize = 50;
+
+impl StageDef {
+    /// Returns every collision triangle as a binary STL, for 3D printing and CAD tools.
+    ///
+    /// Triangles are placed as they are in the stagedef, so collision header rotations and
+    /// animations aren't applied. Vertices and normals are converted to the given up axis, to match
+    /// the tool the file is meant for.
+    pub fn export_collision_stl(&self, up_axis: UpAxis) -> Vec<u8> {
+        let triangles: Vec<[Vector3; 3]> = self
+            .collision_headers
+            .iter()
+            .flat_map(|header| header.collision_triangles.iter())
+            .map(CollisionTriangle::get_vertices)
+            .collect();
+
+        let mut stl = Vec::with_capacity(STL_HEADER_SIZE + 4 + triangles.len() * STL_TRIANGLE_SIZE);
+
+        // Readers guess a file is ASCII STL if it starts with "solid", so the header mustn't
+        let mut header = [0; STL_HEADER_SIZE];
+        let description = b"Collision exported from MKBViewer";
+        header[..description.len()].copy_from_slice(description);
+        stl.extend_from_slice(&header);
+
+        // Writing to a Vec can't fail
+        stl.write_u32::<LittleEndian>(triangles.len() as u32).unwrap();
+        for vertices in &triangles {
+            for vector in std::iter::once(get_normal(vertices)).chain(vertices.iter().copied()) {
+                for value in up_axis.from_game([vector.x, vector.y, vector.z]) {
+                    stl.write_f32::<LittleEndian>(value).unwrap();
+                }
+            }
+            stl.write_u16::<LittleEndian>(0).unwrap();
+        }
+
+        stl
+    }
+}
+
+impl StageDef {
+    /// Returns a CSV of every object in each global list, keyed by the name of the kind of object,
+    /// for spreadsheets.
+    ///
+    /// Each row is an object's index followed by its fields, with nested fields like positions
+    /// split into one column per component (``position_x``, ``position_y`` and so on). Position and
+    /// rotation come first, then the rest of the fields alphabetically. Kinds of object the stage
+    /// doesn't have are left out.
+    pub fn export_csv(&self) -> HashMap<&'static str, String> {
+        let mut csvs = HashMap::new();
+        macro_rules! add_csvs {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                $(add_csv(&mut csvs, &self.$list);)*
+            };
+        }
+        stagedef_object_lists!(add_csvs);
+        csvs
+    }
+}
+
+fn add_csv<T>(csvs: &mut HashMap<&'static str, String>, objects: &[GlobalStagedefObject<T>])
+where
+    T: StageDefObject + serde::Serialize,
+{
+    let rows: Vec<(u32, Vec<(String, String)>)> = objects
+        .iter()
+        .map(|object| {
+            // Going through a string keeps floats as short as they're written, where converting
+            // straight to a value would widen them to f64 first. Objects are plain data, so neither
+            // can fail
+            let json = serde_json::to_string(&*object.object.lock().unwrap()).unwrap_or_default();
+            let value: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+            let mut fields = Vec::new();
+            flatten_csv_fields(String::new(), &value, &mut fields);
+            fields.sort_by_key(|(column, _)| (get_column_order(column), column.clone()));
+            (object.index, fields)
+        })
+        .collect();
+    let Some((_, first_fields)) = rows.first() else {
+        return;
+    };
+
+    let mut csv = String::from("index");
+    for (column, _) in first_fields {
+        csv.push(',');
+        csv.push_str(&escape_csv(column));
+    }
+    csv.push('\n');
+    for (index, fields) in &rows {
+        csv.push_str(&index.to_string());
+        for (_, value) in fields {
+            csv.push(',');
+            csv.push_str(&escape_csv(value));
+        }
+        csv.push('\n');
+    }
+    csvs.insert(T::get_name(), csv);
+}
+
+/// Adds a column for every field of ``value``, naming nested fields after the fields they're in.
+fn flatten_csv_fields(prefix: String, value: &serde_json::Value, fields: &mut Vec<(String, String)>) {
+    let join = |name: &str| if prefix.is_empty() { name.to_string() } else { format!("{prefix}_{name}") };
+    match value {
+        serde_json::Value::Object(object) => {
+            for (name, value) in object {
+                flatten_csv_fields(join(name), value, fields);
+            }
+        }
+        serde_json::Value::Array(array) => {
+            for (index, value) in array.iter().enumerate() {
+                flatten_csv_fields(join(&index.to_string()), value, fields);
+            }
+        }
+        serde_json::Value::String(string) => fields.push((prefix, string.clone())),
+        serde_json::Value::Null => fields.push((prefix, String::new())),
+        value => fields.push((prefix, value.to_string())),
+    }
+}
+
+/// Puts position columns first, then rotation, then everything else.
+fn get_column_order(column: &str) -> u8 {
+    if column.starts_with("position") {
+        0
+    } else if column.starts_with("rotation") {
+        1
+    } else {
+        2
+    }
+}
+
+/// Quotes a CSV value if it has anything in it that would otherwise split it up.
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Returns the unit normal of a triangle with counter-clockwise winding, or zero for degenerate
+/// triangles. STL readers work out normals themselves when they're zero.
+fn get_normal([v1, v2, v3]: &[Vector3; 3]) -> Vector3 {
+    let edge1 = [v2.x - v1.x, v2.y - v1.y, v2.z - v1.z];
+    let edge2 = [v3.x - v1.x, v3.y - v1.y, v3.z - v1.z];
+    let x = edge1[1] * edge2[2] - edge1[2] * edge2[1];
+    let y = edge1[2] * edge2[0] - edge1[0] * edge2[2];
+    let z = edge1[0] * edge2[1] - edge1[1] * edge2[0];
+
+    let length = (x * x + y * y + z * z).sqrt();
+    if length < f32::EPSILON {
+        return Vector3::default();
+    }
+    Vector3 {
+        x: x / length,
+        y: y / length,
+        z: z / length,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #![allow(clippy::float_cmp)]
+    use super::*;
+    use crate::stagedef::objects::{CollisionHeader, Goal, GoalType};
+
+    #[test]
+    fn test_export_collision_stl() {
+        let stagedef = StageDef {
+            collision_headers: vec![
+                CollisionHeader {
+                    collision_triangles: vec![CollisionTriangle::floor(), CollisionTriangle::floor()],
+                    ..Default::default()
+                },
+                CollisionHeader {
+                    collision_triangles: vec![CollisionTriangle::floor()],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let stl = stagedef.export_collision_stl(UpAxis::Y);
+        assert_eq!(stl.len(), STL_HEADER_SIZE + 4 + 3 * STL_TRIANGLE_SIZE);
+        assert!(!stl.starts_with(b"solid"));
+        assert_eq!(&stl[STL_HEADER_SIZE..STL_HEADER_SIZE + 4], 3u32.to_le_bytes());
+
+        // The first triangle's normal, then its vertices, as they're placed in the stagedef
+        let read_f32 = |offset: usize| f32::from_le_bytes(stl[offset..offset + 4].try_into().unwrap());
+        let first_triangle = STL_HEADER_SIZE + 4;
+        let vertices = CollisionTriangle::floor().get_vertices();
+        let normal = get_normal(&vertices);
+        assert!((normal.y.abs() - 1.0).abs() < 1e-5, "{normal:?}");
+        let expected_values = [normal, vertices[0], vertices[1], vertices[2]]
+            .into_iter()
+            .flat_map(|vector| [vector.x, vector.y, vector.z]);
+        for (index, expected_value) in expected_values.enumerate() {
+            assert_eq!(read_f32(first_triangle + index * 4), expected_value);
+        }
+
+        // Stages without collision still make a valid, empty STL
+        let stl = StageDef::default().export_collision_stl(UpAxis::Y);
+        assert_eq!(stl.len(), STL_HEADER_SIZE + 4);
+        assert_eq!(&stl[STL_HEADER_SIZE..], 0u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_export_collision_stl_z_up() {
+        let stagedef = StageDef {
+            collision_headers: vec![CollisionHeader {
+                collision_triangles: vec![CollisionTriangle::floor()],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let stl = stagedef.export_collision_stl(UpAxis::Z);
+        let read_f32 = |offset: usize| f32::from_le_bytes(stl[offset..offset + 4].try_into().unwrap());
+        let first_triangle = STL_HEADER_SIZE + 4;
+
+        // The floor faces up, which is along Z rather than Y
+        let normal = [0, 1, 2].map(|axis| read_f32(first_triangle + axis * 4));
+        assert!(normal[1].abs() < 1e-5 && (normal[2].abs() - 1.0).abs() < 1e-5, "{normal:?}");
+
+        let vertices = CollisionTriangle::floor().get_vertices();
+        let expected_values = vertices
+            .iter()
+            .flat_map(|vector| UpAxis::Z.from_game([vector.x, vector.y, vector.z]));
+        for (index, expected_value) in expected_values.enumerate() {
+            assert_eq!(read_f32(first_triangle + 12 + index * 4), expected_value);
+        }
+    }
+
+    #[test]
+    fn test_export_goal_csv() {
+        let stagedef = StageDef::default()
+            .with_goal(Goal::default())
+            .with_goal(Goal {
+                position: Position(Vector3 { x: 1.5, y: -2.0, z: 10.0 }),
+                rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+                goal_type: GoalType::Red,
+            });
+
+        let csvs = stagedef.export_csv();
+        // Only kinds of object the stage has are exported
+        assert_eq!(csvs.keys().collect::<Vec<_>>(), [&"Goal"]);
+
+        let lines: Vec<&str> = csvs["Goal"].lines().collect();
+        assert_eq!(
+            lines[0],
+            "index,position_x,position_y,position_z,rotation_x,rotation_y,rotation_z,goal_type"
+        );
+        assert_eq!(lines[2], "1,1.5,-2.0,10.0,0,32768,0,Red");
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(escape_csv("MODEL, \"A\""), "\"MODEL, \"\"A\"\"\"");
+    }
+}
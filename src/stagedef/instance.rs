@@ -1,31 +1,190 @@
+use super::autosave::{AutosaveLocation, AutosaveSettings, StageAutosave};
 use super::common::*;
-use super::parser::StageDefReader;
+use super::objects::{CollisionHeader, CollisionTriangle};
+use super::parser::{self, StageDefReader};
+use super::snippet::StageSnippet;
 use super::ui_state::*;
+use super::units::UnitSettings;
+use super::validation::{ValidationIssue, ValidationSettings};
 use crate::app::FileHandleWrapper;
-use anyhow::Result;
+use crate::hex_viewer;
+use crate::minimap;
+use crate::renderer::{
+    self, get_closest_line_parameter, CameraMove, CameraPreset, CameraSettings, CollisionHit, RenderStats, StageGeometry,
+    TranslateGizmo,
+};
+use anyhow::{anyhow, Result};
 use byteorder::BigEndian;
 use byteorder::LittleEndian;
+use poll_promise::Promise;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Uniquely identifies a [``StageDefInstance``] for the lifetime of the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceId(u64);
+
+impl InstanceId {
+    /// Returns a new, never before used [``InstanceId``].
+    pub fn next() -> Self {
+        static NEXT_INSTANCE_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
 
 /// Contains a [``StageDef``], as well as extra information about the file
 ///
 /// By default, this will be a big-endian SMB2 stagedef
 pub struct StageDefInstance {
+    pub id: InstanceId,
     pub stagedef: StageDef,
     pub game: Game,
     pub endianness: Endianness,
     pub is_active: bool,
     pub ui_state: StageDefInstanceUiState,
+    pub camera_settings: CameraSettings,
+    /// Geometry from the stagedef, shared with this instance's renderer.
+    pub geometry: Arc<StageGeometry>,
+    /// A camera move to hand to this instance's renderer on the next frame.
+    pub pending_camera_move: Option<CameraMove>,
+    /// A preset view for this instance's renderer to move to on the next frame. The renderer works
+    /// out where the camera goes, as it knows where the camera is now.
+    pub pending_camera_preset: Option<CameraPreset>,
+    /// Seconds until the current camera move finishes, so we know to keep repainting until then.
+    pub camera_animation_time_left: f32,
+    /// How long the stagedef took to read and parse.
+    pub parse_duration: Duration,
+    /// What this instance's renderer did to draw the last frame, written from the paint callback.
+    pub render_stats: Arc<Mutex<RenderStats>>,
+    /// The camera the viewport was last drawn with, written from the paint callback so clicks in
+    /// the viewport can be turned into rays.
+    pub viewport_view_projection: Arc<Mutex<Option<three_d::Mat4>>>,
+    /// Why this instance's renderer couldn't be created, written from the paint callback. While
+    /// this is set, the viewport shows a placeholder instead of trying again every frame.
+    pub renderer_error: Arc<Mutex<Option<String>>>,
+    /// A name the user gave this view, shown instead of the file name.
+    pub custom_title: Option<String>,
+    /// Whether the file's magic numbers matched a format we know. If they didn't, the stagedef was
+    /// parsed on a best guess and is likely wrong.
+    pub is_format_detected: bool,
+    /// A top-down picture of the stage, along with the geometry it was made from so it can be made
+    /// again once the geometry changes.
+    thumbnail: Option<(Arc<StageGeometry>, egui::TextureHandle)>,
+    /// What validating the stagedef last found, along with the geometry and settings it was run
+    /// with so it can be run again once either changes. Edits that leave the geometry alone, such
+    /// as ones made in the inspector, clear it instead.
+    validation: Option<(Arc<StageGeometry>, ValidationSettings, Vec<ValidationIssue>)>,
+    /// Whether the stagedef has been edited since it was last read from the file.
+    is_dirty: bool,
+    /// How many times the stagedef has been edited or reloaded, so anything worked out from it
+    /// can tell when to work it out again.
+    edit_count: u64,
+    /// Whether we've looked for an autosave of this file yet.
+    has_checked_for_autosave: bool,
+    /// When edits were last autosaved, or when we last checked whether there were any to save.
+    last_autosave_time: instant::Instant,
+    /// The file the stagedef was read from, whose raw bytes the hex views show.
+    pub file: FileHandleWrapper,
+    /// A reload of the file running on another thread, which replaces the stagedef once it's done.
+    /// Files can't be reloaded on the web, so this is always ``None`` there.
+    pending_reload: Option<Promise<Result<ParsedStageDef>>>,
+}
+
+/// A collision triangle picked in the viewport, copied out of the stagedef so it can be shown in
+/// the inspector while the stagedef is borrowed by the tree.
+pub struct PickedTriangle {
+    hit: CollisionHit,
+    vertices: [Vector3; 3],
+    normal: Vector3,
+}
+
+impl PickedTriangle {
+    /// Shows the triangle, with its vertices editable. Returns whether any of them were changed, in
+    /// which case the edit can be applied with [``StageDefInstance::set_picked_triangle``].
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.strong(format!(
+            "Collision Triangle {} of Collision Header {}",
+            self.hit.triangle_index + 1,
+            self.hit.header_index + 1
+        ));
+
+        let point = self.hit.point;
+        Position(Vector3 {
+            x: point.x,
+            y: point.y,
+            z: point.z,
+        })
+        .inspect("Picked Point", ui);
+        let original_vertices = self.vertices;
+        for (index, vertex) in self.vertices.iter_mut().enumerate() {
+            let mut position = Position(*vertex);
+            position.inspect_mut(&format!("Vertex {}", index + 1), ui);
+            *vertex = position.0;
+        }
+
+        // Normals are directions rather than distances, so only the up axis applies to them
+        let up_axis = UnitSettings::load(ui.ctx()).up_axis;
+        ui.horizontal(|ui| {
+            ui.label("Normal");
+            for component in up_axis.from_game([self.normal.x, self.normal.y, self.normal.z]) {
+                ui.label(format!("{component:.3}"));
+            }
+        });
+
+        self.vertices != original_vertices
+    }
+}
+
+/// How many pixels wide the top-down thumbnail of each stage is.
+const THUMBNAIL_SIZE: usize = 128;
+
+/// A stagedef that has been read and parsed, but not yet made into a [``StageDefInstance``].
+///
+/// Parsing can take a while for large files, so this is done off the UI thread, and only turned
+/// into a [``StageDefInstance``] once it's done.
+pub struct ParsedStageDef {
+    stagedef: StageDef,
+    game: Game,
+    endianness: Endianness,
+    camera_settings: CameraSettings,
+    geometry: StageGeometry,
+    parse_duration: Duration,
+    is_format_detected: bool,
     file: FileHandleWrapper,
 }
 
-impl StageDefInstance {
-    pub fn new(file: FileHandleWrapper) -> Result<Self> {
+impl ParsedStageDef {
+    /// Like [``parse``](Self::parse), but a panic in the parser is returned as an error, so it only
+    /// fails this file rather than the thread it was parsed on.
+    pub fn parse_catching_panics(file: FileHandleWrapper) -> Result<Self> {
+        let file_name = file.file_name.clone();
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| Self::parse(file)))
+            .unwrap_or_else(|_| Err(anyhow!("The parser crashed while reading {file_name}")))
+    }
+
+    /// Parses the stagedef in the given file. This doesn't touch any UI state, so it's safe to
+    /// call from any thread.
+    pub fn parse(file: FileHandleWrapper) -> Result<Self> {
+        let start_time = instant::Instant::now();
         let game = Game::SMB2;
-        let endianness = Endianness::BigEndian;
 
-        let reader = file.get_cursor();
+        let file = file.decompress()?;
+        let mut reader = file.get_cursor();
+        parser::check_minimum_size(&mut reader, game)?;
+
+        //TODO: Implement game selection
+        let detected_endianness = parser::detect_endianness(&mut reader, game)?;
+        let endianness = match detected_endianness {
+            Some(endianness) => endianness,
+            None => {
+                warn!("Could not detect endianness from magic numbers, assuming big endian");
+                Endianness::BigEndian
+            }
+        };
 
-        //TODO: Implement endianness/game selection
         let mut sd_reader = StageDefReader::new(reader, game);
 
         let stagedef = match endianness {
@@ -33,13 +192,23 @@ impl StageDefInstance {
             Endianness::LittleEndian => sd_reader.read_stagedef::<LittleEndian>()?,
         };
 
+        // Set the far plane so the whole stage is visible by default
+        let mut camera_settings = CameraSettings::default();
+        if let Some(diagonal) = stagedef.get_bounding_box_diagonal() {
+            camera_settings = camera_settings.with_far_from_diagonal(diagonal);
+        }
+
+        let geometry = StageGeometry::from_stagedef(&stagedef);
+
         Ok(Self {
             stagedef,
             game,
             endianness,
+            camera_settings,
+            geometry,
+            parse_duration: start_time.elapsed(),
+            is_format_detected: detected_endianness.is_some(),
             file,
-            is_active: true,
-            ui_state: StageDefInstanceUiState::default(),
         })
     }
 
@@ -47,3 +216,792 @@ impl StageDefInstance {
         self.file.file_name.clone()
     }
 }
+
+impl StageDefInstance {
+    /// Reads and parses the given file on the current thread.
+    pub fn new(file: FileHandleWrapper) -> Result<Self> {
+        Ok(Self::from_parsed(ParsedStageDef::parse(file)?))
+    }
+
+    /// Makes an instance out of an already parsed stagedef.
+    pub fn from_parsed(parsed: ParsedStageDef) -> Self {
+        // Unrecognised files are most useful to look at byte by byte, so start with the raw header open
+        let ui_state = StageDefInstanceUiState {
+            show_raw_header: !parsed.is_format_detected,
+            ..Default::default()
+        };
+
+        Self {
+            id: InstanceId::next(),
+            stagedef: parsed.stagedef,
+            game: parsed.game,
+            endianness: parsed.endianness,
+            file: parsed.file,
+            is_active: true,
+            ui_state,
+            camera_settings: parsed.camera_settings,
+            geometry: Arc::new(parsed.geometry),
+            pending_camera_move: None,
+            pending_camera_preset: None,
+            camera_animation_time_left: 0.0,
+            parse_duration: parsed.parse_duration,
+            render_stats: Arc::default(),
+            viewport_view_projection: Arc::default(),
+            renderer_error: Arc::default(),
+            custom_title: None,
+            is_format_detected: parsed.is_format_detected,
+            thumbnail: None,
+            validation: None,
+            is_dirty: false,
+            edit_count: 0,
+            has_checked_for_autosave: false,
+            last_autosave_time: instant::Instant::now(),
+            pending_reload: None,
+        }
+    }
+
+    pub fn get_filename(&self) -> String {
+        self.file.file_name.clone()
+    }
+
+    pub fn get_file_path(&self) -> Option<PathBuf> {
+        self.file.file_path.clone()
+    }
+
+    /// Opens the folder containing this instance's file in the system file browser.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_containing_folder(&self) {
+        let Some(folder) = self.file.file_path.as_deref().and_then(Path::parent) else {
+            warn!("Don't know which folder {} is in", self.get_filename());
+            return;
+        };
+
+        if let Err(e) = opener::open(folder) {
+            warn!("Could not open {}: {e}", folder.display());
+        }
+    }
+
+    /// Asks where to save this instance's collision as a binary STL, then saves it there.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_collision_stl(&self) {
+        let file_name = self.get_filename();
+        let file_stem = file_name.split('.').next().unwrap_or(&file_name);
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("STL", &["stl"])
+            .set_file_name(&format!("{file_stem}.stl"))
+            .save_file()
+        else {
+            return;
+        };
+
+        if let Err(e) = std::fs::write(&path, self.stagedef.export_collision_stl(self.ui_state.unit_settings.up_axis)) {
+            warn!("Could not save {}: {e}", path.display());
+        }
+    }
+
+    /// Asks for a folder to save a CSV of each kind of object in this instance's stagedef to, then
+    /// saves them there, named after the file and the kind of object.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_csv(&self) {
+        let file_name = self.get_filename();
+        let file_stem = file_name.split('.').next().unwrap_or(&file_name);
+        let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+
+        for (object_name, csv) in self.stagedef.export_csv() {
+            let object_name = object_name.to_lowercase().replace(' ', "_");
+            let path = folder.join(format!("{file_stem}_{object_name}.csv"));
+            if let Err(e) = std::fs::write(&path, csv) {
+                warn!("Could not save {}: {e}", path.display());
+            }
+        }
+    }
+
+    /// Whether the stagedef has been edited since it was read from the file.
+    pub fn has_unsaved_edits(&self) -> bool {
+        self.is_dirty
+    }
+
+    /// Returns how many times the stagedef has been edited or reloaded. Anything worked out from
+    /// the stagedef is out of date once this changes.
+    pub fn get_edit_count(&self) -> u64 {
+        self.edit_count
+    }
+
+    /// Notes that the stagedef was edited, so it's no longer what was read from the file and its
+    /// validation issues may be out of date.
+    fn mark_edited(&mut self) {
+        self.is_dirty = true;
+        self.edit_count += 1;
+        self.validation = None;
+    }
+
+    fn get_autosave_location(&self) -> AutosaveLocation {
+        AutosaveLocation::for_file(&self.file.file_name, self.file.file_path.as_ref())
+    }
+
+    /// Looks for an autosave of this file the first time it's called, removes the autosave if the
+    /// user discarded it, and autosaves any edits once the interval in ``settings`` has passed.
+    ///
+    /// ``storage`` is only used on the web, where files have no location to save next to.
+    pub fn update_autosave(
+        &mut self,
+        settings: &AutosaveSettings,
+        mut storage: Option<&mut (dyn eframe::Storage + 'static)>,
+    ) {
+        let location = self.get_autosave_location();
+
+        if !self.has_checked_for_autosave {
+            self.has_checked_for_autosave = true;
+            match location.read(storage.as_deref()).map(|json| StageAutosave::from_json(&json)) {
+                Some(Ok(autosave)) => self.ui_state.found_autosave = Some(autosave),
+                Some(Err(e)) => warn!("Ignoring unreadable autosave of {}: {e}", self.get_filename()),
+                None => (),
+            }
+        }
+
+        if self.ui_state.should_clear_autosave {
+            self.ui_state.should_clear_autosave = false;
+            if let Err(e) = location.clear(storage.as_deref_mut()) {
+                warn!("Could not remove the autosave of {}: {e}", self.get_filename());
+            }
+        }
+
+        if !settings.enabled || self.last_autosave_time.elapsed().as_secs_f32() < settings.interval_seconds {
+            return;
+        }
+        self.last_autosave_time = instant::Instant::now();
+
+        // Don't overwrite an autosave the user hasn't decided what to do with yet
+        if self.ui_state.found_autosave.is_some() || !self.has_unsaved_edits() {
+            return;
+        }
+
+        let result = StageAutosave::from_stagedef(&self.stagedef)
+            .to_json()
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(location.write(json, storage)?));
+        match result {
+            Ok(()) => debug!("Autosaved {} to {location:?}", self.get_filename()),
+            Err(e) => warn!("Could not autosave {}: {e}", self.get_filename()),
+        }
+    }
+
+    /// Offers to restore an autosave of this file from a previous session, if one was found.
+    pub fn show_autosave_prompt(&mut self, ctx: &egui::Context) {
+        if self.ui_state.found_autosave.is_none() {
+            return;
+        }
+
+        let title = self.get_title();
+        let (mut should_restore, mut should_discard) = (false, false);
+        egui::Window::new("Restore autosave?")
+            .id(egui::Id::new(("autosave_prompt", self.id)))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{title} has autosaved edits from a previous session."));
+                ui.horizontal(|ui| {
+                    should_restore = ui.button("Restore").clicked();
+                    should_discard = ui.button("Discard").clicked();
+                });
+            });
+
+        if should_restore {
+            if let Some(autosave) = self.ui_state.found_autosave.take() {
+                self.stagedef.restore_autosave(autosave);
+                self.ui_state.selected_tree_items.clear();
+                self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+                self.mark_edited();
+            }
+        } else if should_discard {
+            self.ui_state.found_autosave = None;
+            self.ui_state.should_clear_autosave = true;
+        }
+    }
+
+    /// Reloads the file from disk, asking for confirmation first if the stagedef has been edited.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn request_reload(&mut self) {
+        if self.has_unsaved_edits() {
+            self.ui_state.is_confirming_reload = true;
+        } else {
+            self.reload();
+        }
+    }
+
+    /// Starts reading this instance's file from disk again on another thread. Once it's read,
+    /// [``poll_pending_reload``](Self::poll_pending_reload) replaces the stagedef with it, keeping
+    /// the view as it is.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload(&mut self) {
+        self.ui_state.is_confirming_reload = false;
+        if self.is_reloading() {
+            return;
+        }
+        let Some(file_path) = self.file.file_path.clone() else {
+            warn!("Don't know where {} is on disk, so it can't be reloaded", self.get_filename());
+            return;
+        };
+
+        let file_type = self.file.file_type;
+        self.pending_reload = Some(Promise::spawn_thread("reload_stagedef", move || {
+            FileHandleWrapper::from_path(&file_path, file_type)
+                .map_err(anyhow::Error::from)
+                .and_then(ParsedStageDef::parse_catching_panics)
+                .map_err(|e| e.context(format!("Could not reload {}", file_path.display())))
+        }));
+    }
+
+    /// Whether the file is being read again, during which the tree and inspector can't be used.
+    pub fn is_reloading(&self) -> bool {
+        self.pending_reload.is_some()
+    }
+
+    /// Replaces the stagedef with the one read by [``reload``](Self::reload), if it's finished.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn poll_pending_reload(&mut self) {
+        let Some(promise) = self.pending_reload.take() else {
+            return;
+        };
+        let parsed = match promise.try_take() {
+            Ok(Ok(parsed)) => parsed,
+            Ok(Err(e)) => {
+                warn!("{e:#}");
+                self.ui_state.reload_error = Some(format!("{e:#}"));
+                return;
+            }
+            Err(promise) => {
+                self.pending_reload = Some(promise);
+                return;
+            }
+        };
+
+        self.stagedef = parsed.stagedef;
+        self.game = parsed.game;
+        self.endianness = parsed.endianness;
+        self.geometry = Arc::new(parsed.geometry);
+        self.parse_duration = parsed.parse_duration;
+        self.is_format_detected = parsed.is_format_detected;
+        self.file = parsed.file;
+        self.is_dirty = false;
+        self.edit_count += 1;
+        self.ui_state.reload_error = None;
+        // Tree items are identified by position, so the selection may no longer line up
+        self.ui_state.selected_tree_items.clear();
+        self.ui_state.picked_collision_triangle = None;
+        self.ui_state.pending_deletion = None;
+        self.ui_state.pending_move = None;
+        self.ui_state.is_confirming_deletion = false;
+    }
+
+    /// Asks the user whether to reload the file over their edits, if they tried to.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn show_reload_confirmation(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.is_confirming_reload {
+            return;
+        }
+
+        let title = self.get_title();
+        let mut is_open = true;
+        egui::Window::new("Reload from disk?")
+            .id(egui::Id::new(("reload_confirmation", self.id)))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                ui.label(format!("{title} has been edited. Reloading it will lose those edits."));
+                ui.horizontal(|ui| {
+                    if ui.button("Reload").clicked() {
+                        self.reload();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.ui_state.is_confirming_reload = false;
+                    }
+                });
+            });
+
+        if !is_open {
+            self.ui_state.is_confirming_reload = false;
+        }
+    }
+
+    /// Tells the user why the last reload failed, until they dismiss it. The stagedef is left as it
+    /// was before the reload.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn show_reload_error(&mut self, ctx: &egui::Context) {
+        let Some(error) = &self.ui_state.reload_error else {
+            return;
+        };
+
+        let mut is_dismissed = false;
+        egui::Window::new("Could not reload file")
+            .id(egui::Id::new(("reload_error", self.id)))
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+                is_dismissed = ui.button("OK").clicked();
+            });
+
+        if is_dismissed {
+            self.ui_state.reload_error = None;
+        }
+    }
+
+    /// Returns the size of the file, and its size once decompressed if it was compressed.
+    pub fn get_file_size_text(&self) -> String {
+        let kilobytes = |bytes: usize| bytes as f32 / 1024.0;
+        let size = kilobytes(self.file.buffer.len());
+        match self.file.compressed_size {
+            Some(compressed_size) => format!("{:.1} KB (compressed) → {size:.1} KB", kilobytes(compressed_size)),
+            None => format!("{size:.1} KB"),
+        }
+    }
+
+    /// Returns the name to show for this view: the user's custom title, or the file name.
+    pub fn get_title(&self) -> String {
+        self.custom_title.clone().unwrap_or_else(|| self.get_filename())
+    }
+
+    /// Shows a text field for renaming this view. Clearing it goes back to the file name.
+    pub fn show_rename_ui(&mut self, ui: &mut egui::Ui) {
+        let mut title = self.custom_title.clone().unwrap_or_default();
+        let response = ui.add(egui::TextEdit::singleline(&mut title).hint_text(self.get_filename()));
+        if response.changed() {
+            self.custom_title = (!title.trim().is_empty()).then_some(title);
+        }
+        if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            ui.close_menu();
+        }
+        if ui.button("Use File Name").clicked() {
+            self.custom_title = None;
+            ui.close_menu();
+        }
+    }
+
+    /// Picks the collision triangle under the given point of the viewport, in normalized device
+    /// coordinates, to show in the inspector. Picking nothing clears the picked triangle.
+    pub fn pick_collision_triangle(&mut self, viewport_point: [f32; 2]) {
+        self.ui_state.picked_collision_triangle = self
+            .get_pick_ray(viewport_point)
+            .and_then(|(origin, direction)| self.geometry.pick_collision_triangle(origin, direction));
+    }
+
+    /// Returns the origin and direction of the ray going through the given point of the viewport, in
+    /// normalized device coordinates, once the viewport has been drawn.
+    fn get_pick_ray(&self, viewport_point: [f32; 2]) -> Option<(three_d::Vec3, three_d::Vec3)> {
+        let view_projection = (*self.viewport_view_projection.lock().unwrap())?;
+        renderer::get_pick_ray(view_projection, viewport_point)
+    }
+
+    /// Places an object of the kind picked in the placement palette where the given point of the
+    /// viewport, in normalized device coordinates, hits collision. Nothing's placed if it misses.
+    pub fn place_object_at(&mut self, viewport_point: [f32; 2]) {
+        let hit = self
+            .get_pick_ray(viewport_point)
+            .and_then(|(origin, direction)| self.geometry.pick_collision_triangle(origin, direction));
+        let Some(hit) = hit else {
+            return;
+        };
+
+        let position = Vector3 {
+            x: hit.point.x,
+            y: hit.point.y,
+            z: hit.point.z,
+        };
+        if self.ui_state.placement_palette.place(&mut self.stagedef, position) {
+            // Tree items are identified by position, so the selection no longer lines up
+            self.ui_state.selected_tree_items.clear();
+            self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+            self.mark_edited();
+        }
+    }
+
+    /// Returns the translate gizmo for the selected object, if exactly one selected object has a
+    /// position. The arrow being dragged, or otherwise the one under ``hovered_point`` in the
+    /// viewport, is highlighted.
+    pub fn get_translate_gizmo(&self, hovered_point: Option<[f32; 2]>) -> Option<TranslateGizmo> {
+        let access = self.ui_state.get_single_selected_position()?;
+        let mut position = Vector3::default();
+        access(&mut |selected_position: &mut Vector3| position = *selected_position);
+
+        let mut gizmo = TranslateGizmo::new([position.x, position.y, position.z], self.ui_state.gizmo_settings.scale);
+        gizmo.active_axis = match self.ui_state.translate_drag {
+            Some(drag) => Some(drag.axis),
+            None => hovered_point
+                .and_then(|point| self.get_pick_ray(point))
+                .and_then(|(origin, direction)| gizmo.pick_axis(origin, direction)),
+        };
+        Some(gizmo)
+    }
+
+    /// Starts moving the selected object along the axis of the translate gizmo arrow under the given
+    /// point of the viewport, in normalized device coordinates, if there is one.
+    pub fn start_translate_drag(&mut self, viewport_point: [f32; 2]) {
+        self.ui_state.translate_drag = self.get_translate_drag(viewport_point);
+    }
+
+    fn get_translate_drag(&self, viewport_point: [f32; 2]) -> Option<TranslateDrag> {
+        let gizmo = self.get_translate_gizmo(None)?;
+        let (origin, direction) = self.get_pick_ray(viewport_point)?;
+        let axis = gizmo.pick_axis(origin, direction)?;
+        let start = three_d::Vec3::from(gizmo.position);
+        let start_parameter = get_closest_line_parameter(start, axis.get_direction(), origin, direction)?;
+
+        let [x, y, z] = gizmo.position;
+        Some(TranslateDrag {
+            axis,
+            start_position: Vector3 { x, y, z },
+            start_parameter,
+        })
+    }
+
+    /// Moves the object being dragged with the translate gizmo along its axis, to follow the given
+    /// point of the viewport, in normalized device coordinates. The moved component is snapped to
+    /// the grid if that's turned on.
+    pub fn drag_translate_gizmo(&mut self, viewport_point: [f32; 2]) {
+        let (Some(new_position), Some(access)) = (
+            self.get_dragged_position(viewport_point),
+            self.ui_state.get_single_selected_position(),
+        ) else {
+            return;
+        };
+
+        let mut is_moved = false;
+        access(&mut |position: &mut Vector3| {
+            is_moved = *position != new_position;
+            *position = new_position;
+        });
+        if is_moved {
+            self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+            self.mark_edited();
+        }
+    }
+
+    fn get_dragged_position(&self, viewport_point: [f32; 2]) -> Option<Vector3> {
+        let drag = self.ui_state.translate_drag?;
+        let (origin, direction) = self.get_pick_ray(viewport_point)?;
+        let start = three_d::vec3(drag.start_position.x, drag.start_position.y, drag.start_position.z);
+        let parameter = get_closest_line_parameter(start, drag.axis.get_direction(), origin, direction)?;
+
+        let mut position = drag.start_position;
+        let component = drag.axis.get_component_mut(&mut position);
+        let gizmo_settings = self.ui_state.gizmo_settings;
+        *component = gizmo_settings.snap(*component + parameter - drag.start_parameter);
+        Some(position)
+    }
+
+    /// Returns a copy of the collision triangle picked in the viewport, if there is one.
+    pub fn get_picked_triangle(&self) -> Option<PickedTriangle> {
+        let hit = self.ui_state.picked_collision_triangle?;
+        let triangle = self
+            .stagedef
+            .collision_headers
+            .get(hit.header_index)?
+            .collision_triangles
+            .get(hit.triangle_index)?;
+        Some(PickedTriangle {
+            hit,
+            vertices: triangle.get_vertices(),
+            normal: triangle.normal,
+        })
+    }
+
+    /// Replaces the picked collision triangle with one made from the edited vertices, recomputing
+    /// everything the game stores about it, and rebuilds the geometry drawn in the viewport to match.
+    pub fn set_picked_triangle(&mut self, picked: &PickedTriangle) {
+        let triangle = self
+            .stagedef
+            .collision_headers
+            .get_mut(picked.hit.header_index)
+            .and_then(|header| header.collision_triangles.get_mut(picked.hit.triangle_index));
+        if let Some(triangle) = triangle {
+            let [a, b, c] = picked.vertices;
+            *triangle = CollisionTriangle::from_vertices(a, b, c);
+            self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+            self.mark_edited();
+        }
+    }
+
+    /// Returns a top-down thumbnail of the stage. It's only drawn when the geometry changes, so this
+    /// is cheap to call every frame.
+    pub fn get_thumbnail(&mut self, ctx: &egui::Context) -> egui::TextureHandle {
+        if let Some((geometry, texture)) = &self.thumbnail {
+            if Arc::ptr_eq(geometry, &self.geometry) {
+                return texture.clone();
+            }
+        }
+
+        let image = minimap::render_thumbnail(&self.geometry, THUMBNAIL_SIZE);
+        let texture = ctx.load_texture(
+            format!("stagedef_thumbnail_{:?}", self.id),
+            image,
+            egui::TextureOptions::LINEAR,
+        );
+        self.thumbnail = Some((self.geometry.clone(), texture.clone()));
+        texture
+    }
+
+    /// Shows the thumbnail at full size, for hover previews.
+    pub fn show_thumbnail(&mut self, ui: &mut egui::Ui) {
+        let texture = self.get_thumbnail(ui.ctx());
+        ui.image(texture.id(), egui::Vec2::splat(THUMBNAIL_SIZE as f32));
+    }
+
+    /// Shows the thumbnail as a small icon, which shows it at full size when hovered.
+    pub fn show_thumbnail_icon(&mut self, ui: &mut egui::Ui) {
+        let texture = self.get_thumbnail(ui.ctx());
+        let icon_size = ui.spacing().interact_size.y;
+        ui.image(texture.id(), egui::Vec2::splat(icon_size))
+            .on_hover_ui(|ui| self.show_thumbnail(ui));
+    }
+
+    /// Smoothly moves the camera to look at the start position from slightly behind and above it.
+    pub fn move_camera_to_start_position(&mut self) {
+        let start = &self.stagedef.start_position.position;
+        self.pending_camera_move = Some(CameraMove {
+            position: [start.x, start.y + 5.0, start.z + 10.0],
+            target: [start.x, start.y, start.z],
+        });
+        self.camera_animation_time_left = self.camera_settings.animation_duration;
+    }
+
+    /// Smoothly moves the camera to the given preset view of the whole stage.
+    pub fn view_camera_preset(&mut self, preset: CameraPreset) {
+        self.pending_camera_preset = Some(preset);
+        self.camera_animation_time_left = self.camera_settings.animation_duration;
+    }
+
+    /// Asks the user whether to delete a large selection of objects, if they tried to.
+    pub fn show_deletion_confirmation(&mut self, ctx: &egui::Context) {
+        if !self.ui_state.is_confirming_deletion {
+            return;
+        }
+
+        let count = self.ui_state.get_deletable_selection_count();
+        let title = self.get_title();
+        let mut is_open = true;
+        egui::Window::new(format!("Delete {count} objects?"))
+            .id(egui::Id::new(("delete_confirmation", self.id)))
+            .collapsible(false)
+            .resizable(false)
+            .open(&mut is_open)
+            .show(ctx, |ui| {
+                ui.label(format!("Delete the {count} selected objects from {title}?"));
+                ui.horizontal(|ui| {
+                    if ui.button("Delete").clicked() {
+                        self.ui_state.delete_selection();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.ui_state.is_confirming_deletion = false;
+                    }
+                });
+            });
+
+        if !is_open {
+            self.ui_state.is_confirming_deletion = false;
+        }
+    }
+
+    /// Carries out a deletion the user requested from the tree, if any, and rebuilds the geometry
+    /// drawn in the viewport to match.
+    pub fn apply_pending_deletion(&mut self) {
+        if let Some(delete) = self.ui_state.pending_deletion.take() {
+            delete(&mut self.stagedef);
+            // Tree items are identified by position, so the selection no longer lines up
+            self.ui_state.selected_tree_items.clear();
+            self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+            self.mark_edited();
+        }
+    }
+
+    /// Carries out a move the user made by dragging in the tree, if any.
+    pub fn apply_pending_move(&mut self) {
+        if let Some(move_object) = self.ui_state.pending_move.take() {
+            move_object(&mut self.stagedef);
+            // Tree items are identified by position, so the selection no longer lines up
+            self.ui_state.selected_tree_items.clear();
+            self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+            self.mark_edited();
+        }
+    }
+
+    /// Adds the objects in JSON pasted from the clipboard to the end of their global lists, or
+    /// shows why they couldn't be added.
+    pub fn paste_json(&mut self, json: &str) {
+        match StageSnippet::from_clipboard_json(json) {
+            Ok(snippet) => {
+                self.stagedef.import_snippet(snippet);
+                self.ui_state.paste_error = None;
+                // Tree items are identified by position, so the selection no longer lines up
+                self.ui_state.selected_tree_items.clear();
+                self.geometry = Arc::new(StageGeometry::from_stagedef(&self.stagedef));
+                self.mark_edited();
+            }
+            Err(err) => self.ui_state.paste_error = Some(err.to_string()),
+        }
+    }
+
+    /// Smoothly moves the camera to look down at the given point on the XZ plane, at the height of
+    /// the start position.
+    pub fn move_camera_to_xz(&mut self, [x, z]: [f32; 2]) {
+        let y = self.stagedef.start_position.position.y;
+        self.pending_camera_move = Some(CameraMove {
+            position: [x, y + 30.0, z + 30.0],
+            target: [x, y, z],
+        });
+        self.camera_animation_time_left = self.camera_settings.animation_duration;
+    }
+
+    /// Shows a top-down minimap of this instance's stage. Clicking it moves the camera there.
+    pub fn show_minimap(&mut self, ui: &mut egui::Ui) {
+        if let Some(point) = self.ui_state.minimap.show(ui, &self.geometry) {
+            self.move_camera_to_xz(point);
+        }
+    }
+
+    /// Returns the length of the longest collision header animation in this stage, in seconds.
+    pub fn get_animation_duration(&self) -> f32 {
+        self.stagedef
+            .collision_headers
+            .iter()
+            .map(CollisionHeader::get_animation_duration)
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns each collision header's animated rotation at the current point in the animation
+    /// timeline, in degrees.
+    pub fn get_animation_preview_rotations(&self) -> Vec<Option<[f32; 3]>> {
+        self.stagedef
+            .collision_headers
+            .iter()
+            .map(|header| header.get_rotation_at(self.ui_state.animation_time))
+            .collect()
+    }
+
+    /// Shows a timeline that can be dragged to preview collision header animations.
+    pub fn show_animation_timeline(&mut self, ui: &mut egui::Ui) {
+        let duration = self.get_animation_duration();
+        ui.horizontal(|ui| {
+            ui.label("Animation");
+            ui.spacing_mut().slider_width = (ui.available_width() - 150.0).max(100.0);
+            ui.add(egui::Slider::new(&mut self.ui_state.animation_time, 0.0..=duration).suffix("s"));
+            if ui.button("Reset").clicked() {
+                self.ui_state.animation_time = 0.0;
+            }
+        });
+    }
+
+    /// Returns every problem [``StageDef::validate``] finds in this instance's stagedef. Validation
+    /// is only run again once the stagedef or the validation settings change, so this is cheap to
+    /// call every frame.
+    pub fn get_validation_issues(&mut self) -> &[ValidationIssue] {
+        let settings = self.ui_state.validation_settings;
+        let is_stale = !matches!(
+            &self.validation,
+            Some((geometry, validated_settings, _)) if Arc::ptr_eq(geometry, &self.geometry) && *validated_settings == settings
+        );
+        if is_stale {
+            self.validation = None;
+        }
+
+        let (_, _, issues) = self
+            .validation
+            .get_or_insert_with(|| (self.geometry.clone(), settings, self.stagedef.validate(&settings)));
+        issues
+    }
+
+    /// Marks the stagedef as edited, and so validates it again, if the inspector was used this frame.
+    /// Inspector edits don't say whether they changed anything, so clicking, dragging or typing in it
+    /// is taken as an edit.
+    pub fn check_inspector_edits(&mut self, ctx: &egui::Context, is_pointer_over_inspector: bool) {
+        let (is_pointer_down, has_events) = {
+            let input = ctx.input();
+            (input.pointer.any_down(), !input.events.is_empty())
+        };
+        let was_pointer_down = self.ui_state.is_pointer_down_in_inspector;
+        // A drag started in the inspector, such as on a number, carries on if it leaves it
+        self.ui_state.is_pointer_down_in_inspector = is_pointer_down && (was_pointer_down || is_pointer_over_inspector);
+        let is_typing = has_events && ctx.memory().focus().is_some();
+
+        if was_pointer_down || self.ui_state.is_pointer_down_in_inspector || is_typing {
+            self.mark_edited();
+        }
+    }
+
+    /// Lists every problem [``StageDef::validate``] finds in this instance's stagedef.
+    pub fn show_validation_panel(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.strong("Validation");
+            self.ui_state.validation_settings.show_ui(ui);
+        });
+        let issues = self.get_validation_issues();
+        if issues.is_empty() {
+            ui.label("No problems found");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for issue in issues {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {issue}"));
+            }
+        });
+    }
+
+    /// Shows a button that copies the selected objects to the clipboard as a [``StageSnippet``],
+    /// which [``StageDef::import_snippet``] can add to another stagedef.
+    ///
+    /// [``StageSnippet``]: super::snippet::StageSnippet
+    pub fn show_export_selection_button(&self, ui: &mut egui::Ui) {
+        let snippet = &self.ui_state.selection_snippet;
+        let button = egui::Button::new(format!("Export Selection ({})", snippet.len()));
+        let response = ui
+            .add_enabled(!snippet.is_empty(), button)
+            .on_hover_text("Copies the selected objects to the clipboard as JSON");
+
+        if response.clicked() {
+            match get_object_json(snippet) {
+                Ok(json) => ui.output().copied_text = json,
+                Err(err) => warn!("Failed to serialize the selection: {err}"),
+            }
+        }
+    }
+
+    /// Shows timings and sizes useful when looking into performance problems.
+    pub fn show_debug_overlay(&self, ui: &mut egui::Ui) {
+        let render_stats = *self.render_stats.lock().unwrap();
+        let frame_time = ui.ctx().input().unstable_dt;
+        let memory_usage = self.stagedef.get_approximate_memory_usage();
+
+        ui.monospace(format!("Parse time:  {:.1} ms", self.parse_duration.as_secs_f64() * 1000.0));
+        ui.monospace(format!("Frame time:  {:.1} ms", frame_time * 1000.0));
+        ui.monospace(format!("Render time: {:.1} ms", render_stats.render_time.as_secs_f64() * 1000.0));
+        ui.monospace(format!("Draw calls:  {}", render_stats.draw_calls));
+        ui.monospace(format!("Triangles:   {}", render_stats.triangles));
+        ui.monospace(format!("Memory:      ~{:.1} KiB", memory_usage as f64 / 1024.0));
+    }
+
+    /// Shows the raw bytes of this instance's file, highlighting the bytes of the selected object.
+    pub fn show_hex_viewer(&mut self, ui: &mut egui::Ui) {
+        let highlight = self.ui_state.highlighted_byte_range.clone();
+        self.ui_state.hex_viewer.show(ui, &self.file.buffer, highlight);
+    }
+
+    /// Shows the first bytes of the file with the SMB2 file header's fields marked, for comparing
+    /// files in a format we don't recognise against one we do.
+    pub fn show_raw_header(&self, ui: &mut egui::Ui) {
+        const RAW_HEADER_SIZE: usize = 0x100;
+
+        ui.strong("Raw Header");
+        if !self.is_format_detected {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "⚠ This file's format wasn't recognised. Fields are labelled where they would be in SMB2.",
+            );
+        }
+
+        let header = &self.file.buffer[..self.file.buffer.len().min(RAW_HEADER_SIZE)];
+        hex_viewer::show_annotated(ui, header, &parser::get_smb2_file_header_fields());
+    }
+}
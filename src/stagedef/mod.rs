@@ -1,5 +1,22 @@
+//! Everything to do with reading, editing and displaying Monkey Ball stagedefs.
+//!
+//! The [``common::StageDef``] structure and the traits its objects implement live in [``common``],
+//! each kind of object has its own module in [``objects``], and [``parser``] reads them from a file.
+//! [``instance``] and [``ui_state``] display and edit them, and need the ``gui`` feature.
+pub mod autosave;
 pub mod common;
+pub mod compression;
+pub mod diff;
+pub mod error;
+pub mod export;
+#[cfg(feature = "gui")]
 pub mod instance;
 pub mod objects;
 pub mod parser;
+pub mod placement;
+pub mod snippet;
+pub mod summary;
+#[cfg(feature = "gui")]
 pub mod ui_state;
+pub mod units;
+pub mod validation;
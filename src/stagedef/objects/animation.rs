@@ -0,0 +1,378 @@
+use super::super::common::*;
+
+const KEYFRAME_SIZE: u32 = 0x14;
+
+/// How a collision header's animation plays out.
+///
+/// Seesaws don't use an animation header at all - instead they tilt in response to the ball, using
+/// the parameters in [``SeesawParameters``].
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, FromPrimitive, ToPrimitive)]
+pub enum AnimationType {
+    #[default]
+    LoopingAnimation = 0x0,
+    PlayOnceAnimation = 0x1,
+    Seesaw = 0x2,
+}
+
+impl AnimationType {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            AnimationType::LoopingAnimation => "Looping Animation",
+            AnimationType::PlayOnceAnimation => "Play Once Animation",
+            AnimationType::Seesaw => "Seesaw",
+        }
+    }
+}
+
+impl Display for AnimationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+/// The type decides whether [``CollisionHeaderAnimation``] holds seesaw parameters or keyframes, so
+/// it's read-only rather than letting the two disagree.
+#[cfg(feature = "gui")]
+impl EguiInspect for AnimationType {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.label(self.get_name());
+        });
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        self.inspect(label, ui);
+    }
+}
+
+/// How a collision header's animation is playing when the stage starts, before any switch is
+/// pressed.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, FromPrimitive, ToPrimitive)]
+pub enum AnimationState {
+    #[default]
+    Play = 0x0,
+    Pause = 0x1,
+    PlayBackwards = 0x2,
+    FastForward = 0x3,
+    FastBackwards = 0x4,
+}
+
+impl AnimationState {
+    pub const ALL: [AnimationState; 5] = [
+        AnimationState::Play,
+        AnimationState::Pause,
+        AnimationState::PlayBackwards,
+        AnimationState::FastForward,
+        AnimationState::FastBackwards,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            AnimationState::Play => "Play",
+            AnimationState::Pause => "Pause",
+            AnimationState::PlayBackwards => "Play Backwards",
+            AnimationState::FastForward => "Fast Forward",
+            AnimationState::FastBackwards => "Fast Backwards",
+        }
+    }
+}
+
+impl Display for AnimationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.get_name())
+    }
+}
+
+#[cfg(feature = "gui")]
+impl EguiInspect for AnimationState {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.label(self.get_name());
+        });
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label(label)
+            .selected_text(self.get_name())
+            .show_ui(ui, |ui| {
+                for state in AnimationState::ALL {
+                    ui.selectable_value(self, state, state.get_name());
+                }
+            });
+    }
+}
+
+/// The parameters controlling how a seesaw collision header responds to the ball.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct SeesawParameters {
+    #[cfg_attr(feature = "gui", inspect(name = "Sensitivity"))]
+    pub sensitivity: f32,
+    #[cfg_attr(feature = "gui", inspect(name = "Friction"))]
+    pub friction: f32,
+    #[cfg_attr(feature = "gui", inspect(name = "Spring"))]
+    pub spring: f32,
+}
+
+/// A single keyframe of an animated value.
+///
+/// ``time`` is in the same unit as a collision header's animation loop point, seconds.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct Keyframe {
+    pub easing: u32,
+    pub time: f32,
+    pub value: f32,
+    pub in_tangent: f32,
+    pub out_tangent: f32,
+}
+
+impl Keyframe {
+    /// Returns the value at ``time`` between this keyframe and the ``next`` one, using this
+    /// keyframe's easing: 0 holds the value, 1 is linear, and anything else is a cubic Hermite
+    /// curve using the keyframes' tangents.
+    fn interpolate(&self, next: &Keyframe, time: f32) -> f32 {
+        let duration = next.time - self.time;
+        if duration <= 0.0 {
+            return next.value;
+        }
+        let t = ((time - self.time) / duration).clamp(0.0, 1.0);
+
+        match self.easing {
+            0 => self.value,
+            1 => self.value + (next.value - self.value) * t,
+            _ => {
+                let (t2, t3) = (t * t, t * t * t);
+                (2.0 * t3 - 3.0 * t2 + 1.0) * self.value
+                    + (t3 - 2.0 * t2 + t) * self.out_tangent * duration
+                    + (-2.0 * t3 + 3.0 * t2) * next.value
+                    + (t3 - t2) * next.in_tangent * duration
+            }
+        }
+    }
+}
+
+/// Returns the value of an animation channel at ``time``, or ``None`` if the channel has no
+/// keyframes. Before the first keyframe and after the last one, their values are held.
+pub fn evaluate_keyframes(keyframes: &[Keyframe], time: f32) -> Option<f32> {
+    let first = keyframes.first()?;
+    if time <= first.time {
+        return Some(first.value);
+    }
+
+    let value = keyframes.windows(2).find(|pair| time < pair[1].time).map_or_else(
+        || keyframes[keyframes.len() - 1].value,
+        |pair| pair[0].interpolate(&pair[1], time),
+    );
+    Some(value)
+}
+
+impl StageDefObject for Keyframe {
+    fn get_name() -> &'static str {
+        "Keyframe"
+    }
+    fn get_description() -> &'static str {
+        "A single keyframe of an animated value."
+    }
+    fn get_size() -> u32 {
+        KEYFRAME_SIZE
+    }
+}
+
+impl StageDefParsable for Keyframe {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let easing = reader.read_u32::<B>()?;
+        let time = reader.read_f32::<B>()?;
+        let value = reader.read_f32::<B>()?;
+        let in_tangent = reader.read_f32::<B>()?;
+        let out_tangent = reader.read_f32::<B>()?;
+        reader.check_read_size(start_offset, KEYFRAME_SIZE, Self::get_name())?;
+
+        Ok(Self {
+            easing,
+            time,
+            value,
+            in_tangent,
+            out_tangent,
+        })
+    }
+}
+
+/// The keyframes used to animate a collision header, one list per channel.
+#[derive(Default, Debug, PartialEq, Clone)]
+pub struct AnimationHeader {
+    pub rotation_x: Vec<Keyframe>,
+    pub rotation_y: Vec<Keyframe>,
+    pub rotation_z: Vec<Keyframe>,
+    pub position_x: Vec<Keyframe>,
+    pub position_y: Vec<Keyframe>,
+    pub position_z: Vec<Keyframe>,
+}
+
+impl AnimationHeader {
+    /// Returns the rotation around each axis at ``time``, in degrees. Channels without keyframes
+    /// stay at 0.
+    pub fn get_rotation_at(&self, time: f32) -> [f32; 3] {
+        [&self.rotation_x, &self.rotation_y, &self.rotation_z]
+            .map(|keyframes| evaluate_keyframes(keyframes, time).unwrap_or(0.0))
+    }
+
+    /// Returns the time of the last keyframe in any channel.
+    pub fn get_last_keyframe_time(&self) -> f32 {
+        [
+            &self.rotation_x,
+            &self.rotation_y,
+            &self.rotation_z,
+            &self.position_x,
+            &self.position_y,
+            &self.position_z,
+        ]
+        .iter()
+        .filter_map(|keyframes| keyframes.last())
+        .map(|keyframe| keyframe.time)
+        .fold(0.0, f32::max)
+    }
+}
+
+/// What drives the movement of a collision header, depending on its [``AnimationType``].
+///
+/// Seesaw parameters and the animation header are never both used by the same collision header.
+#[derive(Default, Debug, PartialEq, Clone)]
+pub enum CollisionHeaderAnimation {
+    /// The collision header doesn't move.
+    #[default]
+    None,
+    Seesaw(SeesawParameters),
+    Keyframed(AnimationHeader),
+}
+
+impl CollisionHeaderAnimation {
+    /// Returns the number of keyframes in each channel, in the order rotation X/Y/Z then position
+    /// X/Y/Z, or ``None`` if this isn't keyframed.
+    fn get_keyframe_counts(&self) -> Option<[usize; 6]> {
+        let CollisionHeaderAnimation::Keyframed(animation) = self else {
+            return None;
+        };
+        Some(
+            [
+                &animation.rotation_x,
+                &animation.rotation_y,
+                &animation.rotation_z,
+                &animation.position_x,
+                &animation.position_y,
+                &animation.position_z,
+            ]
+            .map(Vec::len),
+        )
+    }
+}
+
+impl Display for CollisionHeaderAnimation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CollisionHeaderAnimation::None => write!(f, "None"),
+            CollisionHeaderAnimation::Seesaw(_) => write!(f, "Seesaw"),
+            CollisionHeaderAnimation::Keyframed(_) => {
+                let keyframe_count: usize = self.get_keyframe_counts().unwrap_or_default().iter().sum();
+                write!(f, "{keyframe_count} keyframes")
+            }
+        }
+    }
+}
+
+/// Seesaw parameters can be edited, but keyframes are only summarized, as there can be hundreds.
+#[cfg(feature = "gui")]
+impl EguiInspect for CollisionHeaderAnimation {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.strong(label);
+        match self {
+            CollisionHeaderAnimation::None => {
+                ui.label("This collision header doesn't move.");
+            }
+            CollisionHeaderAnimation::Seesaw(seesaw) => seesaw.inspect("Seesaw", ui),
+            CollisionHeaderAnimation::Keyframed(_) => {
+                let counts = self.get_keyframe_counts().unwrap_or_default();
+                egui::Grid::new(ui.next_auto_id()).striped(true).show(ui, |ui| {
+                    let channels = ["Rotation X", "Rotation Y", "Rotation Z", "Position X", "Position Y", "Position Z"];
+                    for (channel, count) in channels.into_iter().zip(counts) {
+                        ui.label(channel);
+                        ui.label(format!("{count} keyframes"));
+                        ui.end_row();
+                    }
+                });
+            }
+        }
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        match self {
+            CollisionHeaderAnimation::Seesaw(seesaw) => {
+                ui.strong(label);
+                seesaw.inspect_mut("Seesaw", ui);
+            }
+            _ => self.inspect(label, ui),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keyframe(easing: u32, time: f32, value: f32) -> Keyframe {
+        Keyframe {
+            easing,
+            time,
+            value,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate_keyframes() {
+        assert_eq!(evaluate_keyframes(&[], 1.0), None);
+
+        let keyframes = [
+            keyframe(1, 1.0, 10.0),
+            keyframe(0, 3.0, 30.0),
+            keyframe(2, 4.0, 0.0),
+            keyframe(1, 6.0, 90.0),
+        ];
+
+        // Held before the first and after the last keyframe
+        assert_eq!(evaluate_keyframes(&keyframes, 0.0), Some(10.0));
+        assert_eq!(evaluate_keyframes(&keyframes, 10.0), Some(90.0));
+
+        // Linear
+        assert_eq!(evaluate_keyframes(&keyframes, 2.0), Some(20.0));
+        // Constant
+        assert_eq!(evaluate_keyframes(&keyframes, 3.5), Some(30.0));
+        // Hermite with flat tangents passes through the keyframes and is symmetric around the midpoint
+        assert_eq!(evaluate_keyframes(&keyframes, 4.0), Some(0.0));
+        assert_eq!(evaluate_keyframes(&keyframes, 5.0), Some(45.0));
+        let quarter = evaluate_keyframes(&keyframes, 4.5).unwrap();
+        let three_quarters = evaluate_keyframes(&keyframes, 5.5).unwrap();
+        assert!((quarter + three_quarters - 90.0).abs() < 1e-4);
+        assert!(quarter < 22.5);
+    }
+
+    #[test]
+    fn test_animation_header_rotation() {
+        let animation = AnimationHeader {
+            rotation_y: vec![keyframe(1, 0.0, 0.0), keyframe(1, 2.0, 180.0)],
+            position_x: vec![keyframe(1, 0.0, 0.0), keyframe(1, 5.0, 10.0)],
+            ..Default::default()
+        };
+
+        assert_eq!(animation.get_rotation_at(1.0), [0.0, 90.0, 0.0]);
+        assert_eq!(animation.get_last_keyframe_time(), 5.0);
+    }
+}
@@ -2,12 +2,13 @@ use super::super::common::*;
 
 const BACKGROUND_MODEL_SIZE: u32 = 0x38;
 
-#[derive(EguiInspect)]
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct BackgroundModel {
     unk_0x0: u32,
     model_name: String,
     unk_0x8: u32,
-    position: Vector3,
+    position: Position,
     rotation: ShortVector3,
     unk_0x1e: u16,
     scale: Vector3,
@@ -26,6 +27,15 @@ impl StageDefObject for BackgroundModel {
     fn get_size() -> u32 {
         BACKGROUND_MODEL_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
+    fn is_decoration() -> bool {
+        true
+    }
 }
 
 impl Display for BackgroundModel {
@@ -35,7 +45,7 @@ impl Display for BackgroundModel {
 }
 
 impl StageDefParsable for BackgroundModel {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
@@ -53,13 +63,13 @@ impl StageDefParsable for BackgroundModel {
         reader.read_u32::<B>()?;
         reader.read_u32::<B>()?;
         reader.read_u32::<B>()?;
-        assert!(reader.stream_position()? == start_offset + u64::from(BACKGROUND_MODEL_SIZE));
+        reader.check_read_size(start_offset, BACKGROUND_MODEL_SIZE, Self::get_name())?;
 
         Ok(Self {
             unk_0x0,
             model_name,
             unk_0x8,
-            position,
+            position: position.into(),
             rotation,
             unk_0x1e,
             scale,
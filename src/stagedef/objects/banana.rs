@@ -2,9 +2,10 @@ use super::super::common::*;
 
 const BANANA_SIZE: u32 = 0x10;
 
-#[derive(EguiInspect)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct Banana {
-    pub position: Vector3,
+    pub position: Position,
     pub banana_type: BananaType,
 }
 
@@ -18,6 +19,24 @@ impl StageDefObject for Banana {
     fn get_size() -> u32 {
         BANANA_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
+    #[cfg(feature = "gui")]
+    fn get_tree_color(&self) -> Option<egui::Color32> {
+        Some(self.banana_type.get_color())
+    }
+    #[cfg(feature = "gui")]
+    fn has_tree_inline_editor() -> bool {
+        true
+    }
+    #[cfg(feature = "gui")]
+    fn show_tree_inline_editor(&mut self, ui: &mut egui::Ui) {
+        self.banana_type.inspect_mut("", ui);
+    }
 }
 
 impl Display for Banana {
@@ -26,12 +45,23 @@ impl Display for Banana {
     }
 }
 
-#[derive(PartialEq, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, FromPrimitive, ToPrimitive, serde::Serialize, serde::Deserialize)]
 pub enum BananaType {
     Single = 0x0,
     Bunch = 0x1,
 }
 
+#[cfg(feature = "gui")]
+impl BananaType {
+    /// Returns the color this banana type is drawn with. Bunches are a deeper orange, like in-game.
+    pub fn get_color(&self) -> egui::Color32 {
+        match self {
+            BananaType::Single => egui::Color32::from_rgb(255, 230, 50),
+            BananaType::Bunch => egui::Color32::from_rgb(255, 150, 20),
+        }
+    }
+}
+
 impl Display for BananaType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -41,9 +71,13 @@ impl Display for BananaType {
     }
 }
 
+#[cfg(feature = "gui")]
 impl EguiInspect for BananaType {
-    fn inspect(&self, _label: &str, _ui: &mut egui::Ui) {
-        unimplemented!();
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.colored_label(self.get_color(), format!("{self:?}"));
+        });
     }
     fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
         egui::ComboBox::from_label(label)
@@ -56,15 +90,22 @@ impl EguiInspect for BananaType {
 }
 
 impl StageDefParsable for Banana {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
         R: ReadBytesExtSmb,
     {
         let position = reader.read_vec3::<B>()?;
-        let banana_type: BananaType = FromPrimitive::from_u32(reader.read_u32::<B>()?)
-            .ok_or_else(|| anyhow::Error::msg("Failed to parse banana type"))?;
-        Ok(Self { position, banana_type })
+        let banana_type_value = reader.read_u32::<B>()?;
+        let banana_type: BananaType =
+            FromPrimitive::from_u32(banana_type_value).ok_or(ParseError::UnknownEnumValue {
+                type_name: "banana type",
+                value: banana_type_value,
+            })?;
+        Ok(Self {
+            position: position.into(),
+            banana_type,
+        })
     }
 }
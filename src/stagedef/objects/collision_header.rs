@@ -2,34 +2,38 @@ use super::super::common::*;
 use super::*;
 
 const COLLISION_HEADER_SIZE: u32 = 0x49C;
+const COLLISION_HEADER_SIZE_SMB1: u32 = 0xC4;
 
 #[derive(Default)]
 pub struct CollisionHeader {
-    pub center_of_rotation_position: Vector3,
+    pub center_of_rotation_position: Position,
+    /// The header's rotation before any animation, around ``center_of_rotation_position``.
+    pub initial_rotation: ShortVector3,
     pub conveyor_vector: Vector3,
 
-    /*pub collision_triangles: Vec<CollisionTriangle>,
+    pub collision_triangles: Vec<CollisionTriangle>,
     pub collision_grid_start_x: f32,
     pub collision_grid_start_z: f32,
     pub collision_grid_step_size_x: f32,
     pub collision_grid_step_size_z: f32,
     pub collision_grid_step_count_x: u32,
     pub collision_grid_step_count_z: u32,
+    /// The indices into ``collision_triangles`` of the triangles in each grid cell, row by row.
+    /// A triangle can be in more than one cell.
+    pub collision_grid_cells: Vec<Vec<u16>>,
 
-    pub seesaw_sensitivity: f32,
-    pub seesaw_friction: f32,
-    pub seesaw_spring: f32,
-
-    pub animation_loop_point: f32,
-    pub animation_state_init: AnimationState,
     pub animation_type: AnimationType,
+    pub animation: CollisionHeaderAnimation,
+    /// When a looping animation goes back to the start, in seconds.
+    pub animation_loop_point: f32,
+    /// Identifies this header's animation, so it can be controlled by switches. 0 means none.
     pub animation_id: u16,
+    /// How fast the textures of this header's level models scroll, if they do.
+    pub texture_scroll: Option<TextureScroll>,
+    /// Whether the animation starts out playing, paused, reversed and so on.
+    pub animation_state_init: AnimationState,
+    pub unknowns: CollisionHeaderUnknowns,
 
-    pub unk0x9c: u32,
-    pub unk0xa0: u32,
-    pub unk0xb0: u32,
-    pub unk0xd0: u32,
-    pub unk0xa6: u16,*/
     pub goals: Vec<GlobalStagedefObject<Goal>>,
     pub bumpers: Vec<GlobalStagedefObject<Bumper>>,
     pub jamabars: Vec<GlobalStagedefObject<Jamabar>>,
@@ -40,6 +44,9 @@ pub struct CollisionHeader {
     pub fallout_volumes: Vec<GlobalStagedefObject<FalloutVolume>>,
 
     pub background_models: Vec<GlobalStagedefObject<BackgroundModel>>,
+    pub level_model_instances: Vec<GlobalStagedefObject<LevelModelInstance>>,
+    pub reflective_models: Vec<GlobalStagedefObject<ReflectiveModel>>,
+    pub level_model_names: Vec<String>,
 }
 
 impl StageDefObject for CollisionHeader {
@@ -49,9 +56,100 @@ impl StageDefObject for CollisionHeader {
         "Collision Header"
     }
     fn get_description() -> &'static str {
-        "A collision header."
+        "A collision header - a group of collision and objects that move together.\n\
+         \n\
+         Its **animation type** decides how it moves:\n\
+         - **Looping animation**: plays its keyframes on repeat, from the loop point.\n\
+         - **Play once animation**: plays its keyframes once, usually when triggered by a switch.\n\
+         - **Seesaw**: tilts in response to the ball, using its seesaw parameters instead of keyframes."
     }
     fn get_size() -> u32 {
         COLLISION_HEADER_SIZE
     }
+    /// SMB1 collision headers lack the conveyor vector, loop point and texture scroll, and the
+    /// padding after them.
+    fn get_size_for_game(game: Game) -> u32 {
+        match game {
+            Game::SMB1 => COLLISION_HEADER_SIZE_SMB1,
+            Game::SMB2 | Game::SMBDX => COLLISION_HEADER_SIZE,
+        }
+    }
+}
+
+impl CollisionHeader {
+    /// Returns how long this header's animation lasts: its loop point, or the time of its last
+    /// keyframe if it has no loop point. Headers without keyframed animation last 0 seconds.
+    pub fn get_animation_duration(&self) -> f32 {
+        match &self.animation {
+            CollisionHeaderAnimation::Keyframed(_) if self.animation_loop_point > 0.0 => self.animation_loop_point,
+            CollisionHeaderAnimation::Keyframed(animation) => animation.get_last_keyframe_time(),
+            _ => 0.0,
+        }
+    }
+
+    /// Returns this header's animated rotation at ``time`` seconds, in degrees, or ``None`` if it
+    /// isn't keyframe animated.
+    ///
+    /// Looping animations wrap around at the loop point, and play-once animations hold their
+    /// final rotation.
+    pub fn get_rotation_at(&self, time: f32) -> Option<[f32; 3]> {
+        let CollisionHeaderAnimation::Keyframed(animation) = &self.animation else {
+            return None;
+        };
+
+        let time = match self.animation_type {
+            AnimationType::LoopingAnimation if self.animation_loop_point > 0.0 => time.rem_euclid(self.animation_loop_point),
+            _ => time,
+        };
+        Some(animation.get_rotation_at(time))
+    }
+}
+
+/// Fields of a [``CollisionHeader``] whose purpose is not yet known.
+///
+/// These are preserved as-is so they can be written back out, and shown as hex for research.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub struct CollisionHeaderUnknowns {
+    pub unk0x9c: u32,
+    pub unk0xa0: u32,
+    pub unk0xa6: u16,
+    pub unk0xb0: u32,
+    pub unk0xd0: u32,
+}
+
+#[cfg(feature = "gui")]
+impl CollisionHeaderUnknowns {
+    fn show_fields(&self, ui: &mut egui::Ui) {
+        egui::Grid::new(ui.next_auto_id()).striped(true).show(ui, |ui| {
+            for (name, value) in [
+                ("0x9C", format!("{:#010X}", self.unk0x9c)),
+                ("0xA0", format!("{:#010X}", self.unk0xa0)),
+                ("0xA6", format!("{:#06X}", self.unk0xa6)),
+                ("0xB0", format!("{:#010X}", self.unk0xb0)),
+                ("0xD0", format!("{:#010X}", self.unk0xd0)),
+            ] {
+                ui.label(name);
+                ui.monospace(value);
+                ui.end_row();
+            }
+        });
+    }
+}
+
+impl Display for CollisionHeaderUnknowns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "5 fields")
+    }
+}
+
+#[cfg(feature = "gui")]
+impl EguiInspect for CollisionHeaderUnknowns {
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.strong(label);
+        self.show_fields(ui);
+    }
+
+    fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
+        self.inspect(label, ui);
+    }
 }
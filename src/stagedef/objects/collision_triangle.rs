@@ -0,0 +1,266 @@
+use super::super::common::*;
+
+const COLLISION_TRIANGLE_SIZE: u32 = 0x40;
+
+#[derive(Default, Debug, PartialEq)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct CollisionTriangle {
+    #[cfg_attr(feature = "gui", inspect(name = "Vertex 1 Position"))]
+    pub position: Position,
+    #[cfg_attr(feature = "gui", inspect(name = "Normal"))]
+    pub normal: Vector3,
+    #[cfg_attr(feature = "gui", inspect(name = "Rotation"))]
+    pub rotation: ShortVector3,
+    pub delta_x2_x1: f32,
+    pub delta_y2_y1: f32,
+    pub delta_x3_x1: f32,
+    pub delta_y3_y1: f32,
+    pub x_tangent: f32,
+    pub y_tangent: f32,
+    pub x_bitangent: f32,
+    pub y_bitangent: f32,
+}
+
+impl CollisionTriangle {
+    /// Returns the three vertices of this triangle in stage space.
+    ///
+    /// Vertices 2 and 3 are stored as 2D offsets from vertex 1 on the plane of the triangle, so we
+    /// rotate them into place the same way the game does (Z, then X, then Y).
+    pub fn get_vertices(&self) -> [Vector3; 3] {
+        let (sin_x, cos_x) = from_short_angle(self.rotation.x).sin_cos();
+        let (sin_y, cos_y) = from_short_angle(self.rotation.y).sin_cos();
+        let (sin_z, cos_z) = from_short_angle(self.rotation.z).sin_cos();
+
+        let rotate = |x: f32, y: f32| {
+            // Z
+            let (x, y, z) = (x * cos_z - y * sin_z, x * sin_z + y * cos_z, 0.0);
+            // X
+            let (x, y, z) = (x, y * cos_x - z * sin_x, y * sin_x + z * cos_x);
+            // Y
+            let (x, y, z) = (x * cos_y + z * sin_y, y, -x * sin_y + z * cos_y);
+
+            Vector3 {
+                x: self.position.x + x,
+                y: self.position.y + y,
+                z: self.position.z + z,
+            }
+        };
+
+        [
+            rotate(0.0, 0.0),
+            rotate(self.delta_x2_x1, self.delta_y2_y1),
+            rotate(self.delta_x3_x1, self.delta_y3_y1),
+        ]
+    }
+
+    /// Makes a triangle from its three vertices in stage space, working out everything else the
+    /// game stores about it.
+    ///
+    /// The triangle is rotated so that vertex 2 lies along the X axis from vertex 1, which the game
+    /// relies on when checking which side of each edge the ball is on. The tangent and bitangent are
+    /// the inward facing normals of the edges from vertex 2 to 3 and from vertex 3 to 1, on the
+    /// plane of the triangle. The normal faces the side the vertices go anticlockwise around.
+    pub fn from_vertices(a: Vector3, b: Vector3, c: Vector3) -> Self {
+        let ba = [b.x - a.x, b.y - a.y, b.z - a.z];
+        let ca = [c.x - a.x, c.y - a.y, c.z - a.z];
+        let cross = [
+            ba[1] * ca[2] - ba[2] * ca[1],
+            ba[2] * ca[0] - ba[0] * ca[2],
+            ba[0] * ca[1] - ba[1] * ca[0],
+        ];
+        let [normal_x, normal_y, normal_z] = normalize(cross);
+
+        // Undo the Y and X rotations to lay the triangle flat, then the Z rotation to put vertex 2 on
+        // the X axis. Each is undone by the angle it'll be stored as, so ``get_vertices`` gives back
+        // the same vertices.
+        let rotation_y = to_short_angle(normal_x.atan2(normal_z));
+        let rotation_x = to_short_angle((-normal_y).atan2(normal_x.hypot(normal_z)));
+        let (sin_y, cos_y) = from_short_angle(rotation_y).sin_cos();
+        let (sin_x, cos_x) = from_short_angle(rotation_x).sin_cos();
+        let flatten = |[x, y, z]: [f32; 3]| {
+            // Y
+            let (x, y, z) = (x * cos_y - z * sin_y, y, x * sin_y + z * cos_y);
+            // X
+            (x, y * cos_x + z * sin_x)
+        };
+        let (flat_x2, flat_y2) = flatten(ba);
+        let (flat_x3, flat_y3) = flatten(ca);
+
+        let rotation_z = to_short_angle(flat_y2.atan2(flat_x2));
+        let (sin_z, cos_z) = from_short_angle(rotation_z).sin_cos();
+        let unrotate_z = |x: f32, y: f32| (x * cos_z + y * sin_z, -x * sin_z + y * cos_z);
+        let (delta_x2_x1, delta_y2_y1) = unrotate_z(flat_x2, flat_y2);
+        let (delta_x3_x1, delta_y3_y1) = unrotate_z(flat_x3, flat_y3);
+
+        let [x_tangent, y_tangent, _] = normalize([delta_y2_y1 - delta_y3_y1, delta_x3_x1 - delta_x2_x1, 0.0]);
+        let [x_bitangent, y_bitangent, _] = normalize([delta_y3_y1, -delta_x3_x1, 0.0]);
+
+        Self {
+            position: Position(a),
+            normal: Vector3 {
+                x: normal_x,
+                y: normal_y,
+                z: normal_z,
+            },
+            rotation: ShortVector3 {
+                x: rotation_x,
+                y: rotation_y,
+                z: rotation_z,
+            },
+            delta_x2_x1,
+            delta_y2_y1,
+            delta_x3_x1,
+            delta_y3_y1,
+            x_tangent,
+            y_tangent,
+            x_bitangent,
+            y_bitangent,
+        }
+    }
+
+    /// A flat 10x10 right triangle on the floor (Y = 0), with its corner at the origin.
+    #[cfg(test)]
+    pub(crate) fn floor() -> Self {
+        CollisionTriangle {
+            rotation: ShortVector3 { x: 0x4000, y: 0, z: 0 },
+            delta_x2_x1: 10.0,
+            delta_x3_x1: 0.0,
+            delta_y3_y1: 10.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Scales a vector to a length of 1, leaving it alone if it has no length, as happens with the
+/// edges of degenerate triangles.
+fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = vector.iter().map(|component| component * component).sum::<f32>().sqrt();
+    if length > 0.0 {
+        vector.map(|component| component / length)
+    } else {
+        vector
+    }
+}
+
+/// Converts an angle in radians to the game's 16-bit angles, where 0x10000 is a full turn.
+fn to_short_angle(angle: f32) -> u16 {
+    (angle / std::f32::consts::TAU * 65536.0).round().rem_euclid(65536.0) as u16
+}
+
+fn from_short_angle(angle: u16) -> f32 {
+    f32::from(angle) / 65536.0 * std::f32::consts::TAU
+}
+
+impl StageDefObject for CollisionTriangle {
+    fn get_name() -> &'static str {
+        "Collision Triangle"
+    }
+    fn get_description() -> &'static str {
+        "A triangle of the stage's collision mesh."
+    }
+    fn get_size() -> u32 {
+        COLLISION_TRIANGLE_SIZE
+    }
+}
+
+impl Display for CollisionTriangle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.position)
+    }
+}
+
+impl StageDefParsable for CollisionTriangle {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let position = reader.read_vec3::<B>()?;
+        let normal = reader.read_vec3::<B>()?;
+        let rotation = reader.read_vec3_short::<B>()?;
+        reader.read_u16::<B>()?;
+        let delta_x2_x1 = reader.read_f32::<B>()?;
+        let delta_y2_y1 = reader.read_f32::<B>()?;
+        let delta_x3_x1 = reader.read_f32::<B>()?;
+        let delta_y3_y1 = reader.read_f32::<B>()?;
+        let x_tangent = reader.read_f32::<B>()?;
+        let y_tangent = reader.read_f32::<B>()?;
+        let x_bitangent = reader.read_f32::<B>()?;
+        let y_bitangent = reader.read_f32::<B>()?;
+        reader.check_read_size(start_offset, COLLISION_TRIANGLE_SIZE, Self::get_name())?;
+
+        Ok(Self {
+            position: position.into(),
+            normal,
+            rotation,
+            delta_x2_x1,
+            delta_y2_y1,
+            delta_x3_x1,
+            delta_y3_y1,
+            x_tangent,
+            y_tangent,
+            x_bitangent,
+            y_bitangent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 0.0001, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_from_vertices() {
+        // A 2x2 right triangle standing up on the XY plane, as stored in a stagedef
+        let triangle = CollisionTriangle::from_vertices(
+            Vector3 { x: 1.0, y: 0.0, z: 0.0 },
+            Vector3 { x: 1.0, y: 2.0, z: 0.0 },
+            Vector3 { x: -1.0, y: 0.0, z: 0.0 },
+        );
+
+        assert_eq!(triangle.position, Vector3 { x: 1.0, y: 0.0, z: 0.0 });
+        assert_eq!(triangle.normal, Vector3 { x: 0.0, y: 0.0, z: 1.0 });
+        assert_eq!(triangle.rotation, ShortVector3 { x: 0, y: 0, z: 0x4000 });
+        assert_near(triangle.delta_x2_x1, 2.0);
+        assert_near(triangle.delta_y2_y1, 0.0);
+        assert_near(triangle.delta_x3_x1, 0.0);
+        assert_near(triangle.delta_y3_y1, 2.0);
+        assert_near(triangle.x_tangent, -std::f32::consts::FRAC_1_SQRT_2);
+        assert_near(triangle.y_tangent, -std::f32::consts::FRAC_1_SQRT_2);
+        assert_near(triangle.x_bitangent, 1.0);
+        assert_near(triangle.y_bitangent, 0.0);
+    }
+
+    #[test]
+    fn test_from_vertices_round_trip() {
+        let vertices = [
+            Vector3 { x: 3.0, y: -1.0, z: 2.0 },
+            Vector3 { x: 7.5, y: 4.0, z: -6.0 },
+            Vector3 { x: -2.0, y: 5.0, z: 1.0 },
+        ];
+        let triangle = CollisionTriangle::from_vertices(vertices[0], vertices[1], vertices[2]);
+
+        // Rotations are rounded to the nearest 16-bit angle, so allow for a little error
+        assert!(triangle.delta_y2_y1.abs() < 0.01, "Vertex 2 should be on the X axis");
+        for (vertex, expected) in triangle.get_vertices().iter().zip(vertices.iter()) {
+            assert!((vertex.x - expected.x).abs() < 0.01, "{vertex} != {expected}");
+            assert!((vertex.y - expected.y).abs() < 0.01, "{vertex} != {expected}");
+            assert!((vertex.z - expected.z).abs() < 0.01, "{vertex} != {expected}");
+        }
+    }
+
+    #[test]
+    fn test_from_vertices_degenerate() {
+        let point = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let triangle = CollisionTriangle::from_vertices(point, point, point);
+        assert_eq!(triangle.normal, Vector3::default());
+        assert!(triangle.get_vertices().iter().all(|vertex| *vertex == point));
+    }
+}
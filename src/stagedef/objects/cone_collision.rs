@@ -2,9 +2,10 @@ use super::super::common::*;
 
 const CONE_COL_SIZE: u32 = 0x20;
 
-#[derive(EguiInspect)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct ConeCollision {
-    pub position: Vector3,
+    pub position: Position,
     pub rotation: ShortVector3,
     pub radius_1: f32,
     pub height: f32,
@@ -21,6 +22,12 @@ impl StageDefObject for ConeCollision {
     fn get_size() -> u32 {
         CONE_COL_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
 }
 
 impl Display for ConeCollision {
@@ -30,7 +37,7 @@ impl Display for ConeCollision {
 }
 
 impl StageDefParsable for ConeCollision {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
@@ -45,7 +52,7 @@ impl StageDefParsable for ConeCollision {
         let radius_2 = reader.read_f32::<B>()?;
 
         Ok(Self {
-            position,
+            position: position.into(),
             rotation,
             radius_1,
             height,
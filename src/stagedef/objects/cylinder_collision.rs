@@ -2,9 +2,10 @@ use super::super::common::*;
 
 const CYL_COL_SIZE: u32 = 0x1C;
 
-#[derive(EguiInspect)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct CylinderCollision {
-    pub position: Vector3,
+    pub position: Position,
     pub radius: f32,
     pub height: f32,
     pub rotation: ShortVector3,
@@ -21,6 +22,12 @@ impl StageDefObject for CylinderCollision {
     fn get_size() -> u32 {
         CYL_COL_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
 }
 
 impl Display for CylinderCollision {
@@ -30,7 +37,7 @@ impl Display for CylinderCollision {
 }
 
 impl StageDefParsable for CylinderCollision {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
@@ -43,7 +50,7 @@ impl StageDefParsable for CylinderCollision {
         let unk0x1a = reader.read_u16::<B>()?;
 
         Ok(Self {
-            position,
+            position: position.into(),
             radius,
             height,
             rotation,
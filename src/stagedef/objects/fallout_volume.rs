@@ -2,11 +2,23 @@ use super::super::common::*;
 
 const FALLOUT_VOLUME_SIZE: u32 = 0x20;
 
-#[derive(EguiInspect)]
+/// A box that makes the ball fall out when it enters it.
+///
+/// The layout is the same in SMB1 and SMB2:
+///
+/// | Offset | Type | Field |
+/// |--------|------|-------|
+/// | 0x00 | ``Vector3`` | Center position |
+/// | 0x0C | ``Vector3`` | Size along each axis |
+/// | 0x18 | ``ShortVector3`` | Rotation |
+/// | 0x1E | ``u16`` | Padding |
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct FalloutVolume {
-    pub position: Vector3,
-    pub size: Vector3,
+    pub position: Position,
+    pub size: Position,
     pub rotation: ShortVector3,
+    /// Always 0 in vanilla stages.
     pub unk0x1e: u16,
 }
 
@@ -20,6 +32,12 @@ impl StageDefObject for FalloutVolume {
     fn get_size() -> u32 {
         FALLOUT_VOLUME_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
 }
 
 impl Display for FalloutVolume {
@@ -29,7 +47,7 @@ impl Display for FalloutVolume {
 }
 
 impl StageDefParsable for FalloutVolume {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
@@ -41,8 +59,8 @@ impl StageDefParsable for FalloutVolume {
         let unk0x1e = reader.read_u16::<B>()?;
 
         Ok(Self {
-            position,
-            size,
+            position: position.into(),
+            size: size.into(),
             rotation,
             unk0x1e,
         })
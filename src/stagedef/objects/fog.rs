@@ -0,0 +1,156 @@
+use super::super::common::*;
+
+const FOG_SIZE: u32 = 0x24;
+const MYSTERY_3_SIZE: u32 = 0x24;
+
+/// The fog a stage is drawn with, pointed to by the SMB2 file header at 0xBC.
+///
+/// | Offset | Type | Field |
+/// |--------|------|-------|
+/// | 0x00 | ``u8`` | Fog type |
+/// | 0x01 | ``u8`` | Enabled |
+/// | 0x02 | ``u16`` | Padding |
+/// | 0x04 | ``f32`` | Start distance |
+/// | 0x08 | ``f32`` | End distance |
+/// | 0x0C | ``Vector3`` | Color, as red, green and blue from 0.0 to 1.0 |
+/// | 0x18 | ``u32`` x3 | Unknown |
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct Fog {
+    /// The GX fog function, e.g. 2 for linear fog.
+    #[cfg_attr(feature = "gui", inspect(name = "Fog Type"))]
+    pub fog_type: u8,
+    #[cfg_attr(feature = "gui", inspect(name = "Enabled"))]
+    pub enabled: u8,
+    #[cfg_attr(feature = "gui", inspect(name = "Start Distance"))]
+    pub start_distance: f32,
+    #[cfg_attr(feature = "gui", inspect(name = "End Distance"))]
+    pub end_distance: f32,
+    #[cfg_attr(feature = "gui", inspect(name = "Color"))]
+    pub color: Vector3,
+    pub unk0x18: u32,
+    pub unk0x1c: u32,
+    pub unk0x20: u32,
+}
+
+impl StageDefObject for Fog {
+    fn get_name() -> &'static str {
+        "Fog"
+    }
+    fn get_description() -> &'static str {
+        "The fog the stage is drawn with: how far from the camera it starts and ends, and its color."
+    }
+    fn get_size() -> u32 {
+        FOG_SIZE
+    }
+}
+
+impl Display for Fog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:.1} to {:.1}", self.start_distance, self.end_distance)
+    }
+}
+
+impl StageDefParsable for Fog {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let fog_type = reader.read_u8()?;
+        let enabled = reader.read_u8()?;
+        reader.read_u16::<B>()?;
+        let start_distance = reader.read_f32::<B>()?;
+        let end_distance = reader.read_f32::<B>()?;
+        let color = reader.read_vec3::<B>()?;
+        let unk0x18 = reader.read_u32::<B>()?;
+        let unk0x1c = reader.read_u32::<B>()?;
+        let unk0x20 = reader.read_u32::<B>()?;
+
+        reader.check_read_size(start_offset, Self::get_size(), Self::get_name())?;
+
+        Ok(Self {
+            fog_type,
+            enabled,
+            start_distance,
+            end_distance,
+            color,
+            unk0x18,
+            unk0x1c,
+            unk0x20,
+        })
+    }
+}
+
+/// A structure pointed to by the SMB2 file header at 0xD4, believed to be related to fog or other
+/// effects. Stages with animated fog tend to have one alongside their [``Fog``].
+///
+/// | Offset | Type | Field |
+/// |--------|------|-------|
+/// | 0x00 | ``Vector3`` | Unknown, 1.0 on every axis in vanilla stages |
+/// | 0x0C | ``u32`` x6 | Unknown, 0 in vanilla stages |
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct Mystery3 {
+    #[cfg_attr(feature = "gui", inspect(name = "Unknown 0x00"))]
+    pub unk0x0: Vector3,
+    pub unk0xc: u32,
+    pub unk0x10: u32,
+    pub unk0x14: u32,
+    pub unk0x18: u32,
+    pub unk0x1c: u32,
+    pub unk0x20: u32,
+}
+
+impl StageDefObject for Mystery3 {
+    fn get_name() -> &'static str {
+        "Mystery 3"
+    }
+    fn get_description() -> &'static str {
+        "A structure whose purpose is not yet known, but is believed to be related to fog or other \
+         effects. The first three values are 1.0 and the rest are 0 in vanilla stages."
+    }
+    fn get_size() -> u32 {
+        MYSTERY_3_SIZE
+    }
+}
+
+impl Display for Mystery3 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.unk0x0)
+    }
+}
+
+impl StageDefParsable for Mystery3 {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let unk0x0 = reader.read_vec3::<B>()?;
+        let unk0xc = reader.read_u32::<B>()?;
+        let unk0x10 = reader.read_u32::<B>()?;
+        let unk0x14 = reader.read_u32::<B>()?;
+        let unk0x18 = reader.read_u32::<B>()?;
+        let unk0x1c = reader.read_u32::<B>()?;
+        let unk0x20 = reader.read_u32::<B>()?;
+
+        reader.check_read_size(start_offset, Self::get_size(), Self::get_name())?;
+
+        Ok(Self {
+            unk0x0,
+            unk0xc,
+            unk0x10,
+            unk0x14,
+            unk0x18,
+            unk0x1c,
+            unk0x20,
+        })
+    }
+}
@@ -2,16 +2,38 @@ use super::super::common::*;
 
 const GOAL_SIZE: u32 = 0x14;
 
-#[derive(Default, Debug, PartialEq, EguiInspect)]
+/// A goal, which is 0x14 bytes in every game. SMB1 only differs from SMB2 in how the type is
+/// stored. SMBDX is assumed to keep SMB2's layout, but this hasn't been checked against a real
+/// Deluxe stagedef.
+///
+/// | Offset | Type | Field |
+/// |--------|------|-------|
+/// | 0x00 | ``Vector3`` | Position |
+/// | 0x0C | ``ShortVector3`` | Rotation |
+/// | 0x12 | ``u8`` | Type: an index in SMB2 and SMBDX, or ``'B'``, ``'G'`` or ``'R'`` in SMB1 |
+/// | 0x13 | ``u8`` | Padding |
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct Goal {
-    #[inspect(name = "Position")]
-    pub position: Vector3,
-    #[inspect(name = "Rotation")]
+    #[cfg_attr(feature = "gui", inspect(name = "Position"))]
+    pub position: Position,
+    #[cfg_attr(feature = "gui", inspect(name = "Rotation"))]
     pub rotation: ShortVector3,
-    #[inspect(name = "Goal Type")]
+    #[cfg_attr(feature = "gui", inspect(name = "Goal Type"))]
     pub goal_type: GoalType,
 }
 
+impl Goal {
+    /// A goal on the Z axis with everything else left as the default, to tell goals apart by.
+    #[cfg(test)]
+    pub(crate) fn at_z(z: f32) -> Self {
+        Goal {
+            position: Position(Vector3 { x: 0.0, y: 0.0, z }),
+            ..Default::default()
+        }
+    }
+}
+
 impl Display for Goal {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.position)
@@ -28,9 +50,27 @@ impl StageDefObject for Goal {
     fn get_size() -> u32 {
         GOAL_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
+    #[cfg(feature = "gui")]
+    fn get_tree_color(&self) -> Option<egui::Color32> {
+        Some(self.goal_type.get_color())
+    }
+    #[cfg(feature = "gui")]
+    fn has_tree_inline_editor() -> bool {
+        true
+    }
+    #[cfg(feature = "gui")]
+    fn show_tree_inline_editor(&mut self, ui: &mut egui::Ui) {
+        self.goal_type.inspect_mut("", ui);
+    }
 }
 
-#[derive(Default, FromPrimitive, ToPrimitive, Debug, PartialEq)]
+#[derive(Default, FromPrimitive, ToPrimitive, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum GoalType {
     #[default]
     Blue = 0x0,
@@ -38,9 +78,25 @@ pub enum GoalType {
     Red = 0x2,
 }
 
+#[cfg(feature = "gui")]
+impl GoalType {
+    /// Returns the color of this goal type's ribbon in-game.
+    pub fn get_color(&self) -> egui::Color32 {
+        match self {
+            GoalType::Blue => egui::Color32::from_rgb(90, 150, 255),
+            GoalType::Green => egui::Color32::from_rgb(80, 200, 90),
+            GoalType::Red => egui::Color32::from_rgb(240, 80, 80),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
 impl EguiInspect for GoalType {
-    fn inspect(&self, _label: &str, _ui: &mut egui::Ui) {
-        unimplemented!();
+    fn inspect(&self, label: &str, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            ui.colored_label(self.get_color(), format!("{self:?}"));
+        });
     }
 
     fn inspect_mut(&mut self, label: &str, ui: &mut egui::Ui) {
@@ -54,22 +110,53 @@ impl EguiInspect for GoalType {
     }
 }
 
+impl GoalType {
+    /// Converts an SMB1 goal type, which is stored as an ASCII character rather than an index.
+    fn from_smb1_u8(value: u8) -> Option<Self> {
+        match value {
+            b'B' => Some(GoalType::Blue),
+            b'G' => Some(GoalType::Green),
+            b'R' => Some(GoalType::Red),
+            _ => None,
+        }
+    }
+}
+
 impl StageDefParsable for Goal {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
         R: ReadBytesExtSmb,
     {
+        Self::try_from_reader_for_game::<R, B>(reader, Game::SMB2)
+    }
+
+    fn try_from_reader_for_game<R, B>(reader: &mut R, game: Game) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
         let position = reader.read_vec3::<B>()?;
         let rotation = reader.read_vec3_short::<B>()?;
 
-        let goal_type: GoalType =
-            FromPrimitive::from_u8(reader.read_u8()?).ok_or_else(|| anyhow::Error::msg("Failed to parse goal type"))?;
+        let goal_type_value = reader.read_u8()?;
+        let goal_type = match game {
+            Game::SMB1 => GoalType::from_smb1_u8(goal_type_value),
+            Game::SMB2 | Game::SMBDX => FromPrimitive::from_u8(goal_type_value),
+        }
+        .ok_or(ParseError::UnknownEnumValue {
+            type_name: "goal type",
+            value: u32::from(goal_type_value),
+        })?;
         reader.read_u8()?;
+        reader.check_read_size(start_offset, GOAL_SIZE, Self::get_name())?;
 
         Ok(Self {
-            position,
+            position: position.into(),
             rotation,
             goal_type,
         })
@@ -2,9 +2,10 @@ use super::super::common::*;
 
 const JAMABAR_SIZE: u32 = 0x20;
 
-#[derive(EguiInspect)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct Jamabar {
-    pub position: Vector3,
+    pub position: Position,
     pub rotation: ShortVector3,
     pub scale: Vector3,
 }
@@ -19,6 +20,12 @@ impl StageDefObject for Jamabar {
     fn get_size() -> u32 {
         JAMABAR_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
 }
 
 impl Display for Jamabar {
@@ -28,19 +35,22 @@ impl Display for Jamabar {
 }
 
 impl StageDefParsable for Jamabar {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
         R: ReadBytesExtSmb,
     {
+        let start_offset = reader.stream_position()?;
+
         let position = reader.read_vec3::<B>()?;
         let rotation = reader.read_vec3_short::<B>()?;
-        reader.read_u8()?;
+        reader.read_u16::<B>()?;
         let scale = reader.read_vec3::<B>()?;
+        reader.check_read_size(start_offset, JAMABAR_SIZE, Self::get_name())?;
 
         Ok(Self {
-            position,
+            position: position.into(),
             rotation,
             scale,
         })
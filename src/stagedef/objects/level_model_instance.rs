@@ -0,0 +1,65 @@
+use super::super::common::*;
+
+const LEVEL_MODEL_INSTANCE_SIZE: u32 = 0x24;
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct LevelModelInstance {
+    #[cfg_attr(feature = "gui", inspect(name = "Model Name"))]
+    pub model_name: String,
+    #[cfg_attr(feature = "gui", inspect(name = "Position"))]
+    pub position: Position,
+    #[cfg_attr(feature = "gui", inspect(name = "Rotation"))]
+    pub rotation: ShortVector3,
+    #[cfg_attr(feature = "gui", inspect(name = "Scale"))]
+    pub scale: Vector3,
+}
+
+impl StageDefObject for LevelModelInstance {
+    fn get_name() -> &'static str {
+        "Level Model Instance"
+    }
+    fn get_description() -> &'static str {
+        "A placement of a level model, with its own transform."
+    }
+    fn get_size() -> u32 {
+        LEVEL_MODEL_INSTANCE_SIZE
+    }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
+}
+
+impl Display for LevelModelInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.model_name, self.position)
+    }
+}
+
+impl StageDefParsable for LevelModelInstance {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let model_name = reader.read_model_name_from_ptr_a::<B>()?;
+        let position = reader.read_vec3::<B>()?;
+        let rotation = reader.read_vec3_short::<B>()?;
+        reader.read_u16::<B>()?;
+        let scale = reader.read_vec3::<B>()?;
+        reader.check_read_size(start_offset, LEVEL_MODEL_INSTANCE_SIZE, Self::get_name())?;
+
+        Ok(Self {
+            model_name,
+            position: position.into(),
+            rotation,
+            scale,
+        })
+    }
+}
@@ -1,21 +1,35 @@
+pub use animation::*;
 pub use banana::*;
 pub use bumper::*;
 pub use collision_header::*;
+pub use collision_triangle::*;
 pub use cone_collision::*;
 pub use cylinder_collision::*;
 pub use fallout_volume::*;
+pub use fog::*;
 pub use goal::*;
 pub use jamabar::*;
+pub use level_model_instance::*;
+pub use reflective_model::*;
 pub use sphere_collision::*;
+pub use start_position::*;
+pub use texture_scroll::*;
 pub use background_model::*;
 
+pub mod animation;
 pub mod banana;
 pub mod bumper;
 pub mod collision_header;
+pub mod collision_triangle;
 pub mod cone_collision;
 pub mod cylinder_collision;
 pub mod fallout_volume;
+pub mod fog;
 pub mod goal;
 pub mod jamabar;
+pub mod level_model_instance;
+pub mod reflective_model;
 pub mod sphere_collision;
+pub mod start_position;
+pub mod texture_scroll;
 pub mod background_model;
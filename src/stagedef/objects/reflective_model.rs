@@ -0,0 +1,72 @@
+use super::super::common::*;
+
+const REFLECTIVE_MODEL_SIZE: u32 = 0xC;
+const REFLECTIVE_MODEL_SIZE_SMB1: u32 = 0x8;
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct ReflectiveModel {
+    #[cfg_attr(feature = "gui", inspect(name = "Model Name"))]
+    pub model_name: String,
+    /// Only present in SMB2 and SMBDX stagedefs.
+    #[cfg_attr(feature = "gui", inspect(name = "Flags (SMB2)"))]
+    pub flags: u32,
+}
+
+impl StageDefObject for ReflectiveModel {
+    fn get_name() -> &'static str {
+        "Reflective Model"
+    }
+    fn get_description() -> &'static str {
+        "A level model that reflects the rest of the stage."
+    }
+    fn get_size() -> u32 {
+        REFLECTIVE_MODEL_SIZE
+    }
+    /// SMB1 reflective models are smaller, as they have no flags.
+    fn get_size_for_game(game: Game) -> u32 {
+        match game {
+            Game::SMB1 => REFLECTIVE_MODEL_SIZE_SMB1,
+            Game::SMB2 | Game::SMBDX => REFLECTIVE_MODEL_SIZE,
+        }
+    }
+}
+
+impl Display for ReflectiveModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.model_name)
+    }
+}
+
+impl StageDefParsable for ReflectiveModel {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        Self::try_from_reader_for_game::<R, B>(reader, Game::SMB2)
+    }
+
+    fn try_from_reader_for_game<R, B>(reader: &mut R, game: Game) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let model_name = reader.read_model_name_from_offset::<B>()?;
+        // Filled in with a pointer to the loaded model at runtime
+        reader.read_u32::<B>()?;
+
+        let flags = match game {
+            Game::SMB1 => 0,
+            Game::SMB2 | Game::SMBDX => reader.read_u32::<B>()?,
+        };
+
+        reader.check_read_size(start_offset, Self::get_size_for_game(game), Self::get_name())?;
+
+        Ok(Self { model_name, flags })
+    }
+}
@@ -2,9 +2,10 @@ use super::super::common::*;
 
 const SPHERE_COL_SIZE: u32 = 0x14;
 
-#[derive(EguiInspect)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
 pub struct SphereCollision {
-    pub position: Vector3,
+    pub position: Position,
     pub radius: f32,
     pub unk0x10: u32,
 }
@@ -19,6 +20,12 @@ impl StageDefObject for SphereCollision {
     fn get_size() -> u32 {
         SPHERE_COL_SIZE
     }
+    fn get_position(&self) -> Option<Vector3> {
+        Some(self.position.0)
+    }
+    fn get_position_mut(&mut self) -> Option<&mut Vector3> {
+        Some(&mut self.position.0)
+    }
 }
 
 impl Display for SphereCollision {
@@ -28,7 +35,7 @@ impl Display for SphereCollision {
 }
 
 impl StageDefParsable for SphereCollision {
-    fn try_from_reader<R, B>(reader: &mut R) -> Result<Self>
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
     where
         Self: Sized,
         B: ByteOrder,
@@ -39,7 +46,7 @@ impl StageDefParsable for SphereCollision {
         let unk0x10 = reader.read_u32::<B>()?;
 
         Ok(Self {
-            position,
+            position: position.into(),
             radius,
             unk0x10,
         })
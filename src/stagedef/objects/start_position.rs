@@ -0,0 +1,78 @@
+use super::super::common::*;
+
+const START_POS_SIZE: u32 = 0x14;
+const START_POS_SIZE_SMBDX: u32 = 0x18;
+
+#[derive(Default, Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct StartPosition {
+    #[cfg_attr(feature = "gui", inspect(name = "Position"))]
+    pub position: Position,
+    #[cfg_attr(feature = "gui", inspect(name = "Rotation"))]
+    pub rotation: ShortVector3,
+    /// Only present in SMBDX stagedefs.
+    #[cfg_attr(feature = "gui", inspect(name = "Unknown 0x14 (SMBDX)"))]
+    pub unk0x14: u32,
+}
+
+impl StageDefObject for StartPosition {
+    fn get_name() -> &'static str {
+        "Start Position"
+    }
+    fn get_description() -> &'static str {
+        "The position and rotation the ball starts at."
+    }
+    fn get_size() -> u32 {
+        START_POS_SIZE
+    }
+    /// SMBDX start positions are larger.
+    fn get_size_for_game(game: Game) -> u32 {
+        match game {
+            Game::SMB1 | Game::SMB2 => START_POS_SIZE,
+            Game::SMBDX => START_POS_SIZE_SMBDX,
+        }
+    }
+}
+
+impl Display for StartPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.position, self.rotation)
+    }
+}
+
+impl StageDefParsable for StartPosition {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        Self::try_from_reader_for_game::<R, B>(reader, Game::SMB2)
+    }
+
+    fn try_from_reader_for_game<R, B>(reader: &mut R, game: Game) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let position = reader.read_vec3::<B>()?;
+        let rotation = reader.read_vec3_short::<B>()?;
+        reader.read_u16::<B>()?;
+
+        let unk0x14 = match game {
+            Game::SMB1 | Game::SMB2 => 0,
+            Game::SMBDX => reader.read_u32::<B>()?,
+        };
+
+        reader.check_read_size(start_offset, Self::get_size_for_game(game), Self::get_name())?;
+
+        Ok(Self {
+            position: position.into(),
+            rotation,
+            unk0x14,
+        })
+    }
+}
@@ -0,0 +1,62 @@
+use super::super::common::*;
+
+const TEXTURE_SCROLL_SIZE: u32 = 0x8;
+
+/// How fast the textures of a collision header's level models scroll, pointed to by the SMB2
+/// collision header at 0xD8.
+///
+/// | Offset | Type | Field |
+/// |--------|------|-------|
+/// | 0x00 | ``f32`` | Speed along X |
+/// | 0x04 | ``f32`` | Speed along Y |
+#[derive(Default, Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "gui", derive(EguiInspect))]
+pub struct TextureScroll {
+    #[cfg_attr(feature = "gui", inspect(name = "Speed X"))]
+    pub speed_x: f32,
+    #[cfg_attr(feature = "gui", inspect(name = "Speed Y"))]
+    pub speed_y: f32,
+}
+
+impl TextureScroll {
+    /// Returns whether this actually moves anything.
+    pub fn is_moving(&self) -> bool {
+        self.speed_x != 0.0 || self.speed_y != 0.0
+    }
+}
+
+impl StageDefObject for TextureScroll {
+    fn get_name() -> &'static str {
+        "Texture Scroll"
+    }
+    fn get_description() -> &'static str {
+        "How fast the textures of the collision header's level models scroll. Only models whose \
+         materials are set up to scroll are affected."
+    }
+    fn get_size() -> u32 {
+        TEXTURE_SCROLL_SIZE
+    }
+}
+
+impl Display for TextureScroll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({:.2}, {:.2})", self.speed_x, self.speed_y)
+    }
+}
+
+impl StageDefParsable for TextureScroll {
+    fn try_from_reader<R, B>(reader: &mut R) -> ParseResult<Self>
+    where
+        Self: Sized,
+        B: ByteOrder,
+        R: ReadBytesExtSmb,
+    {
+        let start_offset = reader.stream_position()?;
+
+        let speed_x = reader.read_f32::<B>()?;
+        let speed_y = reader.read_f32::<B>()?;
+        reader.check_read_size(start_offset, TEXTURE_SCROLL_SIZE, Self::get_name())?;
+
+        Ok(Self { speed_x, speed_y })
+    }
+}
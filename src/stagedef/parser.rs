@@ -1,14 +1,16 @@
 //! Handles parsing of an uncompressed Monkey Ball stage binary.
 use crate::stagedef::common::{
-    Game, GlobalStagedefObject, ShortVector3, StageDef, StageDefObject, StageDefParsable, Vector3,
+    Endianness, FileUsage, Game, GlobalStagedefObject, Position, ShortVector3, StageDef, StageDefObject,
+    StageDefParsable, Vector3,
 };
+use crate::stagedef::error::{ParseError, ParseResult};
 use crate::stagedef::objects::*;
-use anyhow::Result;
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
 use num_traits::FromPrimitive;
 use std::{
     fs::File,
     io::{self, BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+    ops::Range,
 };
 use tracing::{debug, event, warn, Level};
 
@@ -22,33 +24,31 @@ const fn from_start(offset: u64) -> SeekFrom {
 /// Helper function that takes a [``SeekFrom::Start``] and applies the given [``u32``] offset to it.
 ///
 /// Mostly used for convenience for header formats like collision headers.
-/// Does not work on other variants of [``SeekFrom``].
-const fn from_relative(start: SeekFrom, offset: u32) -> SeekFrom {
-    if let SeekFrom::Start(o) = start {
-        SeekFrom::Start(o + offset as u64)
-    } else {
-        panic!("Did not pass a SeekFrom::Start to from_relative");
-    }
+/// Returns [``Err``] if given another variant of [``SeekFrom``], or if the resulting offset wouldn't
+/// fit in the [``u32``] offsets stagedefs use.
+fn from_relative(start: SeekFrom, offset: u32) -> ParseResult<SeekFrom> {
+    let SeekFrom::Start(start_offset) = start else {
+        return Err(ParseError::InvalidOffset("Relative offset wasn't from the start of the file"));
+    };
+    start_offset
+        .checked_add(u64::from(offset))
+        .filter(|o| *o <= u64::from(u32::MAX))
+        .map(SeekFrom::Start)
+        .ok_or(ParseError::InvalidOffset("Relative offset is past the largest possible offset"))
 }
 
 /// Helper function that takes two [``SeekFrom::Start``] objects, and subtracts their offsets.
 ///
-/// Does not work on other variants of [``SeekFrom``].
-/// Returns [``Err``] if the resulting value would be negative.
-fn try_get_offset_difference(x: &SeekFrom, y: &SeekFrom) -> Result<u32> {
-    if let SeekFrom::Start(x_offset) = x {
-        if let SeekFrom::Start(y_offset) = y {
-            if y_offset > x_offset {
-                Err(anyhow::Error::msg("Resulting offset difference was negative"))
-            } else {
-                Ok(u32::try_from(*x_offset).unwrap() - u32::try_from(*y_offset).unwrap())
-            }
-        } else {
-            panic!("Did not pass a SeekFrom::Start to y parameter for difference");
-        }
-    } else {
-        panic!("Did not pass a SeekFrom::Start to x parameter for difference");
-    }
+/// Returns [``Err``] if given another variant of [``SeekFrom``], if the resulting value would be
+/// negative, or if it wouldn't fit in a [``u32``].
+fn try_get_offset_difference(x: &SeekFrom, y: &SeekFrom) -> ParseResult<u32> {
+    let (SeekFrom::Start(x_offset), SeekFrom::Start(y_offset)) = (x, y) else {
+        return Err(ParseError::InvalidOffset("Offset difference wasn't between offsets from the start of the file"));
+    };
+    let difference = x_offset
+        .checked_sub(*y_offset)
+        .ok_or(ParseError::InvalidOffset("Resulting offset difference was negative"))?;
+    u32::try_from(difference).map_err(|_| ParseError::InvalidOffset("Resulting offset difference was too large"))
 }
 
 /// Defines possible file offset types within a [``StageDef``].
@@ -65,15 +65,17 @@ pub enum FileOffset {
 
 /// Extends [``ReadBytesExt``] with methods for reading common [``StageDef``] types.
 pub trait ReadBytesExtSmb: ReadBytesExt + Seek {
-    fn read_vec3<U: ByteOrder>(&mut self) -> Result<Vector3>;
-    fn read_vec3_short<U: ByteOrder>(&mut self) -> Result<ShortVector3>;
-    fn read_offset<U: ByteOrder>(&mut self) -> Result<FileOffset>;
-    fn read_count_offset<U: ByteOrder>(&mut self) -> Result<FileOffset>;
-    fn read_model_name_from_offset<U: ByteOrder>(&mut self) -> Result<String>;
+    fn read_vec3<U: ByteOrder>(&mut self) -> ParseResult<Vector3>;
+    fn read_vec3_short<U: ByteOrder>(&mut self) -> ParseResult<ShortVector3>;
+    fn read_offset<U: ByteOrder>(&mut self) -> ParseResult<FileOffset>;
+    fn read_count_offset<U: ByteOrder>(&mut self) -> ParseResult<FileOffset>;
+    fn read_model_name_from_offset<U: ByteOrder>(&mut self) -> ParseResult<String>;
+    fn read_model_name_from_ptr_a<U: ByteOrder>(&mut self) -> ParseResult<String>;
+    fn check_read_size(&mut self, start_offset: u64, expected_size: u32, object_name: &str) -> ParseResult<()>;
 }
 
 impl<T: ReadBytesExt + Seek> ReadBytesExtSmb for T {
-    fn read_vec3<U: ByteOrder>(&mut self) -> Result<Vector3> {
+    fn read_vec3<U: ByteOrder>(&mut self) -> ParseResult<Vector3> {
         let x = self.read_f32::<U>()?;
         let y = self.read_f32::<U>()?;
         let z = self.read_f32::<U>()?;
@@ -81,7 +83,7 @@ impl<T: ReadBytesExt + Seek> ReadBytesExtSmb for T {
         Ok(Vector3 { x, y, z })
     }
 
-    fn read_vec3_short<U: ByteOrder>(&mut self) -> Result<ShortVector3> {
+    fn read_vec3_short<U: ByteOrder>(&mut self) -> ParseResult<ShortVector3> {
         let x = self.read_u16::<U>()?;
         let y = self.read_u16::<U>()?;
         let z = self.read_u16::<U>()?;
@@ -89,13 +91,13 @@ impl<T: ReadBytesExt + Seek> ReadBytesExtSmb for T {
         Ok(ShortVector3 { x, y, z })
     }
 
-    fn read_offset<U: ByteOrder>(&mut self) -> Result<FileOffset> {
+    fn read_offset<U: ByteOrder>(&mut self) -> ParseResult<FileOffset> {
         let offset = from_start(u64::from(self.read_u32::<U>()?));
 
         Ok(FileOffset::OffsetOnly(offset))
     }
 
-    fn read_count_offset<U: ByteOrder>(&mut self) -> Result<FileOffset> {
+    fn read_count_offset<U: ByteOrder>(&mut self) -> ParseResult<FileOffset> {
         let count = self.read_u32::<U>()?;
         let offset = self.read_u32::<U>()?;
 
@@ -106,17 +108,17 @@ impl<T: ReadBytesExt + Seek> ReadBytesExtSmb for T {
         }
     }
 
-    fn read_model_name_from_offset<U: ByteOrder>(&mut self) -> Result<String> {
+    fn read_model_name_from_offset<U: ByteOrder>(&mut self) -> ParseResult<String> {
         let name_offset = from_start(u64::from(self.read_u32::<U>()?));
         let return_position = from_start(self.stream_position()?);
 
         self.seek(name_offset)?;
         
         let mut u8_arr: Vec<char> = Vec::new();
-        let mut current_byte = 0xFF;
+        let mut current_byte = self.read_u8()?;
         while current_byte != 0x0 {
-            current_byte = self.read_u8()?;
             u8_arr.push(current_byte as char);
+            current_byte = self.read_u8()?;
         }
 
         self.seek(return_position)?;
@@ -124,6 +126,51 @@ impl<T: ReadBytesExt + Seek> ReadBytesExtSmb for T {
         let string = u8_arr.iter().collect::<String>();
         Ok(string)
     }
+
+    /// Reads an offset to a level model pointer (type A), and follows it through the level model
+    /// to get the model's name.
+    fn read_model_name_from_ptr_a<U: ByteOrder>(&mut self) -> ParseResult<String> {
+        let ptr_a_offset = self.read_u32::<U>()?;
+        let return_position = from_start(self.stream_position()?);
+
+        if ptr_a_offset == 0 {
+            return Err(ParseError::InvalidOffset("Level model pointer was null"));
+        }
+
+        // Level model offset is the last field of a level model pointer
+        self.seek(from_start(u64::from(ptr_a_offset) + 0x8))?;
+        let level_model_offset = self.read_u32::<U>()?;
+
+        if level_model_offset == 0 {
+            return Err(ParseError::InvalidOffset("Level model offset was null"));
+        }
+
+        // Model name offset is the second field of a level model
+        self.seek(from_start(u64::from(level_model_offset) + 0x4))?;
+        let model_name = self.read_model_name_from_offset::<U>()?;
+
+        self.seek(return_position)?;
+
+        Ok(model_name)
+    }
+
+    /// Returns [``Err``] if the reader did not advance exactly ``expected_size`` bytes past
+    /// ``start_offset``.
+    fn check_read_size(&mut self, start_offset: u64, expected_size: u32, object_name: &str) -> ParseResult<()> {
+        let expected_position = start_offset + u64::from(expected_size);
+        let actual_position = self.stream_position()?;
+
+        if actual_position == expected_position {
+            Ok(())
+        } else {
+            Err(ParseError::SizeMismatch {
+                object_name: object_name.to_string(),
+                start_offset,
+                expected_end: expected_position,
+                actual_end: actual_position,
+            })
+        }
+    }
 }
 
 /// Extends [``std::io::Seek``] with a method for attempting to seek to a [``FileOffset``].
@@ -200,12 +247,72 @@ const SMB2_FILE_HEADER_FORMAT: StageDefFileHeaderFormat = StageDefFileHeaderForm
     mystery_3_ptr_offset: FileOffset::OffsetOnly(from_start(0xD4)),
 };
 
+impl StageDefFileHeaderFormat {
+    /// Returns the name and the range of bytes of each field in the header that we know about, in
+    /// order of offset.
+    fn get_fields(&self) -> Vec<(&'static str, Range<usize>)> {
+        const COUNT_OFFSET_SIZE: usize = 8;
+        const WORD_SIZE: usize = 4;
+
+        let mut fields: Vec<_> = [
+            ("magic_number_1", self.magic_number_1_offset, WORD_SIZE),
+            ("magic_number_2", self.magic_number_2_offset, WORD_SIZE),
+            ("collision_header_list", self.collision_header_list_offset, COUNT_OFFSET_SIZE),
+            ("start_position_ptr", self.start_position_ptr_offset, WORD_SIZE),
+            ("fallout_position_ptr", self.fallout_position_ptr_offset, WORD_SIZE),
+            ("goal_list", self.goal_list_offset, COUNT_OFFSET_SIZE),
+            ("bumper_list", self.bumper_list_offset, COUNT_OFFSET_SIZE),
+            ("jamabar_list", self.jamabar_list_offset, COUNT_OFFSET_SIZE),
+            ("banana_list", self.banana_list_offset, COUNT_OFFSET_SIZE),
+            ("cone_col_list", self.cone_col_list_offset, COUNT_OFFSET_SIZE),
+            ("sphere_col_list", self.sphere_col_list_offset, COUNT_OFFSET_SIZE),
+            ("cyl_col_list", self.cyl_col_list_offset, COUNT_OFFSET_SIZE),
+            ("fallout_vol_list", self.fallout_vol_list_offset, COUNT_OFFSET_SIZE),
+            ("bg_model_list", self.bg_model_list_offset, COUNT_OFFSET_SIZE),
+            ("fg_model_list", self.fg_model_list_offset, COUNT_OFFSET_SIZE),
+            ("reflective_model_list", self.reflective_model_list_offset, COUNT_OFFSET_SIZE),
+            ("model_instance_list", self.model_instance_list_offset, COUNT_OFFSET_SIZE),
+            ("model_ptr_a_list", self.model_ptr_a_list_offset, COUNT_OFFSET_SIZE),
+            ("model_ptr_b_list", self.model_ptr_b_list_offset, COUNT_OFFSET_SIZE),
+            ("switch_list", self.switch_list_offset, COUNT_OFFSET_SIZE),
+            ("fog_anim_ptr", self.fog_anim_ptr_offset, WORD_SIZE),
+            ("wormhole_list", self.wormhole_list_offset, COUNT_OFFSET_SIZE),
+            ("fog_ptr", self.fog_ptr_offset, WORD_SIZE),
+            ("mystery_3_ptr", self.mystery_3_ptr_offset, WORD_SIZE),
+        ]
+        .into_iter()
+        .filter_map(|(name, offset, size)| match offset {
+            FileOffset::OffsetOnly(SeekFrom::Start(start)) => {
+                let start = start as usize;
+                Some((name, start..start + size))
+            }
+            _ => None,
+        })
+        .collect();
+
+        fields.sort_by_key(|(_, range)| range.start);
+        fields
+    }
+}
+
+/// Returns the name and range of bytes of each known field in the SMB2 file header.
+///
+/// Used to annotate the raw header of files we can't identify, so they can be compared against
+/// a format we know.
+pub fn get_smb2_file_header_fields() -> Vec<(&'static str, Range<usize>)> {
+    SMB2_FILE_HEADER_FORMAT.get_fields()
+}
+
 // TODO: SMB1 file header format
 
 /// Defines the collision header format for Monkey Ball stagedef files.
 ///
 /// This struct stores the offsets as relative offsets from the start of the collision
 /// header. We have to construct this after we know where the header begins in the file.
+///
+/// SMB1 collision headers have no conveyor vector, so everything after where it would be is
+/// 0xC earlier than in SMB2. They also end before the loop point and texture scroll. The unknown
+/// fields are named after their SMB2 offsets.
 struct StageDefCollisionHeaderFormat {
     center_of_rotation_offset: FileOffset,
     initial_rotation_offset: FileOffset,
@@ -250,74 +357,258 @@ struct StageDefCollisionHeaderFormat {
 
 impl StageDefCollisionHeaderFormat {
     #[rustfmt::skip]
-    fn new(game: Game, header_start: SeekFrom) -> Self {
-        match game {
-            SMB2 => Self {
-                center_of_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x0)),
-                initial_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC)),
-                animation_type_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x12)),
-                animation_header_ptr_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x14)),
-                conveyor_vector_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x18)),
-                collision_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x24)),
-                collision_grid_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x28)),
-                collision_grid_start_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x2C)),
-                collision_grid_start_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x30)),
-                collision_grid_step_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x34)),
-                collision_grid_step_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x38)),
-                collision_grid_step_x_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x3C)),
-                collision_grid_step_z_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x40)),
-                goal_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x44)),
-                bumper_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x4C)),
-                jamabar_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x54)),
-                banana_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x5C)),
-                cone_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x64)),
-                sphere_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x6C)),
-                cyl_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x74)),
-                fallout_vol_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x7C)),
-                reflective_model_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x84)),
-                model_instance_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x8C)),
-                model_ptr_b_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x94)),
-                unk0x9c_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x9C)),
-                unk0xa0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA0)),
-                animation_id_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA4)),
-                unk0xa6_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA6)),
-                switch_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA8)),
-                unk0xb0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB0)),
-                mystery_5_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB4)),
-                seesaw_sensitivity_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB8)),
-                seesaw_friction_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xBC)),
-                seesaw_spring_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC0)),
-                wormhole_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC4)),
-                animation_state_init_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xCC)),
-                unk0xd0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD0)),
-                animation_loop_point_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD4)),
-                texture_scroll_ptr_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD8)),
+    fn new(game: Game, header_start: SeekFrom) -> ParseResult<Self> {
+        Ok(match game {
+            Game::SMB1 => Self {
+                center_of_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x0)?),
+                initial_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC)?),
+                animation_type_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x12)?),
+                animation_header_ptr_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x14)?),
+                conveyor_vector_offset: FileOffset::Unused,
+                collision_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x18)?),
+                collision_grid_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x1C)?),
+                collision_grid_start_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x20)?),
+                collision_grid_start_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x24)?),
+                collision_grid_step_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x28)?),
+                collision_grid_step_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x2C)?),
+                collision_grid_step_x_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x30)?),
+                collision_grid_step_z_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x34)?),
+                goal_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x38)?),
+                bumper_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x40)?),
+                jamabar_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x48)?),
+                banana_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x50)?),
+                cone_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x58)?),
+                sphere_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x60)?),
+                cyl_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x68)?),
+                fallout_vol_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x70)?),
+                reflective_model_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x78)?),
+                model_instance_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x80)?),
+                model_ptr_b_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x88)?),
+                unk0x9c_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x90)?),
+                unk0xa0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x94)?),
+                animation_id_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x98)?),
+                unk0xa6_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x9A)?),
+                switch_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x9C)?),
+                unk0xb0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA4)?),
+                mystery_5_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA8)?),
+                seesaw_sensitivity_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xAC)?),
+                seesaw_friction_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB0)?),
+                seesaw_spring_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB4)?),
+                wormhole_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB8)?),
+                animation_state_init_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC0)?),
+                unk0xd0_offset: FileOffset::Unused,
+                animation_loop_point_offset: FileOffset::Unused,
+                texture_scroll_ptr_offset: FileOffset::Unused,
             },
+            Game::SMB2 | Game::SMBDX => Self {
+                center_of_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x0)?),
+                initial_rotation_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC)?),
+                animation_type_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x12)?),
+                animation_header_ptr_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x14)?),
+                conveyor_vector_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x18)?),
+                collision_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x24)?),
+                collision_grid_triangle_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x28)?),
+                collision_grid_start_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x2C)?),
+                collision_grid_start_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x30)?),
+                collision_grid_step_x_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x34)?),
+                collision_grid_step_z_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x38)?),
+                collision_grid_step_x_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x3C)?),
+                collision_grid_step_z_count_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x40)?),
+                goal_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x44)?),
+                bumper_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x4C)?),
+                jamabar_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x54)?),
+                banana_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x5C)?),
+                cone_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x64)?),
+                sphere_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x6C)?),
+                cyl_col_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x74)?),
+                fallout_vol_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x7C)?),
+                reflective_model_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x84)?),
+                model_instance_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x8C)?),
+                model_ptr_b_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x94)?),
+                unk0x9c_offset: FileOffset::OffsetOnly(from_relative(header_start, 0x9C)?),
+                unk0xa0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA0)?),
+                animation_id_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA4)?),
+                unk0xa6_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA6)?),
+                switch_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xA8)?),
+                unk0xb0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB0)?),
+                mystery_5_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB4)?),
+                seesaw_sensitivity_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xB8)?),
+                seesaw_friction_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xBC)?),
+                seesaw_spring_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC0)?),
+                wormhole_list_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xC4)?),
+                animation_state_init_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xCC)?),
+                unk0xd0_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD0)?),
+                animation_loop_point_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD4)?),
+                texture_scroll_ptr_offset: FileOffset::OffsetOnly(from_relative(header_start, 0xD8)?),
+            },
+        })
+    }
+}
+
+/// Wraps a reader, keeping track of the furthest byte that has been read from it.
+///
+/// This lets us find any data past the end of the structures we know about, such as the footer
+/// some Deluxe PC stagedefs have.
+struct TrackingReader<R: Read + Seek> {
+    inner: R,
+    position: u64,
+    furthest_referenced_end: u64,
+}
+
+impl<R: Read + Seek> TrackingReader<R> {
+    fn new(mut inner: R) -> Self {
+        let position = inner.stream_position().unwrap_or(0);
+        Self {
+            inner,
+            position,
+            furthest_referenced_end: 0,
         }
     }
+
+    /// Marks everything up to ``end`` as belonging to a known structure, even if not all of it
+    /// was read.
+    fn mark_referenced_end(&mut self, end: u64) {
+        self.furthest_referenced_end = self.furthest_referenced_end.max(end);
+    }
+}
+
+impl<R: Read + Seek> Read for TrackingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.position += bytes_read as u64;
+        self.mark_referenced_end(self.position);
+        Ok(bytes_read)
+    }
+}
+
+impl<R: Read + Seek> Seek for TrackingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+/// The size of the file header every SMB1 stagedef starts with.
+const SMB1_FILE_HEADER_SIZE: u64 = 0xA0;
+/// The size of the file header every SMB2 and SMBDX stagedef starts with.
+const SMB2_FILE_HEADER_SIZE: u64 = 0x89C;
+
+/// Returns an error if the file is too small to hold a file header for the given game, so files
+/// that obviously aren't stagedefs are rejected before we start following offsets in them.
+pub fn check_minimum_size<R: Seek>(reader: &mut R, game: Game) -> ParseResult<()> {
+    let minimum_size = match game {
+        Game::SMB1 => SMB1_FILE_HEADER_SIZE,
+        Game::SMB2 | Game::SMBDX => SMB2_FILE_HEADER_SIZE,
+    };
+
+    let position = reader.stream_position()?;
+    let size = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+
+    if size < minimum_size {
+        return Err(ParseError::FileTooSmall { size, minimum_size });
+    }
+    Ok(())
+}
+
+/// Guesses the endianness of a stagedef from its magic numbers, which read as 0.0 and 1,000.0 in
+/// the right endianness.
+///
+/// Returns ``None`` if the magic numbers don't match in either endianness.
+pub fn detect_endianness<R: Read + Seek>(reader: &mut R, game: Game) -> ParseResult<Option<Endianness>> {
+    let format = match game {
+        //TODO: Implement SMB1 support
+        Game::SMB1 => return Err(ParseError::UnsupportedGame("SMB1")),
+        Game::SMB2 | Game::SMBDX => SMB2_FILE_HEADER_FORMAT,
+    };
+
+    let mut read_magic_numbers = |big_endian: bool| -> ParseResult<(f32, f32)> {
+        let read_f32 = |reader: &mut R| {
+            if big_endian {
+                reader.read_f32::<BigEndian>()
+            } else {
+                reader.read_f32::<LittleEndian>()
+            }
+        };
+        reader.try_seek(format.magic_number_1_offset)?;
+        let magic_number_1 = read_f32(reader)?;
+        reader.try_seek(format.magic_number_2_offset)?;
+        let magic_number_2 = read_f32(reader)?;
+        Ok((magic_number_1, magic_number_2))
+    };
+
+    let (be_1, be_2) = read_magic_numbers(true)?;
+    if StageDef::are_magic_numbers_expected(be_1, be_2) {
+        return Ok(Some(Endianness::BigEndian));
+    }
+
+    let (le_1, le_2) = read_magic_numbers(false)?;
+    if StageDef::are_magic_numbers_expected(le_1, le_2) {
+        return Ok(Some(Endianness::LittleEndian));
+    }
+
+    Ok(None)
+}
+
+/// Returns the list that was read, or ``None`` if there wasn't one to read or it couldn't be.
+///
+/// Stagedefs leave out the lists they don't use, so a list not being read isn't a problem, unless
+/// it's because of a warning in strict mode.
+fn skip_missing_list<T>(result: ParseResult<T>) -> ParseResult<Option<T>> {
+    match result {
+        Ok(list) => Ok(Some(list)),
+        Err(err @ ParseError::Strict(_)) => Err(err),
+        Err(_) => Ok(None),
+    }
 }
 
 /// Handles reading a stagedef with a given reader, game type, and format.
-// TODO: SMB1 collision header format
 pub struct StageDefReader<R: Read + Seek> {
-    reader: R,
+    reader: TrackingReader<R>,
     game: Game,
     file_header: StageDefFileHeaderFormat,
+    strict: bool,
 }
 
 impl<R: Read + Seek> StageDefReader<R> {
     pub fn new(reader: R, game: Game) -> Self {
         Self {
-            reader,
+            reader: TrackingReader::new(reader),
             game,
             file_header: StageDefFileHeaderFormat::default(),
+            strict: false,
+        }
+    }
+
+    /// Sets whether problems that are normally only logged as warnings, like objects that couldn't
+    /// be read, unknown enum values and collision header lists that aren't in the global lists,
+    /// should stop parsing with an error instead.
+    ///
+    /// This is off by default, so as much of a broken file as possible can still be viewed. It's
+    /// meant for automated checks of stages, where any problem should fail.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Logs a problem that parsing can carry on past, along with what was being read. In strict
+    /// mode, returns it as an error to stop parsing with instead.
+    fn warn_or_fail(&self, warning: ParseError, context: &str) -> ParseResult<()> {
+        match warning {
+            ParseError::Strict(_) => Err(warning),
+            _ if self.strict => Err(ParseError::Strict(Box::new(warning))),
+            _ => {
+                warn!("{context}: {warning}");
+                Ok(())
+            }
         }
     }
 
     // Read in a new StageDef from our reader.
-    pub fn read_stagedef<B: ByteOrder>(&mut self) -> Result<StageDef> {
+    pub fn read_stagedef<B: ByteOrder>(&mut self) -> ParseResult<StageDef> {
         let mut stagedef = StageDef::default();
 
+        check_minimum_size(&mut self.reader, self.game)?;
         self.file_header = self.read_file_header_offsets::<B>()?;
 
         // Read magic numbers
@@ -332,85 +623,165 @@ impl<R: Read + Seek> StageDefReader<R> {
         // Read start position and fallout level
         // TODO: Support multiple start positions
         if self.reader.try_seek(self.file_header.start_position_ptr_offset).is_ok() {
-            stagedef.start_position = self.reader.read_vec3::<B>()?;
+            stagedef.start_position = self.read_start_position::<B>()?;
         }
 
         if self.reader.try_seek(self.file_header.fallout_position_ptr_offset).is_ok() {
             stagedef.fallout_level = self.reader.read_f32::<B>()?;
         }
 
+        // Read fog and mystery 3, which not every stage has
+        stagedef.fog = self.read_stagedef_object::<B, Fog>(self.file_header.fog_ptr_offset)?;
+        stagedef.mystery_3 = self.read_stagedef_object::<B, Mystery3>(self.file_header.mystery_3_ptr_offset)?;
+
         // TODO:: Fill this out...
 
         // Read goal list
-        if let Ok(goals) = self.read_stagedef_list::<B, Goal>(self.file_header.goal_list_offset) {
+        if let Some(goals) = skip_missing_list(self.read_stagedef_list::<B, Goal>(self.file_header.goal_list_offset))? {
             stagedef.goals = goals;
         }
 
         // Read bumper list
-        if let Ok(bumpers) = self.read_stagedef_list::<B, Bumper>(self.file_header.bumper_list_offset) {
+        if let Some(bumpers) =
+            skip_missing_list(self.read_stagedef_list::<B, Bumper>(self.file_header.bumper_list_offset))?
+        {
             stagedef.bumpers = bumpers;
         }
 
         // Read jamabar list
-        if let Ok(jamabars) = self.read_stagedef_list::<B, Jamabar>(self.file_header.jamabar_list_offset) {
+        if let Some(jamabars) =
+            skip_missing_list(self.read_stagedef_list::<B, Jamabar>(self.file_header.jamabar_list_offset))?
+        {
             stagedef.jamabars = jamabars;
         }
 
         // Read banana list
-        if let Ok(bananas) = self.read_stagedef_list::<B, Banana>(self.file_header.banana_list_offset) {
+        if let Some(bananas) =
+            skip_missing_list(self.read_stagedef_list::<B, Banana>(self.file_header.banana_list_offset))?
+        {
             stagedef.bananas = bananas;
         }
 
         // Read cone_col list
-        if let Ok(cone_cols) = self.read_stagedef_list::<B, ConeCollision>(self.file_header.cone_col_list_offset) {
+        if let Some(cone_cols) =
+            skip_missing_list(self.read_stagedef_list::<B, ConeCollision>(self.file_header.cone_col_list_offset))?
+        {
             stagedef.cone_collisions = cone_cols;
         }
 
         // Read sphere_col list
-        if let Ok(sphere_cols) = self.read_stagedef_list::<B, SphereCollision>(self.file_header.sphere_col_list_offset) {
+        if let Some(sphere_cols) =
+            skip_missing_list(self.read_stagedef_list::<B, SphereCollision>(self.file_header.sphere_col_list_offset))?
+        {
             stagedef.sphere_collisions = sphere_cols;
         }
 
         // Read cyl_col list
-        if let Ok(cyl_cols) = self.read_stagedef_list::<B, CylinderCollision>(self.file_header.cyl_col_list_offset) {
+        if let Some(cyl_cols) =
+            skip_missing_list(self.read_stagedef_list::<B, CylinderCollision>(self.file_header.cyl_col_list_offset))?
+        {
             stagedef.cylinder_collisions = cyl_cols;
         }
 
         // Read fallout_vol list
-        if let Ok(fallout_vols) = self.read_stagedef_list::<B, FalloutVolume>(self.file_header.fallout_vol_list_offset) {
+        if let Some(fallout_vols) =
+            skip_missing_list(self.read_stagedef_list::<B, FalloutVolume>(self.file_header.fallout_vol_list_offset))?
+        {
             stagedef.fallout_volumes = fallout_vols;
         }
 
         // Read background_model list
-        if let Ok(background_models) = self.read_stagedef_list::<B, BackgroundModel>(self.file_header.bg_model_list_offset) {
+        if let Some(background_models) =
+            skip_missing_list(self.read_stagedef_list::<B, BackgroundModel>(self.file_header.bg_model_list_offset))?
+        {
             stagedef.background_models = background_models;
         }
 
+        // Read level model instance list
+        if let Some(level_model_instances) =
+            skip_missing_list(self.read_stagedef_list::<B, LevelModelInstance>(self.file_header.model_instance_list_offset))?
+        {
+            stagedef.level_model_instances = level_model_instances;
+        }
+
+        // Read reflective model list
+        if let Some(reflective_models) =
+            skip_missing_list(self.read_stagedef_list::<B, ReflectiveModel>(self.file_header.reflective_model_list_offset))?
+        {
+            stagedef.reflective_models = reflective_models;
+        }
+
         // Read all collision headers - done last so we can properly set up references to other global
         // stagedef objects
-        // TODO: Change based on game
         if let FileOffset::CountOffset(c, o) = self.file_header.collision_header_list_offset {
+            let header_size = CollisionHeader::get_size_for_game(self.game);
             for i in 0..c {
-                let current_offset = from_relative(o, CollisionHeader::get_size() * i);
-                self.reader.seek(current_offset)?;
+                let relative_offset = header_size
+                    .checked_mul(i)
+                    .ok_or(ParseError::InvalidOffset("Collision header list is too long"))?;
+                let current_offset = from_relative(o, relative_offset)?;
+                let header_start = self.reader.seek(current_offset)?;
+                self.reader.mark_referenced_end(header_start + u64::from(header_size));
 
                 stagedef
                     .collision_headers
                     .push(self.read_collision_header::<B>(&stagedef, current_offset)?);
             }
         }
+
+        // Reading the trailing bytes counts them as referenced, so check how much is used first
+        stagedef.file_usage = FileUsage {
+            referenced_end: self.reader.furthest_referenced_end,
+            file_size: self.reader.seek(SeekFrom::End(0))?,
+        };
+        stagedef.trailing_bytes = self.read_trailing_bytes()?;
+        if !stagedef.trailing_bytes.is_empty() {
+            debug!("Found {} trailing bytes after the stagedef", stagedef.trailing_bytes.len());
+        }
+
         Ok(stagedef)
     }
 
+    /// Reads every byte past the end of the furthest structure we've read, so files with a footer
+    /// can be written back out unchanged.
+    ///
+    /// This should be called after everything else has been read.
+    fn read_trailing_bytes(&mut self) -> ParseResult<Vec<u8>> {
+        let footer_start = self.reader.furthest_referenced_end;
+        let file_end = self.reader.seek(SeekFrom::End(0))?;
+
+        let mut trailing_bytes = Vec::new();
+        if file_end > footer_start {
+            self.reader.seek(from_start(footer_start))?;
+            self.reader.read_to_end(&mut trailing_bytes)?;
+        }
+
+        Ok(trailing_bytes)
+    }
+
+    /// Reads a start position at the current position, in the layout used by our reader's game.
+    fn read_start_position<B: ByteOrder>(&mut self) -> ParseResult<StartPosition> {
+        StartPosition::try_from_reader_for_game::<TrackingReader<R>, B>(&mut self.reader, self.game)
+    }
+
     // Determine the default format based on our reader's Game attribute, then use the default format
     // to parse the stagedef's offsets.
-    fn read_file_header_offsets<B: ByteOrder>(&mut self) -> Result<StageDefFileHeaderFormat> {
+    //
+    // Every offset is in the first few hundred bytes of the file, so those are read all at once and
+    // parsed from memory, rather than seeking the reader back and forth for each one.
+    fn read_file_header_offsets<B: ByteOrder>(&mut self) -> ParseResult<StageDefFileHeaderFormat> {
         let default_format = match self.game {
             //TODO: Implement SMB1 support
-            Game::SMB1 => unimplemented!(),
+            Game::SMB1 => return Err(ParseError::UnsupportedGame("SMB1")),
             Game::SMB2 | Game::SMBDX => SMB2_FILE_HEADER_FORMAT,
         };
 
+        let header_size = default_format.get_fields().last().map_or(0, |(_, range)| range.end);
+        let mut header_buffer = vec![0; header_size];
+        self.reader.seek(SeekFrom::Start(0))?;
+        self.reader.read_exact(&mut header_buffer)?;
+        let mut header = Cursor::new(header_buffer.as_slice());
+
         let mut current_format = StageDefFileHeaderFormat::default();
 
         // Read magic number offsets
@@ -418,225 +789,526 @@ impl<R: Read + Seek> StageDefReader<R> {
         current_format.magic_number_2_offset = default_format.magic_number_2_offset;
 
         // Read collision header count/offset
-        if self.reader.try_seek(default_format.collision_header_list_offset).is_ok() {
-            current_format.collision_header_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.collision_header_list_offset).is_ok() {
+            current_format.collision_header_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read start position offset
-        if self.reader.try_seek(default_format.start_position_ptr_offset).is_ok() {
-            current_format.start_position_ptr_offset = self.reader.read_offset::<B>()?;
+        if header.try_seek(default_format.start_position_ptr_offset).is_ok() {
+            current_format.start_position_ptr_offset = header.read_offset::<B>()?;
         }
 
         // Read fallout level offset
-        if self.reader.try_seek(default_format.fallout_position_ptr_offset).is_ok() {
-            current_format.fallout_position_ptr_offset = self.reader.read_offset::<B>()?;
+        if header.try_seek(default_format.fallout_position_ptr_offset).is_ok() {
+            current_format.fallout_position_ptr_offset = header.read_offset::<B>()?;
         }
 
         // Read goal count/offset
-        if self.reader.try_seek(default_format.goal_list_offset).is_ok() {
-            current_format.goal_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.goal_list_offset).is_ok() {
+            current_format.goal_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read bumper count/offset
-        if self.reader.try_seek(default_format.bumper_list_offset).is_ok() {
-            current_format.bumper_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.bumper_list_offset).is_ok() {
+            current_format.bumper_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read jamabar count/offset
-        if self.reader.try_seek(default_format.jamabar_list_offset).is_ok() {
-            current_format.jamabar_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.jamabar_list_offset).is_ok() {
+            current_format.jamabar_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read banana count/offset
-        if self.reader.try_seek(default_format.banana_list_offset).is_ok() {
-            current_format.banana_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.banana_list_offset).is_ok() {
+            current_format.banana_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read cone_col count/offset
-        if self.reader.try_seek(default_format.cone_col_list_offset).is_ok() {
-            current_format.cone_col_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.cone_col_list_offset).is_ok() {
+            current_format.cone_col_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read cyl_col count/offset
-        if self.reader.try_seek(default_format.cyl_col_list_offset).is_ok() {
-            current_format.cyl_col_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.cyl_col_list_offset).is_ok() {
+            current_format.cyl_col_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read fallout_vol count/offset
-        if self.reader.try_seek(default_format.fallout_vol_list_offset).is_ok() {
-            current_format.fallout_vol_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.fallout_vol_list_offset).is_ok() {
+            current_format.fallout_vol_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read bg_model count/offset
-        if self.reader.try_seek(default_format.bg_model_list_offset).is_ok() {
-            current_format.bg_model_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.bg_model_list_offset).is_ok() {
+            current_format.bg_model_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read fg_model count/offset
-        if self.reader.try_seek(default_format.fg_model_list_offset).is_ok() {
-            current_format.fg_model_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.fg_model_list_offset).is_ok() {
+            current_format.fg_model_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read reflective_model count/offset
-        if self.reader.try_seek(default_format.reflective_model_list_offset).is_ok() {
-            current_format.reflective_model_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.reflective_model_list_offset).is_ok() {
+            current_format.reflective_model_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read model_instance_list count/offset
-        if self.reader.try_seek(default_format.model_instance_list_offset).is_ok() {
-            current_format.model_instance_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.model_instance_list_offset).is_ok() {
+            current_format.model_instance_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read model_ptr_a count/offset
-        if self.reader.try_seek(default_format.model_ptr_a_list_offset).is_ok() {
-            current_format.model_ptr_a_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.model_ptr_a_list_offset).is_ok() {
+            current_format.model_ptr_a_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read model_ptr_b count/offset
-        if self.reader.try_seek(default_format.model_ptr_b_list_offset).is_ok() {
-            current_format.model_ptr_b_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.model_ptr_b_list_offset).is_ok() {
+            current_format.model_ptr_b_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read switch count/offset
-        if self.reader.try_seek(default_format.switch_list_offset).is_ok() {
-            current_format.switch_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.switch_list_offset).is_ok() {
+            current_format.switch_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read fog_anim_ptr offset
-        if self.reader.try_seek(default_format.fog_anim_ptr_offset).is_ok() {
-            current_format.fog_anim_ptr_offset = self.reader.read_offset::<B>()?;
+        if header.try_seek(default_format.fog_anim_ptr_offset).is_ok() {
+            current_format.fog_anim_ptr_offset = header.read_offset::<B>()?;
         }
 
         // Read wormhole count/offset
-        if self.reader.try_seek(default_format.wormhole_list_offset).is_ok() {
-            current_format.wormhole_list_offset = self.reader.read_count_offset::<B>()?;
+        if header.try_seek(default_format.wormhole_list_offset).is_ok() {
+            current_format.wormhole_list_offset = header.read_count_offset::<B>()?;
         }
 
         // Read fog_ptr offset
-        if self.reader.try_seek(default_format.fog_ptr_offset).is_ok() {
-            current_format.fog_ptr_offset = self.reader.read_offset::<B>()?;
+        if header.try_seek(default_format.fog_ptr_offset).is_ok() {
+            current_format.fog_ptr_offset = header.read_offset::<B>()?;
         }
 
         // Read mystery_3_ptr offset
-        if self.reader.try_seek(default_format.mystery_3_ptr_offset).is_ok() {
-            current_format.mystery_3_ptr_offset = self.reader.read_offset::<B>()?;
+        if header.try_seek(default_format.mystery_3_ptr_offset).is_ok() {
+            current_format.mystery_3_ptr_offset = header.read_offset::<B>()?;
         }
 
         Ok(current_format)
     }
 
-    // TODO: SMB1 format
-    // Reads a collision header from the specified offset. Does not advance the reader by the max
-    // size of a collision header, 0x49C.
-    fn read_collision_header<B: ByteOrder>(&mut self, stagedef: &StageDef, offset: SeekFrom) -> Result<CollisionHeader> {
-        let current_format = StageDefCollisionHeaderFormat::new(self.game, offset);
+    // Reads a collision header from the specified offset. Does not advance the reader by the size
+    // of a collision header, which depends on the game.
+    fn read_collision_header<B: ByteOrder>(
+        &mut self,
+        stagedef: &StageDef,
+        offset: SeekFrom,
+    ) -> ParseResult<CollisionHeader> {
+        let current_format = StageDefCollisionHeaderFormat::new(self.game, offset)?;
         let mut collision_header = CollisionHeader::default();
 
         // Read center of rotation position
         if self.reader.try_seek(current_format.center_of_rotation_offset).is_ok() {
-            collision_header.center_of_rotation_position = self.reader.read_vec3::<B>()?;
+            collision_header.center_of_rotation_position = self.reader.read_vec3::<B>()?.into();
+        }
+
+        // Read initial rotation, which is applied around the center of rotation
+        if self.reader.try_seek(current_format.initial_rotation_offset).is_ok() {
+            collision_header.initial_rotation = self.reader.read_vec3_short::<B>()?;
+        }
+
+        // Read conveyor vector
+        if self.reader.try_seek(current_format.conveyor_vector_offset).is_ok() {
+            collision_header.conveyor_vector = self.reader.read_vec3::<B>()?;
+        }
+
+        // Read animation type, which decides whether this header is a seesaw or keyframe-animated
+        if self.reader.try_seek(current_format.animation_type_offset).is_ok() {
+            let animation_type_value = self.reader.read_u16::<B>()?;
+            match FromPrimitive::from_u16(animation_type_value) {
+                Some(animation_type) => collision_header.animation_type = animation_type,
+                None => self.warn_or_fail(
+                    ParseError::UnknownEnumValue {
+                        type_name: "animation type",
+                        value: u32::from(animation_type_value),
+                    },
+                    "Failed to read collision header animation type",
+                )?,
+            }
+        }
+
+        match self.read_collision_header_animation::<B>(&current_format, collision_header.animation_type) {
+            Ok(animation) => collision_header.animation = animation,
+            Err(err) => self.warn_or_fail(err, "Failed to read collision header animation")?,
+        }
+
+        if self.reader.try_seek(current_format.animation_id_offset).is_ok() {
+            collision_header.animation_id = self.reader.read_u16::<B>()?;
+        }
+        if self.reader.try_seek(current_format.animation_state_init_offset).is_ok() {
+            let animation_state_value = self.reader.read_u16::<B>()?;
+            match FromPrimitive::from_u16(animation_state_value) {
+                Some(animation_state) => collision_header.animation_state_init = animation_state,
+                None => self.warn_or_fail(
+                    ParseError::UnknownEnumValue {
+                        type_name: "animation state",
+                        value: u32::from(animation_state_value),
+                    },
+                    "Failed to read collision header initial animation state",
+                )?,
+            }
+        }
+        // The loop point and texture scroll are at the very end of the header, so a truncated header
+        // is warned about rather than failing the whole stage
+        if self.reader.try_seek(current_format.animation_loop_point_offset).is_ok() {
+            match self.reader.read_f32::<B>() {
+                Ok(animation_loop_point) => collision_header.animation_loop_point = animation_loop_point,
+                Err(err) => self.warn_or_fail(err.into(), "Failed to read collision header animation loop point")?,
+            }
+        }
+
+        // Read texture scroll. A null pointer means the header doesn't have one
+        if self.reader.try_seek(current_format.texture_scroll_ptr_offset).is_ok() {
+            let texture_scroll = self
+                .reader
+                .read_offset::<B>()
+                .and_then(|offset| self.read_stagedef_object::<B, TextureScroll>(offset));
+            match texture_scroll {
+                Ok(texture_scroll) => collision_header.texture_scroll = texture_scroll,
+                Err(err) => self.warn_or_fail(err, "Failed to read collision header texture scroll")?,
+            }
+        }
+
+        // Read collision grid
+        if self.reader.try_seek(current_format.collision_grid_start_x_offset).is_ok() {
+            collision_header.collision_grid_start_x = self.reader.read_f32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.collision_grid_start_z_offset).is_ok() {
+            collision_header.collision_grid_start_z = self.reader.read_f32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.collision_grid_step_x_offset).is_ok() {
+            collision_header.collision_grid_step_size_x = self.reader.read_f32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.collision_grid_step_z_offset).is_ok() {
+            collision_header.collision_grid_step_size_z = self.reader.read_f32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.collision_grid_step_x_count_offset).is_ok() {
+            collision_header.collision_grid_step_count_x = self.reader.read_u32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.collision_grid_step_z_count_offset).is_ok() {
+            collision_header.collision_grid_step_count_z = self.reader.read_u32::<B>()?;
+        }
+
+        // Read unknown fields
+        if self.reader.try_seek(current_format.unk0x9c_offset).is_ok() {
+            collision_header.unknowns.unk0x9c = self.reader.read_u32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.unk0xa0_offset).is_ok() {
+            collision_header.unknowns.unk0xa0 = self.reader.read_u32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.unk0xa6_offset).is_ok() {
+            collision_header.unknowns.unk0xa6 = self.reader.read_u16::<B>()?;
+        }
+        if self.reader.try_seek(current_format.unk0xb0_offset).is_ok() {
+            collision_header.unknowns.unk0xb0 = self.reader.read_u32::<B>()?;
+        }
+        if self.reader.try_seek(current_format.unk0xd0_offset).is_ok() {
+            collision_header.unknowns.unk0xd0 = self.reader.read_u32::<B>()?;
+        }
+
+        // Read collision triangles, through the collision grid
+        match self.read_collision_grid_cells::<B>(&current_format, &collision_header) {
+            Ok(cells) => collision_header.collision_grid_cells = cells,
+            Err(err) => self.warn_or_fail(err, "Failed to read collision grid")?,
+        }
+        match self.read_collision_triangles::<B>(&current_format, &collision_header.collision_grid_cells) {
+            Ok(triangles) => collision_header.collision_triangles = triangles,
+            Err(err) => self.warn_or_fail(err, "Failed to read collision triangles")?,
         }
 
         // TODO: Fill out the rest of the collision header structs
         // Read goals
-        if let Ok(goals) = self.read_local_object_list::<B, Goal>(
+        if let Some(goals) = skip_missing_list(self.read_local_object_list::<B, Goal>(
             current_format.goal_list_offset,
             self.file_header.goal_list_offset,
             &stagedef.goals,
-        ) {
+        ))? {
             collision_header.goals = goals;
         }
 
         // Read bumpers
-        if let Ok(bumpers) = self.read_local_object_list::<B, Bumper>(
+        if let Some(bumpers) = skip_missing_list(self.read_local_object_list::<B, Bumper>(
             current_format.bumper_list_offset,
             self.file_header.bumper_list_offset,
             &stagedef.bumpers,
-        ) {
+        ))? {
             collision_header.bumpers = bumpers;
         }
 
         // Read jamabars
-        if let Ok(jamabars) = self.read_local_object_list::<B, Jamabar>(
+        if let Some(jamabars) = skip_missing_list(self.read_local_object_list::<B, Jamabar>(
             current_format.jamabar_list_offset,
             self.file_header.jamabar_list_offset,
             &stagedef.jamabars,
-        ) {
+        ))? {
             collision_header.jamabars = jamabars;
         }
 
         // Read bananas
-        if let Ok(bananas) = self.read_local_object_list::<B, Banana>(
+        if let Some(bananas) = skip_missing_list(self.read_local_object_list::<B, Banana>(
             current_format.banana_list_offset,
             self.file_header.banana_list_offset,
             &stagedef.bananas,
-        ) {
+        ))? {
             collision_header.bananas = bananas;
         }
 
         // Read cone_collisions
-        if let Ok(cone_collisions) = self.read_local_object_list::<B, ConeCollision>(
+        if let Some(cone_collisions) = skip_missing_list(self.read_local_object_list::<B, ConeCollision>(
             current_format.cone_col_list_offset,
             self.file_header.cone_col_list_offset,
             &stagedef.cone_collisions,
-        ) {
+        ))? {
             collision_header.cone_collisions = cone_collisions;
         }
 
         // Read sphere_collisions
-        if let Ok(sphere_collisions) = self.read_local_object_list::<B, SphereCollision>(
+        if let Some(sphere_collisions) = skip_missing_list(self.read_local_object_list::<B, SphereCollision>(
             current_format.sphere_col_list_offset,
             self.file_header.sphere_col_list_offset,
             &stagedef.sphere_collisions,
-        ) {
+        ))? {
             collision_header.sphere_collisions = sphere_collisions;
         }
 
         // Read cylinder_collisions
-        if let Ok(cylinder_collisions) = self.read_local_object_list::<B, CylinderCollision>(
+        if let Some(cylinder_collisions) = skip_missing_list(self.read_local_object_list::<B, CylinderCollision>(
             current_format.cyl_col_list_offset,
             self.file_header.cyl_col_list_offset,
             &stagedef.cylinder_collisions,
-        ) {
+        ))? {
             collision_header.cylinder_collisions = cylinder_collisions;
         }
 
         // Read fallout_volumes
-        if let Ok(fallout_volumes) = self.read_local_object_list::<B, FalloutVolume>(
+        if let Some(fallout_volumes) = skip_missing_list(self.read_local_object_list::<B, FalloutVolume>(
             current_format.fallout_vol_list_offset,
             self.file_header.fallout_vol_list_offset,
             &stagedef.fallout_volumes,
-        ) {
+        ))? {
             collision_header.fallout_volumes = fallout_volumes;
         }
 
         // Read background_model list
-        if let Ok(background_models) = self.read_stagedef_list::<B, BackgroundModel>(self.file_header.bg_model_list_offset) {
+        if let Some(background_models) =
+            skip_missing_list(self.read_stagedef_list::<B, BackgroundModel>(self.file_header.bg_model_list_offset))?
+        {
             collision_header.background_models = background_models;
         }
 
+        // Read level model instances
+        if let Some(level_model_instances) = skip_missing_list(self.read_local_object_list::<B, LevelModelInstance>(
+            current_format.model_instance_list_offset,
+            self.file_header.model_instance_list_offset,
+            &stagedef.level_model_instances,
+        ))? {
+            collision_header.level_model_instances = level_model_instances;
+        }
+
+        // Read reflective models
+        if let Some(reflective_models) = skip_missing_list(self.read_local_object_list::<B, ReflectiveModel>(
+            current_format.reflective_model_list_offset,
+            self.file_header.reflective_model_list_offset,
+            &stagedef.reflective_models,
+        ))? {
+            collision_header.reflective_models = reflective_models;
+        }
+
+        // Read level model names through the level model pointer B list
+        if self.reader.try_seek(current_format.model_ptr_b_list_offset).is_ok() {
+            collision_header.level_model_names = self.read_level_model_ptr_b_list::<B>()?;
+        }
+
         Ok(collision_header)
     }
 
+    /// Reads whichever of the seesaw parameters or the animation header the given animation type
+    /// uses.
+    fn read_collision_header_animation<B: ByteOrder>(
+        &mut self,
+        format: &StageDefCollisionHeaderFormat,
+        animation_type: AnimationType,
+    ) -> ParseResult<CollisionHeaderAnimation> {
+        match animation_type {
+            AnimationType::Seesaw => {
+                let mut seesaw = SeesawParameters::default();
+                if self.reader.try_seek(format.seesaw_sensitivity_offset).is_ok() {
+                    seesaw.sensitivity = self.reader.read_f32::<B>()?;
+                }
+                if self.reader.try_seek(format.seesaw_friction_offset).is_ok() {
+                    seesaw.friction = self.reader.read_f32::<B>()?;
+                }
+                if self.reader.try_seek(format.seesaw_spring_offset).is_ok() {
+                    seesaw.spring = self.reader.read_f32::<B>()?;
+                }
+                Ok(CollisionHeaderAnimation::Seesaw(seesaw))
+            }
+            AnimationType::LoopingAnimation | AnimationType::PlayOnceAnimation => {
+                self.reader.try_seek(format.animation_header_ptr_offset)?;
+                match self.reader.read_offset::<B>()? {
+                    FileOffset::OffsetOnly(SeekFrom::Start(0)) => Ok(CollisionHeaderAnimation::None),
+                    animation_header_offset => {
+                        // Count/offset pairs for rotation X/Y/Z, then position X/Y/Z
+                        self.reader.try_seek(animation_header_offset)?;
+                        let mut channels = [FileOffset::Unused; 6];
+                        for channel in &mut channels {
+                            *channel = self.reader.read_count_offset::<B>()?;
+                        }
+
+                        Ok(CollisionHeaderAnimation::Keyframed(AnimationHeader {
+                            rotation_x: self.read_keyframes::<B>(channels[0])?,
+                            rotation_y: self.read_keyframes::<B>(channels[1])?,
+                            rotation_z: self.read_keyframes::<B>(channels[2])?,
+                            position_x: self.read_keyframes::<B>(channels[3])?,
+                            position_y: self.read_keyframes::<B>(channels[4])?,
+                            position_z: self.read_keyframes::<B>(channels[5])?,
+                        }))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads a list of keyframes for a single animation channel. Unused channels have no keyframes.
+    fn read_keyframes<B: ByteOrder>(&mut self, offset: FileOffset) -> ParseResult<Vec<Keyframe>> {
+        if let FileOffset::CountOffset(c, o) = offset {
+            self.reader.seek(o)?;
+            (0..c)
+                .map(|_| Keyframe::try_from_reader::<TrackingReader<R>, B>(&mut self.reader))
+                .collect()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Reads the triangle indices in each cell of a collision header's collision grid.
+    ///
+    /// The grid is a list of pointers, one per cell, to a list of indices into the collision
+    /// triangle list terminated by 0xFFFF. Empty cells have a null pointer.
+    fn read_collision_grid_cells<B: ByteOrder>(
+        &mut self,
+        format: &StageDefCollisionHeaderFormat,
+        collision_header: &CollisionHeader,
+    ) -> ParseResult<Vec<Vec<u16>>> {
+        self.reader.try_seek(format.collision_grid_triangle_list_offset)?;
+        let grid_list_offset = self.reader.read_u32::<B>()?;
+
+        if grid_list_offset == 0 {
+            return Ok(Vec::new());
+        }
+
+        let cell_count = collision_header
+            .collision_grid_step_count_x
+            .checked_mul(collision_header.collision_grid_step_count_z)
+            .ok_or(ParseError::InvalidOffset("Collision grid has too many cells"))?;
+        let mut cells = Vec::new();
+
+        for cell in 0..cell_count {
+            self.reader.seek(from_start(u64::from(grid_list_offset) + u64::from(cell) * 4))?;
+            let cell_list_offset = self.reader.read_u32::<B>()?;
+
+            let mut triangle_indices = Vec::new();
+            if cell_list_offset != 0 {
+                self.reader.seek(from_start(u64::from(cell_list_offset)))?;
+                loop {
+                    let triangle_index = self.reader.read_u16::<B>()?;
+                    if triangle_index == 0xFFFF {
+                        break;
+                    }
+                    triangle_indices.push(triangle_index);
+                }
+            }
+            cells.push(triangle_indices);
+        }
+
+        Ok(cells)
+    }
+
+    /// Reads every collision triangle referenced by the given collision grid cells.
+    ///
+    /// The triangle list itself has no count, so it's read up to the highest triangle index any
+    /// cell refers to.
+    fn read_collision_triangles<B: ByteOrder>(
+        &mut self,
+        format: &StageDefCollisionHeaderFormat,
+        grid_cells: &[Vec<u16>],
+    ) -> ParseResult<Vec<CollisionTriangle>> {
+        self.reader.try_seek(format.collision_triangle_list_offset)?;
+        let triangle_list_offset = self.reader.read_u32::<B>()?;
+
+        if triangle_list_offset == 0 {
+            return Ok(Vec::new());
+        }
+
+        let triangle_count = grid_cells.iter().flatten().map(|&index| u32::from(index) + 1).max().unwrap_or(0);
+
+        self.reader.seek(from_start(u64::from(triangle_list_offset)))?;
+        (0..triangle_count)
+            .map(|_| CollisionTriangle::try_from_reader::<TrackingReader<R>, B>(&mut self.reader))
+            .collect()
+    }
+
+    /// Reads a level model pointer (type B) list at the current position, and resolves each pointer
+    /// into the name of the model it refers to.
+    fn read_level_model_ptr_b_list<B: ByteOrder>(&mut self) -> ParseResult<Vec<String>> {
+        let mut model_names = Vec::new();
+
+        if let FileOffset::CountOffset(c, o) = self.reader.read_count_offset::<B>()? {
+            self.reader.seek(o)?;
+            for _ in 0..c {
+                match self.reader.read_model_name_from_ptr_a::<B>() {
+                    Ok(name) => model_names.push(name),
+                    Err(err) => self.warn_or_fail(err, "Failed to resolve level model name")?,
+                }
+            }
+        }
+
+        Ok(model_names)
+    }
+
+    /// Reads a single object pointed to by the file header, or returns ``None`` if the pointer is
+    /// null or unused.
+    fn read_stagedef_object<B: ByteOrder, T: StageDefParsable>(&mut self, offset: FileOffset) -> ParseResult<Option<T>> {
+        match offset {
+            FileOffset::OffsetOnly(SeekFrom::Start(0)) | FileOffset::Unused => Ok(None),
+            _ => {
+                let start = self.reader.try_seek(offset)?;
+                self.reader.mark_referenced_end(start + u64::from(T::get_size_for_game(self.game)));
+                T::try_from_reader_for_game::<TrackingReader<R>, B>(&mut self.reader, self.game).map(Some)
+            }
+        }
+    }
+
     /// Read a global stagedef object list
     fn read_stagedef_list<B: ByteOrder, T: StageDefParsable>(
         &mut self,
         offset: FileOffset,
-    ) -> Result<Vec<GlobalStagedefObject<T>>> {
+    ) -> ParseResult<Vec<GlobalStagedefObject<T>>> {
         if let FileOffset::CountOffset(c, o) = offset {
             let mut vec = Vec::new();
-            self.reader.seek(o)?;
+            let start = self.reader.seek(o)?;
+            self.reader.mark_referenced_end(start + u64::from(c) * u64::from(T::get_size_for_game(self.game)));
             for i in 0..c {
-                let read_obj = T::try_from_reader::<R, B>(&mut self.reader);
+                let object_offset = self.reader.stream_position()?;
+                let read_obj = T::try_from_reader_for_game::<TrackingReader<R>, B>(&mut self.reader, self.game);
 
                 match read_obj {
-                    Ok(obj) => vec.push(GlobalStagedefObject::new(obj, i)),
-                    Err(err) => warn!("{err}"),
+                    Ok(obj) => vec.push(GlobalStagedefObject::new(obj, i).with_file_offset(object_offset)),
+                    Err(err) => self.warn_or_fail(err, T::get_name())?,
                 }
             }
             Ok(vec)
         } else {
-            Err(anyhow::Error::msg("No object list was read"))
+            Err(ParseError::InvalidOffset("No object list was read"))
         }
     }
 
@@ -649,46 +1321,69 @@ impl<R: Read + Seek> StageDefReader<R> {
         offset: FileOffset,
         global_list_offset: FileOffset,
         global_list: &[GlobalStagedefObject<T>],
-    ) -> Result<Vec<GlobalStagedefObject<T>>> {
+    ) -> ParseResult<Vec<GlobalStagedefObject<T>>> {
         if self.reader.try_seek(offset).is_ok() {
             let local_count_offset = self.reader.read_count_offset::<B>()?;
             if let FileOffset::CountOffset(local_count, local_offset) = local_count_offset {
                 self.reader.seek(local_offset)?;
 
                 // Attempt to get objects from global list and re-adjust indices for our local list
-                let vec = match Self::get_global_objs_from_local_list(local_count, &local_offset, &global_list_offset, global_list) {
-                    Some(objs) => objs,
-                    None => self.read_stagedef_list::<B, T>(local_count_offset)?,
+                let vec = match Self::get_global_objs_from_local_list(
+                    local_count,
+                    &local_offset,
+                    &global_list_offset,
+                    global_list,
+                    self.game,
+                ) {
+                    Ok(objs) => objs,
+                    Err(err) => {
+                        self.warn_or_fail(err, "Reading the local list by itself")?;
+                        self.read_stagedef_list::<B, T>(local_count_offset)?
+                    }
                 };
 
                 Ok(vec)
             } else {
-                Err(anyhow::Error::msg("No object list was read - no local offset"))
+                Err(ParseError::InvalidOffset("No object list was read - no local offset"))
             }
         } else {
-            Err(anyhow::Error::msg("No object list was read - could not seek to given offset"))
+            Err(ParseError::InvalidOffset("No object list was read - could not seek to given offset"))
         }
     }
 
-    /// Return the intersection between a local and global stagedef object list, or ``None`` if no
-    /// overlap exists.
+    /// Return the intersection between a local and global stagedef object list, or why there isn't
+    /// one.
     fn get_global_objs_from_local_list<T: StageDefParsable>(
         local_count: u32,
         local_offset: &SeekFrom,
         global_co: &FileOffset,
         global_obj_list: &[GlobalStagedefObject<T>],
-    ) -> Option<Vec<GlobalStagedefObject<T>>> {
+        game: Game,
+    ) -> ParseResult<Vec<GlobalStagedefObject<T>>> {
+        let size = T::get_size_for_game(game);
         if let FileOffset::CountOffset(global_count, global_offset) = global_co {
             // We want to compare the local offset of this list to the global one to find out
             // where we are in the global list
             if let Ok(diff) = try_get_offset_difference(local_offset, global_offset) {
                 // The difference isn't negative, so the object(s) is likely to be in or after the
                 // global list
-                let global_size = global_count * T::get_size();
+                let global_size = u64::from(*global_count) * u64::from(size);
+                // The local list starts partway through a global object, so it can't be made of
+                // global objects. Some stages do this, so read the local list by itself instead
+                if diff % size != 0 {
+                    Err(ParseError::UnmatchedLocalList {
+                        object_name: T::get_name(),
+                        reason: format!(
+                            "local list starts {:#X} bytes into the global list, which isn't a multiple of the object \
+                             size {:#X}",
+                            diff, size
+                        ),
+                    })
+                }
                 // The difference is within the bounds of the list
-                if diff < global_size {
+                else if u64::from(diff) < global_size {
                     // Get the global starting index for the local list
-                    let global_start_index = diff / T::get_size();
+                    let global_start_index = diff / size;
                     let mut local_reindex_value = 0;
                     let matching_global_objs: Vec<GlobalStagedefObject<T>> = global_obj_list
                         .iter()
@@ -696,37 +1391,38 @@ impl<R: Read + Seek> StageDefReader<R> {
                         .take(local_count as usize)
                         .cloned()
                         .map(|mut local| {
+                            local.global_index = Some(local.index);
                             local.index = local_reindex_value;
                             local_reindex_value += 1;
                             local
                         })
                         .collect();
-                    Some(matching_global_objs)
+                    Ok(matching_global_objs)
                 }
                 // The difference isn't within the bounds of the list, so the object(s) is not in
                 // the global list
                 else {
-                    warn!(
-                        "Failed global object retrieval for type {}: local list of size {:} larger than global list of size {:}",
-                        T::get_name(), diff, global_size
-                    );
-                    None
+                    Err(ParseError::UnmatchedLocalList {
+                        object_name: T::get_name(),
+                        reason: format!("local list of size {diff} larger than global list of size {global_size}"),
+                    })
                 }
             }
             // The difference is negative, so the object(s) is before the global list for some
             // reason
             else {
-                warn!(
-                    "Failed global object retrieval for type {}: objects before list",
-                    T::get_name()
-                );
-                None
+                Err(ParseError::UnmatchedLocalList {
+                    object_name: T::get_name(),
+                    reason: String::from("objects before list"),
+                })
             }
         }
         // There is no global list
         else {
-            warn!("Failed global object retrieval for type {}: no global list", T::get_name());
-            None
+            Err(ParseError::UnmatchedLocalList {
+                object_name: T::get_name(),
+                reason: String::from("no global list"),
+            })
         }
     }
 }
@@ -750,7 +1446,12 @@ mod test {
     /// * Start position: Vec3: 0.0, 2.75, 14.0, ShortVector3: 0, 0, 0
     /// * Fallout level: -20.0
     /// * Goal #1: Position 0.0, 0.0, -115.0, Rotation 0, 0, 0, type: blue
-    fn test_smb2_stagedef_header<T: ByteOrder>() -> Result<Cursor<Vec<u8>>> {
+    /// * Collision header #1 conveyor vector: 1.0, 0.0, -0.5
+    /// * Collision header #1 unknowns: 0x9C 0x12345678, 0xA0 0x9ABCDEF0, 0xA6 0xBEEF, 0xB0 0xCAFEBABE,
+    ///   0xD0 0x0BADF00D
+    /// * Level model pointer B list: 3 pointers at 0x98C, all referring to "TEST_MODEL"
+    /// * Level model instance #1: "TEST_MODEL", Position 1.0, 2.0, 3.0, Scale 1.0, 1.0, 1.0
+    fn test_smb2_stagedef_header<T: ByteOrder>() -> ParseResult<Cursor<Vec<u8>>> {
         use byteorder::WriteBytesExt;
 
         let mut cur = Cursor::new(vec![0; 0x1000]);
@@ -778,6 +1479,11 @@ mod test {
         cur.write_uint::<T>(0x00000007, 4)?;
         cur.write_uint::<T>(0x000008C8, 4)?;
 
+        // level model instance list count/offset
+        cur.seek(from_start(0x84))?;
+        cur.write_uint::<T>(0x00000001, 4)?;
+        cur.write_uint::<T>(0x00000A50, 4)?;
+
         cur.seek(from_start(0x89C))?;
 
         // start position
@@ -835,18 +1541,51 @@ mod test {
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
 
-        cur.seek(from_start(0x1BFC))?;
+        // level model pointer B list
+        cur.seek(from_start(0x98C))?;
+        cur.write_uint::<T>(0x00000A20, 4)?;
+        cur.write_uint::<T>(0x00000A20, 4)?;
+        cur.write_uint::<T>(0x00000A20, 4)?;
 
-        // collision header #1
+        // level model pointer A
+        cur.seek(from_start(0xA20))?;
+        cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x00000001, 4)?;
+        cur.write_uint::<T>(0x00000A30, 4)?;
+
+        // level model
+        cur.seek(from_start(0xA30))?;
+        cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x00000A40, 4)?;
+
+        // level model name
+        cur.seek(from_start(0xA40))?;
+        cur.write_all(b"TEST_MODEL\0")?;
+
+        // level model instance list
+        cur.seek(from_start(0xA50))?;
+        cur.write_uint::<T>(0x00000A20, 4)?;
+        cur.write_uint::<T>(0x3F800000, 4)?;
+        cur.write_uint::<T>(0x40000000, 4)?;
+        cur.write_uint::<T>(0x40400000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x3F800000, 4)?;
+        cur.write_uint::<T>(0x3F800000, 4)?;
+        cur.write_uint::<T>(0x3F800000, 4)?;
+
+        cur.seek(from_start(0x1BFC))?;
+
+        // collision header #1
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x3F800000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0xBF000000, 4)?;
         cur.write_uint::<T>(0x00002098, 4)?;
         cur.write_uint::<T>(0x000119E4, 4)?;
         cur.write_uint::<T>(0xC1A92F92, 4)?;
@@ -873,17 +1612,25 @@ mod test {
         cur.write_uint::<T>(0x00000998, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
-        cur.write_uint::<T>(0x00000000, 4)?;
-        cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x00000001, 4)?;
+        cur.write_uint::<T>(0x00000A50, 4)?;
         cur.write_uint::<T>(0x00000003, 4)?;
         cur.write_uint::<T>(0x0000098C, 4)?;
+        cur.write_uint::<T>(0x12345678, 4)?;
+        cur.write_uint::<T>(0x9ABCDEF0, 4)?;
+        cur.write_uint::<T>(0x0000, 2)?;
+        cur.write_uint::<T>(0xBEEF, 2)?;
+        cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0xCAFEBABE, 4)?;
+        cur.write_uint::<T>(0x00001AFC, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
         cur.write_uint::<T>(0x00000000, 4)?;
-        cur.write_uint::<T>(0x00001AFC, 4)?;
+        cur.write_uint::<T>(0x00000000, 4)?;
+        cur.write_uint::<T>(0x0BADF00D, 4)?;
 
         Ok(cur)
     }
@@ -899,6 +1646,81 @@ mod test {
         assert_eq!(magic_le_test, magic_le_test_bytes);
     }
 
+    #[test]
+    fn test_compressed_stagedef() {
+        use crate::stagedef::compression::{self, Compression};
+        use flate2::write::{GzEncoder, ZlibEncoder};
+
+        let raw = test_smb2_stagedef_header::<BigEndian>().unwrap().into_inner();
+        assert_eq!(Compression::detect(&raw), Compression::None);
+
+        let mut gzip = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&raw).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&raw).unwrap();
+        let zlib = zlib.finish().unwrap();
+
+        for (compressed, expected_compression) in [(gzip, Compression::Gzip), (zlib, Compression::Zlib)] {
+            assert_eq!(Compression::detect(&compressed), expected_compression);
+
+            let decompressed = compression::decompress(compressed).unwrap();
+            assert_eq!(decompressed.compression, expected_compression);
+            assert_eq!(decompressed.buffer, raw, "{expected_compression:?}");
+            let decompressed = decompressed.buffer;
+
+            let mut sd_reader = StageDefReader::new(Cursor::new(decompressed), Game::SMB2);
+            let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+            assert_eq!(stagedef.magic_number_2, 1000.0, "{expected_compression:?}");
+            assert_eq!(stagedef.goals.len(), 1, "{expected_compression:?}");
+        }
+
+        // Raw buffers, and buffers that only look compressed, are passed through untouched
+        assert_eq!(compression::decompress(raw.clone()).unwrap().buffer, raw);
+        assert_eq!(compression::decompress(vec![0x1f, 0x8b, 0x00]).unwrap().buffer, vec![0x1f, 0x8b, 0x00]);
+    }
+
+    #[test]
+    fn test_smb1_file_header_unsupported() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB1);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+        assert!(matches!(err, ParseError::UnsupportedGame("SMB1")), "{err}");
+    }
+
+    #[test]
+    fn test_detect_endianness() {
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        assert_eq!(detect_endianness(&mut file, Game::SMB2).unwrap(), Some(Endianness::BigEndian));
+
+        let mut file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
+        assert_eq!(detect_endianness(&mut file, Game::SMB2).unwrap(), Some(Endianness::LittleEndian));
+
+        let mut file = Cursor::new(vec![0xFF; 0x10]);
+        assert_eq!(detect_endianness(&mut file, Game::SMB2).unwrap(), None);
+
+        let mut file = Cursor::new(vec![0; 0x4]);
+        assert!(matches!(detect_endianness(&mut file, Game::SMB2), Err(ParseError::UnexpectedEof)));
+
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        assert!(matches!(detect_endianness(&mut file, Game::SMB1), Err(ParseError::UnsupportedGame("SMB1"))));
+    }
+
+    #[test]
+    fn test_magic_number_warning() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.get_magic_number_warning(), None);
+
+        // Reading with the wrong endianness gives unusual magic numbers
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+        assert!(stagedef.get_magic_number_warning().is_some());
+    }
+
     #[test]
     fn test_magic_numbers() {
         let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
@@ -917,50 +1739,333 @@ mod test {
     }
 
     #[test]
-    fn test_start_fallout_pos_parse() {
-        let expected_pos = Vector3 {
-            x: 0.0,
-            y: 2.75,
-            z: 14.0,
-        };
-        let expected_rot = ShortVector3 { x: 0, y: 0, z: 0 };
-        let expected_flevel = -20.0;
-
+    fn test_trailing_bytes() {
         let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
         let mut sd_reader = StageDefReader::new(file, Game::SMB2);
         let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
 
-        assert_eq!(stagedef.start_position, expected_pos, "BigEndian");
-        assert_eq!(stagedef.start_rotation, expected_rot, "BigEndian");
-        assert_eq!(stagedef.fallout_level, expected_flevel, "BigEndian");
+        assert!(stagedef.trailing_bytes.is_empty());
+
+        // Pad out the collision header, then append a footer after it
+        let footer = [0x46, 0x4F, 0x4F, 0x54, 0x45, 0x52, 0x00, 0x01];
+        let mut file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
+        file.get_mut().resize(0x1BFC + CollisionHeader::get_size() as usize, 0);
+        file.get_mut().extend_from_slice(&footer);
 
-        let file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
         let mut sd_reader = StageDefReader::new(file, Game::SMB2);
         let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
 
-        assert_eq!(stagedef.start_position, expected_pos, "LittleEndian");
-        assert_eq!(stagedef.start_rotation, expected_rot, "LittleEndian");
-        assert_eq!(stagedef.fallout_level, expected_flevel, "LittleEndian");
+        assert_eq!(stagedef.trailing_bytes, footer);
+        assert_eq!(stagedef.magic_number_2, 1000.0);
+        assert_eq!(stagedef.goals.len(), 1);
     }
 
     #[test]
-    fn test_goal_parse() {
-        let expected_goal = Goal {
-            position: Vector3 {
-                x: 0.0,
-                y: 0.0,
-                z: -115.0,
-            },
-            rotation: ShortVector3 { x: 0, y: 0, z: 0 },
-            goal_type: GoalType::Blue,
-        };
-
+    fn test_file_usage() {
+        // The test stagedef stops partway through its collision header
         let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
         let mut sd_reader = StageDefReader::new(file, Game::SMB2);
         let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
 
-        assert_eq!(*stagedef.goals[0].object.lock().unwrap(), expected_goal);
-    }
+        let header_end = 0x1BFC + u64::from(CollisionHeader::get_size());
+        assert_eq!(stagedef.file_usage.referenced_end, header_end);
+        assert_eq!(stagedef.file_usage.file_size, 0x1CD0);
+        assert!(stagedef.file_usage.is_truncated());
+
+        // Padding it out to the end of the collision header uses the whole file
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.get_mut().resize(header_end as usize, 0);
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.file_usage.file_size, header_end);
+        assert!(!stagedef.file_usage.is_truncated());
+        assert_eq!(stagedef.file_usage.get_unused_size(), 0);
+        assert_eq!(stagedef.file_usage.get_warning(), None);
+    }
+
+    /// Wraps a reader, counting how many times it's sought.
+    #[cfg(test)]
+    struct SeekCountingReader<R> {
+        inner: R,
+        seek_count: usize,
+    }
+
+    #[cfg(test)]
+    impl<R: Read> Read for SeekCountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    #[cfg(test)]
+    impl<R: Seek> Seek for SeekCountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seek_count += 1;
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_file_header_offsets_read_at_once() {
+        let file = SeekCountingReader {
+            inner: test_smb2_stagedef_header::<BigEndian>().unwrap(),
+            seek_count: 0,
+        };
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let file_header = sd_reader.read_file_header_offsets::<BigEndian>().unwrap();
+
+        assert_eq!(sd_reader.reader.inner.seek_count, 1);
+        assert!(matches!(
+            file_header.collision_header_list_offset,
+            FileOffset::CountOffset(1, SeekFrom::Start(0x1BFC))
+        ));
+        assert!(matches!(file_header.start_position_ptr_offset, FileOffset::OffsetOnly(SeekFrom::Start(0x89C))));
+    }
+
+    /// Times reading the file header's offsets through a buffered reader, which throws away its
+    /// buffer whenever it's sought. Run with
+    /// ``cargo test --release bench_read_file_header_offsets -- --ignored --nocapture``.
+    #[test]
+    #[ignore]
+    fn bench_read_file_header_offsets() {
+        const ITERATIONS: u32 = 100_000;
+        let buffer = test_smb2_stagedef_header::<BigEndian>().unwrap().into_inner();
+
+        let start = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            let mut sd_reader = StageDefReader::new(BufReader::new(Cursor::new(buffer.as_slice())), Game::SMB2);
+            sd_reader.read_file_header_offsets::<BigEndian>().unwrap();
+        }
+        println!("{:?} per file header", start.elapsed() / ITERATIONS);
+    }
+
+    #[test]
+    fn test_start_fallout_pos_parse() {
+        let expected_pos = Vector3 {
+            x: 0.0,
+            y: 2.75,
+            z: 14.0,
+        };
+        let expected_rot = ShortVector3 { x: 0, y: 0, z: 0 };
+        let expected_flevel = -20.0;
+
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.start_position.position, expected_pos, "BigEndian");
+        assert_eq!(stagedef.start_position.rotation, expected_rot, "BigEndian");
+        assert_eq!(stagedef.fallout_level, expected_flevel, "BigEndian");
+
+        let file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+
+        assert_eq!(stagedef.start_position.position, expected_pos, "LittleEndian");
+        assert_eq!(stagedef.start_position.rotation, expected_rot, "LittleEndian");
+        assert_eq!(stagedef.fallout_level, expected_flevel, "LittleEndian");
+    }
+
+    #[test]
+    fn test_start_position_game_layouts() {
+        use byteorder::WriteBytesExt;
+
+        let mut cur = Cursor::new(vec![0; 0x20]);
+        cur.write_f32::<BigEndian>(1.0).unwrap();
+        cur.write_f32::<BigEndian>(2.0).unwrap();
+        cur.write_f32::<BigEndian>(3.0).unwrap();
+        cur.write_u16::<BigEndian>(0x4000).unwrap();
+        cur.write_u16::<BigEndian>(0x0000).unwrap();
+        cur.write_u16::<BigEndian>(0x8000).unwrap();
+        cur.write_u16::<BigEndian>(0x0000).unwrap();
+        cur.write_u32::<BigEndian>(0xDEADBEEF).unwrap();
+
+        let expected_pos = Vector3 { x: 1.0, y: 2.0, z: 3.0 };
+        let expected_rot = ShortVector3 {
+            x: 0x4000,
+            y: 0x0000,
+            z: 0x8000,
+        };
+
+        cur.seek(from_start(0x0)).unwrap();
+        let smb2_start = StartPosition::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMB2).unwrap();
+        assert_eq!(smb2_start.position, expected_pos, "SMB2");
+        assert_eq!(smb2_start.rotation, expected_rot, "SMB2");
+        assert_eq!(smb2_start.unk0x14, 0, "SMB2");
+        assert_eq!(cur.stream_position().unwrap(), 0x14, "SMB2");
+
+        cur.seek(from_start(0x0)).unwrap();
+        let smbdx_start = StartPosition::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMBDX).unwrap();
+        assert_eq!(smbdx_start.position, expected_pos, "SMBDX");
+        assert_eq!(smbdx_start.rotation, expected_rot, "SMBDX");
+        assert_eq!(smbdx_start.unk0x14, 0xDEADBEEF, "SMBDX");
+        assert_eq!(cur.stream_position().unwrap(), 0x18, "SMBDX");
+    }
+
+    #[test]
+    fn test_goal_parse() {
+        let expected_goal = Goal {
+            position: Position(Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: -115.0,
+            }),
+            rotation: ShortVector3 { x: 0, y: 0, z: 0 },
+            goal_type: GoalType::Blue,
+        };
+
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(*stagedef.goals[0].object.lock().unwrap(), expected_goal);
+    }
+
+    /// Writes a goal on the Z axis at the cursor, rotated around Y, with the given raw type byte.
+    fn write_goal<B: ByteOrder>(cur: &mut Cursor<Vec<u8>>, z: f32, rotation_y: u16, type_byte: u8) {
+        use byteorder::WriteBytesExt;
+
+        cur.write_f32::<B>(0.0).unwrap();
+        cur.write_f32::<B>(0.0).unwrap();
+        cur.write_f32::<B>(z).unwrap();
+        cur.write_u16::<B>(0x0000).unwrap();
+        cur.write_u16::<B>(rotation_y).unwrap();
+        cur.write_u16::<B>(0x0000).unwrap();
+        cur.write_u8(type_byte).unwrap();
+        cur.write_u8(0x0).unwrap();
+    }
+
+    #[test]
+    fn test_smb1_goal_parse() {
+        let mut cur = Cursor::new(vec![0; 0x14]);
+        write_goal::<BigEndian>(&mut cur, -115.0, 0x8000, b'G');
+
+        let expected_goal = Goal {
+            position: Position(Vector3 {
+                x: 0.0,
+                y: 0.0,
+                z: -115.0,
+            }),
+            rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+            goal_type: GoalType::Green,
+        };
+
+        cur.seek(from_start(0x0)).unwrap();
+        let goal = Goal::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMB1).unwrap();
+        assert_eq!(goal, expected_goal);
+
+        // The same bytes aren't a valid SMB2 goal type
+        cur.seek(from_start(0x0)).unwrap();
+        assert!(matches!(
+            Goal::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMB2),
+            Err(ParseError::UnknownEnumValue {
+                type_name: "goal type",
+                value: 0x47
+            })
+        ));
+    }
+
+    #[test]
+    fn test_smbdx_goal_parse() {
+        // Two goals back to back, as in a little endian Deluxe goal list. This assumes Deluxe keeps
+        // SMB2's goal layout, as it hasn't been checked against a real Deluxe stagedef yet
+        let mut cur = Cursor::new(Vec::new());
+        write_goal::<LittleEndian>(&mut cur, -115.0, 0x8000, 1);
+        write_goal::<LittleEndian>(&mut cur, -230.0, 0x8000, 2);
+
+        cur.seek(from_start(0x0)).unwrap();
+        let first = Goal::try_from_reader_for_game::<_, LittleEndian>(&mut cur, Game::SMBDX).unwrap();
+        assert_eq!(cur.stream_position().unwrap(), u64::from(Goal::get_size()));
+        let second = Goal::try_from_reader_for_game::<_, LittleEndian>(&mut cur, Game::SMBDX).unwrap();
+
+        assert_eq!(
+            first,
+            Goal {
+                position: Position(Vector3 { x: 0.0, y: 0.0, z: -115.0 }),
+                rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+                goal_type: GoalType::Green,
+            }
+        );
+        // The second goal isn't shifted by the first
+        assert_eq!(second.position.z, -230.0);
+        assert_eq!(second.goal_type, GoalType::Red);
+        assert_eq!(cur.stream_position().unwrap(), 0x28);
+    }
+
+    #[test]
+    fn test_smb1_collision_header_goals() {
+        use byteorder::WriteBytesExt;
+
+        assert_eq!(CollisionHeader::get_size_for_game(Game::SMB1), 0xC4);
+
+        // An SMB1 collision header with two goals straight after it
+        let mut cur = Cursor::new(vec![0; 0xC4 + 2 * 0x14]);
+        cur.seek(from_start(0x38)).unwrap();
+        cur.write_u32::<BigEndian>(2).unwrap();
+        cur.write_u32::<BigEndian>(0xC4).unwrap();
+        cur.seek(from_start(0x98)).unwrap();
+        cur.write_u16::<BigEndian>(7).unwrap();
+
+        cur.seek(from_start(0xC4)).unwrap();
+        write_goal::<BigEndian>(&mut cur, -115.0, 0x8000, b'B');
+        write_goal::<BigEndian>(&mut cur, -230.0, 0x8000, b'R');
+
+        let mut sd_reader = StageDefReader::new(cur, Game::SMB1);
+        let collision_header = sd_reader
+            .read_collision_header::<BigEndian>(&StageDef::default(), from_start(0x0))
+            .unwrap();
+
+        assert_eq!(collision_header.goals.len(), 2);
+        let goals: Vec<Goal> = collision_header
+            .goals
+            .iter()
+            .map(|goal| goal.object.lock().unwrap().clone())
+            .collect();
+        assert_eq!(goals[0].position.z, -115.0);
+        assert_eq!(goals[0].goal_type, GoalType::Blue);
+        assert_eq!(goals[1].position.z, -230.0);
+        assert_eq!(goals[1].goal_type, GoalType::Red);
+        assert_eq!(goals[1].rotation, ShortVector3 { x: 0, y: 0x8000, z: 0 });
+
+        // Fields after where SMB2's conveyor vector would be are read from their SMB1 offsets
+        assert_eq!(collision_header.animation_id, 7);
+        assert_eq!(collision_header.conveyor_vector, Vector3::default());
+    }
+
+    #[test]
+    fn test_smb1_reflective_model_parse() {
+        use byteorder::WriteBytesExt;
+
+        // Two SMB1 reflective models back to back, followed by their names
+        let mut cur = Cursor::new(vec![0; 0x40]);
+        cur.write_u32::<BigEndian>(0x20).unwrap();
+        cur.write_u32::<BigEndian>(0x0).unwrap();
+        cur.write_u32::<BigEndian>(0x30).unwrap();
+        cur.write_u32::<BigEndian>(0x0).unwrap();
+        cur.seek(from_start(0x20)).unwrap();
+        cur.write_all(b"REFLECT_A\0").unwrap();
+        cur.seek(from_start(0x30)).unwrap();
+        cur.write_all(b"REFLECT_B\0").unwrap();
+
+        assert_eq!(ReflectiveModel::get_size_for_game(Game::SMB1), 0x8);
+        assert_eq!(ReflectiveModel::get_size_for_game(Game::SMB2), 0xC);
+
+        cur.seek(from_start(0x0)).unwrap();
+        for (expected_name, expected_position) in [("REFLECT_A", 0x8), ("REFLECT_B", 0x10)] {
+            let model = ReflectiveModel::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMB1).unwrap();
+            assert_eq!(model.model_name, expected_name);
+            assert_eq!(model.flags, 0);
+            assert_eq!(cur.stream_position().unwrap(), expected_position);
+        }
+
+        // Read as SMB2, the first model runs into the second's name pointer
+        cur.seek(from_start(0x0)).unwrap();
+        let model = ReflectiveModel::try_from_reader_for_game::<_, BigEndian>(&mut cur, Game::SMB2).unwrap();
+        assert_eq!(model.model_name, "REFLECT_A");
+        assert_eq!(model.flags, 0x30);
+        assert_eq!(cur.stream_position().unwrap(), 0xC);
+    }
 
     #[test]
     fn test_banana_parse() {
@@ -971,15 +2076,46 @@ mod test {
         assert_eq!(stagedef.bananas.len(), 7);
     }
 
+    #[test]
+    fn test_object_file_ranges() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.goals[0].get_file_range(Game::SMB2), Some(0x8B4..0x8C8));
+        assert_eq!(stagedef.bananas[1].get_file_range(Game::SMB2), Some(0x8D8..0x8E8));
+
+        // Local lists share their objects with the global lists, offsets included
+        assert_eq!(stagedef.collision_headers[0].goals[0].file_offset, Some(0x8B4));
+    }
+
+    #[test]
+    fn test_local_objects_keep_global_index() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.bananas[6].index, 6);
+        assert_eq!(stagedef.bananas[6].global_index, None);
+
+        let local_bananas = &stagedef.collision_headers[0].bananas;
+        assert_eq!(local_bananas.len(), 7);
+        for (local_index, banana) in local_bananas.iter().enumerate() {
+            assert_eq!(banana.index, local_index as u32);
+            assert_eq!(banana.global_index, Some(local_index as u32));
+            assert!(std::sync::Arc::ptr_eq(&banana.object, &stagedef.bananas[local_index].object));
+        }
+    }
+
     #[test]
     fn test_collision_header_goal_parse() {
         tracing_subscriber::fmt().with_max_level(Level::DEBUG).init();
         let expected_goal = Goal {
-            position: Vector3 {
+            position: Position(Vector3 {
                 x: 0.0,
                 y: 0.0,
                 z: -115.0,
-            },
+            }),
             rotation: ShortVector3 { x: 0, y: 0, z: 0 },
             goal_type: GoalType::Blue,
         };
@@ -994,6 +2130,755 @@ mod test {
         let test_goal = stagedef.collision_headers[0].goals[0].object.lock().unwrap();
         assert_eq!(*test_goal, expected_goal);
     }
+    #[cfg(test)]
+    /// Returns the test stagedef with two global bumpers at 0xB80 and a global jamabar at 0xBC0, with
+    /// the first collision header referring to the second bumper and the jamabar.
+    fn test_bumper_jamabar_stagedef<T: ByteOrder>() -> ParseResult<Cursor<Vec<u8>>> {
+        use byteorder::WriteBytesExt;
+
+        let mut cur = test_smb2_stagedef_header::<T>()?;
+
+        // File header bumper and jamabar lists
+        cur.seek(from_start(0x20))?;
+        cur.write_u32::<T>(2)?;
+        cur.write_u32::<T>(0x00000B80)?;
+        cur.write_u32::<T>(1)?;
+        cur.write_u32::<T>(0x00000BC0)?;
+
+        // Collision header #1 bumper and jamabar lists
+        cur.seek(from_start(0x1BFC + 0x4C))?;
+        cur.write_u32::<T>(1)?;
+        cur.write_u32::<T>(0x00000BA0)?;
+        cur.write_u32::<T>(1)?;
+        cur.write_u32::<T>(0x00000BC0)?;
+
+        cur.seek(from_start(0xB80))?;
+        for (position, rotation_y, scale) in [(1.0, 0x4000, 1.0), (2.0, 0x8000, 2.0), (3.0, 0xC000, 3.0)] {
+            for component in [position, 0.0, -position] {
+                cur.write_f32::<T>(component)?;
+            }
+            cur.write_u16::<T>(0)?;
+            cur.write_u16::<T>(rotation_y)?;
+            cur.write_u16::<T>(0)?;
+            cur.write_u16::<T>(0)?;
+            for _ in 0..3 {
+                cur.write_f32::<T>(scale)?;
+            }
+        }
+
+        Ok(cur)
+    }
+
+    #[test]
+    fn test_collision_header_bumper_parse() {
+        let expected_bumper = Bumper {
+            position: Position(Vector3 { x: 2.0, y: 0.0, z: -2.0 }),
+            rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+            scale: Vector3 { x: 2.0, y: 2.0, z: 2.0 },
+        };
+
+        let file = test_bumper_jamabar_stagedef::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.bumpers.len(), 2);
+
+        let local_bumpers = &stagedef.collision_headers[0].bumpers;
+        assert_eq!(local_bumpers.len(), 1);
+        assert_eq!(local_bumpers[0].index, 0);
+        assert_eq!(local_bumpers[0].global_index, Some(1));
+        assert!(std::sync::Arc::ptr_eq(&local_bumpers[0].object, &stagedef.bumpers[1].object));
+        assert_eq!(*local_bumpers[0].object.lock().unwrap(), expected_bumper);
+    }
+
+    #[test]
+    fn test_collision_header_jamabar_parse() {
+        let expected_jamabar = Jamabar {
+            position: Position(Vector3 { x: 3.0, y: 0.0, z: -3.0 }),
+            rotation: ShortVector3 { x: 0, y: 0xC000, z: 0 },
+            scale: Vector3 { x: 3.0, y: 3.0, z: 3.0 },
+        };
+
+        let file = test_bumper_jamabar_stagedef::<LittleEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+
+        assert_eq!(stagedef.jamabars.len(), 1);
+
+        let local_jamabars = &stagedef.collision_headers[0].jamabars;
+        assert_eq!(local_jamabars.len(), 1);
+        assert_eq!(local_jamabars[0].global_index, Some(0));
+        assert!(std::sync::Arc::ptr_eq(&local_jamabars[0].object, &stagedef.jamabars[0].object));
+        assert_eq!(*local_jamabars[0].object.lock().unwrap(), expected_jamabar);
+    }
+
+    #[test]
+    fn test_collision_header_conveyor_parse() {
+        let expected_conveyor = Vector3 { x: 1.0, y: 0.0, z: -0.5 };
+
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.collision_headers[0].conveyor_vector, expected_conveyor, "BigEndian");
+
+        let file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+
+        assert_eq!(stagedef.collision_headers[0].conveyor_vector, expected_conveyor, "LittleEndian");
+    }
+
+    #[cfg(test)]
+    /// Returns the test stagedef with a 2x1 collision grid in the first collision header, at 0xD00.
+    /// Both cells contain triangle 1 of the two triangles at 0xC00, and the first also contains
+    /// triangle 0.
+    fn test_collision_grid_stagedef<T: ByteOrder>() -> ParseResult<Cursor<Vec<u8>>> {
+        use byteorder::WriteBytesExt;
+
+        let mut cur = test_smb2_stagedef_header::<T>()?;
+
+        cur.seek(from_start(0x1BFC + 0x24))?;
+        cur.write_u32::<T>(0x00000C00)?;
+        cur.write_u32::<T>(0x00000D00)?;
+        cur.seek(from_start(0x1BFC + 0x3C))?;
+        cur.write_u32::<T>(2)?;
+        cur.write_u32::<T>(1)?;
+
+        // Triangles, told apart by the X position of their first vertex
+        for (index, x) in [1.0, 2.0].into_iter().enumerate() {
+            cur.seek(from_start(0xC00 + 0x40 * index as u64))?;
+            cur.write_f32::<T>(x)?;
+        }
+
+        // Grid cell pointers, then each cell's triangle index list
+        cur.seek(from_start(0xD00))?;
+        cur.write_u32::<T>(0x00000D10)?;
+        cur.write_u32::<T>(0x00000D20)?;
+        cur.seek(from_start(0xD10))?;
+        for index in [0, 1, 0xFFFF] {
+            cur.write_u16::<T>(index)?;
+        }
+        cur.seek(from_start(0xD20))?;
+        for index in [1, 0xFFFF] {
+            cur.write_u16::<T>(index)?;
+        }
+
+        Ok(cur)
+    }
+
+    #[test]
+    fn test_collision_grid_shared_triangle() {
+        let file = test_collision_grid_stagedef::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        let col_header = &stagedef.collision_headers[0];
+
+        assert_eq!(col_header.collision_grid_cells, vec![vec![0, 1], vec![1]]);
+
+        // The shared triangle is only read once
+        assert_eq!(col_header.collision_triangles.len(), 2);
+        assert_eq!(col_header.collision_triangles[0].position.x, 1.0);
+        assert_eq!(col_header.collision_triangles[1].position.x, 2.0);
+    }
+
+    #[test]
+    fn test_collision_grid_oversized_step_counts() {
+        use byteorder::WriteBytesExt;
+
+        let mut file = test_collision_grid_stagedef::<BigEndian>().unwrap();
+        file.seek(from_start(0x1BFC + 0x3C)).unwrap();
+        file.write_u32::<BigEndian>(0x10000).unwrap();
+        file.write_u32::<BigEndian>(0x10000).unwrap();
+
+        // The grid is skipped with a warning normally, so strict mode is used to see why
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2).with_strict(true);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+        assert!(
+            matches!(&err, ParseError::Strict(inner) if matches!(**inner, ParseError::InvalidOffset("Collision grid has too many cells"))),
+            "{err}"
+        );
+    }
+
+    #[cfg(test)]
+    /// Returns the test stagedef with the first collision header's animation type set, an animation
+    /// ID of 3, a loop point of 60 seconds, and a seesaw and an animation header (with one rotation Y and two position Y
+    /// keyframes at 0xB40) filled in.
+    ///
+    /// Both are written regardless of type, so we can check the type alone decides which is read.
+    fn test_animated_stagedef<T: ByteOrder>(animation_type: u16) -> ParseResult<Cursor<Vec<u8>>> {
+        use byteorder::WriteBytesExt;
+
+        let mut cur = test_smb2_stagedef_header::<T>()?;
+
+        cur.seek(from_start(0x1BFC + 0x12))?;
+        cur.write_u16::<T>(animation_type)?;
+        cur.write_u32::<T>(0x00000B00)?;
+
+        cur.seek(from_start(0x1BFC + 0xB8))?;
+        cur.write_f32::<T>(0.25)?;
+        cur.write_f32::<T>(0.5)?;
+        cur.write_f32::<T>(0.75)?;
+
+        cur.seek(from_start(0x1BFC + 0xA4))?;
+        cur.write_u16::<T>(3)?;
+
+        cur.seek(from_start(0x1BFC + 0xD4))?;
+        cur.write_f32::<T>(60.0)?;
+
+        cur.seek(from_start(0xB08))?;
+        cur.write_u32::<T>(1)?;
+        cur.write_u32::<T>(0x00000B40)?;
+        cur.seek(from_start(0xB20))?;
+        cur.write_u32::<T>(2)?;
+        cur.write_u32::<T>(0x00000B54)?;
+
+        cur.seek(from_start(0xB40))?;
+        for (time, value) in [(0.0, 90.0), (0.0, 0.0), (60.0, 10.0)] {
+            cur.write_u32::<T>(2)?;
+            cur.write_f32::<T>(time)?;
+            cur.write_f32::<T>(value)?;
+            cur.write_f32::<T>(0.0)?;
+            cur.write_f32::<T>(0.0)?;
+        }
+
+        Ok(cur)
+    }
+
+    #[test]
+    fn test_fog_and_mystery_3_parse() {
+        use byteorder::WriteBytesExt;
+
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0xBC)).unwrap();
+        file.write_u32::<BigEndian>(0xE00).unwrap();
+        file.seek(from_start(0xD4)).unwrap();
+        file.write_u32::<BigEndian>(0xE40).unwrap();
+
+        file.seek(from_start(0xE00)).unwrap();
+        file.write_u8(2).unwrap();
+        file.write_u8(1).unwrap();
+        file.write_u16::<BigEndian>(0).unwrap();
+        for value in [50.0, 300.0, 0.5, 0.25, 1.0] {
+            file.write_f32::<BigEndian>(value).unwrap();
+        }
+
+        file.seek(from_start(0xE40)).unwrap();
+        for value in [1.0, 1.0, 1.0] {
+            file.write_f32::<BigEndian>(value).unwrap();
+        }
+        file.write_u32::<BigEndian>(0x12345678).unwrap();
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        let fog = stagedef.fog.unwrap();
+        assert_eq!((fog.fog_type, fog.enabled), (2, 1));
+        assert_eq!((fog.start_distance, fog.end_distance), (50.0, 300.0));
+        assert_eq!(fog.color, Vector3 { x: 0.5, y: 0.25, z: 1.0 });
+
+        let mystery_3 = stagedef.mystery_3.unwrap();
+        assert_eq!(mystery_3.unk0x0, Vector3 { x: 1.0, y: 1.0, z: 1.0 });
+        assert_eq!(mystery_3.unk0xc, 0x12345678);
+        assert_eq!(mystery_3.unk0x10, 0);
+    }
+
+    #[test]
+    fn test_fog_and_mystery_3_missing() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.fog, None);
+        assert_eq!(stagedef.mystery_3, None);
+    }
+
+    #[test]
+    fn test_collision_header_texture_scroll() {
+        use byteorder::WriteBytesExt;
+
+        // The test stagedef doesn't have one
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.collision_headers[0].texture_scroll, None);
+
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0x1BFC + 0xD8)).unwrap();
+        file.write_u32::<BigEndian>(0x2100).unwrap();
+        file.seek(from_start(0x2100)).unwrap();
+        file.write_f32::<BigEndian>(0.5).unwrap();
+        file.write_f32::<BigEndian>(-0.25).unwrap();
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(
+            stagedef.collision_headers[0].texture_scroll,
+            Some(TextureScroll {
+                speed_x: 0.5,
+                speed_y: -0.25
+            })
+        );
+    }
+
+    #[test]
+    fn test_collision_header_initial_rotation() {
+        use byteorder::WriteBytesExt;
+
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0x1BFC + 0xC)).unwrap();
+        for value in [0x4000, 0x8000, 0xC000] {
+            file.write_u16::<BigEndian>(value).unwrap();
+        }
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(
+            stagedef.collision_headers[0].initial_rotation,
+            ShortVector3 {
+                x: 0x4000,
+                y: 0x8000,
+                z: 0xC000
+            }
+        );
+    }
+
+    #[test]
+    fn test_collision_header_no_animation() {
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.collision_headers[0].animation_type, AnimationType::LoopingAnimation);
+        assert_eq!(stagedef.collision_headers[0].animation, CollisionHeaderAnimation::None);
+    }
+
+    #[test]
+    fn test_collision_header_animation_state_init_parse() {
+        use byteorder::WriteBytesExt;
+
+        for (value, expected_state) in [
+            (0, AnimationState::Play),
+            (1, AnimationState::Pause),
+            (2, AnimationState::PlayBackwards),
+            (3, AnimationState::FastForward),
+            (4, AnimationState::FastBackwards),
+        ] {
+            let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+            file.seek(from_start(0x1BFC + 0xCC)).unwrap();
+            file.write_u16::<BigEndian>(value).unwrap();
+
+            let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+            let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+            assert_eq!(stagedef.collision_headers[0].animation_state_init, expected_state);
+        }
+
+        // An unknown state leaves the default, unless parsing strictly
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0x1BFC + 0xCC)).unwrap();
+        file.write_u16::<BigEndian>(0x10).unwrap();
+
+        let mut sd_reader = StageDefReader::new(file.clone(), Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.collision_headers[0].animation_state_init, AnimationState::Play);
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2).with_strict(true);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+        assert!(
+            matches!(&err, ParseError::Strict(inner) if matches!(**inner, ParseError::UnknownEnumValue { value: 0x10, .. })),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_collision_header_seesaw_parse() {
+        let expected_seesaw = SeesawParameters {
+            sensitivity: 0.25,
+            friction: 0.5,
+            spring: 0.75,
+        };
+
+        let file = test_animated_stagedef::<BigEndian>(2).unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.collision_headers[0].animation_type, AnimationType::Seesaw);
+        assert_eq!(
+            stagedef.collision_headers[0].animation,
+            CollisionHeaderAnimation::Seesaw(expected_seesaw)
+        );
+    }
+
+    #[test]
+    fn test_collision_header_keyframed_animation_parse() {
+        let keyframe = |time, value| Keyframe {
+            easing: 2,
+            time,
+            value,
+            in_tangent: 0.0,
+            out_tangent: 0.0,
+        };
+        let expected_animation = AnimationHeader {
+            rotation_y: vec![keyframe(0.0, 90.0)],
+            position_y: vec![keyframe(0.0, 0.0), keyframe(60.0, 10.0)],
+            ..Default::default()
+        };
+
+        for (animation_type_value, animation_type) in
+            [(0, AnimationType::LoopingAnimation), (1, AnimationType::PlayOnceAnimation)]
+        {
+            let file = test_animated_stagedef::<LittleEndian>(animation_type_value).unwrap();
+            let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+            let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+
+            assert_eq!(stagedef.collision_headers[0].animation_type, animation_type);
+            assert_eq!(stagedef.collision_headers[0].animation_loop_point, 60.0);
+            assert_eq!(stagedef.collision_headers[0].animation_id, 3);
+            assert_eq!(stagedef.collision_headers[0].get_animation_duration(), 60.0);
+            assert_eq!(stagedef.collision_headers[0].get_rotation_at(90.0), Some([0.0, 90.0, 0.0]));
+            assert_eq!(
+                stagedef.collision_headers[0].animation,
+                CollisionHeaderAnimation::Keyframed(expected_animation.clone()),
+                "{animation_type:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_collision_header_without_objects() {
+        for (animation_type_value, animation_type) in [(0, AnimationType::LoopingAnimation), (2, AnimationType::Seesaw)] {
+            // Clear the header's collision and every object list, leaving only its animation data
+            let mut file = test_animated_stagedef::<BigEndian>(animation_type_value).unwrap();
+            file.seek(from_start(0x1BFC + 0x24)).unwrap();
+            file.write_all(&[0; 0x8]).unwrap();
+            file.seek(from_start(0x1BFC + 0x44)).unwrap();
+            file.write_all(&[0; 0x58]).unwrap();
+
+            let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+            let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+            assert_eq!(stagedef.collision_headers.len(), 1);
+            let header = &stagedef.collision_headers[0];
+
+            assert!(header.collision_triangles.is_empty());
+            assert!(header.goals.is_empty() && header.bananas.is_empty() && header.level_model_names.is_empty());
+            // The global lists are still there, just not in this header
+            assert_eq!(stagedef.goals.len(), 1);
+
+            assert_eq!(header.animation_type, animation_type);
+            assert_eq!(header.animation_id, 3);
+            assert_eq!(header.animation_loop_point, 60.0);
+            assert_eq!(header.conveyor_vector, Vector3 { x: 1.0, y: 0.0, z: -0.5 });
+            match animation_type {
+                AnimationType::Seesaw => assert!(matches!(header.animation, CollisionHeaderAnimation::Seesaw(_))),
+                _ => assert!(matches!(header.animation, CollisionHeaderAnimation::Keyframed(_))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_collision_header_unknown_animation_type() {
+        let file = test_animated_stagedef::<BigEndian>(0x7).unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        // Unknown types fall back to the default rather than failing the whole stagedef
+        assert_eq!(stagedef.collision_headers.len(), 1);
+        assert_eq!(stagedef.collision_headers[0].animation_type, AnimationType::LoopingAnimation);
+    }
+
+    #[test]
+    fn test_collision_triangle_vertices() {
+        use byteorder::WriteBytesExt;
+
+        let mut cur = Cursor::new(vec![0; 0x40]);
+        // position
+        cur.write_f32::<BigEndian>(1.0).unwrap();
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        // normal
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        cur.write_f32::<BigEndian>(1.0).unwrap();
+        // rotation - 90 degrees around Z
+        cur.write_u16::<BigEndian>(0x0000).unwrap();
+        cur.write_u16::<BigEndian>(0x0000).unwrap();
+        cur.write_u16::<BigEndian>(0x4000).unwrap();
+        cur.write_u16::<BigEndian>(0x0000).unwrap();
+        // deltas
+        cur.write_f32::<BigEndian>(2.0).unwrap();
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        cur.write_f32::<BigEndian>(0.0).unwrap();
+        cur.write_f32::<BigEndian>(2.0).unwrap();
+
+        cur.seek(from_start(0x0)).unwrap();
+        let triangle = CollisionTriangle::try_from_reader::<_, BigEndian>(&mut cur).unwrap();
+        let vertices = triangle.get_vertices();
+
+        let expected = [[1.0, 0.0, 0.0], [1.0, 2.0, 0.0], [-1.0, 0.0, 0.0]];
+        for (vertex, expected) in vertices.iter().zip(expected.iter()) {
+            assert!((vertex.x - expected[0]).abs() < 0.0001, "{vertex}");
+            assert!((vertex.y - expected[1]).abs() < 0.0001, "{vertex}");
+            assert!((vertex.z - expected[2]).abs() < 0.0001, "{vertex}");
+        }
+    }
+
+    #[test]
+    fn test_smb2_file_header_fields() {
+        let fields = get_smb2_file_header_fields();
+        assert_eq!(fields[0], ("magic_number_1", 0x0..0x4));
+        assert!(fields.contains(&("goal_list", 0x18..0x20)));
+        assert!(fields.iter().all(|(_, range)| range.end <= SMB2_FILE_HEADER_SIZE as usize));
+
+        // Sorted, and no two fields share bytes
+        for pair in fields.windows(2) {
+            assert!(pair[0].1.end <= pair[1].1.start, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn test_fallout_volume_layout() {
+        #[rustfmt::skip]
+        let bytes = [
+            // Position (0, -20, 5)
+            0x00, 0x00, 0x00, 0x00, 0xC1, 0xA0, 0x00, 0x00, 0x40, 0xA0, 0x00, 0x00,
+            // Size (200, 10, 100)
+            0x43, 0x48, 0x00, 0x00, 0x41, 0x20, 0x00, 0x00, 0x42, 0xC8, 0x00, 0x00,
+            // Rotation
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00,
+            // Padding
+            0x00, 0x00,
+        ];
+        assert_eq!(bytes.len() as u32, FalloutVolume::get_size());
+
+        let volume = FalloutVolume::try_from_reader::<_, BigEndian>(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(volume.position, Vector3 { x: 0.0, y: -20.0, z: 5.0 });
+        assert_eq!(volume.size, Vector3 { x: 200.0, y: 10.0, z: 100.0 });
+        assert_eq!(volume.rotation, ShortVector3 { x: 0, y: 0x4000, z: 0 });
+        assert_eq!(volume.unk0x1e, 0);
+    }
+
+    #[test]
+    fn test_misaligned_local_list() {
+        use byteorder::WriteBytesExt;
+
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        // Make the second global banana's X position double as the type of a banana read from 4
+        // bytes into the global list
+        file.seek(from_start(0x8D8)).unwrap();
+        file.write_u32::<BigEndian>(0x00000001).unwrap();
+        // Point the collision header's banana list 4 bytes into the global list
+        file.seek(from_start(0x1BFC + 0x5C)).unwrap();
+        file.write_u32::<BigEndian>(1).unwrap();
+        file.write_u32::<BigEndian>(0x8CC).unwrap();
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.bananas.len(), 7);
+
+        // Read directly, rather than handing back the first global banana
+        let bananas = &stagedef.collision_headers[0].bananas;
+        assert_eq!(bananas.len(), 1);
+        assert_eq!(bananas[0].global_index, None);
+        assert!(!std::sync::Arc::ptr_eq(&bananas[0].object, &stagedef.bananas[0].object));
+        let banana = bananas[0].object.lock().unwrap();
+        assert_eq!(banana.position, Vector3 { x: 1.2, y: -102.0, z: 0.0 });
+        assert_eq!(banana.banana_type, BananaType::Bunch);
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        use byteorder::WriteBytesExt;
+
+        // The first global banana has a type that doesn't exist
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0x8C8 + 0xC)).unwrap();
+        file.write_u32::<BigEndian>(0xFF).unwrap();
+
+        // Normally it's skipped, and the rest of the stage is still read
+        let mut sd_reader = StageDefReader::new(file.clone(), Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.bananas.len(), 6);
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2).with_strict(true);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+        assert!(
+            matches!(&err, ParseError::Strict(inner) if matches!(**inner, ParseError::UnknownEnumValue { value: 0xFF, .. })),
+            "{err}"
+        );
+
+        // A collision header list that isn't part of the global list is read on its own normally
+        let mut file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        file.seek(from_start(0x1BFC + 0x5C)).unwrap();
+        file.write_u32::<BigEndian>(1).unwrap();
+        file.write_u32::<BigEndian>(0x8CC).unwrap();
+
+        let mut sd_reader = StageDefReader::new(file.clone(), Game::SMB2);
+        assert!(sd_reader.read_stagedef::<BigEndian>().is_ok());
+
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2).with_strict(true);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+        assert!(matches!(err, ParseError::Strict(_)), "{err}");
+    }
+
+    #[test]
+    fn test_collision_header_unknowns_preserved() {
+        let expected_unknowns = CollisionHeaderUnknowns {
+            unk0x9c: 0x12345678,
+            unk0xa0: 0x9ABCDEF0,
+            unk0xa6: 0xBEEF,
+            unk0xb0: 0xCAFEBABE,
+            unk0xd0: 0x0BADF00D,
+        };
+
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+        assert_eq!(stagedef.collision_headers[0].unknowns, expected_unknowns, "BigEndian");
+
+        let file = test_smb2_stagedef_header::<LittleEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<LittleEndian>().unwrap();
+        assert_eq!(stagedef.collision_headers[0].unknowns, expected_unknowns, "LittleEndian");
+    }
+
+    #[test]
+    fn test_level_model_instance_parse() {
+        let expected_instance = LevelModelInstance {
+            model_name: String::from("TEST_MODEL"),
+            position: Position(Vector3 { x: 1.0, y: 2.0, z: 3.0 }),
+            rotation: ShortVector3 { x: 0, y: 0, z: 0 },
+            scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+        };
+
+        let file = test_smb2_stagedef_header::<BigEndian>().unwrap();
+        let mut sd_reader = StageDefReader::new(file, Game::SMB2);
+        let stagedef = sd_reader.read_stagedef::<BigEndian>().unwrap();
+
+        assert_eq!(stagedef.level_model_instances.len(), 1);
+        assert_eq!(*stagedef.level_model_instances[0].object.lock().unwrap(), expected_instance);
+
+        let col_header = &stagedef.collision_headers[0];
+        assert_eq!(col_header.level_model_instances.len(), 1);
+        assert_eq!(col_header.level_model_instances[0].object.lock().unwrap().model_name, "TEST_MODEL");
+        assert_eq!(col_header.level_model_names, vec!["TEST_MODEL"; 3]);
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let mut sd_reader = StageDefReader::new(Cursor::new(Vec::new()), Game::SMB2);
+        assert!(matches!(
+            sd_reader.read_stagedef::<BigEndian>(),
+            Err(ParseError::FileTooSmall {
+                size: 0,
+                minimum_size: 0x89C
+            })
+        ));
+    }
+
+    #[test]
+    fn test_tiny_file() {
+        let mut sd_reader = StageDefReader::new(Cursor::new(vec![0; 0x10]), Game::SMB2);
+        let err = sd_reader.read_stagedef::<BigEndian>().unwrap_err();
+
+        assert!(matches!(
+            err,
+            ParseError::FileTooSmall {
+                size: 0x10,
+                minimum_size: 0x89C
+            }
+        ));
+        assert!(err.to_string().starts_with("File too small to be a valid stagedef"));
+    }
+
+    #[test]
+    fn test_background_model_truncated() {
+        let mut file = Cursor::new(vec![0; 0x20]);
+        let bg_model = BackgroundModel::try_from_reader::<_, BigEndian>(&mut file);
+
+        assert!(matches!(bg_model, Err(ParseError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn test_unknown_banana_type() {
+        use byteorder::WriteBytesExt;
+
+        let mut file = Cursor::new(vec![0; 0x10]);
+        file.seek(from_start(0xC)).unwrap();
+        file.write_u32::<BigEndian>(0x2).unwrap();
+        file.seek(from_start(0x0)).unwrap();
+
+        let banana = Banana::try_from_reader::<_, BigEndian>(&mut file);
+        assert!(matches!(
+            banana,
+            Err(ParseError::UnknownEnumValue {
+                type_name: "banana type",
+                value: 0x2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_relative_offset_near_u32_max() {
+        let max = u64::from(u32::MAX);
+        assert_eq!(from_relative(from_start(max - 0x10), 0x10).unwrap(), from_start(max));
+        assert!(matches!(
+            from_relative(from_start(max - 0x10), 0x11),
+            Err(ParseError::InvalidOffset(_))
+        ));
+        assert!(matches!(from_relative(from_start(u64::MAX), 0x1), Err(ParseError::InvalidOffset(_))));
+        assert!(matches!(from_relative(SeekFrom::Current(0), 0x1), Err(ParseError::InvalidOffset(_))));
+
+        // A collision header right at the end of the offset range can't have its fields addressed
+        assert!(StageDefCollisionHeaderFormat::new(Game::SMB2, from_start(max - 0x10)).is_err());
+    }
+
+    #[test]
+    fn test_offset_difference_near_u32_max() {
+        let max = u64::from(u32::MAX);
+        assert_eq!(try_get_offset_difference(&from_start(max), &from_start(0)).unwrap(), u32::MAX);
+        assert!(matches!(
+            try_get_offset_difference(&from_start(max + 1), &from_start(0)),
+            Err(ParseError::InvalidOffset(_))
+        ));
+        assert!(matches!(
+            try_get_offset_difference(&SeekFrom::End(0), &from_start(0)),
+            Err(ParseError::InvalidOffset(_))
+        ));
+    }
+
+    #[test]
+    fn test_negative_offset_difference() {
+        assert_eq!(try_get_offset_difference(&from_start(0x20), &from_start(0x10)).unwrap(), 0x10);
+        assert!(matches!(
+            try_get_offset_difference(&from_start(0x10), &from_start(0x20)),
+            Err(ParseError::InvalidOffset(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_into_anyhow() {
+        let mut file = Cursor::new(vec![0; 0x4]);
+        let err: anyhow::Error = Banana::try_from_reader::<_, BigEndian>(&mut file).unwrap_err().into();
+
+        assert!(matches!(err.downcast_ref::<ParseError>(), Some(ParseError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn test_check_read_size_mismatch() {
+        let mut file = Cursor::new(vec![0; 0x40]);
+        file.seek(from_start(0x30)).unwrap();
+
+        assert!(file.check_read_size(0x0, 0x30, "BG Model").is_ok());
+        assert!(matches!(
+            file.check_read_size(0x0, 0x38, "BG Model"),
+            Err(ParseError::SizeMismatch {
+                expected_end: 0x38,
+                actual_end: 0x30,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn element_size_test() {
         assert_eq!(true, true);
@@ -0,0 +1,192 @@
+//! Placing new objects in a [``StageDef``] by clicking on its collision in the viewport.
+use super::common::*;
+use super::objects::*;
+
+/// How many of the most recently placed kinds of object the palette keeps at hand.
+const RECENT_KIND_COUNT: usize = 3;
+
+/// The kinds of object that can be placed at a point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceableKind {
+    Goal,
+    Banana,
+    Bumper,
+    Jamabar,
+}
+
+impl PlaceableKind {
+    pub const ALL: [PlaceableKind; 4] = [
+        PlaceableKind::Goal,
+        PlaceableKind::Banana,
+        PlaceableKind::Bumper,
+        PlaceableKind::Jamabar,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            PlaceableKind::Goal => Goal::get_name(),
+            PlaceableKind::Banana => Banana::get_name(),
+            PlaceableKind::Bumper => Bumper::get_name(),
+            PlaceableKind::Jamabar => Jamabar::get_name(),
+        }
+    }
+}
+
+impl StageDef {
+    /// Adds a new object of the given kind at ``position`` to the end of its global list, and
+    /// returns its index there.
+    ///
+    /// New objects aren't rotated, use the most common type of their kind (blue goals and single
+    /// bananas), and are placed at their normal size.
+    pub fn place_object(&mut self, kind: PlaceableKind, position: Vector3) -> u32 {
+        let unscaled = Vector3 { x: 1.0, y: 1.0, z: 1.0 };
+        match kind {
+            PlaceableKind::Goal => Self::push_placed_object(
+                &mut self.goals,
+                Goal {
+                    position: position.into(),
+                    rotation: ShortVector3::default(),
+                    goal_type: GoalType::Blue,
+                },
+            ),
+            PlaceableKind::Banana => Self::push_placed_object(
+                &mut self.bananas,
+                Banana {
+                    position: position.into(),
+                    banana_type: BananaType::Single,
+                },
+            ),
+            PlaceableKind::Bumper => Self::push_placed_object(
+                &mut self.bumpers,
+                Bumper {
+                    position: position.into(),
+                    rotation: ShortVector3::default(),
+                    scale: unscaled,
+                },
+            ),
+            PlaceableKind::Jamabar => Self::push_placed_object(
+                &mut self.jamabars,
+                Jamabar {
+                    position: position.into(),
+                    rotation: ShortVector3::default(),
+                    scale: unscaled,
+                },
+            ),
+        }
+    }
+
+    fn push_placed_object<T>(list: &mut Vec<GlobalStagedefObject<T>>, object: T) -> u32 {
+        Self::push_global_object(list, object);
+        list.len() as u32 - 1
+    }
+}
+
+/// The kind of object that clicking in the viewport places, along with the kinds placed most
+/// recently so they're quick to pick again.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct PlacementPalette {
+    /// ``None`` when clicking in the viewport picks collision triangles instead.
+    pub selected: Option<PlaceableKind>,
+    /// The most recently placed kind comes first.
+    pub recent: Vec<PlaceableKind>,
+}
+
+impl PlacementPalette {
+    /// Places an object of the selected kind at ``position``, if a kind is selected, and moves
+    /// that kind to the front of the recently used ones. Returns whether anything was placed.
+    pub fn place(&mut self, stagedef: &mut StageDef, position: Vector3) -> bool {
+        let Some(kind) = self.selected else {
+            return false;
+        };
+        stagedef.place_object(kind, position);
+
+        self.recent.retain(|recent_kind| *recent_kind != kind);
+        self.recent.insert(0, kind);
+        self.recent.truncate(RECENT_KIND_COUNT);
+        true
+    }
+}
+
+#[cfg(feature = "gui")]
+impl PlacementPalette {
+    /// Shows the recently placed kinds as toggles, followed by a menu of every kind. Clicking a
+    /// selected kind again goes back to picking collision.
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        for kind in self.recent.clone() {
+            self.show_kind_toggle(kind, ui);
+        }
+        ui.menu_button("Place", |ui| {
+            for kind in PlaceableKind::ALL {
+                if self.show_kind_toggle(kind, ui).clicked() {
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("Pick a kind of object, then click on collision in the viewport to place one (Esc to stop)");
+    }
+
+    fn show_kind_toggle(&mut self, kind: PlaceableKind, ui: &mut egui::Ui) -> egui::Response {
+        let is_selected = self.selected == Some(kind);
+        let response = ui.selectable_label(is_selected, kind.get_name());
+        if response.clicked() {
+            self.selected = (!is_selected).then_some(kind);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_place_object_at_point() {
+        let mut stagedef = StageDef::default().with_goal(Goal::default());
+        let point = Vector3 { x: 1.0, y: 2.5, z: -3.0 };
+
+        assert_eq!(stagedef.place_object(PlaceableKind::Goal, point), 1);
+        let goal = stagedef.goals[1].object.lock().unwrap().clone();
+        assert_eq!(
+            goal,
+            Goal {
+                position: point.into(),
+                rotation: ShortVector3::default(),
+                goal_type: GoalType::Blue,
+            }
+        );
+        assert_eq!(stagedef.goals[1].index, 1);
+
+        assert_eq!(stagedef.place_object(PlaceableKind::Bumper, point), 0);
+        let bumper = stagedef.bumpers[0].object.lock().unwrap().clone();
+        assert_eq!(bumper.position, point);
+        assert_eq!(bumper.scale, Vector3 { x: 1.0, y: 1.0, z: 1.0 });
+    }
+
+    #[test]
+    fn test_palette_recent_kinds() {
+        let mut stagedef = StageDef::default();
+        let mut palette = PlacementPalette::default();
+
+        // Nothing is placed until a kind is picked
+        assert!(!palette.place(&mut stagedef, Vector3::default()));
+        assert!(stagedef.goals.is_empty());
+
+        for kind in [
+            PlaceableKind::Goal,
+            PlaceableKind::Banana,
+            PlaceableKind::Goal,
+            PlaceableKind::Bumper,
+            PlaceableKind::Jamabar,
+        ] {
+            palette.selected = Some(kind);
+            assert!(palette.place(&mut stagedef, Vector3::default()));
+        }
+        assert_eq!(
+            palette.recent,
+            [PlaceableKind::Jamabar, PlaceableKind::Bumper, PlaceableKind::Goal]
+        );
+        assert_eq!(stagedef.goals.len(), 2);
+        assert_eq!(stagedef.bananas.len(), 1);
+    }
+}
@@ -0,0 +1,197 @@
+//! A handful of objects taken out of a [``StageDef``], for sharing between stages as JSON.
+use super::common::*;
+use super::objects::*;
+use thiserror::Error;
+
+/// Why JSON from the clipboard couldn't be turned into objects.
+#[derive(Debug, Error)]
+pub enum PasteError {
+    #[error("The clipboard doesn't contain valid object JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    /// The ``type`` field didn't name any kind of object we know about.
+    #[error("Unknown object type \"{0}\"")]
+    UnknownType(String),
+    /// The JSON was valid, but there weren't any objects in it.
+    #[error("The clipboard JSON doesn't contain any objects")]
+    Empty,
+}
+
+/// Copies of some of the objects in a [``StageDef``], without the stagedef around them.
+///
+/// Each list holds the objects in the order they were added, so they're indexed from 0 no matter
+/// where they were in the stagedef they came from.
+#[derive(Default, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct StageSnippet {
+    pub goals: Vec<Goal>,
+    pub bumpers: Vec<Bumper>,
+    pub jamabars: Vec<Jamabar>,
+    pub bananas: Vec<Banana>,
+    pub cone_collisions: Vec<ConeCollision>,
+    pub sphere_collisions: Vec<SphereCollision>,
+    pub cylinder_collisions: Vec<CylinderCollision>,
+    pub fallout_volumes: Vec<FalloutVolume>,
+    pub background_models: Vec<BackgroundModel>,
+    pub level_model_instances: Vec<LevelModelInstance>,
+    pub reflective_models: Vec<ReflectiveModel>,
+}
+
+impl StageSnippet {
+    /// Adds a copy of the given object to the end of its list.
+    pub fn add<T: StageDefObjectList + Clone>(&mut self, object: &T) {
+        T::get_snippet_list(self).push(object.clone());
+    }
+
+    /// Returns how many objects there are in the snippet, across every list.
+    pub fn len(&self) -> usize {
+        macro_rules! total_len {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                [$(self.$list.len()),*].into_iter().sum()
+            };
+        }
+        stagedef_object_lists!(total_len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Reads objects pasted from the clipboard. This is either a single object with a ``type``
+    /// field, as copied with [``get_tagged_object_json``], or a whole snippet.
+    pub fn from_clipboard_json(json: &str) -> Result<Self, PasteError> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let type_name = match value.as_object_mut().and_then(|object| object.remove("type")) {
+            Some(serde_json::Value::String(type_name)) => type_name,
+            Some(type_name) => return Err(PasteError::UnknownType(type_name.to_string())),
+            None => {
+                let snippet: Self = serde_json::from_value(value)?;
+                return if snippet.is_empty() { Err(PasteError::Empty) } else { Ok(snippet) };
+            }
+        };
+
+        let mut snippet = Self::default();
+        macro_rules! add_json_to_named_list {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                $(
+                    if snippet.add_json_if_named::<$object>(&type_name, &value)? {
+                        return Ok(snippet);
+                    }
+                )*
+            };
+        }
+        stagedef_object_lists!(add_json_to_named_list);
+        Err(PasteError::UnknownType(type_name))
+    }
+
+    /// Adds ``value`` to the snippet as a ``T`` if ``type_name`` is the name of ``T``, returning
+    /// whether it was.
+    fn add_json_if_named<T>(&mut self, type_name: &str, value: &serde_json::Value) -> serde_json::Result<bool>
+    where
+        T: StageDefObject + StageDefObjectList + serde::de::DeserializeOwned,
+    {
+        if type_name != T::get_name() {
+            return Ok(false);
+        }
+        T::get_snippet_list(self).push(T::deserialize(value)?);
+        Ok(true)
+    }
+}
+
+impl StageDef {
+    /// Adds every object in the snippet to the end of the matching global list. The objects aren't
+    /// added to any collision header.
+    pub fn import_snippet(&mut self, snippet: StageSnippet) {
+        fn append<T: StageDefObjectList>(stagedef: &mut StageDef, objects: Vec<T>) {
+            for object in objects {
+                StageDef::push_global_object(T::get_global_list(stagedef), object);
+            }
+        }
+
+        macro_rules! append_lists {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                $(append(self, snippet.$list);)*
+            };
+        }
+        stagedef_object_lists!(append_lists);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_export_and_import_snippet() {
+        let mut source = StageDef::default();
+        for z in 0..5 {
+            source = source.with_goal(Goal::at_z(z as f32));
+        }
+
+        // Export the 2nd and 4th goals
+        let mut snippet = StageSnippet::default();
+        for goal in [&source.goals[1], &source.goals[3]] {
+            snippet.add(&*goal.object.lock().unwrap());
+        }
+        let json = get_object_json(&snippet).unwrap();
+
+        let mut destination = StageDef::default().with_goal(Goal::at_z(-1.0));
+        destination.import_snippet(StageSnippet::from_json(&json).unwrap());
+
+        let goals: Vec<_> = destination
+            .goals
+            .iter()
+            .map(|goal| (goal.index, goal.object.lock().unwrap().position.z))
+            .collect();
+        assert_eq!(goals, [(0, -1.0), (1, 1.0), (2, 3.0)]);
+        assert!(destination.bananas.is_empty());
+    }
+
+    #[test]
+    fn test_paste_goal_json() {
+        let json = r#"{
+            "type": "Goal",
+            "position": { "x": 1.5, "y": -2.0, "z": 10.0 },
+            "rotation": { "x": 0, "y": 32768, "z": 0 },
+            "goal_type": "Red"
+        }"#;
+        let snippet = StageSnippet::from_clipboard_json(json).unwrap();
+        assert_eq!(
+            snippet.goals,
+            [Goal {
+                position: Position(Vector3 { x: 1.5, y: -2.0, z: 10.0 }),
+                rotation: ShortVector3 { x: 0, y: 0x8000, z: 0 },
+                goal_type: GoalType::Red,
+            }]
+        );
+        assert_eq!(snippet.len(), 1);
+
+        // Objects copied with their type round trip
+        let goal = Goal::at_z(4.0);
+        let snippet = StageSnippet::from_clipboard_json(&get_tagged_object_json(&goal).unwrap()).unwrap();
+        assert_eq!(snippet.goals, [goal]);
+
+        // Pasting appends with the next index
+        let mut stagedef = StageDef::default().with_goal(Goal::at_z(-1.0));
+        stagedef.import_snippet(snippet);
+        assert_eq!(stagedef.goals[1].index, 1);
+    }
+
+    #[test]
+    fn test_paste_invalid_json() {
+        assert!(matches!(StageSnippet::from_clipboard_json("{ not json"), Err(PasteError::Json(_))));
+        assert!(matches!(
+            StageSnippet::from_clipboard_json(r#"{ "type": "Spaceship" }"#),
+            Err(PasteError::UnknownType(type_name)) if type_name == "Spaceship"
+        ));
+        // A known type with the wrong fields
+        assert!(matches!(
+            StageSnippet::from_clipboard_json(r#"{ "type": "Goal", "position": 5 }"#),
+            Err(PasteError::Json(_))
+        ));
+        assert!(matches!(StageSnippet::from_clipboard_json("{}"), Err(PasteError::Empty)));
+    }
+}
@@ -0,0 +1,66 @@
+//! A short overview of a [``StageDef``], for tools that want to know what's in a stage without
+//! handling every object.
+use super::common::*;
+use super::objects::*;
+use std::collections::BTreeMap;
+
+/// What a [``StageDef``] contains, without the objects themselves.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct StageSummary {
+    pub magic_numbers: (f32, f32),
+    pub start_position: Vector3,
+    pub fallout_level: f32,
+    pub collision_header_count: usize,
+    /// How many objects are in each global list, by object name.
+    pub object_counts: BTreeMap<&'static str, usize>,
+    /// Every model the stagedef refers to, in alphabetical order.
+    pub model_names: Vec<String>,
+}
+
+impl StageDef {
+    pub fn get_summary(&self) -> StageSummary {
+        macro_rules! object_counts {
+            ($($object:ty => $list:ident),* $(,)?) => {
+                [$((<$object>::get_name(), self.$list.len())),*]
+            };
+        }
+        let object_counts = stagedef_object_lists!(object_counts).into_iter().collect();
+
+        let mut model_names: Vec<String> = self.model_names.iter().cloned().collect();
+        model_names.sort();
+
+        StageSummary {
+            magic_numbers: (self.magic_number_1, self.magic_number_2),
+            start_position: self.start_position.position.0,
+            fallout_level: self.fallout_level,
+            collision_header_count: self.collision_headers.len(),
+            object_counts,
+            model_names,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summary_json() {
+        let stagedef = StageDef {
+            magic_number_2: 1000.0,
+            fallout_level: -20.0,
+            ..Default::default()
+        }
+        .with_goal(Goal::default())
+        .with_goal(Goal::default());
+
+        let summary = stagedef.get_summary();
+        assert_eq!(summary.object_counts[Goal::get_name()], 2);
+        assert_eq!(summary.object_counts[Banana::get_name()], 0);
+
+        let json: serde_json::Value = serde_json::from_str(&get_object_json(&summary).unwrap()).unwrap();
+        assert_eq!(json["fallout_level"], -20.0);
+        assert_eq!(json["magic_numbers"][1], 1000.0);
+        assert_eq!(json["object_counts"]["Goal"], 2);
+    }
+}
@@ -1,12 +1,211 @@
+use super::autosave::StageAutosave;
 use super::common::*;
-use egui::{Id, Ui};
+use super::objects::{CollisionHeader, Fog, Mystery3, StartPosition, TextureScroll};
+use super::placement::PlacementPalette;
+use super::snippet::StageSnippet;
+use super::units::UnitSettings;
+use super::validation::ValidationSettings;
+use crate::hex_viewer::{self, HexViewer};
+use crate::minimap::Minimap;
+use crate::renderer::{CollisionHit, CollisionRenderSettings, GizmoAxis, GizmoSettings, LayerSettings, LodSettings};
+use egui::{Color32, Id, RichText, Ui};
 use std::collections::HashSet;
+use std::ops::Range;
+use tracing::warn;
 
-type Inspectable<'a> = (&'a mut (dyn EguiInspect), String, &'static str);
+/// A selected tree item, shown in the inspector.
+pub struct Inspectable<'a> {
+    pub field: &'a mut (dyn EguiInspect),
+    pub label: String,
+    pub description: &'static str,
+    /// The indices of the collision headers that refer to this object, if it's in a global list.
+    pub referencing_headers: Option<Vec<usize>>,
+    /// Where this object is relative to the start position, if it has a position.
+    pub relative_position: Option<Vector3>,
+    /// The bytes this object was read from, if it was read from the file.
+    pub file_range: Option<Range<usize>>,
+}
+
+impl Inspectable<'_> {
+    /// Shows where this object is relative to the start position, if it has a position.
+    pub fn show_relative_position(&self, ui: &mut Ui) {
+        if let Some(relative_position) = &self.relative_position {
+            Position(*relative_position).inspect("Relative to start", ui);
+        }
+    }
+
+    /// Shows the bytes this object was read from as a hex dump, if it was read from ``buffer``.
+    pub fn show_raw_bytes(&self, ui: &mut Ui, buffer: &[u8]) {
+        let Some(file_range) = &self.file_range else {
+            return;
+        };
+
+        ui.weak(format!("Raw bytes at {:#X}", file_range.start));
+        match buffer.get(file_range.clone()) {
+            Some(bytes) => hex_viewer::show_inline(ui, bytes),
+            None => {
+                ui.colored_label(ui.visuals().warn_fg_color, "⚠ Past the end of the file");
+            }
+        }
+    }
+
+    /// Lists the collision headers that refer to this object, if it's in a global list.
+    pub fn show_references(&self, ui: &mut Ui) {
+        let Some(referencing_headers) = &self.referencing_headers else {
+            return;
+        };
+
+        egui::CollapsingHeader::new(format!("References ({})", referencing_headers.len()))
+            .id_source(("references", &self.label))
+            .show(ui, |ui| {
+                if referencing_headers.is_empty() {
+                    ui.weak("No collision header refers to this object");
+                }
+                for header_index in referencing_headers {
+                    ui.label(format!("Collision Header {}", header_index + 1));
+                }
+            });
+    }
+}
+
+/// Removes one object from a stagedef, along with every reference to it.
+type ObjectRemoval = Box<dyn FnOnce(&mut StageDef)>;
+
+/// Moves one object to a new position in its list.
+type ObjectMove = Box<dyn FnOnce(&mut StageDef)>;
+
+/// Calls the given function with the position of one object, which is shared with the stagedef, so
+/// it can be read or moved.
+pub type PositionAccess = Box<dyn Fn(&mut dyn FnMut(&mut Vector3))>;
+
+/// An object being moved along one axis by dragging an arrow of the translate gizmo.
+#[derive(Clone, Copy, Debug)]
+pub struct TranslateDrag {
+    pub axis: GizmoAxis,
+    /// Where the object was when the drag started.
+    pub start_position: Vector3,
+    /// How far along the axis, from where the object started, the arrow was grabbed.
+    pub start_parameter: f32,
+}
+
+/// A tree element being dragged to a new position in its list.
+#[derive(Clone, Copy, Debug)]
+struct TreeDrag {
+    /// The list the element is in, identified like [``PendingSelection::List``].
+    list_id: Id,
+    from_index: usize,
+}
+
+/// Deleting more selected objects than this at once asks for confirmation first.
+const DELETE_CONFIRMATION_THRESHOLD: usize = 10;
+
+/// Objects to select all of at once, replacing the current selection.
+///
+/// Tree items only get their ids when they're drawn, so this waits until the next time the tree
+/// is, opening any lists it needs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PendingSelection {
+    /// Every object in one list in the tree, identified by the id made from its header.
+    List(Id),
+    /// Every object in the stagedef's global list of the type with this name.
+    Type(&'static str),
+    /// Every object in every global list.
+    All,
+}
+
+impl PendingSelection {
+    /// Returns whether every object in the given list should be selected.
+    fn includes_list(&self, list_id: Id, type_name: &str, is_global: bool) -> bool {
+        match self {
+            PendingSelection::List(id) => *id == list_id,
+            PendingSelection::Type(name) => is_global && *name == type_name,
+            PendingSelection::All => is_global,
+        }
+    }
+
+    /// Returns whether this selects objects in the stagedef's global lists, which may be hidden in a
+    /// collapsed header.
+    fn includes_global_lists(&self) -> bool {
+        matches!(self, PendingSelection::Type(_) | PendingSelection::All)
+    }
+}
 
 #[derive(Default)]
 pub struct StageDefInstanceUiState {
     pub selected_tree_items: HashSet<Id>,
+    pub collision_render_settings: CollisionRenderSettings,
+    pub layer_settings: LayerSettings,
+    pub lod_settings: LodSettings,
+    pub gizmo_settings: GizmoSettings,
+    pub show_hex_viewer: bool,
+    pub hex_viewer: HexViewer,
+    /// Shows the start of the file with the known header fields marked.
+    pub show_raw_header: bool,
+    /// Which unit distances are shown in, in the inspector.
+    pub unit_settings: UnitSettings,
+    pub show_minimap: bool,
+    pub minimap: Minimap,
+    pub show_validation: bool,
+    pub validation_settings: ValidationSettings,
+    /// Whether a click or drag that started in the inspector is still going, so the edits it makes
+    /// are validated even once the pointer leaves the inspector.
+    pub is_pointer_down_in_inspector: bool,
+    pub show_debug_overlay: bool,
+    /// What clicking on collision in the viewport places, if anything.
+    pub placement_palette: PlacementPalette,
+    /// The collision triangle last clicked in the viewport, shown in the inspector.
+    pub picked_collision_triangle: Option<CollisionHit>,
+    /// The bytes of the most recently selected object in the file, if known.
+    pub highlighted_byte_range: Option<Range<usize>>,
+    /// Where the animation preview timeline is, in seconds.
+    pub animation_time: f32,
+    /// Copies of the selected objects shown in the tree, rebuilt every time the tree is drawn.
+    pub selection_snippet: StageSnippet,
+    /// The selected objects already recorded this frame, so objects shown in both a global and a
+    /// local list are only recorded once.
+    selected_objects: HashSet<usize>,
+    /// How to remove each selected object shown in the tree, rebuilt every time the tree is drawn.
+    /// Only objects in lists are included, as nothing else in a stagedef can be deleted.
+    selected_object_removals: Vec<ObjectRemoval>,
+    /// How to get at the position of each selected object that has one, rebuilt every time the tree
+    /// is drawn.
+    selected_positions: Vec<PositionAccess>,
+    /// The translate gizmo arrow being dragged, if any.
+    pub translate_drag: Option<TranslateDrag>,
+    /// Whether we're waiting for the user to confirm deleting a large selection.
+    pub is_confirming_deletion: bool,
+    /// Whether we're waiting for the user to confirm reloading the file over their edits.
+    pub is_confirming_reload: bool,
+    /// Why the last reload failed, until it's dismissed.
+    pub reload_error: Option<String>,
+    /// An autosave of this file from a previous session, until the user restores or discards it.
+    pub found_autosave: Option<StageAutosave>,
+    /// Whether the user discarded the autosave, so it should be removed from wherever it's kept.
+    pub should_clear_autosave: bool,
+    /// Objects the user asked to delete, removed from the stagedef before the next frame is drawn.
+    pub pending_deletion: Option<ObjectRemoval>,
+    /// Objects to select the next time the tree is drawn.
+    pub pending_selection: Option<PendingSelection>,
+    /// The pending selection being applied while the tree is drawn this frame.
+    applying_selection: Option<PendingSelection>,
+    /// Which collision headers refer to each object, found again every time the tree is drawn.
+    collision_header_references: CollisionHeaderReferences,
+    /// Where the start position is, found again every time the tree is drawn.
+    start_position: Vector3,
+    /// Which game the stagedef shown in the tree is from, for finding the bytes each object was
+    /// read from.
+    game: Game,
+    /// Whether the inspector also shows object positions relative to the start position.
+    pub show_relative_positions: bool,
+    /// Whether the inspector also shows the raw bytes each object was read from.
+    pub show_raw_bytes: bool,
+    /// The tree element being dragged, if any.
+    tree_drag: Option<TreeDrag>,
+    /// An object the user dragged to a new position in its list, moved before the next frame is
+    /// drawn.
+    pub pending_move: Option<ObjectMove>,
+    /// Why the last JSON pasted into the tree couldn't be added, until it's dismissed.
+    pub paste_error: Option<String>,
 }
 
 impl StageDefInstanceUiState {
@@ -15,10 +214,12 @@ impl StageDefInstanceUiState {
         field: &'a mut T,
         inspector_label: &'static str,
         inspector_label_index: Option<usize>,
+        global_index: Option<u32>,
         inspector_description: &'static str,
+        text_color: Option<Color32>,
         inspectables: &mut Vec<Inspectable<'a>>,
         ui: &mut Ui,
-    ) {
+    ) -> (bool, egui::Response) {
         let modifiers = ui.ctx().input().modifiers;
         let selected = &mut self.selected_tree_items;
         let shift_pushed = modifiers.shift;
@@ -27,13 +228,20 @@ impl StageDefInstanceUiState {
         let next_id = ui.next_auto_id();
         let is_selected = selected.contains(&next_id);
 
-        let formatted_label = match inspector_label_index {
-            Some(i) => format!("{inspector_label} {}: {}", i + 1, field.to_string()),
-            None => format!("{inspector_label}: {}", field.to_string()),
+        let formatted_label = match (inspector_label_index, global_index) {
+            (Some(i), Some(g)) => format!("{inspector_label} {} (global #{}): {}", i + 1, g + 1, field.to_string()),
+            (Some(i), None) => format!("{inspector_label} {}: {}", i + 1, field.to_string()),
+            (None, _) => format!("{inspector_label}: {}", field.to_string()),
         };
 
+        let mut label_text = RichText::new(&formatted_label);
+        if let Some(color) = text_color {
+            label_text = label_text.color(color);
+        }
+
         // TODO: Implement proper multi-selection when Shift is held
-        if ui.selectable_label(is_selected, &formatted_label).clicked() {
+        let response = ui.selectable_label(is_selected, label_text);
+        if response.clicked() {
             // Allow selecting individual elements
             if !modifier_pushed {
                 selected.clear();
@@ -47,22 +255,52 @@ impl StageDefInstanceUiState {
         }
 
         if is_selected {
-            inspectables.push((field, formatted_label, inspector_description));
+            inspectables.push(Inspectable {
+                field,
+                label: formatted_label,
+                description: inspector_description,
+                referencing_headers: None,
+                relative_position: None,
+                file_range: None,
+            });
         }
+
+        (is_selected, response)
     }
 
     pub fn display_tree_and_inspector<'a>(
         &mut self,
         stagedef: &'a mut StageDef,
+        game: Game,
         inspectables: &mut Vec<Inspectable<'a>>,
         ui: &mut Ui,
     ) {
-        egui::CollapsingHeader::new("Stagedef").show(ui, |ui| {
+        self.game = game;
+        self.highlighted_byte_range = None;
+        self.selection_snippet = StageSnippet::default();
+        self.selected_objects.clear();
+        self.selected_object_removals.clear();
+        self.selected_positions.clear();
+        self.collision_header_references = stagedef.get_collision_header_references();
+        self.start_position = stagedef.start_position.position.0;
+        self.applying_selection = self.pending_selection.take();
+        if self.applying_selection.is_some() {
+            self.selected_tree_items.clear();
+        }
+
+        let magic_number_warning = stagedef.get_magic_number_warning();
+
+        let is_selecting_global_lists = self.applying_selection.map_or(false, |s| s.includes_global_lists());
+        let stagedef_header = egui::CollapsingHeader::new("Stagedef").open(is_selecting_global_lists.then_some(true));
+        let stagedef_response = stagedef_header.show(ui, |ui| {
+            let magic_number_color = magic_number_warning.as_ref().map(|_| ui.visuals().warn_fg_color);
             self.display_tree_element(
                 &mut stagedef.magic_number_1,
                 "Magic Number",
                 Some(0),
-                "A magic number woah",
+                None,
+                "A magic number woah. This is 0.0 in every known stagedef.",
+                magic_number_color,
                 inspectables,
                 ui,
             );
@@ -70,74 +308,667 @@ impl StageDefInstanceUiState {
                 &mut stagedef.magic_number_2,
                 "Magic Number",
                 Some(1),
-                "Another magic number woah",
+                None,
+                "Another magic number woah. This is 1,000.0 in every known stagedef.",
+                magic_number_color,
                 inspectables,
                 ui,
             );
+            if let Some(warning) = &magic_number_warning {
+                ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {warning}"));
+            }
 
             self.display_tree_element(
                 &mut stagedef.start_position,
-                "Start Position",
+                StartPosition::get_name(),
                 None,
-                "Start Position",
-                inspectables,
-                ui,
-            );
-            self.display_tree_element(
-                &mut stagedef.start_rotation,
-                "Start Rotation",
                 None,
-                "Start Rotation",
+                StartPosition::get_description(),
+                None,
                 inspectables,
                 ui,
             );
 
-            self.display_tree_stagedef_object(ui, &mut stagedef.goals, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.bumpers, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.jamabars, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.bananas, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.cone_collisions, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.sphere_collisions, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.cylinder_collisions, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.fallout_volumes, inspectables);
-            self.display_tree_stagedef_object(ui, &mut stagedef.background_models, inspectables);
+            if stagedef.fog.is_some() || stagedef.mystery_3.is_some() {
+                let has_fog = stagedef.fog.is_some();
+                egui::CollapsingHeader::new("Fog").show(ui, |ui| {
+                    if let Some(fog) = &mut stagedef.fog {
+                        self.display_tree_element(
+                            fog,
+                            Fog::get_name(),
+                            None,
+                            None,
+                            Fog::get_description(),
+                            None,
+                            inspectables,
+                            ui,
+                        );
+                    }
+                    if let Some(mystery_3) = &mut stagedef.mystery_3 {
+                        self.display_tree_element(
+                            mystery_3,
+                            Mystery3::get_name(),
+                            None,
+                            None,
+                            Mystery3::get_description(),
+                            None,
+                            inspectables,
+                            ui,
+                        );
+                        if !has_fog {
+                            ui.weak("This stage has mystery 3, but no fog header");
+                        }
+                    }
+                });
+            }
+
+            self.display_tree_stagedef_object(ui, &mut stagedef.goals, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.bumpers, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.jamabars, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.bananas, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.cone_collisions, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.sphere_collisions, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.cylinder_collisions, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.fallout_volumes, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.background_models, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.level_model_instances, None, inspectables);
+            self.display_tree_stagedef_object(ui, &mut stagedef.reflective_models, None, inspectables);
 
             egui::CollapsingHeader::new(format!("Collision Headers ({})", stagedef.collision_headers.len())).show(
                 ui,
                 |ui| {
                     for (col_header_idx, col_header) in stagedef.collision_headers.iter_mut().enumerate() {
                         egui::CollapsingHeader::new(format!("Collision Header {}", col_header_idx + 1)).show(ui, |ui| {
-                            self.display_tree_stagedef_object(ui, &mut col_header.goals, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.bumpers, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.jamabars, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.bananas, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.cone_collisions, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.sphere_collisions, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.cylinder_collisions, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.fallout_volumes, inspectables);
-                            self.display_tree_stagedef_object(ui, &mut col_header.background_models, inspectables);
+                            let header_index = Some(col_header_idx);
+                            self.display_tree_element(
+                                &mut col_header.initial_rotation,
+                                "Initial Rotation",
+                                None,
+                                None,
+                                "The collision header's rotation before any animation, around its center of rotation.\n\
+                                 \n\
+                                 Keyframed rotation replaces this while the animation is being previewed.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            // Headers used only for their animation have no objects, so these are shown even when
+                            // every list below is empty
+                            self.display_tree_element(
+                                &mut col_header.center_of_rotation_position,
+                                "Center of Rotation",
+                                None,
+                                None,
+                                "The point the collision header rotates around.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.conveyor_vector,
+                                "Conveyor Vector",
+                                None,
+                                None,
+                                "The direction and speed the collision header's surfaces push the ball in.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.animation_type,
+                                "Animation Type",
+                                None,
+                                None,
+                                CollisionHeader::get_description(),
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.animation,
+                                "Animation",
+                                None,
+                                None,
+                                "The seesaw parameters or keyframes that move the collision header, depending on its \
+                                 animation type.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.animation_id,
+                                "Animation ID",
+                                None,
+                                None,
+                                "Identifies the collision header's animation, so switches can control it. 0 means \
+                                 none.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.animation_loop_point,
+                                "Animation Loop Point",
+                                None,
+                                None,
+                                "When a looping animation goes back to the start, in seconds.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_element(
+                                &mut col_header.animation_state_init,
+                                "Initial Animation State",
+                                None,
+                                None,
+                                "Whether the collision header's animation is playing, paused, reversed or fast \
+                                 forwarded when the stage starts, before any switch is pressed.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            if let Some(texture_scroll) = &mut col_header.texture_scroll {
+                                self.display_tree_element(
+                                    texture_scroll,
+                                    TextureScroll::get_name(),
+                                    None,
+                                    None,
+                                    TextureScroll::get_description(),
+                                    None,
+                                    inspectables,
+                                    ui,
+                                );
+                            }
+                            self.display_tree_element(
+                                &mut col_header.unknowns,
+                                "Raw/Unknown",
+                                None,
+                                None,
+                                "Collision header fields whose purpose is not yet known, shown as hex.",
+                                None,
+                                inspectables,
+                                ui,
+                            );
+                            self.display_tree_stagedef_object(ui, &mut col_header.goals, header_index, inspectables);
+                            self.display_tree_stagedef_object(ui, &mut col_header.bumpers, header_index, inspectables);
+                            self.display_tree_stagedef_object(ui, &mut col_header.jamabars, header_index, inspectables);
+                            self.display_tree_stagedef_object(ui, &mut col_header.bananas, header_index, inspectables);
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.cone_collisions,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.sphere_collisions,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.cylinder_collisions,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.fallout_volumes,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.background_models,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.level_model_instances,
+                                header_index,
+                                inspectables,
+                            );
+                            self.display_tree_stagedef_object(
+                                ui,
+                                &mut col_header.reflective_models,
+                                header_index,
+                                inspectables,
+                            );
+
+                            if !col_header.level_model_names.is_empty() {
+                                let header_title = format!("Level Models ({})", col_header.level_model_names.len());
+                                egui::CollapsingHeader::new(header_title).show(ui, |ui| {
+                                    for (index, model_name) in col_header.level_model_names.iter_mut().enumerate() {
+                                        self.display_tree_element(
+                                            model_name,
+                                            "Level Model",
+                                            Some(index),
+                                            None,
+                                            "The name of a level model used by this collision header.",
+                                            None,
+                                            inspectables,
+                                            ui,
+                                        );
+                                    }
+                                });
+                            }
                         });
                     }
                 },
             );
+
+            if !stagedef.trailing_bytes.is_empty() {
+                ui.weak(format!("Footer: {} trailing bytes", stagedef.trailing_bytes.len()));
+            }
+        });
+        stagedef_response.header_response.context_menu(|ui| {
+            if ui.button("Select All Objects").clicked() {
+                self.pending_selection = Some(PendingSelection::All);
+                ui.close_menu();
+            }
         });
+        self.applying_selection = None;
+
+        if self.tree_drag.is_some() {
+            if ui.input().pointer.any_down() {
+                ui.output().cursor_icon = egui::CursorIcon::Grabbing;
+            } else {
+                // Dropped somewhere other than its own list
+                self.tree_drag = None;
+            }
+        }
     }
 
     fn display_tree_stagedef_object<'a, T>(
         &mut self,
         ui: &mut Ui,
         objects: &'a mut Vec<GlobalStagedefObject<T>>,
+        collision_header_index: Option<usize>,
         inspectables: &mut Vec<Inspectable<'a>>,
     ) where
-        T: StageDefObject + EguiInspect + Display + 'a,
+        T: StageDefObject + StageDefObjectList + EguiInspect + Display + Clone + serde::Serialize + 'static,
     {
         if objects.is_empty() { return }
 
+        let is_global = collision_header_index.is_none();
+
         let header_title = format!("{}s ({})", T::get_name(), objects.len());
-        egui::CollapsingHeader::new(header_title).show(ui, |ui| {
+        let list_id = ui.make_persistent_id(&header_title);
+        let is_selecting_all = self
+            .applying_selection
+            .map_or(false, |s| s.includes_list(list_id, T::get_name(), is_global));
+        let list_header = egui::CollapsingHeader::new(header_title).open(is_selecting_all.then_some(true));
+        let mut drop_index = None;
+        let list_response = list_header.show(ui, |ui| {
             for (index, object) in objects.iter_mut().enumerate() {
-                self.display_tree_element(object, T::get_name(), Some(index), T::get_description(), inspectables, ui);
+                let text_color = object.object.lock().unwrap().get_tree_color();
+                let file_range = object.get_file_range(self.game);
+                let global_index = object.global_index;
+                // The inspectable borrows the object for the rest of the frame, so keep our own
+                // handle to it for the context menu and inline editor
+                let shared_object = object.object.clone();
+
+                if !T::has_tree_inline_editor() {
+                    if is_selecting_all {
+                        self.selected_tree_items.insert(ui.next_auto_id());
+                    }
+                    let (is_selected, response) = self.display_tree_element(
+                        object,
+                        T::get_name(),
+                        Some(index),
+                        global_index,
+                        T::get_description(),
+                        text_color,
+                        inspectables,
+                        ui,
+                    );
+                    drop_index = self.update_tree_drag(ui, &response, list_id, index).or(drop_index);
+                    if is_selected {
+                        self.record_selected_object(&shared_object);
+                        self.add_object_details(&shared_object, is_global, file_range.clone(), inspectables);
+                        if file_range.is_some() {
+                            self.highlighted_byte_range = file_range.clone();
+                        }
+                    }
+                    let response = Self::show_object_tooltip(response, &shared_object, file_range);
+                    self.show_object_context_menu(response, &shared_object);
+                    continue;
+                }
+
+                ui.horizontal(|ui| {
+                    if is_selecting_all {
+                        self.selected_tree_items.insert(ui.next_auto_id());
+                    }
+                    let (is_selected, response) = self.display_tree_element(
+                        object,
+                        T::get_name(),
+                        Some(index),
+                        global_index,
+                        T::get_description(),
+                        text_color,
+                        inspectables,
+                        ui,
+                    );
+                    drop_index = self.update_tree_drag(ui, &response, list_id, index).or(drop_index);
+                    if is_selected {
+                        self.record_selected_object(&shared_object);
+                        self.add_object_details(&shared_object, is_global, file_range.clone(), inspectables);
+                        if file_range.is_some() {
+                            self.highlighted_byte_range = file_range.clone();
+                        }
+                    }
+                    let response = Self::show_object_tooltip(response, &shared_object, file_range);
+                    self.show_object_context_menu(response, &shared_object);
+                    ui.push_id(index, |ui| {
+                        shared_object.lock().unwrap().show_tree_inline_editor(ui);
+                    });
+                });
+            }
+        });
+        self.finish_tree_drag::<T>(ui, drop_index, collision_header_index);
+        list_response.header_response.context_menu(|ui| {
+            if ui.button(format!("Select All {}s", T::get_name())).clicked() {
+                self.pending_selection = Some(PendingSelection::List(list_id));
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Adds what the inspector shows alongside a selected object's fields to its inspectable, which
+    /// was just added by [``display_tree_element``](Self::display_tree_element).
+    fn add_object_details<T: StageDefObject>(
+        &self,
+        object: &std::sync::Arc<std::sync::Mutex<T>>,
+        is_global: bool,
+        file_range: Option<Range<usize>>,
+        inspectables: &mut [Inspectable<'_>],
+    ) {
+        let Some(inspectable) = inspectables.last_mut() else {
+            return;
+        };
+
+        if is_global {
+            inspectable.referencing_headers = Some(self.collision_header_references.get(object).to_vec());
+        }
+        let position = object.lock().unwrap().get_position();
+        inspectable.relative_position = position.map(|position| position.relative_to(self.start_position));
+        inspectable.file_range = file_range;
+    }
+
+    /// Starts dragging a tree element to reorder its list. If another element in the same list is
+    /// being dragged over this one instead, shows where it would go and returns this one's index.
+    fn update_tree_drag(&mut self, ui: &Ui, response: &egui::Response, list_id: Id, index: usize) -> Option<usize> {
+        if response.interact(egui::Sense::drag()).drag_started() {
+            self.tree_drag = Some(TreeDrag {
+                list_id,
+                from_index: index,
+            });
+        }
+
+        let drag = self.tree_drag.filter(|drag| drag.list_id == list_id && drag.from_index != index)?;
+        if !ui.rect_contains_pointer(response.rect) {
+            return None;
+        }
+
+        // The dragged element takes this one's place, so this one moves towards where it was
+        let y = if index > drag.from_index {
+            response.rect.bottom()
+        } else {
+            response.rect.top()
+        };
+        ui.painter().hline(response.rect.x_range(), y, ui.visuals().selection.stroke);
+        Some(index)
+    }
+
+    /// Queues the dragged object to be moved, if it was just dropped on another element in its list.
+    fn finish_tree_drag<T: StageDefObjectList + 'static>(
+        &mut self,
+        ui: &Ui,
+        drop_index: Option<usize>,
+        collision_header_index: Option<usize>,
+    ) {
+        let (Some(drag), Some(to)) = (self.tree_drag, drop_index) else {
+            return;
+        };
+        if !ui.input().pointer.any_released() {
+            return;
+        }
+
+        self.tree_drag = None;
+        let from = drag.from_index;
+        self.pending_move = Some(Box::new(move |stagedef| {
+            match collision_header_index {
+                Some(collision_header_index) => stagedef.move_local_object::<T>(collision_header_index, from, to),
+                None => stagedef.move_object::<T>(from, to),
+            };
+        }));
+    }
+
+    /// Adds a copy of the given object to the selection snippet and remembers how to delete it, and
+    /// how to move it if it has a position, unless it's already been recorded this frame.
+    fn record_selected_object<T: StageDefObject + StageDefObjectList + Clone + 'static>(
+        &mut self,
+        object: &std::sync::Arc<std::sync::Mutex<T>>,
+    ) {
+        let object_address = std::sync::Arc::as_ptr(object) as usize;
+        if !self.selected_objects.insert(object_address) {
+            return;
+        }
+
+        self.selection_snippet.add(&*object.lock().unwrap());
+        let removed_object = object.clone();
+        self.selected_object_removals.push(Box::new(move |stagedef| {
+            stagedef.remove_object(&removed_object);
+        }));
+        if object.lock().unwrap().get_position().is_some() {
+            let object = object.clone();
+            self.selected_positions.push(Box::new(move |f: &mut dyn FnMut(&mut Vector3)| {
+                if let Some(position) = object.lock().unwrap().get_position_mut() {
+                    f(position);
+                }
+            }));
+        }
+    }
+
+    /// Returns how to get at the position of the selected object, if exactly one selected object has
+    /// a position. Only objects in lists are included, as the start position isn't shared.
+    pub fn get_single_selected_position(&self) -> Option<&PositionAccess> {
+        match self.selected_positions.as_slice() {
+            [position] => Some(position),
+            _ => None,
+        }
+    }
+
+    /// Returns how many selected objects would be removed by [``delete_selection``](Self::delete_selection).
+    pub fn get_deletable_selection_count(&self) -> usize {
+        self.selected_object_removals.len()
+    }
+
+    /// Shows why the last paste failed, until it's dismissed.
+    pub fn show_paste_error(&mut self, ui: &mut Ui) {
+        let Some(error) = &self.paste_error else {
+            return;
+        };
+
+        let mut is_dismissed = false;
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(ui.visuals().error_fg_color, format!("Could not paste: {error}"));
+                is_dismissed = ui.small_button("✖").clicked();
+            });
+        });
+
+        if is_dismissed {
+            self.paste_error = None;
+        }
+    }
+
+    /// Deletes the selected objects, asking for confirmation first if there are a lot of them.
+    pub fn request_delete_selection(&mut self) {
+        if self.get_deletable_selection_count() > DELETE_CONFIRMATION_THRESHOLD {
+            self.is_confirming_deletion = true;
+        } else {
+            self.delete_selection();
+        }
+    }
+
+    /// Queues every selected object in a list to be removed before the next frame is drawn.
+    /// Selected tree items that aren't list objects, like the start position, are left alone.
+    pub fn delete_selection(&mut self) {
+        self.is_confirming_deletion = false;
+        if self.selected_object_removals.is_empty() {
+            return;
+        }
+
+        let removals = std::mem::take(&mut self.selected_object_removals);
+        self.pending_deletion = Some(Box::new(move |stagedef| {
+            for remove in removals {
+                remove(stagedef);
+            }
+        }));
+    }
+
+    /// Shows every field of a tree element's object, and where it was read from, while it's
+    /// hovered, so it can be checked without selecting it.
+    fn show_object_tooltip<T: StageDefObject + EguiInspect>(
+        response: egui::Response,
+        object: &std::sync::Arc<std::sync::Mutex<T>>,
+        file_range: Option<Range<usize>>,
+    ) -> egui::Response {
+        response.on_hover_ui(|ui| {
+            object.lock().unwrap().inspect(T::get_name(), ui);
+            if let Some(file_range) = file_range {
+                ui.separator();
+                ui.weak(format!("File offset: {:#X}", file_range.start));
+            }
+        })
+    }
+
+    /// Adds a context menu to a tree element that lets the user copy its object's data, or delete
+    /// it.
+    ///
+    /// This always acts on the clicked object, regardless of what else is selected.
+    fn show_object_context_menu<T: StageDefObject + StageDefObjectList + serde::Serialize + 'static>(
+        &mut self,
+        response: egui::Response,
+        object: &std::sync::Arc<std::sync::Mutex<T>>,
+    ) {
+        response.context_menu(|ui| {
+            let position = object.lock().unwrap().get_position();
+            if let Some(position) = position {
+                if ui.button("Copy position").clicked() {
+                    // Copied positions are for pasting into other tools, so use their up axis
+                    let [x, y, z] = self.unit_settings.up_axis.from_game([position.x, position.y, position.z]);
+                    ui.output().copied_text = Vector3 { x, y, z }.get_clipboard_text();
+                    ui.close_menu();
+                }
+            }
+            if ui.button("Copy as JSON").clicked() {
+                match get_tagged_object_json(&*object.lock().unwrap()) {
+                    Ok(json) => ui.output().copied_text = json,
+                    Err(err) => warn!("Failed to serialize {}: {err}", T::get_name()),
+                }
+                ui.close_menu();
+            }
+            ui.separator();
+            if ui.button("Delete").clicked() {
+                let object = object.clone();
+                self.pending_deletion = Some(Box::new(move |stagedef| {
+                    stagedef.remove_object(&object);
+                }));
+                ui.close_menu();
             }
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stagedef::objects::{Goal, GoalType};
+
+    #[test]
+    fn test_delete_selection() {
+        let goal_types = [GoalType::Blue, GoalType::Green, GoalType::Red];
+        let mut stagedef = StageDef::new();
+        for goal_type in goal_types {
+            stagedef = stagedef.with_goal(Goal {
+                goal_type,
+                ..Default::default()
+            });
+        }
+
+        // The header refers to every goal, in the same order as the global list
+        stagedef.add_collision_header_referencing::<Goal>(&[0, 1, 2]);
+
+        // Select the blue and red goals, with the red goal selected in both lists
+        let blue_goal = stagedef.goals[0].object.clone();
+        let red_goal = stagedef.goals[2].object.clone();
+        let mut ui_state = StageDefInstanceUiState::default();
+        ui_state.record_selected_object(&blue_goal);
+        ui_state.record_selected_object(&red_goal);
+        ui_state.record_selected_object(&stagedef.collision_headers[0].goals[2].object);
+        assert_eq!(ui_state.get_deletable_selection_count(), 2);
+
+        ui_state.request_delete_selection();
+        assert!(!ui_state.is_confirming_deletion);
+        (ui_state.pending_deletion.take().unwrap())(&mut stagedef);
+
+        assert_eq!(stagedef.goals.len(), 1);
+        assert_eq!(stagedef.goals[0].index, 0);
+        assert_eq!(stagedef.goals[0].object.lock().unwrap().goal_type, GoalType::Green);
+
+        let local_goals = &stagedef.collision_headers[0].goals;
+        assert_eq!(local_goals.len(), 1);
+        assert!(local_goals[0].shares_object_with(&stagedef.goals[0]));
+        assert_eq!(local_goals[0].index, 0);
+        assert_eq!(local_goals[0].global_index, Some(0));
+    }
+
+    #[test]
+    fn test_pending_selection_lists() {
+        let goal_list = Id::new("Goals (3)");
+        let local_goal_list = Id::new("Collision Header 1").with("Goals (3)");
+
+        // Selecting one list only picks that list, whether it's global or local
+        let selection = PendingSelection::List(local_goal_list);
+        assert!(selection.includes_list(local_goal_list, "Goal", false));
+        assert!(!selection.includes_list(goal_list, "Goal", true));
+        assert!(!selection.includes_global_lists());
+
+        // Selecting a type or everything only picks global lists, so nothing is selected twice
+        let selection = PendingSelection::Type("Goal");
+        assert!(selection.includes_list(goal_list, "Goal", true));
+        assert!(!selection.includes_list(local_goal_list, "Goal", false));
+        assert!(!selection.includes_list(Id::new("Bumpers (1)"), "Bumper", true));
+        assert!(selection.includes_global_lists());
+
+        let selection = PendingSelection::All;
+        assert!(selection.includes_list(goal_list, "Goal", true));
+        assert!(selection.includes_list(Id::new("Bumpers (1)"), "Bumper", true));
+        assert!(!selection.includes_list(local_goal_list, "Goal", false));
+        assert!(selection.includes_global_lists());
+    }
+
+    #[test]
+    fn test_delete_large_selection_asks_first() {
+        let mut stagedef = StageDef::new();
+        for _ in 0..=DELETE_CONFIRMATION_THRESHOLD {
+            stagedef = stagedef.with_goal(Goal::default());
+        }
+
+        let mut ui_state = StageDefInstanceUiState::default();
+        for goal in &stagedef.goals {
+            ui_state.record_selected_object(&goal.object);
+        }
+
+        ui_state.request_delete_selection();
+        assert!(ui_state.is_confirming_deletion);
+        assert!(ui_state.pending_deletion.is_none());
+
+        ui_state.delete_selection();
+        (ui_state.pending_deletion.take().unwrap())(&mut stagedef);
+        assert!(stagedef.goals.is_empty());
+    }
+}
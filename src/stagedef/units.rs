@@ -0,0 +1,196 @@
+//! Converting distances between the game's own units and units that are easier to reason about.
+//!
+//! Values are always stored in game units, with Y up; conversion only happens when they're shown or
+//! edited.
+#[cfg(feature = "gui")]
+use egui::Id;
+
+/// A unit that distances can be displayed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistanceUnit {
+    #[default]
+    GameUnits,
+    Meters,
+}
+
+/// Which axis points up in the coordinates shown to the user.
+///
+/// Monkey Ball is Y-up, but many modelling tools are Z-up. Converting between the two is a
+/// rotation around X, so handedness is kept and nothing is mirrored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+impl UpAxis {
+    /// Converts a position from the game's Y-up coordinates to this convention.
+    pub fn from_game(self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            UpAxis::Y => [x, y, z],
+            UpAxis::Z => [x, -z, y],
+        }
+    }
+
+    /// Converts a position in this convention back to the game's Y-up coordinates.
+    pub fn to_game(self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        match self {
+            UpAxis::Y => [x, y, z],
+            UpAxis::Z => [x, z, -y],
+        }
+    }
+}
+
+/// How distances in the inspector are displayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitSettings {
+    pub unit: DistanceUnit,
+    /// How many meters one game unit is treated as, when displaying in meters.
+    pub meters_per_game_unit: f32,
+    /// Which axis is up in displayed and copied positions.
+    pub up_axis: UpAxis,
+}
+
+impl Default for UnitSettings {
+    fn default() -> Self {
+        Self {
+            unit: DistanceUnit::GameUnits,
+            meters_per_game_unit: 1.0,
+            up_axis: UpAxis::Y,
+        }
+    }
+}
+
+impl UnitSettings {
+    pub const METERS_PER_GAME_UNIT_RANGE: std::ops::RangeInclusive<f32> = 0.001..=1000.0;
+
+    /// Returns how many display units there are per game unit.
+    pub fn get_scale(&self) -> f32 {
+        match self.unit {
+            DistanceUnit::GameUnits => 1.0,
+            DistanceUnit::Meters => self.meters_per_game_unit.clamp(
+                *Self::METERS_PER_GAME_UNIT_RANGE.start(),
+                *Self::METERS_PER_GAME_UNIT_RANGE.end(),
+            ),
+        }
+    }
+
+    pub fn get_suffix(&self) -> &'static str {
+        match self.unit {
+            DistanceUnit::GameUnits => "",
+            DistanceUnit::Meters => " m",
+        }
+    }
+
+    /// Converts a distance in game units to the display unit.
+    pub fn get_display_value(&self, game_units: f32) -> f32 {
+        game_units * self.get_scale()
+    }
+
+    /// Converts an edited distance in the display unit back to game units.
+    ///
+    /// If the displayed value is the same as ``original`` converted for display, ``original`` is
+    /// returned exactly, so values that weren't edited don't drift from being converted back and
+    /// forth.
+    pub fn get_game_units(&self, displayed: f32, original: f32) -> f32 {
+        if displayed == self.get_display_value(original) {
+            original
+        } else {
+            displayed / self.get_scale()
+        }
+    }
+
+    /// Makes these settings available to inspectors drawn with the given context this frame.
+    ///
+    /// Inspectors only get a label and a [``egui::Ui``] to work with, so this is how they find out
+    /// which unit to use.
+    #[cfg(feature = "gui")]
+    pub fn store(self, ctx: &egui::Context) {
+        ctx.data().insert_temp(Self::get_id(), self);
+    }
+
+    /// Returns the settings last stored with [``UnitSettings::store``], or the defaults.
+    #[cfg(feature = "gui")]
+    pub fn load(ctx: &egui::Context) -> Self {
+        ctx.data().get_temp(Self::get_id()).unwrap_or_default()
+    }
+
+    #[cfg(feature = "gui")]
+    fn get_id() -> Id {
+        Id::new("stagedef_unit_settings")
+    }
+
+    /// Displays editable fields for each setting.
+    #[cfg(feature = "gui")]
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.radio_value(&mut self.unit, DistanceUnit::GameUnits, "Game units");
+        ui.radio_value(&mut self.unit, DistanceUnit::Meters, "Meters");
+        ui.add_enabled(
+            self.unit == DistanceUnit::Meters,
+            egui::DragValue::new(&mut self.meters_per_game_unit)
+                .clamp_range(Self::METERS_PER_GAME_UNIT_RANGE)
+                .speed(0.01)
+                .prefix("Meters per game unit: "),
+        );
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Up axis:");
+            ui.radio_value(&mut self.up_axis, UpAxis::Y, "Y (game)");
+            ui.radio_value(&mut self.up_axis, UpAxis::Z, "Z");
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_game_units_unchanged() {
+        let settings = UnitSettings::default();
+
+        assert_eq!(settings.get_display_value(-115.25), -115.25);
+        assert_eq!(settings.get_game_units(3.0, -115.25), 3.0);
+    }
+
+    #[test]
+    fn test_meters_round_trip_without_drift() {
+        let settings = UnitSettings {
+            unit: DistanceUnit::Meters,
+            meters_per_game_unit: 0.3,
+            ..Default::default()
+        };
+
+        // Showing a value over and over without editing it must never change it
+        for original in [0.1, -115.0, 2.75, 14.0, 1.0e-7, 123_456.79] {
+            let mut value: f32 = original;
+            for _ in 0..100 {
+                value = settings.get_game_units(settings.get_display_value(value), value);
+            }
+            assert_eq!(value, original);
+        }
+
+        // Edits are converted back to game units
+        let edited = settings.get_game_units(3.0, 0.0);
+        assert!((edited - 10.0).abs() < 1e-4);
+        assert!((settings.get_display_value(edited) - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_up_axis_conversion() {
+        let game = [1.5, -2.0, -115.25];
+        assert_eq!(UpAxis::Y.from_game(game), game);
+        assert_eq!(UpAxis::Y.to_game(game), game);
+
+        // Up in the game is up in Z-up, and the game's +Z (towards the default camera) becomes -Y
+        assert_eq!(UpAxis::Z.from_game([0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert_eq!(UpAxis::Z.from_game([0.0, 0.0, 1.0]), [0.0, -1.0, 0.0]);
+        assert_eq!(UpAxis::Z.to_game([0.0, 0.0, 1.0]), [0.0, 1.0, 0.0]);
+
+        for up_axis in [UpAxis::Y, UpAxis::Z] {
+            assert_eq!(up_axis.to_game(up_axis.from_game(game)), game);
+            assert_eq!(up_axis.from_game(up_axis.to_game(game)), game);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! Checks a [``StageDef``] for mistakes that parse fine, but won't behave as intended in game
+use super::common::*;
+use crate::stagedef::objects::{CollisionTriangle, Goal, TextureScroll};
+use std::collections::BTreeMap;
+
+/// How far below a goal's position the surface it sits on can be, before we consider it floating.
+const GOAL_MAX_HEIGHT_ABOVE_SURFACE: f32 = 1.0;
+/// How far above a goal's position the surface it sits on can be, before we consider it buried.
+const GOAL_MAX_DEPTH_BELOW_SURFACE: f32 = 0.5;
+
+/// Which of the optional checks to run when validating a [``StageDef``].
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationSettings {
+    /// Checks that every goal sits on or just above collision. This ignores collision header
+    /// animations, so it can be wrong for goals on moving parts of a stage.
+    pub check_goal_reachability: bool,
+}
+
+#[cfg(feature = "gui")]
+impl ValidationSettings {
+    pub fn show_ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.check_goal_reachability, "Check goals are on collision")
+            .on_hover_text("Flags goals with no collision just below them. Ignores animations.");
+    }
+}
+
+/// A single problem found in a [``StageDef``].
+#[derive(Debug, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// The magic numbers at the start of the file aren't the ones we expect, so it was likely
+    /// misparsed. ``warning`` is the one [``StageDef::get_magic_number_warning``] gives.
+    UnusualMagicNumbers { warning: String },
+    /// More than one collision header uses the same non-zero animation ID, so switches can't tell
+    /// their animations apart.
+    DuplicateAnimationId {
+        animation_id: u16,
+        collision_header_indices: Vec<usize>,
+    },
+    /// A goal has no collision directly below it, or is buried in collision, so the ball likely
+    /// can't get to it.
+    GoalNotOnCollision { goal_index: usize },
+    /// A collision header scrolls its level models' textures, but doesn't have any level models.
+    UnusedTextureScroll { collision_header_index: usize },
+    /// A bumper or jamabar has a zero or negative scale along some axis, so it's flattened or
+    /// turned inside out in game. ``object_name`` is the kind of object, like ``"Bumper"``.
+    NonPositiveScale { object_name: &'static str, index: usize },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::UnusualMagicNumbers { warning } => write!(f, "{warning}"),
+            ValidationIssue::DuplicateAnimationId {
+                animation_id,
+                collision_header_indices,
+            } => {
+                let headers = collision_header_indices
+                    .iter()
+                    .map(|index| (index + 1).to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "Collision headers {headers} share animation ID {animation_id}")
+            }
+            ValidationIssue::GoalNotOnCollision { goal_index } => {
+                write!(f, "Goal {} isn't on or just above any collision", goal_index + 1)
+            }
+            ValidationIssue::UnusedTextureScroll { collision_header_index } => {
+                write!(
+                    f,
+                    "Collision header {} has a texture scroll, but no level models for it to scroll",
+                    collision_header_index + 1
+                )
+            }
+            ValidationIssue::NonPositiveScale { object_name, index } => {
+                write!(f, "{object_name} {} has a zero or negative scale", index + 1)
+            }
+        }
+    }
+}
+
+impl StageDef {
+    /// Returns every problem found in this stagedef, running the optional checks enabled in
+    /// ``settings``.
+    pub fn validate(&self, settings: &ValidationSettings) -> Vec<ValidationIssue> {
+        let mut issues = Vec::new();
+        issues.extend(
+            self.get_magic_number_warning()
+                .map(|warning| ValidationIssue::UnusualMagicNumbers { warning }),
+        );
+        self.validate_animation_ids(&mut issues);
+        self.validate_texture_scrolls(&mut issues);
+        self.validate_scales(&self.bumpers, |bumper| bumper.scale, &mut issues);
+        self.validate_scales(&self.jamabars, |jamabar| jamabar.scale, &mut issues);
+        if settings.check_goal_reachability {
+            self.validate_goal_reachability(&mut issues);
+        }
+        issues
+    }
+
+    fn validate_animation_ids(&self, issues: &mut Vec<ValidationIssue>) {
+        // Ordered, so issues come out in the same order every frame
+        let mut headers_by_id: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+        for (index, header) in self.collision_headers.iter().enumerate() {
+            // 0 means the header's animation isn't controlled by anything, so it can be shared
+            if header.animation_id != 0 {
+                headers_by_id.entry(header.animation_id).or_default().push(index);
+            }
+        }
+
+        issues.extend(
+            headers_by_id
+                .into_iter()
+                .filter(|(_, indices)| indices.len() > 1)
+                .map(|(animation_id, collision_header_indices)| ValidationIssue::DuplicateAnimationId {
+                    animation_id,
+                    collision_header_indices,
+                }),
+        );
+    }
+
+    fn validate_texture_scrolls(&self, issues: &mut Vec<ValidationIssue>) {
+        for (index, header) in self.collision_headers.iter().enumerate() {
+            // Texture scrolls that don't move anything are harmless, so only flag ones that would
+            let is_scrolling = header.texture_scroll.filter(TextureScroll::is_moving).is_some();
+            if is_scrolling && header.level_model_names.is_empty() {
+                issues.push(ValidationIssue::UnusedTextureScroll {
+                    collision_header_index: index,
+                });
+            }
+        }
+    }
+
+    fn validate_scales<T: StageDefObject>(
+        &self,
+        objects: &[GlobalStagedefObject<T>],
+        get_scale: impl Fn(&T) -> Vector3,
+        issues: &mut Vec<ValidationIssue>,
+    ) {
+        for object in objects {
+            let scale = get_scale(&object.object.lock().unwrap());
+            // NaN isn't positive either, so it's flagged too
+            if ![scale.x, scale.y, scale.z].iter().all(|&component| component > 0.0) {
+                issues.push(ValidationIssue::NonPositiveScale {
+                    object_name: T::get_name(),
+                    index: object.index as usize,
+                });
+            }
+        }
+    }
+
+    fn validate_goal_reachability(&self, issues: &mut Vec<ValidationIssue>) {
+        let triangles: Vec<[Vector3; 3]> = self
+            .collision_headers
+            .iter()
+            .flat_map(|header| header.collision_triangles.iter())
+            .map(CollisionTriangle::get_vertices)
+            .collect();
+
+        // Positions come out in global list order, so the nth goal's is at index n
+        let goal_positions = self.iter_positions().filter(|(name, _)| *name == Goal::get_name());
+        for (goal_index, (_, position)) in goal_positions.enumerate() {
+            // Start the ray a little above the goal, so goals sunk slightly into the floor still count
+            let origin = [position.x, position.y + GOAL_MAX_DEPTH_BELOW_SURFACE, position.z];
+            let max_distance = GOAL_MAX_DEPTH_BELOW_SURFACE + GOAL_MAX_HEIGHT_ABOVE_SURFACE;
+            let is_on_collision = triangles
+                .iter()
+                .filter_map(|triangle| intersect_ray_triangle(origin, [0.0, -1.0, 0.0], triangle))
+                .any(|distance| distance <= max_distance);
+
+            if !is_on_collision {
+                issues.push(ValidationIssue::GoalNotOnCollision { goal_index });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::stagedef::objects::{Bumper, CollisionHeader, Goal, GoalType, Jamabar};
+
+    fn header_with_animation_id(animation_id: u16) -> CollisionHeader {
+        CollisionHeader {
+            animation_id,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_duplicate_animation_ids() {
+        let stagedef = StageDef {
+            collision_headers: [0, 5, 0, 7, 5].into_iter().map(header_with_animation_id).collect(),
+            ..StageDef::new()
+        };
+
+        let issues = stagedef.validate(&ValidationSettings::default());
+        assert_eq!(
+            issues,
+            vec![ValidationIssue::DuplicateAnimationId {
+                animation_id: 5,
+                collision_header_indices: vec![1, 4],
+            }]
+        );
+        assert_eq!(issues[0].to_string(), "Collision headers 2, 5 share animation ID 5");
+    }
+
+    #[test]
+    fn test_unused_texture_scroll() {
+        let header = |speed_x, level_model_names: &[&str]| CollisionHeader {
+            texture_scroll: Some(TextureScroll { speed_x, speed_y: 0.0 }),
+            level_model_names: level_model_names.iter().map(|name| name.to_string()).collect(),
+            ..Default::default()
+        };
+        let stagedef = StageDef {
+            collision_headers: vec![
+                header(0.5, &["MODEL_A"]),
+                header(0.5, &[]),
+                header(0.0, &[]),
+                CollisionHeader::default(),
+            ],
+            ..StageDef::new()
+        };
+
+        // Only the header scrolling nothing is flagged, and one that doesn't move is left alone
+        let issues = stagedef.validate(&ValidationSettings::default());
+        assert_eq!(
+            issues,
+            [ValidationIssue::UnusedTextureScroll {
+                collision_header_index: 1
+            }]
+        );
+        assert_eq!(
+            issues[0].to_string(),
+            "Collision header 2 has a texture scroll, but no level models for it to scroll"
+        );
+    }
+
+    #[test]
+    fn test_non_positive_scale() {
+        let scale = |x, y, z| Vector3 { x, y, z };
+        let bumper = |scale| Bumper {
+            position: Position::default(),
+            rotation: ShortVector3::default(),
+            scale,
+        };
+        let stagedef = StageDef::new()
+            .with_bumper(bumper(scale(1.0, 1.0, 1.0)))
+            .with_bumper(bumper(scale(1.0, 0.0, 1.0)))
+            .with_bumper(bumper(scale(2.0, 2.0, -2.0)))
+            .with_jamabar(Jamabar {
+                position: Position::default(),
+                rotation: ShortVector3::default(),
+                scale: scale(0.0, 0.0, 0.0),
+            });
+
+        let issues = stagedef.validate(&ValidationSettings::default());
+        assert_eq!(
+            issues,
+            [("Bumper", 1), ("Bumper", 2), ("Jamabar", 0)]
+                .map(|(object_name, index)| ValidationIssue::NonPositiveScale { object_name, index })
+        );
+        assert_eq!(issues[0].to_string(), "Bumper 2 has a zero or negative scale");
+    }
+
+    #[test]
+    fn test_goal_reachability() {
+        let mut stagedef = StageDef {
+            collision_headers: vec![CollisionHeader {
+                collision_triangles: vec![CollisionTriangle::floor()],
+                ..Default::default()
+            }],
+            ..StageDef::new()
+        };
+        // On the floor, floating, buried, and off the edge
+        for (x, y, z) in [(2.0, 0.0, 2.0), (2.0, 20.0, 2.0), (2.0, -5.0, 2.0), (20.0, 0.0, 20.0)] {
+            stagedef = stagedef.with_goal(Goal {
+                position: Position(Vector3 { x, y, z }),
+                rotation: ShortVector3::default(),
+                goal_type: GoalType::Blue,
+            });
+        }
+
+        // Only run when asked for
+        assert!(stagedef.validate(&ValidationSettings::default()).is_empty());
+
+        let settings = ValidationSettings {
+            check_goal_reachability: true,
+        };
+        let issues = stagedef.validate(&settings);
+        assert_eq!(
+            issues,
+            [1, 2, 3].map(|goal_index| ValidationIssue::GoalNotOnCollision { goal_index })
+        );
+        assert_eq!(issues[0].to_string(), "Goal 2 isn't on or just above any collision");
+    }
+
+    #[test]
+    fn test_unusual_magic_numbers() {
+        assert!(StageDef::new().validate(&ValidationSettings::default()).is_empty());
+
+        let stagedef = StageDef {
+            magic_number_2: 1.0,
+            ..StageDef::new()
+        };
+        let issues = stagedef.validate(&ValidationSettings::default());
+        assert_eq!(
+            issues,
+            [ValidationIssue::UnusualMagicNumbers {
+                warning: stagedef.get_magic_number_warning().unwrap()
+            }]
+        );
+        assert!(issues[0].to_string().starts_with("Magic numbers are 0 and 1"));
+    }
+}
@@ -0,0 +1,74 @@
+//! The look of the whole UI, chosen from the View menu
+use egui::{Color32, Stroke, Visuals};
+
+/// A set of [``Visuals``] for the whole app.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    /// Black backgrounds with white text and outlines, for telling things apart more easily.
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+            Theme::HighContrast => "High Contrast",
+        }
+    }
+
+    pub fn get_visuals(&self) -> Visuals {
+        match self {
+            Theme::Dark => Visuals::dark(),
+            Theme::Light => Visuals::light(),
+            Theme::HighContrast => {
+                let mut visuals = Visuals::dark();
+                visuals.override_text_color = Some(Color32::WHITE);
+                visuals.panel_fill = Color32::BLACK;
+                visuals.window_fill = Color32::BLACK;
+                visuals.window_stroke = Stroke::new(1.0, Color32::WHITE);
+                visuals.faint_bg_color = Color32::from_gray(24);
+                visuals.extreme_bg_color = Color32::BLACK;
+                visuals.selection.bg_fill = Color32::from_rgb(0, 90, 200);
+                visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+                visuals.hyperlink_color = Color32::from_rgb(110, 200, 255);
+                visuals.warn_fg_color = Color32::from_rgb(255, 210, 0);
+                visuals.error_fg_color = Color32::from_rgb(255, 90, 90);
+
+                let widgets = &mut visuals.widgets;
+                widgets.noninteractive.bg_stroke = Stroke::new(1.0, Color32::from_gray(200));
+                for widget in [&mut widgets.inactive, &mut widgets.hovered, &mut widgets.active, &mut widgets.open] {
+                    widget.bg_stroke = Stroke::new(1.0, Color32::WHITE);
+                    widget.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+                }
+                widgets.inactive.bg_fill = Color32::BLACK;
+                widgets.hovered.bg_fill = Color32::from_gray(60);
+                widgets.active.bg_fill = Color32::from_gray(90);
+                visuals
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_theme_visuals() {
+        assert!(Theme::Dark.get_visuals().dark_mode);
+        assert!(!Theme::Light.get_visuals().dark_mode);
+
+        // High contrast is meant to be as far from the background as possible
+        let high_contrast = Theme::HighContrast.get_visuals();
+        assert_eq!(high_contrast.override_text_color, Some(Color32::WHITE));
+        assert_eq!(high_contrast.panel_fill, Color32::BLACK);
+        assert_eq!(high_contrast.extreme_bg_color, Color32::BLACK);
+    }
+}
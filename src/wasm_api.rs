@@ -0,0 +1,71 @@
+//! Functions for calling the parser from JavaScript, without the rest of the viewer.
+//!
+//! Only built with the ``wasm-api`` feature.
+use crate::stagedef::common::{Endianness, Game, ParseError};
+use crate::stagedef::compression;
+use crate::stagedef::parser::{self, StageDefReader};
+use crate::stagedef::summary::StageSummary;
+use anyhow::{anyhow, Result};
+use byteorder::{BigEndian, LittleEndian};
+use std::io::Cursor;
+use wasm_bindgen::prelude::*;
+
+/// Parses the given stage file, compressed or not, and returns a summary of it as a JSON string.
+///
+/// ``game`` is 1 for SMB2 and 2 for SMBDX. SMB1 (0) can't be parsed yet, so it's rejected like an
+/// unparseable file: the JSON is an object with a single ``error`` string instead.
+#[wasm_bindgen]
+pub fn parse_stagedef(bytes: &[u8], game: u8, little_endian: bool) -> JsValue {
+    let endianness = if little_endian {
+        Endianness::LittleEndian
+    } else {
+        Endianness::BigEndian
+    };
+
+    let json = match parse_summary(bytes, game, endianness) {
+        Ok(summary) => serde_json::to_string(&summary),
+        Err(e) => serde_json::to_string(&serde_json::json!({ "error": e.to_string() })),
+    };
+    JsValue::from_str(&json.unwrap_or_default())
+}
+
+fn parse_summary(bytes: &[u8], game: u8, endianness: Endianness) -> Result<StageSummary> {
+    let game = match game {
+        //TODO: Accept SMB1 once the parser supports its file header
+        0 => return Err(ParseError::UnsupportedGame("SMB1").into()),
+        1 => Game::SMB2,
+        2 => Game::SMBDX,
+        _ => return Err(anyhow!("Unknown game {game}, expected 1 (SMB2) or 2 (SMBDX)")),
+    };
+
+    let mut reader = Cursor::new(compression::decompress(bytes.to_vec())?.buffer);
+    parser::check_minimum_size(&mut reader, game)?;
+
+    let mut sd_reader = StageDefReader::new(reader, game);
+    let stagedef = match endianness {
+        Endianness::BigEndian => sd_reader.read_stagedef::<BigEndian>()?,
+        Endianness::LittleEndian => sd_reader.read_stagedef::<LittleEndian>()?,
+    };
+    Ok(stagedef.get_summary())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_stagedef_signature() {
+        // Calling into JS doesn't work outside the browser, so this only checks the exported signature
+        let _: fn(&[u8], u8, bool) -> JsValue = parse_stagedef;
+
+        let error = parse_summary(&[], 7, Endianness::BigEndian).unwrap_err();
+        assert!(error.to_string().starts_with("Unknown game 7"));
+    }
+
+    #[test]
+    fn test_parse_summary_rejects_smb1() {
+        // Big enough for any file header, so it's the game that's rejected rather than the size
+        let error = parse_summary(&[0; 0x1000], 0, Endianness::BigEndian).unwrap_err();
+        assert!(matches!(error.downcast_ref::<ParseError>(), Some(ParseError::UnsupportedGame("SMB1"))));
+    }
+}